@@ -1,19 +1,21 @@
 use std::error::Error as StdError;
 use std::ffi::OsStr;
 use std::fmt::Display;
+use std::fs::{File, OpenOptions};
 use std::io::{self, Write};
 use std::process::{Child, ExitStatus};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::Arc;
-use std::time::Duration;
-use std::{env, fmt, thread};
+use std::time::{Duration, Instant};
+use std::{env, fmt, mem, thread};
 
 use console::Color::{Cyan, Green, Red, Yellow};
 use console::{Color, Style, Term};
 use parking_lot::Mutex;
 
-use crate::parser::Diagnostic;
+use crate::parser::{DiagSink, Diagnostic};
 use crate::prelude::*;
 use crate::util::{ErrorExt as _, ImgCache, ProcessLines};
 
@@ -28,6 +30,39 @@ pub struct StdioOpts {
     /// Whether to use colored output (auto-detected by default)
     #[arg(long)]
     pub color: Option<bool>,
+    /// Format of song diagnostics (warnings and errors) and of the error a command fails with
+    #[arg(long, value_enum, default_value_t, value_name = "FORMAT")]
+    pub diagnostics_format: DiagnosticsFormat,
+    /// Where diagnostics are printed with --diagnostics-format json, other messages stay on stderr.
+    /// Human diagnostics are always printed to stderr along with other messages
+    #[arg(long, value_enum, default_value_t, value_name = "STREAM")]
+    pub diagnostics_stream: DiagnosticsStream,
+    /// Also write the messages printed to stderr to FILE, without colors
+    #[arg(long, value_name = "FILE")]
+    pub log_file: Option<PathBuf>,
+    /// Append to the --log-file instead of overwriting it
+    #[arg(long, requires = "log_file")]
+    pub append: bool,
+}
+
+/// See `StdioOpts::diagnostics_format`.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum DiagnosticsFormat {
+    /// Styled text for people
+    #[default]
+    Human,
+    /// One JSON object per line, for CI and other tools
+    Json,
+}
+
+/// See `StdioOpts::diagnostics_stream`.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum DiagnosticsStream {
+    /// Standard error, along with other messages
+    #[default]
+    Stderr,
+    /// Standard output, so that every line of it is a JSON object, eg. for editor plugins
+    Stdout,
 }
 
 impl StdioOpts {
@@ -49,6 +84,25 @@ pub struct MakeOpts {
     /// Keep the TeX file when generating PDF. Use twice to keep TeX build directory as well.
     #[arg(short = 'k', long, action = clap::ArgAction::Count)]
     pub keep: u8,
+    /// Only render songs FIRST to LAST in PDF outputs for a quick preview, without ToC.
+    /// These are song numbers (from 1, in book order), not page numbers
+    #[arg(long, value_name = "FIRST-LAST")]
+    pub preview: Option<SongRange>,
+    /// Set a variable available in templates as vars.KEY, may be used multiple times
+    #[arg(short = 'D', long = "define", value_name = "KEY=VALUE")]
+    pub define: Vec<TemplateVar>,
+    /// After building, check that all outputs are newer than the project files and fail otherwise
+    #[arg(long)]
+    pub verify_fresh: bool,
+    /// Render up to N outputs in parallel, TeX still only runs one at a time
+    #[arg(short = 'j', long, value_name = "N", default_value_t = 1)]
+    pub jobs: usize,
+    /// Load the project and print the outputs that would be rendered, without rendering them
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Keep rendering the other outputs after one fails, the errors are reported at the end
+    #[arg(long)]
+    pub keep_going: bool,
     #[clap(flatten)]
     pub stdio: StdioOpts,
 }
@@ -62,6 +116,69 @@ impl From<StdioOpts> for MakeOpts {
     }
 }
 
+/// An inclusive range of songs numbered from 1, eg. `5-10` or just `7`, see `MakeOpts::preview`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SongRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl FromStr for SongRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let num = |s: &str| match s.trim().parse() {
+            Ok(0) | Err(_) => Err(format!("'{}' is not a song number (numbered from 1)", s)),
+            Ok(num) => Ok(num),
+        };
+
+        let (first, last) = match s.split_once('-') {
+            Some((first, last)) => (num(first)?, num(last)?),
+            None => (num(s)?, num(s)?),
+        };
+        if first > last {
+            return Err(format!(
+                "Invalid range '{}', {} is after {}",
+                s, first, last
+            ));
+        }
+
+        Ok(Self { first, last })
+    }
+}
+
+impl fmt::Display for SongRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
+/// A variable passed to templates from the command line, see `MakeOpts::define`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TemplateVar {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for TemplateVar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("'{}' is not in the KEY=VALUE form", s))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("Missing variable name in '{}'", s));
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
 pub mod verbosity {
     pub const QUIET: u8 = 0;
     pub const NORMAL: u8 = 1;
@@ -76,6 +193,9 @@ pub mod keeplevel {
 
 pub type ParserDiags = Arc<Mutex<Vec<Diagnostic>>>;
 
+/// Durations of pipeline phases in the order they ran, see `App::time()`.
+pub type Timings = Arc<Mutex<Vec<(&'static str, Duration)>>>;
+
 #[derive(Clone, Copy, Debug)]
 #[repr(transparent)]
 pub struct InterruptFlag(pub &'static AtomicBool);
@@ -91,6 +211,28 @@ impl fmt::Display for InterruptError {
 
 impl StdError for InterruptError {}
 
+/// Returned by `App::child_wait()` and `ProcessLines::read_line()` when their deadline has passed.
+#[derive(Clone, Copy, Debug)]
+pub struct DeadlineError;
+
+impl DeadlineError {
+    #[inline]
+    pub fn check(deadline: Option<Instant>) -> Result<(), DeadlineError> {
+        match deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(DeadlineError),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for DeadlineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Time limit exceeded.")
+    }
+}
+
+impl StdError for DeadlineError {}
+
 impl InterruptFlag {
     #[inline]
     pub fn interrupted(&self) -> bool {
@@ -123,6 +265,36 @@ impl InterruptFlag {
             }
         }
     }
+
+    /// Like `channel_recv()`, but gives up after `timeout`.
+    /// Returns `None` on timeout or when the channel is closed.
+    pub fn channel_recv_timeout<T>(
+        &self,
+        rx: &Receiver<T>,
+        timeout: Duration,
+    ) -> Result<Option<T>, InterruptError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(left.min(Duration::from_millis(50))) {
+                Ok(val) => return Ok(Some(val)),
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.check_interrupted()?;
+                    if Instant::now() >= deadline {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Counts of warnings and errors reported through `App`, see `App::diag_summary()`.
+#[derive(Default, Debug)]
+struct DiagCounts {
+    warnings: AtomicU32,
+    errors: AtomicU32,
 }
 
 /// Runtime config and stdio output fns.
@@ -133,11 +305,28 @@ pub struct App {
     post_process: bool,
     /// See `keeplevel` for levels.
     keep_interm: u8,
+    preview: Option<SongRange>,
+    /// Template variables, see `MakeOpts::define`.
+    vars: Vec<TemplateVar>,
+    /// See `MakeOpts::verify_fresh`.
+    verify_fresh: bool,
+    /// See `MakeOpts::jobs`.
+    jobs: usize,
+    /// See `MakeOpts::dry_run`.
+    dry_run: bool,
+    /// See `MakeOpts::keep_going`.
+    keep_going: bool,
 
     // stdio stuff
     term: Term,
+    /// Output collected by a `buffered()` app, printed at once by `flush_buffer()`.
+    stderr_buffer: Option<Arc<Mutex<Vec<u8>>>>,
+    /// See `StdioOpts::log_file`, shared between clones.
+    log_file: Option<Arc<Mutex<File>>>,
     /// See `verbosity` for levels.
     verbosity: u8,
+    diagnostics_format: DiagnosticsFormat,
+    diagnostics_stream: DiagnosticsStream,
     test_mode: bool,
 
     /// bard self exe binary path
@@ -150,6 +339,12 @@ pub struct App {
 
     /// Parser diagnostic messages, these are only collected in `test_mode`.
     parser_diags: Option<ParserDiags>,
+    /// Shared between clones so that all reports are counted.
+    diag_counts: Arc<DiagCounts>,
+    /// Durations of pipeline phases, only collected with `with_timings()`, see `bard bench`.
+    timings: Option<Timings>,
+    /// Use `tex = "none"` regardless of configuration, see `TexTools::initialize()`.
+    tex_disabled: bool,
 }
 
 impl App {
@@ -158,13 +353,26 @@ impl App {
             interrupt,
             post_process: !opts.no_postprocess,
             keep_interm: opts.keep,
+            preview: opts.preview,
+            vars: opts.define.clone(),
+            verify_fresh: opts.verify_fresh,
+            jobs: opts.jobs.max(1),
+            dry_run: opts.dry_run,
+            keep_going: opts.keep_going,
             term: Term::stderr(),
+            stderr_buffer: None,
+            log_file: None,
             verbosity: opts.stdio.verbosity(),
+            diagnostics_format: opts.stdio.diagnostics_format,
+            diagnostics_stream: opts.stdio.diagnostics_stream,
             test_mode: false,
             bard_exe: env::current_exe().expect("Could not get path to bard self binary"),
             self_name: "bard",
             img_cache: ImgCache::new(),
             parser_diags: None,
+            diag_counts: Arc::new(DiagCounts::default()),
+            timings: None,
+            tex_disabled: false,
         }
     }
 
@@ -175,13 +383,26 @@ impl App {
             interrupt,
             post_process,
             keep_interm: keeplevel::ALL,
+            preview: None,
+            vars: vec![],
+            verify_fresh: false,
+            jobs: 1,
+            dry_run: false,
+            keep_going: false,
             term: Term::stderr(),
+            stderr_buffer: None,
+            log_file: None,
             verbosity: 2,
+            diagnostics_format: DiagnosticsFormat::Human,
+            diagnostics_stream: DiagnosticsStream::Stderr,
             test_mode: true,
             bard_exe,
             self_name: "bard",
             img_cache: ImgCache::new(),
             parser_diags: Some(Arc::new(Mutex::new(vec![]))),
+            diag_counts: Arc::new(DiagCounts::default()),
+            timings: None,
+            tex_disabled: false,
         }
     }
 
@@ -213,6 +434,118 @@ impl App {
         self.bard_exe.as_path()
     }
 
+    pub fn preview(&self) -> Option<SongRange> {
+        self.preview
+    }
+
+    pub fn vars(&self) -> &[TemplateVar] {
+        &self.vars
+    }
+
+    pub fn with_var(mut self, key: &str, value: &str) -> Self {
+        self.vars.push(TemplateVar {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn with_diagnostics_format(mut self, format: DiagnosticsFormat) -> Self {
+        self.diagnostics_format = format;
+        self
+    }
+
+    pub fn with_diagnostics_stream(mut self, stream: DiagnosticsStream) -> Self {
+        self.diagnostics_stream = stream;
+        self
+    }
+
+    pub fn verify_fresh(&self) -> bool {
+        self.verify_fresh
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn with_dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+
+    /// Opens the `--log-file` at `path`, which is truncated unless `append` is set.
+    pub fn with_log_file(mut self, path: &Path, append: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)
+            .with_context(|| format!("Could not open log file {:?}", path))?;
+        self.log_file = Some(Arc::new(Mutex::new(file)));
+        Ok(self)
+    }
+
+    pub fn keep_going(&self) -> bool {
+        self.keep_going
+    }
+
+    pub fn with_keep_going(mut self) -> Self {
+        self.keep_going = true;
+        self
+    }
+
+    pub fn with_verify_fresh(mut self) -> Self {
+        self.verify_fresh = true;
+        self
+    }
+
+    pub fn with_preview(mut self, preview: SongRange) -> Self {
+        self.preview = Some(preview);
+        self
+    }
+
+    pub fn with_timings(mut self) -> Self {
+        self.timings = Some(Timings::default());
+        self
+    }
+
+    pub fn tex_disabled(&self) -> bool {
+        self.tex_disabled
+    }
+
+    pub fn with_tex_disabled(mut self) -> Self {
+        self.tex_disabled = true;
+        self
+    }
+
+    /// Runs `f` and records how long it took as `phase` if timings are collected.
+    pub fn time<T>(&self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        if let Some(timings) = self.timings.as_ref() {
+            timings.lock().push((phase, start.elapsed()));
+        }
+        res
+    }
+
+    /// Returns the timings recorded so far and clears them.
+    pub fn take_timings(&self) -> Vec<(&'static str, Duration)> {
+        self.timings
+            .as_ref()
+            .map(|timings| std::mem::take(&mut *timings.lock()))
+            .unwrap_or_default()
+    }
+
     pub fn img_cache(&self) -> &ImgCache {
         &self.img_cache
     }
@@ -231,9 +564,11 @@ impl App {
         self.interrupt
     }
 
-    pub fn child_wait(&self, child: &mut Child) -> Result<ExitStatus> {
+    /// Waits for the `child` to exit, fails with `DeadlineError` once the `deadline` passes.
+    pub fn child_wait(&self, child: &mut Child, deadline: Option<Instant>) -> Result<ExitStatus> {
         loop {
             self.check_interrupted()?;
+            DeadlineError::check(deadline)?;
 
             if let Some(status) = child.try_wait()? {
                 return Ok(status);
@@ -249,8 +584,50 @@ impl App {
         self.term.style().fg(color).bright().bold()
     }
 
-    fn indent_line(line: &str) {
-        eprintln!("             {}", line);
+    /// Returns a clone of the app whose stderr output is collected instead of printed,
+    /// so that outputs rendered in parallel don't interleave their messages.
+    pub fn buffered(&self) -> Self {
+        let mut this = self.clone();
+        this.stderr_buffer = Some(Arc::new(Mutex::new(vec![])));
+        this
+    }
+
+    /// Prints the output collected by a `buffered()` app.
+    pub fn flush_buffer(&self) {
+        if let Some(buffer) = self.stderr_buffer.as_ref() {
+            let data = mem::take(&mut *buffer.lock());
+            let _ = io::stderr().lock().write_all(&data);
+            self.write_log(&data);
+        }
+    }
+
+    pub fn write_stderr(&self, data: &[u8]) {
+        match self.stderr_buffer.as_ref() {
+            Some(buffer) => buffer.lock().extend_from_slice(data),
+            None => {
+                let _ = io::stderr().lock().write_all(data);
+                self.write_log(data);
+            }
+        }
+    }
+
+    /// Writes `data` printed to stderr to the `--log-file`, if any, without color codes.
+    /// Errors are ignored so that a failing log doesn't fail the build.
+    fn write_log(&self, data: &[u8]) {
+        if let Some(file) = self.log_file.as_ref() {
+            let data = String::from_utf8_lossy(data);
+            let _ = file
+                .lock()
+                .write_all(console::strip_ansi_codes(&data).as_bytes());
+        }
+    }
+
+    pub fn eprint(&self, args: fmt::Arguments) {
+        self.write_stderr(fmt::format(args).as_bytes());
+    }
+
+    fn indent_line(&self, line: &str) {
+        self.eprint(format_args!("             {}\n", line));
     }
 
     fn status_inner(&self, kind: impl Display, style: &Style, status: impl Display) {
@@ -258,12 +635,11 @@ impl App {
             return;
         }
 
-        eprint!("{:>12}", style.apply_to(kind));
         let status = format!("{}", status);
         let mut lines = status.lines();
         let first = lines.next().unwrap_or("");
-        eprintln!(" {}", first);
-        lines.for_each(Self::indent_line);
+        self.eprint(format_args!("{:>12} {}\n", style.apply_to(kind), first));
+        lines.for_each(|line| self.indent_line(line));
     }
 
     pub fn indent(&self, status: impl Display) {
@@ -272,7 +648,7 @@ impl App {
         }
 
         let status = format!("{}", status);
-        status.lines().for_each(Self::indent_line);
+        status.lines().for_each(|line| self.indent_line(line));
     }
 
     pub fn status(&self, verb: &str, status: impl Display) {
@@ -285,7 +661,11 @@ impl App {
             return;
         }
 
-        eprint!("{:>12} {}", self.color(Cyan).apply_to(verb), status);
+        self.eprint(format_args!(
+            "{:>12} {}",
+            self.color(Cyan).apply_to(verb),
+            status
+        ));
     }
 
     pub fn success(&self, verb: impl Display) {
@@ -293,10 +673,16 @@ impl App {
     }
 
     pub fn warning(&self, msg: impl Display) {
+        self.diag_counts.warnings.fetch_add(1, Ordering::Relaxed);
         self.status_inner("Warning", &self.color(Yellow), msg);
     }
 
     pub fn error(&self, error: Error) {
+        // Counted and printed regardless of verbosity, like diagnostics
+        self.diag_counts.errors.fetch_add(1, Ordering::Relaxed);
+        if self.print_json(|| Self::error_to_json(&error)) {
+            return;
+        }
         if self.verbosity == 0 {
             return;
         }
@@ -306,7 +692,11 @@ impl App {
 
         if let Some(source) = error.ultimate_source() {
             if source.is::<InterruptError>() {
-                eprintln!("  {} {}", color.apply_to("|"), InterruptError);
+                self.eprint(format_args!(
+                    "  {} {}\n",
+                    color.apply_to("|"),
+                    InterruptError
+                ));
                 return;
             }
         }
@@ -315,17 +705,82 @@ impl App {
         while let Some(err) = source {
             let err_str = format!("{}", err);
             for line in err_str.lines() {
-                eprintln!("  {} {}", color.apply_to("|"), line);
+                self.eprint(format_args!("  {} {}\n", color.apply_to("|"), line));
             }
 
             source = err.source();
         }
     }
 
+    /// The error as printed by `error()` with `--diagnostics-format json`, in the format
+    /// of `Diagnostic::to_json()` without a location or code. The messages of the errors
+    /// that caused it are listed in `causes`.
+    pub fn error_to_json(error: &Error) -> serde_json::Value {
+        let causes: Vec<_> = error.chain().skip(1).map(|err| err.to_string()).collect();
+        serde_json::json!({
+            "path": null,
+            "line": null,
+            "column": null,
+            "severity": "error",
+            "code": null,
+            "message": error.to_string(),
+            "causes": causes,
+        })
+    }
+
     pub fn error_generic(&self, msg: impl Display) {
+        self.diag_counts.errors.fetch_add(1, Ordering::Relaxed);
         self.status_inner("Error", &self.color(Red), msg);
     }
 
+    /// Numbers of warnings and errors reported since the last summary, see `diag_summary()`.
+    pub fn diag_counts(&self) -> (u32, u32) {
+        (
+            self.diag_counts.warnings.load(Ordering::Relaxed),
+            self.diag_counts.errors.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Print the number of warnings and errors reported since the last summary
+    /// and reset the counts.
+    pub fn diag_summary(&self) {
+        let warnings = self.diag_counts.warnings.swap(0, Ordering::Relaxed);
+        let errors = self.diag_counts.errors.swap(0, Ordering::Relaxed);
+        let plural = |n: u32| if n == 1 { "" } else { "s" };
+
+        let color = if warnings == 0 && errors == 0 {
+            Green
+        } else {
+            Yellow
+        };
+        self.status_inner(
+            "Finished",
+            &self.color(color),
+            format!(
+                "with {} warning{}, {} error{}.",
+                warnings,
+                plural(warnings),
+                errors,
+                plural(errors)
+            ),
+        );
+    }
+
+    /// Prints a JSON message as one line with `--diagnostics-format json`, to the `--diagnostics-stream`.
+    /// Returns `false` with the human format.
+    fn print_json(&self, json: impl FnOnce() -> serde_json::Value) -> bool {
+        if self.diagnostics_format != DiagnosticsFormat::Json {
+            return false;
+        }
+        match self.diagnostics_stream {
+            DiagnosticsStream::Stderr => self.eprint(format_args!("{}\n", json())),
+            DiagnosticsStream::Stdout => {
+                let _ = writeln!(io::stdout().lock(), "{}", json());
+            }
+        }
+        true
+    }
+
     pub fn parser_diag(&self, diag: Diagnostic) {
         if self.test_mode {
             self.parser_diags
@@ -335,8 +790,19 @@ impl App {
                 .push(diag.clone());
         }
 
-        if diag.is_error() {
+        if self.print_json(|| diag.to_json()) {
+            // Printed regardless of verbosity, the counts are kept for the summary
+            if diag.is_error() {
+                self.diag_counts.errors.fetch_add(1, Ordering::Relaxed);
+            } else if !diag.is_notice() {
+                self.diag_counts.warnings.fetch_add(1, Ordering::Relaxed);
+            }
+        } else if diag.is_error() {
             self.error_generic(diag);
+        } else if diag.is_notice() {
+            if self.verbosity >= verbosity::VERBOSE {
+                self.status("Note", diag);
+            }
         } else {
             self.warning(diag);
         }
@@ -347,40 +813,57 @@ impl App {
         ps_lines: &mut ProcessLines,
         program: impl AsRef<OsStr>,
         status: &str,
+        deadline: Option<Instant>,
     ) -> Result<()> {
         let program = program.as_ref();
         if self.verbosity == 0 {
             return Ok(());
         }
 
-        let stderr = io::stderr();
-        let mut stderr = stderr.lock();
+        // The output is shown as a single scrolled line with normal verbosity,
+        // which can't be done when buffered, in that case the lines are only collected.
+        let scrolled = self.verbosity == 1 && self.stderr_buffer.is_none();
+        let shown = self.verbosity > 1;
 
-        if self.verbosity == 1 {
+        if scrolled {
             eprintln!()
         }
-        while let Some(line) = ps_lines
-            .read_line(self.interrupt)
-            .with_context(|| format!("Error reading output of program {:?}", program))?
-        {
-            if self.verbosity == 1 {
-                let _ = self.term.clear_last_lines(1);
-                eprint!("{}: ", status);
+        let mut read_lines = || -> Result<()> {
+            while let Some(line) = ps_lines
+                .read_line(self.interrupt, deadline)
+                .with_context(|| format!("Error reading output of program {:?}", program))?
+            {
+                if scrolled {
+                    let _ = self.term.clear_last_lines(1);
+                    eprint!("{}: ", status);
+                    io::stderr().write_all(&line).unwrap();
+                    self.write_log(format!("{}: ", status).as_bytes());
+                    self.write_log(&line);
+                } else if shown && !self.test_mode {
+                    self.write_stderr(&line);
+                } else if shown {
+                    // Workaround for https://github.com/rust-lang/rust/issues/90785
+                    let mut line = String::from_utf8_lossy(&line).to_string();
+                    line.retain(|c| !c.is_control());
+                    self.eprint(format_args!("{}\n", line));
+                }
             }
+            Ok(())
+        };
 
-            if !self.test_mode {
-                stderr.write_all(&line).unwrap();
-            } else {
-                // Workaround for https://github.com/rust-lang/rust/issues/90785
-                let mut line = String::from_utf8_lossy(&line).to_string();
-                line.retain(|c| !c.is_control());
-                eprintln!("{}", line);
-            }
-        }
-        if self.verbosity == 1 {
+        // NB. The scrolled line is cleared even when interrupted,
+        // so that the caller may print the collected output in its place.
+        let res = read_lines();
+        if scrolled {
             let _ = self.term.clear_last_lines(1);
         }
 
-        Ok(())
+        res
+    }
+}
+
+impl DiagSink for App {
+    fn report(&self, diagnostic: Diagnostic) {
+        self.parser_diag(diagnostic);
     }
 }
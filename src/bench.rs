@@ -0,0 +1,157 @@
+//! Repeated builds of a project with timing of the pipeline phases, see `bard bench`.
+//!
+//! TeX and scripts are not run, so that the timings only cover bard itself
+//! and don't depend on external programs.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::app::App;
+use crate::prelude::*;
+use crate::project::Project;
+
+/// Durations of one phase over all the runs.
+#[derive(Debug)]
+pub struct PhaseStats {
+    pub phase: &'static str,
+    pub samples: Vec<Duration>,
+}
+
+impl PhaseStats {
+    fn new(phase: &'static str) -> Self {
+        Self {
+            phase,
+            samples: vec![],
+        }
+    }
+
+    fn secs(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().map(Duration::as_secs_f64)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.secs().sum::<f64>() / self.samples.len().max(1) as f64
+    }
+
+    pub fn min(&self) -> f64 {
+        self.secs().fold(f64::INFINITY, f64::min)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.secs().fold(0.0, f64::max)
+    }
+
+    /// Sample standard deviation, zero with less than two samples.
+    pub fn stddev(&self) -> f64 {
+        let n = self.samples.len();
+        if n < 2 {
+            return 0.0;
+        }
+
+        let mean = self.mean();
+        let var = self.secs().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        var.sqrt()
+    }
+}
+
+#[derive(Debug)]
+pub struct BenchReport {
+    pub runs: u32,
+    pub songs: usize,
+    pub outputs: usize,
+    /// Stats of the phases recorded with `App::time()` followed by the `total` of each run.
+    pub phases: Vec<PhaseStats>,
+}
+
+impl BenchReport {
+    pub fn phase(&self, phase: &str) -> Option<&PhaseStats> {
+        self.phases.iter().find(|stats| stats.phase == phase)
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} runs, {} songs, {} outputs (TeX and scripts not run)",
+            self.runs, self.songs, self.outputs
+        )?;
+        writeln!(
+            f,
+            "{:<12} {:>10} {:>10} {:>10} {:>10}",
+            "phase", "mean", "min", "max", "stddev"
+        )?;
+
+        let ms = |secs: f64| format!("{:.2} ms", secs * 1000.0);
+        for stats in self.phases.iter() {
+            writeln!(
+                f,
+                "{:<12} {:>10} {:>10} {:>10} {:>10}",
+                stats.phase,
+                ms(stats.mean()),
+                ms(stats.min()),
+                ms(stats.max()),
+                ms(stats.stddev())
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads and renders the project at `path` `runs` times after one warm-up run which isn't counted.
+///
+/// `app` has to collect timings, have TeX and post-processing disabled,
+/// see `App::with_timings()` and `App::with_tex_disabled()`.
+/// Durations of phases which run several times in one build, such as rendering of each output, are summed up.
+pub fn bench(app: &App, path: &Path, runs: u32) -> Result<BenchReport> {
+    if app.post_process() || !app.tex_disabled() {
+        bail!("Benchmarks are only run without post-processing, ie. without TeX and scripts.");
+    }
+    if runs == 0 {
+        bail!("The number of runs has to be at least 1.");
+    }
+
+    let mut phases: Vec<PhaseStats> = vec![];
+    let mut total = PhaseStats::new("total");
+    let (mut songs, mut outputs) = (0, 0);
+    for run in 0..=runs {
+        app.check_interrupted()?;
+        app.take_timings();
+
+        let start = Instant::now();
+        let mut project = app.time("load", || Project::new(app, path))?;
+        project.render(app)?;
+        let elapsed = start.elapsed();
+
+        if run == 0 {
+            songs = project.songs().len();
+            outputs = project.settings.output.len();
+            continue;
+        }
+
+        total.samples.push(elapsed);
+        let timings = app.take_timings();
+        for (phase, _) in timings.iter() {
+            if !phases.iter().any(|stats| stats.phase == *phase) {
+                phases.push(PhaseStats::new(phase));
+            }
+        }
+        for stats in phases.iter_mut() {
+            let sum = timings
+                .iter()
+                .filter(|(phase, _)| *phase == stats.phase)
+                .map(|(_, duration)| *duration)
+                .sum();
+            stats.samples.push(sum);
+        }
+    }
+    phases.push(total);
+
+    Ok(BenchReport {
+        runs,
+        songs,
+        outputs,
+        phases,
+    })
+}
@@ -1,19 +1,28 @@
 //! AST of a bard songbook
 
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+use std::{fs, iter, mem};
 
 use image::image_dimensions;
-use serde::Serialize;
+use lexical_sort::lexical_cmp;
+use serde::{Deserialize, Serialize};
+use strum::Display;
 
-use crate::music::Notation;
+use crate::app::App;
+use crate::music::{self, Chromatic, Instrument, Notation, Spelling};
 use crate::prelude::*;
-use crate::project::Settings;
-use crate::util::{sort_lexical_by, BStr, ImgCache};
+use crate::project::{book_collator, AlternateChords, CapoChords, Settings};
+use crate::util::collation::Collator;
+use crate::util::lilypond::{self, LilypondNotFoundError};
+use crate::util::qr::QrCode;
+use crate::util::{BStr, ImgCache};
 
 pub mod version;
 mod xml;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type")]
 pub enum Block {
     #[serde(rename = "b-verse")]
@@ -27,12 +36,14 @@ pub enum Block {
     /// An HTML block contains inlines which can only be `Text`, `HtmlTag`, or `Break`.
     #[serde(rename = "b-html-block")]
     HtmlBlock(Inlines),
+    #[serde(rename = "b-lilypond")]
+    Lilypond(Lilypond),
 }
 
 impl Block {
     pub fn chorus_num(&self) -> Option<u32> {
         if let Self::Verse(Verse {
-            label: VerseLabel::Chorus(num),
+            label: VerseLabel::Chorus(num) | VerseLabel::NamedChorus { num, .. },
             ..
         }) = self
         {
@@ -42,18 +53,66 @@ impl Block {
         }
     }
 
+    /// The number and name of a chorus named by a `{chorus: name}` annotation.
+    pub fn chorus_name(&self) -> Option<(u32, &BStr)> {
+        if let Self::Verse(Verse {
+            label:
+                VerseLabel::NamedChorus {
+                    num: Some(num),
+                    name,
+                },
+            ..
+        }) = self
+        {
+            Some((*num, name))
+        } else {
+            None
+        }
+    }
+
     /// Used to remove chorus numbers in case there's one chorus.
     pub fn remove_chorus_num(&mut self) {
         if let Self::Verse(verse) = self {
-            if let VerseLabel::Chorus(num) = &mut verse.label {
+            if let VerseLabel::Chorus(num) | VerseLabel::NamedChorus { num, .. } = &mut verse.label
+            {
                 *num = None;
             }
 
-            verse
-                .paragraphs
-                .iter_mut()
-                .flat_map(|p| p.iter_mut())
-                .for_each(Inline::remove_chorus_num);
+            verse.for_each_chorus_ref(&mut |cr| cr.num = None);
+        }
+    }
+
+    /// Sets the name of chorus references to the chorus of the same number in `names`, if any.
+    pub fn name_chorus_refs(&mut self, names: &[(u32, BStr)]) {
+        if let Self::Verse(verse) = self {
+            verse.for_each_chorus_ref(&mut |cr| {
+                cr.name = names
+                    .iter()
+                    .find(|(num, _)| Some(*num) == cr.num)
+                    .map(|(_, name)| name.clone());
+            });
+        }
+    }
+
+    fn chords_only(&self) -> Self {
+        match self {
+            Self::Verse(verse) => Self::Verse(verse.chords_only()),
+            other => other.clone(),
+        }
+    }
+
+    fn ascii_only(&self) -> Self {
+        match self {
+            Self::Verse(verse) => Self::Verse(verse.ascii_only()),
+            Self::BulletList(list) => Self::BulletList(BulletList {
+                items: list
+                    .items
+                    .iter()
+                    .map(|item| ascii_punctuation(item))
+                    .collect(),
+            }),
+            Self::HtmlBlock(inlines) => Self::HtmlBlock(inlines.ascii_only()),
+            other => other.clone(),
         }
     }
 
@@ -70,10 +129,17 @@ impl Block {
             _ => None,
         }
     }
+
+    fn lilypond_mut(&mut self) -> Option<&mut Lilypond> {
+        match self {
+            Self::Lilypond(lilypond) => Some(lilypond),
+            _ => None,
+        }
+    }
 }
 
 /// Needed for Inline enum tagging in JSON and similar...
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Inlines {
     pub inlines: Box<[Inline]>,
 }
@@ -83,8 +149,14 @@ impl Inlines {
         Self { inlines }
     }
 
-    fn remove_chorus_num(&mut self) {
-        self.inlines.iter_mut().for_each(Inline::remove_chorus_num);
+    fn for_each_chorus_ref(&mut self, f: &mut dyn FnMut(&mut ChorusRef)) {
+        self.inlines
+            .iter_mut()
+            .for_each(|inline| inline.for_each_chorus_ref(f));
+    }
+
+    fn ascii_only(&self) -> Self {
+        Self::new(self.inlines.iter().map(Inline::ascii_only).collect())
     }
 }
 
@@ -102,7 +174,7 @@ impl AsRef<[Inline]> for Inlines {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
 pub enum Inline {
@@ -110,6 +182,9 @@ pub enum Inline {
     Text { text: BStr },
     #[serde(rename = "i-chord")]
     Chord(Chord),
+    /// A line of chords without lyrics, eg. an instrumental intro, see `Verse::group_chord_lines()`.
+    #[serde(rename = "i-chord-sequence")]
+    ChordSequence(ChordSequence),
     /// All line breaks are considered hard breaks
     #[serde(rename = "i-break")]
     Break,
@@ -125,6 +200,8 @@ pub enum Inline {
     ChorusRef(ChorusRef),
     #[serde(rename = "i-tag")]
     HtmlTag(HtmlTag),
+    #[serde(rename = "i-nav")]
+    Nav { marker: NavMarker },
 
     /// Only used internally by the parser to apply transposition.
     /// Removed from the resulting AST, except in tests where this
@@ -142,6 +219,37 @@ impl Inline {
         matches!(self, Self::Break)
     }
 
+    /// Removes trailing whitespace of a line of inlines, including text in a chord at the end of the line.
+    fn trim_end_line(line: &mut Vec<Inline>) {
+        while let Some(last) = line.last_mut() {
+            match last {
+                Self::Text { text } if text.trim_end().is_empty() => {
+                    line.pop();
+                }
+                Self::Text { text } => {
+                    *text = text.trim_end().into();
+                    break;
+                }
+                Self::Chord(chord) => {
+                    let mut inlines = chord.inlines.to_vec();
+                    Self::trim_end_line(&mut inlines);
+                    chord.inlines = inlines.into();
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// `true` for line breaks and whitespace-only text.
+    fn is_blank(&self) -> bool {
+        match self {
+            Self::Break => true,
+            Self::Text { text } => text.trim().is_empty(),
+            _ => false,
+        }
+    }
+
     pub fn is_xpose(&self) -> bool {
         matches!(self, Self::Transpose(..))
     }
@@ -153,16 +261,108 @@ impl Inline {
         }
     }
 
-    fn remove_chorus_num(&mut self) {
+    fn for_each_chorus_ref(&mut self, f: &mut dyn FnMut(&mut ChorusRef)) {
+        match self {
+            Inline::Chord(c) => c.for_each_chorus_ref(f),
+            Inline::Emph(e) => e.for_each_chorus_ref(f),
+            Inline::Strong(s) => s.for_each_chorus_ref(f),
+            Inline::ChorusRef(cr) => f(cr),
+            _ => {}
+        }
+    }
+
+    /// Lyrics are dropped, chords are kept without their lyrics as baseline chords.
+    /// Elements other than text are kept as well.
+    fn chords_only(&self) -> Option<Self> {
+        match self {
+            Self::Chord(chord) => Some(Self::Chord(Chord::new(
+                chord.chord.clone(),
+                chord.alt_chord.clone(),
+                chord.alternate.clone(),
+                chord.backticks,
+                true,
+                vec![],
+            ))),
+            Self::ChordSequence(..)
+            | Self::ChorusRef(..)
+            | Self::Image(..)
+            | Self::HtmlTag(..)
+            | Self::Nav { .. } => Some(self.clone()),
+            _ => None,
+        }
+    }
+
+    /// Chord accidentals, smart punctuation, and navigation markers are replaced with ASCII.
+    fn ascii_only(&self) -> Self {
+        match self {
+            Self::Text { text } => Self::text(ascii_punctuation(text)),
+            Self::Chord(chord) => Self::Chord(chord.ascii_only()),
+            Self::ChordSequence(seq) => Self::ChordSequence(ChordSequence {
+                chords: seq.chords.iter().map(Chord::ascii_only).collect(),
+            }),
+            Self::Emph(inlines) => Self::Emph(inlines.ascii_only()),
+            Self::Strong(inlines) => Self::Strong(inlines.ascii_only()),
+            Self::Link(link) => Self::Link(Link::new(
+                link.url.clone(),
+                ascii_punctuation(&link.title),
+                ascii_punctuation(&link.text),
+            )),
+            Self::Nav { marker } => Self::text(marker.ascii_text()),
+            other => other.clone(),
+        }
+    }
+
+    fn select_alternate(&mut self, which: AlternateChords) {
         match self {
-            Inline::Chord(c) => c.remove_chorus_num(),
-            Inline::Emph(e) => e.remove_chorus_num(),
-            Inline::Strong(s) => s.remove_chorus_num(),
-            Inline::ChorusRef(cr) => cr.num = None,
+            Self::Chord(chord) => chord.select_alternate(which),
+            Self::ChordSequence(seq) => seq
+                .chords
+                .iter_mut()
+                .for_each(|chord| chord.select_alternate(which)),
+            Self::Emph(inlines) | Self::Strong(inlines) => inlines
+                .inlines
+                .iter_mut()
+                .for_each(|inline| inline.select_alternate(which)),
             _ => {}
         }
     }
 
+    /// Applies `f` to chord sets of chords in the inline, see `Chord::map_chord_sets()`.
+    fn map_chord_sets<F: FnMut(&mut BStr)>(&mut self, f: &mut F) {
+        match self {
+            Self::Chord(chord) => chord.map_chord_sets(f),
+            Self::ChordSequence(seq) => seq
+                .chords
+                .iter_mut()
+                .for_each(|chord| chord.map_chord_sets(f)),
+            Self::Emph(inlines) | Self::Strong(inlines) => inlines
+                .inlines
+                .iter_mut()
+                .for_each(|inline| inline.map_chord_sets(f)),
+            _ => {}
+        }
+    }
+
+    fn collect_chords<'s>(&'s self, chords: &mut Vec<&'s Chord>) {
+        match self {
+            Self::Chord(chord) => chords.push(chord),
+            Self::ChordSequence(seq) => chords.extend(seq.chords.iter()),
+            Self::Emph(inlines) | Self::Strong(inlines) => inlines
+                .inlines
+                .iter()
+                .for_each(|inline| inline.collect_chords(chords)),
+            _ => {}
+        }
+    }
+
+    /// `true` for chords without lyrics, see `Verse::group_chord_lines()`.
+    fn is_lyricless_chord(&self) -> bool {
+        match self {
+            Self::Chord(chord) => chord.inlines.iter().all(Inline::is_blank),
+            _ => false,
+        }
+    }
+
     fn image(&self) -> Option<&Image> {
         match self {
             Self::Image(image) => Some(image),
@@ -178,10 +378,13 @@ impl Inline {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Chord {
     pub chord: BStr,
     pub alt_chord: Option<BStr>,
+    /// A simpler alternate of the chord set, written as `` `[Cmaj7|C]` ``.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alternate: Option<ChordAlternate>,
     pub backticks: usize,
     pub baseline: bool,
     pub inlines: Box<[Inline]>,
@@ -191,6 +394,7 @@ impl Chord {
     pub fn new(
         chord: BStr,
         alt_chord: Option<BStr>,
+        alternate: Option<ChordAlternate>,
         backticks: usize,
         baseline: bool,
         inlines: Vec<Inline>,
@@ -198,18 +402,125 @@ impl Chord {
         Self {
             chord,
             alt_chord,
+            alternate,
             backticks,
             baseline,
             inlines: inlines.into(),
         }
     }
 
-    fn remove_chorus_num(&mut self) {
-        self.inlines.iter_mut().for_each(Inline::remove_chorus_num);
+    fn for_each_chorus_ref(&mut self, f: &mut dyn FnMut(&mut ChorusRef)) {
+        self.inlines
+            .iter_mut()
+            .for_each(|inline| inline.for_each_chorus_ref(f));
+    }
+
+    fn ascii_only(&self) -> Self {
+        Self::new(
+            music::ascii_accidentals(&self.chord).into(),
+            self.alt_chord
+                .as_deref()
+                .map(|alt_chord| music::ascii_accidentals(alt_chord).into()),
+            self.alternate.as_ref().map(ChordAlternate::ascii_only),
+            self.backticks,
+            self.baseline,
+            self.inlines.iter().map(Inline::ascii_only).collect(),
+        )
+    }
+
+    /// Replaces the chord with its alternate, if any, according to `which`.
+    /// With `AlternateChords::Both` the alternate follows the chord in parentheses.
+    fn select_alternate(&mut self, which: AlternateChords) {
+        let alternate = match self.alternate.take() {
+            Some(alternate) => alternate,
+            None => return,
+        };
+
+        match which {
+            AlternateChords::Primary => self.alternate = Some(alternate),
+            AlternateChords::Alternate => {
+                self.chord = alternate.chord;
+                self.alt_chord = alternate.alt_chord;
+            }
+            AlternateChords::Both => {
+                self.chord = format!("{} ({})", self.chord, alternate.chord).into();
+                if let (Some(alt_chord), Some(alt_alternate)) =
+                    (self.alt_chord.as_mut(), alternate.alt_chord)
+                {
+                    *alt_chord = format!("{} ({})", alt_chord, alt_alternate).into();
+                }
+            }
+        }
+    }
+
+    /// Applies `f` to the chord set, its alternate, and the alt chords, eg. to transpose them.
+    /// Lyrics in `inlines` are left as they are.
+    fn map_chord_sets<F: FnMut(&mut BStr)>(&mut self, f: &mut F) {
+        let chord_sets = iter::once(&mut self.chord)
+            .chain(self.alt_chord.as_mut())
+            .chain(self.alternate.iter_mut().flat_map(|alternate| {
+                iter::once(&mut alternate.chord).chain(alternate.alt_chord.as_mut())
+            }));
+        for chord_set in chord_sets {
+            f(chord_set);
+        }
+    }
+}
+
+/// Transposes a chord set by `by` semitones in place, spelled in `notation`, including bass notes of slash chords.
+/// Black keys are spelled according to `spelling`.
+///
+/// Chord sets with a part which can't be parsed in `notation`, such as alt chords in another notation
+/// or `N.C.`, are left as they are.
+fn transpose_chord_set(chord_set: &mut BStr, by: i32, notation: Notation, spelling: Spelling) {
+    if let Ok(transposed) = music::transpose_spelled(chord_set, by, notation, notation, spelling) {
+        *chord_set = transposed.into();
+    }
+}
+
+/// Replaces the chord set with the shapes fingered with a capo at the `capo` fret, see `music::capo_shapes()`,
+/// or adds them in parentheses with `CapoChords::Both`. Chord sets which can't be parsed are left as they are.
+fn capo_chord_set(chord_set: &mut BStr, capo: u32, which: CapoChords, notation: Notation) {
+    let shapes: BStr = match music::capo_shapes(chord_set, capo, notation) {
+        Ok(shapes) => shapes.into(),
+        Err(_) => return,
+    };
+
+    match which {
+        CapoChords::Sounding => {}
+        CapoChords::Shapes => *chord_set = shapes,
+        CapoChords::Both => *chord_set = format!("{} ({})", chord_set, shapes).into(),
+    }
+}
+
+/// Chords of a line without lyrics, see `Verse::group_chord_lines()`.
+/// The chords have no `inlines`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChordSequence {
+    pub chords: Box<[Chord]>,
+}
+
+/// The alternate of a chord set, see `Chord::alternate`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChordAlternate {
+    pub chord: BStr,
+    /// The alternate transposed like `Chord::alt_chord`.
+    pub alt_chord: Option<BStr>,
+}
+
+impl ChordAlternate {
+    fn ascii_only(&self) -> Self {
+        Self {
+            chord: music::ascii_accidentals(&self.chord).into(),
+            alt_chord: self
+                .alt_chord
+                .as_deref()
+                .map(|alt_chord| music::ascii_accidentals(alt_chord).into()),
+        }
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Link {
     pub url: BStr,
     pub title: BStr,
@@ -222,7 +533,7 @@ impl Link {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Image {
     pub path: BStr,
     pub title: BStr,
@@ -274,10 +585,31 @@ impl Image {
     }
 }
 
-#[derive(Serialize, Debug)]
+/// A LilyPond snippet written in a ```` ```lilypond ```` code block, see `Book::render_lilypond()`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Lilypond {
+    pub source: BStr,
+    /// The rendered snippet, resolved during book postprocessing,
+    /// left unset if LilyPond is not available.
+    pub image: Option<Image>,
+}
+
+impl Lilypond {
+    pub fn new(source: BStr) -> Self {
+        Self {
+            source,
+            image: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ChorusRef {
     pub num: Option<u32>,
     pub prefix_space: BStr,
+    /// Name of the referenced chorus if it's named by a `{chorus: name}` annotation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<BStr>,
 }
 
 impl ChorusRef {
@@ -285,18 +617,98 @@ impl ChorusRef {
         Self {
             num,
             prefix_space: if prefix_space { " ".into() } else { "".into() },
+            name: None,
         }
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct HtmlTag {
     pub name: BStr,
     pub attrs: BTreeMap<BStr, BStr>,
 }
 
+/// Musical navigation markers, ie. repeat signs, segno, coda, and the like.
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum NavMarker {
+    RepeatStart,
+    RepeatEnd,
+    Segno,
+    Coda,
+    ToCoda,
+    Fine,
+    DaCapo,
+    DalSegno,
+    DaCapoAlFine,
+    DaCapoAlCoda,
+    DalSegnoAlFine,
+    DalSegnoAlCoda,
+}
+
+impl NavMarker {
+    /// Parse a marker from the content of `{...}` in the source, eg. `coda` or `D.S. al Coda`,
+    /// or from the repeat signs `|:` and `:|`.
+    pub fn parse(src: &str) -> Option<Self> {
+        let src = src.to_lowercase().replace('.', "");
+        let src: Vec<_> = src.split_whitespace().collect();
+
+        Some(match src.join(" ").as_str() {
+            "|:" => Self::RepeatStart,
+            ":|" => Self::RepeatEnd,
+            "segno" => Self::Segno,
+            "coda" => Self::Coda,
+            "to coda" => Self::ToCoda,
+            "fine" => Self::Fine,
+            "dc" | "da capo" => Self::DaCapo,
+            "ds" | "dal segno" => Self::DalSegno,
+            "dc al fine" | "da capo al fine" => Self::DaCapoAlFine,
+            "dc al coda" | "da capo al coda" => Self::DaCapoAlCoda,
+            "ds al fine" | "dal segno al fine" => Self::DalSegnoAlFine,
+            "ds al coda" | "dal segno al coda" => Self::DalSegnoAlCoda,
+            _ => return None,
+        })
+    }
+
+    /// Plain text of the marker, used instead of music symbols in outputs with `ascii_only` set.
+    pub fn ascii_text(self) -> &'static str {
+        match self {
+            Self::RepeatStart => "|:",
+            Self::RepeatEnd => ":|",
+            Self::Segno => "Segno",
+            Self::Coda => "Coda",
+            Self::ToCoda => "To Coda",
+            Self::Fine => "Fine",
+            Self::DaCapo => "D.C.",
+            Self::DalSegno => "D.S.",
+            Self::DaCapoAlFine => "D.C. al Fine",
+            Self::DaCapoAlCoda => "D.C. al Coda",
+            Self::DalSegnoAlFine => "D.S. al Fine",
+            Self::DalSegnoAlCoda => "D.S. al Coda",
+        }
+    }
+}
+
+/// Replaces typographic quotes, dashes, and ellipses produced by smart punctuation
+/// with their ASCII equivalents.
+fn ascii_punctuation(text: &str) -> BStr {
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '‘' | '’' => res.push('\''),
+            '“' | '”' => res.push('"'),
+            '–' => res.push_str("--"),
+            '—' => res.push_str("---"),
+            '…' => res.push_str("..."),
+            c => res.push(c),
+        }
+    }
+    res.into()
+}
+
 /// Transposition extensions. See Comment in `Inline`.
-#[derive(Serialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 pub enum Transpose {
     #[serde(rename = "t-transpose")]
     Transpose(i32),
@@ -311,38 +723,171 @@ pub enum Transpose {
     AltNone,
 }
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum VerseLabel {
     Verse(u32),
     Chorus(Option<u32>),
+    /// A chorus named by a `{chorus: name}` annotation, shown instead of the book's `chorus_label`.
+    NamedChorus {
+        num: Option<u32>,
+        name: BStr,
+    },
     Custom(BStr),
     None {},
 }
 
 impl VerseLabel {
-    fn is_some(&self) -> bool {
+    pub fn is_some(&self) -> bool {
         !matches!(self, Self::None {})
     }
 }
 
 pub type Paragraph = Box<[Inline]>;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Verse {
     pub label: VerseLabel,
     pub paragraphs: Vec<Paragraph>,
+    /// How many times the verse is sung if more than once, set by a `{repeat: N}` annotation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<u32>,
 }
 
 impl Verse {
     pub fn new(label: VerseLabel, paragraphs: Vec<Paragraph>) -> Self {
-        Self { label, paragraphs }
+        Self {
+            label,
+            paragraphs,
+            repeat: None,
+        }
+    }
+
+    pub fn with_repeat(mut self, repeat: Option<u32>) -> Self {
+        self.repeat = repeat;
+        self
     }
 
     pub fn is_empty(&self) -> bool {
         self.paragraphs.is_empty()
     }
 
+    /// Lines without any chords are removed, chords on the same line are separated by a space.
+    fn chords_only(&self) -> Self {
+        let paragraphs = self
+            .paragraphs
+            .iter()
+            .filter_map(|para| {
+                let mut res = vec![];
+                for line in para.split(Inline::is_break) {
+                    let mut line = line.iter().filter_map(Inline::chords_only).peekable();
+                    if line.peek().is_none() {
+                        continue;
+                    }
+
+                    if !res.is_empty() {
+                        res.push(Inline::Break);
+                    }
+                    for (i, inline) in line.enumerate() {
+                        if i > 0 {
+                            res.push(Inline::text(" "));
+                        }
+                        res.push(inline);
+                    }
+                }
+
+                (!res.is_empty()).then(|| res.into())
+            })
+            .collect();
+
+        Self::new(self.label.clone(), paragraphs).with_repeat(self.repeat)
+    }
+
+    fn ascii_only(&self) -> Self {
+        let label = match &self.label {
+            VerseLabel::Custom(label) => VerseLabel::Custom(ascii_punctuation(label)),
+            VerseLabel::NamedChorus { num, name } => VerseLabel::NamedChorus {
+                num: *num,
+                name: ascii_punctuation(name),
+            },
+            label => label.clone(),
+        };
+        let paragraphs = self
+            .paragraphs
+            .iter()
+            .map(|para| para.iter().map(Inline::ascii_only).collect())
+            .collect();
+
+        Self::new(label, paragraphs).with_repeat(self.repeat)
+    }
+
+    /// Trims whitespace at the end of each line and removes blank lines,
+    /// a blank line inside a paragraph splits it in two.
+    ///
+    /// Lines are considered blank if they only contain whitespace, eg. non-breaking spaces
+    /// from copy-pasted lyrics, which Markdown doesn't treat as blank lines.
+    /// Empty lines, ie. explicit line breaks such as a `\` below a verse label, are kept.
+    pub fn normalize_whitespace(&mut self) {
+        fn flush(lines: &mut Vec<Vec<Inline>>, paragraphs: &mut Vec<Paragraph>) {
+            if lines.iter().any(|line| !line.is_empty()) {
+                paragraphs.push(lines.join(&Inline::Break).into());
+            }
+            lines.clear();
+        }
+
+        let mut paragraphs = vec![];
+        for para in mem::take(&mut self.paragraphs) {
+            let mut lines = vec![];
+            for line in para.split(Inline::is_break) {
+                if !line.is_empty() && line.iter().all(Inline::is_blank) {
+                    flush(&mut lines, &mut paragraphs);
+                    continue;
+                }
+
+                let mut line = line.to_vec();
+                Inline::trim_end_line(&mut line);
+                lines.push(line);
+            }
+
+            flush(&mut lines, &mut paragraphs);
+        }
+
+        self.paragraphs = paragraphs;
+    }
+
+    /// Replaces lines which only contain chords without lyrics, such as an instrumental intro
+    /// written as `` `Am` `C` `G` ``, with a `ChordSequence`, so that the chords are laid out
+    /// next to each other rather than above empty space.
+    pub fn group_chord_lines(&mut self) {
+        for para in self.paragraphs.iter_mut() {
+            let lines: Vec<_> = para
+                .split(Inline::is_break)
+                .map(|line| {
+                    let is_chord_line = line.iter().any(Inline::is_lyricless_chord)
+                        && line
+                            .iter()
+                            .all(|inline| inline.is_lyricless_chord() || inline.is_blank());
+                    if !is_chord_line {
+                        return line.to_vec();
+                    }
+
+                    let chords = line
+                        .iter()
+                        .filter_map(|inline| match inline {
+                            Inline::Chord(chord) => Some(Chord {
+                                inlines: Box::default(),
+                                ..chord.clone()
+                            }),
+                            _ => None,
+                        })
+                        .collect();
+                    vec![Inline::ChordSequence(ChordSequence { chords })]
+                })
+                .collect();
+            *para = lines.join(&Inline::Break).into();
+        }
+    }
+
     fn inlines(&self) -> impl Iterator<Item = &Inline> {
         self.paragraphs.iter().flat_map(|p| p.iter())
     }
@@ -350,22 +895,425 @@ impl Verse {
     fn inlines_mut(&mut self) -> impl Iterator<Item = &mut Inline> {
         self.paragraphs.iter_mut().flat_map(|p| p.iter_mut())
     }
+
+    fn for_each_chorus_ref(&mut self, f: &mut dyn FnMut(&mut ChorusRef)) {
+        self.inlines_mut()
+            .for_each(|inline| inline.for_each_chorus_ref(f));
+    }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BulletList {
     pub items: Box<[BStr]>,
 }
 
-#[derive(Serialize, Debug)]
+/// Song-level settings, set using the `!name=value` extension in the song's content.
+#[derive(Clone, Debug)]
+pub struct SongMeta {
+    /// Whether the song should be listed in the index (ToC).
+    pub index: bool,
+    /// Whether the song should not be split across pages if it fits on one.
+    pub keep_together: bool,
+}
+
+impl Default for SongMeta {
+    fn default() -> Self {
+        Self {
+            index: true,
+            keep_together: false,
+        }
+    }
+}
+
+/// Authorship and copyright of a song, set using annotations such as `{author: name}`.
+///
+/// Each of the fields may be given multiple times, eg. for songs with multiple authors.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Attribution {
+    pub authors: Vec<BStr>,
+    pub composers: Vec<BStr>,
+    pub arrangers: Vec<BStr>,
+    /// Copyright notice lines.
+    pub copyright: Vec<BStr>,
+    /// CCLI song number, see `Format::Licenses`.
+    pub ccli: Option<BStr>,
+    /// License under which the song is used, eg. `CCLI` or `Public Domain`.
+    pub license: Option<BStr>,
+}
+
+impl Attribution {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Adds a value of the attribution `field`, returns `false` if the field is not recognized.
+    ///
+    /// Single-valued fields (`ccli` and `license`) are overwritten.
+    pub fn add(&mut self, field: &str, value: impl Into<BStr>) -> bool {
+        let list = match field {
+            "author" => &mut self.authors,
+            "composer" => &mut self.composers,
+            "arranger" => &mut self.arrangers,
+            "copyright" => &mut self.copyright,
+            "ccli" => {
+                self.ccli = Some(value.into());
+                return true;
+            }
+            "license" => {
+                self.license = Some(value.into());
+                return true;
+            }
+            _ => return false,
+        };
+        list.push(value.into());
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Song {
     pub title: BStr,
+    /// Identifier derived from the title for use in file names and URLs,
+    /// unique within the book, see `slug()` and `Book::dedup_slugs()`.
+    pub slug: BStr,
     pub subtitles: Box<[BStr]>,
     pub blocks: Vec<Block>,
     pub notation: Notation,
+    pub index: bool,
+    /// Layout hint to not split the song across pages, see `SongMeta::keep_together`.
+    pub keep_together: bool,
+    /// Glossary entries annotated in this song with `{gloss: term = definition}`.
+    pub glossary: Vec<GlossEntry>,
+    pub attribution: Attribution,
+    /// Link to a recording or a related page, annotated with `{link: url}` or `{audio: url}`.
+    pub link: Option<BStr>,
+    /// QR code of `link`, only set in outputs with `qr_codes` enabled, see `Song::with_qr()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr: Option<Image>,
+    /// Capo position annotated with `{capo: N}`. In outputs, this is the capo the chords
+    /// are shown relative to, if any, see `Song::with_capo()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capo: Option<u32>,
+    /// Key of the song annotated with `{key: G}`, the chords are relative to it
+    /// in the Nashville and Roman notations, see `Song::with_notation()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<BStr>,
+    /// Label of the book section starting with this song, set on the first song
+    /// of each section defined in the `songs` setting, see `Book::add_songs()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<BStr>,
 }
 
 impl Song {
+    /// Directory of QR code images relative to the output directory, see `Song::with_qr()`.
+    pub const QR_DIR: &'static str = "qr";
+
+    /// Copy of the song with lyrics removed, keeping structure and chords,
+    /// used for outputs with `chords_only` set.
+    pub fn chords_only(&self) -> Self {
+        Self {
+            blocks: self.blocks.iter().map(Block::chords_only).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Copy of the song with chord accidentals, smart punctuation, and navigation markers
+    /// replaced with plain ASCII, used for outputs with `ascii_only` set.
+    pub fn ascii_only(&self) -> Self {
+        Self {
+            title: ascii_punctuation(&self.title),
+            subtitles: self
+                .subtitles
+                .iter()
+                .map(|subtitle| ascii_punctuation(subtitle))
+                .collect(),
+            blocks: self.blocks.iter().map(Block::ascii_only).collect(),
+            glossary: self
+                .glossary
+                .iter()
+                .map(|entry| {
+                    GlossEntry::new(
+                        ascii_punctuation(&entry.term),
+                        ascii_punctuation(&entry.definition),
+                    )
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Copy of the song with chords replaced by their alternates according to `which`,
+    /// used for outputs with `alternate_chords` set.
+    pub fn with_alternate_chords(&self, which: AlternateChords) -> Self {
+        let mut song = self.clone();
+        for verse in song.blocks.iter_mut().filter_map(Block::verse_mut) {
+            verse
+                .paragraphs
+                .iter_mut()
+                .flat_map(|para| para.iter_mut())
+                .for_each(|inline| inline.select_alternate(which));
+        }
+        song
+    }
+
+    /// Copy of the song with chords transposed by `by` semitones, used for outputs with `transpose` set.
+    /// Black keys are spelled with sharps when transposing up and with flats when transposing down, see `Spelling::for_direction()`.
+    /// Songs in the Nashville and Roman notations are left as they are, see `is_transposable()`.
+    pub fn transposed(&self, by: i32) -> Self {
+        self.transposed_spelled(by, Spelling::for_direction(by))
+    }
+
+    /// Copy of the song transposed from its `{key: ...}` to the tonic `to_key`, used for outputs with `to_key` set,
+    /// eg. a song in `Em` is in `Gm` with `to_key = "G"`. Chords are transposed by at most a tritone up or down
+    /// and spelled according to the key signature of the new key, see `Spelling::for_key()`.
+    ///
+    /// Returns `None` if the song has no key. Songs in the Nashville and Roman notations are left as they are.
+    pub fn transposed_to(&self, to_key: &str) -> Option<Self> {
+        if !self.is_transposable() {
+            return Some(self.clone());
+        }
+
+        let key = self.key.as_deref()?;
+        let from = music::parse_key(key, self.notation)?;
+        let to = music::parse_key(to_key, self.notation)?;
+        let minor = key.trim().ends_with('m');
+        let tonic = to_key.trim().trim_end_matches('m');
+        let spelling = Spelling::for_key(tonic, minor, self.notation.key_notation())?;
+
+        let by = i32::from(to - from);
+        let by = if by > 6 { by - 12 } else { by };
+        Some(self.transposed_spelled(by, spelling))
+    }
+
+    fn transposed_spelled(&self, by: i32, spelling: Spelling) -> Self {
+        let mut song = self.clone();
+        if !self.is_transposable() {
+            return song;
+        }
+
+        let notation = self.notation;
+        song.map_chord_sets(|chord_set| transpose_chord_set(chord_set, by, notation, spelling));
+        if let Some(key) = song.key.as_mut() {
+            transpose_chord_set(key, by, notation, spelling);
+        }
+        song
+    }
+
+    /// Copy of the song with chords shown as played with a capo according to `which`, used for outputs with `capo` set,
+    /// eg. with the shapes fingered with the capo, ie. transposed down by the capo position.
+    /// The song's own `{capo: N}` annotation takes precedence over `output_capo`,
+    /// `capo` is set to the position used, or `None` if there's no capo.
+    pub fn with_capo(&self, output_capo: Option<u32>, which: CapoChords) -> Self {
+        let mut song = self.clone();
+        song.capo = self.capo.or(output_capo).filter(|&capo| capo > 0);
+        let capo = match song.capo {
+            Some(capo) if which != CapoChords::Sounding && self.is_transposable() => capo,
+            _ => return song,
+        };
+
+        let notation = self.notation;
+        song.map_chord_sets(|chord_set| capo_chord_set(chord_set, capo, which, notation));
+        song
+    }
+
+    /// Copy of the song with chords converted to the `to` notation, used for outputs with `notation` set.
+    /// Converting between an absolute notation and the Nashville or Roman notation is relative to the song's `key`,
+    /// eg. `G/B` becomes `1/3` in the key of G. Returns `None` if such a conversion is needed and the song has no key.
+    ///
+    /// Chord sets which can't be parsed are left as they are, as is the key, except that it's respelled
+    /// when converting to another absolute notation.
+    pub fn with_notation(&self, to: Notation) -> Option<Self> {
+        let mut song = self.clone();
+        let from = self.notation;
+        if from == to {
+            return Some(song);
+        }
+
+        let by = if from.is_relative() == to.is_relative() {
+            0
+        } else {
+            let tonic = music::parse_key(self.key.as_ref()?, from)?.num() as i32;
+            if to.is_relative() {
+                -tonic
+            } else {
+                tonic
+            }
+        };
+
+        song.map_chord_sets(|chord_set| {
+            if let Ok(converted) = music::transpose(chord_set, by, from, to) {
+                *chord_set = converted.into();
+            }
+        });
+        if let Some(key) = song.key.as_mut().filter(|_| !to.is_relative()) {
+            if let Ok(converted) = music::transpose(key, 0, from.key_notation(), to) {
+                *key = converted.into();
+            }
+        }
+        song.notation = to;
+        Some(song)
+    }
+
+    /// Copy of the song with chord accidentals written as `♯` and `♭`, see `music::unicode_accidentals()`,
+    /// used for outputs with `unicode_accidentals` set. Chords are kept with ASCII accidentals up to this point,
+    /// so this is applied after the other conversions. Alt chords in another notation are left as they are.
+    pub fn with_unicode_accidentals(&self) -> Self {
+        let mut song = self.clone();
+        let notation = self.notation;
+        song.map_chord_sets(|chord_set| {
+            *chord_set = music::unicode_accidentals(chord_set, notation).into();
+        });
+        if let Some(key) = song.key.as_mut() {
+            *key = music::unicode_accidentals(key, notation.key_notation()).into();
+        }
+        song
+    }
+
+    /// Chords in the Nashville and Roman notations are relative to the key, so they're not transposed.
+    fn is_transposable(&self) -> bool {
+        !self.notation.is_relative()
+    }
+
+    /// Applies `f` to all chord sets in the song, see `Chord::map_chord_sets()`.
+    fn map_chord_sets(&mut self, mut f: impl FnMut(&mut BStr)) {
+        for verse in self.blocks.iter_mut().filter_map(Block::verse_mut) {
+            verse
+                .paragraphs
+                .iter_mut()
+                .flat_map(|para| para.iter_mut())
+                .for_each(|inline| inline.map_chord_sets(&mut f));
+        }
+    }
+
+    /// Copy of the song with the QR code image of its `link` in the `qr` directory of `output_dir`,
+    /// used for outputs with `qr_codes` set. Songs without a link are left as they are.
+    /// The image is only written by `write_qr()` when the output is rendered.
+    pub fn with_qr(&self, output_dir: &Path) -> Result<Self> {
+        let mut song = self.clone();
+        let link = match self.link.as_ref() {
+            Some(link) => link,
+            None => return Ok(song),
+        };
+
+        let qr = self.qr_code(link)?;
+        let path = format!("{}/{}.png", Self::QR_DIR, self.slug);
+        let full_path = output_dir.join(&path);
+        let size = qr.image_size();
+        song.qr = Some(Image {
+            path: path.into(),
+            title: link.clone(),
+            class: "qr".into(),
+            width: size,
+            height: size,
+            full_path: Some(full_path),
+        });
+        Ok(song)
+    }
+
+    /// Writes the QR code image set by `with_qr()`, if any.
+    pub fn write_qr(&self) -> Result<()> {
+        let (link, full_path) = match (&self.link, &self.qr) {
+            (
+                Some(link),
+                Some(Image {
+                    full_path: Some(full_path),
+                    ..
+                }),
+            ) => (link, full_path),
+            _ => return Ok(()),
+        };
+
+        if let Some(dir) = full_path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create directory {:?}", dir))?;
+        }
+        self.qr_code(link)?.write_png(full_path)
+    }
+
+    fn qr_code(&self, link: &str) -> Result<QrCode> {
+        QrCode::encode(link.as_bytes()).with_context(|| {
+            format!(
+                "Could not create QR code of the link of song {:?}",
+                self.title
+            )
+        })
+    }
+
+    /// Copy of the song with chord sequences split back into chords separated by spaces,
+    /// used for templates older than AST version 1.19, see `Verse::group_chord_lines()`.
+    pub fn without_chord_sequences(&self) -> Self {
+        let mut song = self.clone();
+        for verse in song.blocks.iter_mut().filter_map(Block::verse_mut) {
+            for para in verse.paragraphs.iter_mut() {
+                let mut inlines = Vec::with_capacity(para.len());
+                for inline in para.iter() {
+                    match inline {
+                        Inline::ChordSequence(seq) => {
+                            for (i, chord) in seq.chords.iter().enumerate() {
+                                if i > 0 {
+                                    inlines.push(Inline::text(" "));
+                                }
+                                inlines.push(Inline::Chord(chord.clone()));
+                            }
+                        }
+                        inline => inlines.push(inline.clone()),
+                    }
+                }
+                *para = inlines.into();
+            }
+        }
+        song
+    }
+
+    /// Copy of the song with LilyPond snippets turned back into preformatted blocks,
+    /// used for templates older than AST version 1.28.
+    pub fn without_lilypond(&self) -> Self {
+        let mut song = self.clone();
+        for block in song.blocks.iter_mut() {
+            if let Block::Lilypond(lilypond) = block {
+                *block = Block::Pre {
+                    text: lilypond.source.clone(),
+                };
+            }
+        }
+        song
+    }
+
+    /// Distinct chords used in the song in order of appearance.
+    ///
+    /// Chord sets are split into individual chords, see `music::split_chords()`.
+    pub fn chords(&self) -> Vec<(Chromatic, &str)> {
+        let mut chord_sets = vec![];
+        self.blocks
+            .iter()
+            .filter_map(Block::verse)
+            .flat_map(Verse::inlines)
+            .for_each(|inline| inline.collect_chords(&mut chord_sets));
+
+        let mut chords: Vec<(Chromatic, &str)> = vec![];
+        for chord_set in chord_sets {
+            for (base, chord) in music::split_chords(&chord_set.chord, self.notation) {
+                if !chords.iter().any(|(_, seen)| *seen == chord) {
+                    chords.push((base, chord));
+                }
+            }
+        }
+        chords
+    }
+
+    pub fn iter_images(&self) -> impl Iterator<Item = &Image> {
+        self.blocks
+            .iter()
+            .filter_map(Block::verse)
+            .flat_map(|v| v.inlines())
+            .filter_map(Inline::image)
+    }
+
+    /// A song is empty if it has no content blocks, ie. nothing is rendered apart from its title.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
     /// AST postprocessing.
     ///
     /// This entails removing empty paragraphs and verses
@@ -373,11 +1321,12 @@ impl Song {
     ///
     /// Distinct from `Book::postprocess()`, this is done by `Parser`.
     pub fn postprocess(&mut self) {
-        // Remove paragraphs which contain nothing or linebreaks only
+        // Remove paragraphs which contain nothing, linebreaks, or whitespace only,
+        // eg. those which only contained annotations
         for verse in self.blocks.iter_mut().filter_map(Block::verse_mut) {
             verse
                 .paragraphs
-                .retain(|para| para.iter().any(|inline| !inline.is_break()));
+                .retain(|para| para.iter().any(|inline| !inline.is_blank()));
         }
 
         // Remove verses which have no paragraphs and no label
@@ -388,7 +1337,92 @@ impl Song {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct GlossEntry {
+    pub term: BStr,
+    pub definition: BStr,
+}
+
+impl GlossEntry {
+    pub fn new(term: impl Into<BStr>, definition: impl Into<BStr>) -> Self {
+        Self {
+            term: term.into(),
+            definition: definition.into(),
+        }
+    }
+}
+
+/// A chord in the chord index, see `chord_index()`.
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct ChordEntry {
+    pub chord: BStr,
+}
+
+/// Turns a song `title` into a lowercase ASCII identifier usable in file names and URLs,
+/// eg. `"Wild Mountain Thyme"` becomes `"wild-mountain-thyme"`.
+pub fn slug(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in deunicode::deunicode(title).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+
+    if slug.is_empty() {
+        slug.push_str("song");
+    }
+    slug
+}
+
+/// Unique chords used in `songs`, sorted by base tone and then by name, see `Song::chords()`.
+pub fn chord_index(songs: &[Song]) -> Vec<ChordEntry> {
+    let mut chords: Vec<(Chromatic, &str)> = songs.iter().flat_map(Song::chords).collect();
+
+    chords.sort_by(|(lhs_base, lhs), (rhs_base, rhs)| {
+        lhs_base
+            .num()
+            .cmp(&rhs_base.num())
+            .then_with(|| lexical_cmp(lhs, rhs))
+            .then_with(|| lhs.cmp(rhs))
+    });
+    chords.dedup_by(|(_, lhs), (_, rhs)| lhs == rhs);
+    chords
+        .into_iter()
+        .map(|(_, chord)| ChordEntry {
+            chord: chord.into(),
+        })
+        .collect()
+}
+
+/// Attribution of a song for the book's credits page, see `credits()`.
+#[derive(Serialize, Clone, Debug)]
+pub struct Credit {
+    pub title: BStr,
+    /// Index of the song in the book.
+    pub idx: usize,
+    pub attribution: Attribution,
+}
+
+/// Attribution of `songs` which have any, in the order of songs.
+pub fn credits(songs: &[Song]) -> Vec<Credit> {
+    songs
+        .iter()
+        .enumerate()
+        .filter(|(_, song)| !song.attribution.is_empty())
+        .map(|(idx, song)| Credit {
+            title: song.title.clone(),
+            idx,
+            attribution: song.attribution.clone(),
+        })
+        .collect()
+}
+
+#[derive(Serialize, Clone, Debug)]
 pub struct SongRef {
     pub title: BStr,
     /// index of the song in the Book::songs vector
@@ -402,13 +1436,100 @@ impl SongRef {
             idx,
         }
     }
+
+    /// See `Song::ascii_only()`.
+    pub fn ascii_only(&self) -> Self {
+        Self {
+            title: ascii_punctuation(&self.title),
+            idx: self.idx,
+        }
+    }
+}
+
+/// Criteria for ordering songs, see `Book::songs_ordered_by()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    /// The order in which the songs were loaded, ie. as given by the `songs` setting.
+    Source,
+    /// Song title, compared lexically (case- and accent-insensitive) according to the book's `locale`.
+    /// This is the order of `songs_sorted`.
+    Title,
+    /// The first subtitle (typically the author), then title. Songs without subtitles come first.
+    Subtitle,
+}
+
+impl SortKey {
+    pub fn compare(self, collator: &Collator, lhs: &Song, rhs: &Song) -> Ordering {
+        match self {
+            Self::Source => Ordering::Equal,
+            Self::Title => collator.compare(&lhs.title, &rhs.title),
+            Self::Subtitle => {
+                let subtitle = |song: &Song| song.subtitles.first().map(|s| s.to_string());
+                match (subtitle(lhs), subtitle(rhs)) {
+                    (Some(lhs), Some(rhs)) => collator.compare(&lhs, &rhs),
+                    (lhs, rhs) => lhs.is_some().cmp(&rhs.is_some()),
+                }
+                .then_with(|| Self::Title.compare(collator, lhs, rhs))
+            }
+        }
+    }
+}
+
+/// What to do with songs that have no content, see `Song::is_empty()`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptySongs {
+    /// Fail the build.
+    Error,
+    /// Issue a warning and leave the song out.
+    #[default]
+    Warn,
+    /// Leave the song out silently.
+    Skip,
+    /// Keep the song in the book.
+    Keep,
+}
+
+/// What to do with chords not playable on the book's instrument, see `Instrument::plays()`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum UnplayableChords {
+    /// Fail the build.
+    Error,
+    /// Issue a warning for each song with such chords.
+    #[default]
+    Warn,
+}
+
+/// What to do with songs without a copyright notice, see `Attribution::copyright`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingCopyright {
+    /// Fail the build, for publication.
+    Error,
+    /// Issue a warning.
+    Warn,
+    /// Don't check copyright notices.
+    #[default]
+    Ignore,
 }
 
 #[derive(Debug)]
 pub struct Book {
     pub songs: Vec<Song>,
     pub songs_sorted: Vec<SongRef>,
+    /// Glossary entries of all songs, deduplicated and sorted by term.
+    pub glossary: Vec<GlossEntry>,
     pub notation: Notation,
+    pub empty_songs: EmptySongs,
+    pub missing_copyright: MissingCopyright,
+    /// Instrument whose chord set the songs' chords are checked against, if any.
+    pub instrument: Option<Instrument>,
+    pub unplayable_chords: UnplayableChords,
+    /// Number of songs at the start of `songs` loaded from pinned files, see `Settings::pin_inputs()`.
+    pub pinned: usize,
+    /// Collation of song titles according to the `locale` field in the `[book]` section.
+    pub collator: Collator,
 }
 
 impl Book {
@@ -416,11 +1537,31 @@ impl Book {
         Book {
             songs: vec![],
             songs_sorted: vec![],
+            glossary: vec![],
             notation: settings.notation,
+            empty_songs: settings.empty_songs,
+            missing_copyright: settings.missing_copyright,
+            instrument: settings.instrument,
+            unplayable_chords: settings.unplayable_chords,
+            pinned: 0,
+            collator: book_collator(&settings.book),
         }
     }
 
-    pub fn add_songs(&mut self, mut songs: Vec<Song>) {
+    /// Adds songs of an input file in the `section`, if any.
+    /// The first song of a section gets its label, see `Song::section`.
+    pub fn add_songs(&mut self, mut songs: Vec<Song>, section: Option<&str>) {
+        let current = self
+            .songs
+            .iter()
+            .rev()
+            .find_map(|song| song.section.as_deref());
+        if section != current {
+            if let Some(first) = songs.first_mut() {
+                first.section = section.map(BStr::from);
+            }
+        }
+
         self.songs.reserve(songs.len());
         self.songs.append(&mut songs);
     }
@@ -428,27 +1569,271 @@ impl Book {
     /// Book-level postprocessing.
     ///
     /// Steps taken:
-    /// 1. Generation of the songs_sorted vec,
-    /// 2. Resolving of image elements (checking path, reading image dimensions).
-    pub fn postprocess(&mut self, output_dir: &Path, img_cache: &ImgCache) -> Result<()> {
+    /// 1. Handling of empty songs according to `empty_songs`,
+    /// 2. Checking copyright notices according to `missing_copyright`,
+    /// 3. Checking chords against the `instrument` according to `unplayable_chords`,
+    /// 4. Generation of the songs_sorted vec, songs with `index` unset are left out
+    ///    and pinned songs come first,
+    /// 5. Collection of the book glossary from songs' glossary entries,
+    /// 6. Resolving of image elements (checking path, reading image dimensions).
+    ///
+    /// LilyPond snippets are rendered only when the book is rendered, see `render_lilypond()`.
+    pub fn postprocess(&mut self, app: &App, output_dir: &Path) -> Result<()> {
+        self.remove_empty_songs(app)?;
+        self.dedup_slugs();
+        self.check_copyright(app)?;
+        self.check_chords(app)?;
         self.songs.shrink_to_fit();
-        self.songs_sorted = self.songs.iter().enumerate().map(SongRef::new).collect();
-        sort_lexical_by(&mut self.songs_sorted, |songref| songref.title.as_ref());
+        let mut songs_sorted = self.songs_ordered_by(SortKey::Title);
+        songs_sorted.retain(|song_ref| self.songs[song_ref.idx].index);
+        // Pinned songs stay at the start in their book order
+        let pinned = self.pinned;
+        songs_sorted.sort_by_key(|song_ref| (song_ref.idx >= pinned, song_ref.idx.min(pinned)));
+        self.songs_sorted = songs_sorted;
+
+        self.collect_glossary(app);
 
         for image in self.iter_images_mut() {
-            image.resolve(output_dir, img_cache)?;
+            image.resolve(output_dir, app.img_cache())?;
         }
 
         Ok(())
     }
 
-    pub fn iter_images(&self) -> impl Iterator<Item = &Image> {
-        self.songs
+    /// Renders LilyPond snippets to images in `output_dir`, see `util::lilypond`.
+    /// If LilyPond can't be run, the snippets are left without an image and a warning is issued.
+    /// Each LilyPond run is limited to `timeout`.
+    pub fn render_lilypond(
+        &mut self,
+        app: &App,
+        output_dir: &Path,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        for song in self.songs.iter_mut() {
+            for snippet in song.blocks.iter_mut().filter_map(Block::lilypond_mut) {
+                app.check_interrupted()?;
+                let path = match lilypond::render_png(app, &snippet.source, output_dir, timeout) {
+                    Ok(path) => path,
+                    Err(err) if err.is::<LilypondNotFoundError>() => {
+                        app.warning(err);
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        return Err(err.context(format!(
+                            "Could not render LilyPond snippet in song {:?}",
+                            song.title
+                        )))
+                    }
+                };
+
+                let mut image = Image::new(path.into(), "".into(), "center".into());
+                image.resolve(output_dir, app.img_cache())?;
+                snippet.image = Some(image);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes song slugs unique by appending `-2`, `-3`, etc. to repeated ones, in book order.
+    fn dedup_slugs(&mut self) {
+        let mut seen = HashSet::with_capacity(self.songs.len());
+        for song in self.songs.iter_mut() {
+            if !seen.contains(&song.slug) {
+                seen.insert(song.slug.clone());
+                continue;
+            }
+
+            let slug = (2..)
+                .map(|n| BStr::from(format!("{}-{}", song.slug, n)))
+                .find(|slug| !seen.contains(slug))
+                .unwrap();
+            seen.insert(slug.clone());
+            song.slug = slug;
+        }
+    }
+
+    fn remove_empty_songs(&mut self, app: &App) -> Result<()> {
+        let mut empty = self.songs.iter().filter(|song| song.is_empty()).peekable();
+        if empty.peek().is_none() {
+            return Ok(());
+        }
+
+        if self.empty_songs == EmptySongs::Keep {
+            return Ok(());
+        }
+
+        match self.empty_songs {
+            EmptySongs::Error => {
+                let titles: Vec<_> = empty.map(|song| format!("'{}'", song.title)).collect();
+                bail!(
+                    "Songs with no content found: {}\nHint: Use the 'empty_songs' setting in bard.toml to skip or keep empty songs.",
+                    titles.join(", ")
+                );
+            }
+            EmptySongs::Warn => {
+                for song in empty {
+                    app.warning(format!(
+                        "Song '{}' has no content, it is left out of the book.",
+                        song.title
+                    ));
+                }
+            }
+            EmptySongs::Skip | EmptySongs::Keep => {}
+        }
+
+        self.pinned -= self.songs[..self.pinned]
             .iter()
-            .flat_map(|s| s.blocks.iter())
-            .filter_map(Block::verse)
-            .flat_map(|v| v.inlines())
-            .filter_map(Inline::image)
+            .filter(|song| song.is_empty())
+            .count();
+        // Sections starting with an empty song start with the next one instead
+        let mut section = None;
+        for song in self.songs.iter_mut() {
+            if song.is_empty() {
+                section = song.section.take().or(section);
+            } else if let Some(section) = section.take() {
+                song.section.get_or_insert(section);
+            }
+        }
+        self.songs.retain(|song| !song.is_empty());
+        Ok(())
+    }
+
+    /// Songs with `index` unset are not checked, as these are typically not songs, but eg. instructions.
+    fn check_copyright(&self, app: &App) -> Result<()> {
+        let mut missing = self
+            .songs
+            .iter()
+            .filter(|song| song.index && song.attribution.copyright.is_empty())
+            .peekable();
+        if missing.peek().is_none() {
+            return Ok(());
+        }
+
+        match self.missing_copyright {
+            MissingCopyright::Error => {
+                let titles: Vec<_> = missing.map(|song| format!("'{}'", song.title)).collect();
+                bail!(
+                    "Songs without a copyright notice found: {}\nHint: Add a {{copyright: ...}} annotation to the songs, or use the 'missing_copyright' setting in bard.toml.",
+                    titles.join(", ")
+                );
+            }
+            MissingCopyright::Warn => {
+                for song in missing {
+                    app.warning(format!("Song '{}' has no copyright notice.", song.title));
+                }
+            }
+            MissingCopyright::Ignore => {}
+        }
+
+        Ok(())
+    }
+
+    fn check_chords(&self, app: &App) -> Result<()> {
+        let instrument = match self.instrument {
+            Some(instrument) => instrument,
+            None => return Ok(()),
+        };
+
+        let unplayable: Vec<_> = self
+            .songs
+            .iter()
+            .map(|song| {
+                let chords: Vec<_> = song
+                    .chords()
+                    .into_iter()
+                    .map(|(_, chord)| chord)
+                    .filter(|chord| !instrument.plays(chord, song.notation))
+                    .collect();
+                (song, chords)
+            })
+            .filter(|(_, chords)| !chords.is_empty())
+            .collect();
+        if unplayable.is_empty() {
+            return Ok(());
+        }
+
+        match self.unplayable_chords {
+            UnplayableChords::Error => {
+                let songs: Vec<_> = unplayable
+                    .iter()
+                    .map(|(song, chords)| format!("'{}' ({})", song.title, chords.join(", ")))
+                    .collect();
+                bail!(
+                    "Songs with chords not playable on {} found: {}\nHint: Simplify the chords, or use the 'unplayable_chords' setting in bard.toml to only warn about them.",
+                    instrument,
+                    songs.join(", ")
+                );
+            }
+            UnplayableChords::Warn => {
+                for (song, chords) in unplayable {
+                    app.warning(format!(
+                        "Song '{}' has chords not playable on {}: {}",
+                        song.title,
+                        instrument,
+                        chords.join(", ")
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merge glossary entries of all songs into the book glossary.
+    ///
+    /// Identical entries are only kept once. If a term is defined differently,
+    /// the first definition is used and a warning is issued.
+    fn collect_glossary(&mut self, app: &App) {
+        let mut glossary: BTreeMap<&str, (&GlossEntry, &Song)> = BTreeMap::new();
+        for song in self.songs.iter() {
+            for entry in song.glossary.iter() {
+                match glossary.get(&*entry.term) {
+                    None => {
+                        glossary.insert(&entry.term, (entry, song));
+                    }
+                    Some((first, _)) if first.definition == entry.definition => {}
+                    Some((first, first_song)) => app.warning(format!(
+                        "Conflicting glossary definitions of '{}': '{}' in song '{}' and '{}' in song '{}', using the former.",
+                        entry.term, first.definition, first_song.title, entry.definition, song.title,
+                    )),
+                }
+            }
+        }
+
+        let mut glossary: Vec<_> = glossary
+            .into_values()
+            .map(|(entry, _)| entry.clone())
+            .collect();
+        glossary.sort_by(|lhs, rhs| self.collator.compare(&lhs.term, &rhs.term));
+        self.glossary = glossary;
+    }
+
+    /// Returns references to all songs ordered by `key`, see `songs_ordered_with()`.
+    pub fn songs_ordered_by(&self, key: SortKey) -> Vec<SongRef> {
+        self.songs_ordered_with(|lhs, rhs| key.compare(&self.collator, lhs, rhs))
+    }
+
+    /// Returns references to all songs (including those with `index` unset) ordered by `cmp`.
+    ///
+    /// The sort is stable, ie. songs that compare equal keep their source order.
+    /// The book is not modified.
+    pub fn songs_ordered_with<F>(&self, mut cmp: F) -> Vec<SongRef>
+    where
+        F: FnMut(&Song, &Song) -> Ordering,
+    {
+        let mut refs: Vec<_> = self.songs.iter().enumerate().map(SongRef::new).collect();
+        refs.sort_by(|lhs, rhs| cmp(&self.songs[lhs.idx], &self.songs[rhs.idx]));
+        refs
+    }
+
+    /// Images referenced in the book sorted by path, each path is only yielded once,
+    /// so that anything derived from the images doesn't depend on the order of songs.
+    pub fn iter_images(&self) -> impl Iterator<Item = &Image> {
+        let mut images: Vec<_> = self.songs.iter().flat_map(Song::iter_images).collect();
+        images.sort_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+        images.dedup_by(|lhs, rhs| lhs.path == rhs.path);
+        images.into_iter()
     }
 
     pub fn iter_images_mut(&mut self) -> impl Iterator<Item = &mut Image> {
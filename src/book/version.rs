@@ -1,5 +1,7 @@
 use std::fmt;
+use std::fs;
 
+use regex::Regex;
 use semver::Version;
 
 use crate::app::App;
@@ -29,6 +31,65 @@ pub static AST_VERSION_LOG: &[AstVersion] = &[
     AstVersion::new(1, 0, "Initial version"),
     AstVersion::new(1, 1, "New style, added support for HTML snippets, TTF font files, and baseline chords"),
     AstVersion::new(1, 2, "Added scaling of images in HTML via the dpi setting, width and height are now provided in i-image elements"),
+    AstVersion::new(1, 3, "Added the index field to songs, songs with index unset are left out of songs_sorted"),
+    AstVersion::new(1, 4, "Added i-nav elements for repeat signs, segno, coda, and similar navigation markers"),
+    AstVersion::new(1, 5, "Added the partial field, set when only a subset of songs is rendered as a preview"),
+    AstVersion::new(1, 6, "Added the page_start field with the number of the first page of songs"),
+    AstVersion::new(1, 7, "Added the glossary field with entries collected from {gloss: term = definition} annotations"),
+    AstVersion::new(1, 8, "Added the vars field with variables defined on the command line"),
+    AstVersion::new(1, 9, "Added the chords field with the index of chords used in the book, enabled by chord_index"),
+    AstVersion::new(1, 10, "Added the attribution field to songs and the credits field with attribution of all songs"),
+    AstVersion::new(1, 11, "Added the pinned field with the number of songs loaded from pinned files"),
+    AstVersion::new(1, 12, "Added the ccli and license fields to song attribution"),
+    AstVersion::new(1, 13, "Added the front_matter_numbering field to output"),
+    AstVersion::new(1, 14, "Added the keep_together field to songs"),
+    AstVersion::new(1, 15, "Added the ascii_only field to output"),
+    AstVersion::new(1, 16, "Added the alternate field to chords written as [chord|alternate] and the alternate_chords field to output"),
+    AstVersion::new(1, 17, "Added the slug field to songs and the split field with paths between files of outputs split per song"),
+    AstVersion::new(1, 18, "Added the link field to songs, the qr field with the QR code image of the link, and the qr_codes field to output"),
+    AstVersion::new(1, 19, "Added i-chord-sequence elements for lines of chords without lyrics, such as instrumental intros"),
+    AstVersion::new(1, 20, "Added the repeat field to verses sung more than once, set by {repeat: N} annotations"),
+    AstVersion::new(1, 21, "Added the transpose field to output"),
+    AstVersion::new(1, 22, "Added the capo field to songs and output, and the capo_label book field"),
+    AstVersion::new(1, 23, "Added the capo_chords field to output"),
+    AstVersion::new(1, 24, "Added the key field to songs and the notation field to output"),
+    AstVersion::new(1, 25, "Added the section field to songs starting a section of the book"),
+    AstVersion::new(1, 26, "Added the toc_keys field to output"),
+    AstVersion::new(1, 27, "Added named_chorus verse labels set by {chorus: name} annotations and the name field to chorus references"),
+    AstVersion::new(1, 28, "Added b-lilypond blocks with LilyPond snippets and their rendered image"),
+    AstVersion::new(1, 29, "Added the unicode_accidentals field to output"),
+    AstVersion::new(1, 30, "Added the to_key field to output"),
+];
+
+/// A template construct which still works, but has been superseded by a newer AST element.
+pub struct AstDeprecation {
+    pub since: Version,
+    /// Regex matching the construct in template source.
+    pattern: &'static str,
+    pub element: &'static str,
+    pub replacement: &'static str,
+}
+
+impl AstDeprecation {
+    pub const fn new(
+        ver_maj: u32,
+        ver_min: u32,
+        pattern: &'static str,
+        element: &'static str,
+        replacement: &'static str,
+    ) -> Self {
+        Self {
+            since: Version::new(ver_maj as u64, ver_min as u64, 0),
+            pattern,
+            element,
+            replacement,
+        }
+    }
+}
+
+pub static AST_DEPRECATIONS: &[AstDeprecation] = &[
+    AstDeprecation::new(1, 2, r"\bimg_w\s+path\b", "img_w path", "width"),
+    AstDeprecation::new(1, 2, r"\bimg_h\s+path\b", "img_h path", "height"),
 ];
 
 pub fn current() -> &'static Version {
@@ -48,6 +109,30 @@ fn log_changes(app: &App, since: &Version) {
     }
 }
 
+/// Returns the deprecated constructs used in template `source`, see `AST_DEPRECATIONS`.
+pub fn deprecated_in(source: &str) -> impl Iterator<Item = &'static AstDeprecation> + '_ {
+    AST_DEPRECATIONS.iter().filter(move |dep| {
+        Regex::new(dep.pattern)
+            .expect("Internal error: Invalid deprecation pattern")
+            .is_match(source)
+    })
+}
+
+pub fn deprecation_check(app: &App, tpl_path: &Path) {
+    // Errors reading the template are reported when rendering
+    let source = match fs::read_to_string(tpl_path) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+
+    for dep in deprecated_in(&source) {
+        app.warning(format!(
+            "Template {:?} uses '{}', which is deprecated since version {}, use '{}' instead.",
+            tpl_path, dep.element, dep.since, dep.replacement,
+        ));
+    }
+}
+
 pub fn compat_check(app: &App, tpl_path: &Path, tpl_version: &Version) {
     let current = current();
     if current < tpl_version {
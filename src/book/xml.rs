@@ -8,19 +8,27 @@ use super::*;
 xml_write!(struct Chord {
     chord,
     alt_chord,
+    alternate,
     backticks,
     baseline,
     inlines,
 } -> |w| {
+    let alternate = alternate.unwrap();
     w.tag("chord")
         .attr(chord)
         .attr_opt("alt-chord", alt_chord.unwrap())
+        .attr_opt("alternate", &alternate.as_ref().map(|alt| &alt.chord))
+        .attr_opt("alt-alternate", &alternate.as_ref().and_then(|alt| alt.alt_chord.as_ref()))
         .attr(backticks)
         .attr(baseline)
         .content()?
         .many(inlines)?
 });
 
+xml_write!(struct ChordSequence { chords, } -> |w| {
+    w.tag("chord-sequence").content()?.many(chords)?
+});
+
 xml_write!(struct Link {
     url,
     title,
@@ -53,10 +61,12 @@ xml_write!(struct Image {
 xml_write!(struct ChorusRef {
     num,
     prefix_space,
+    name,
 } -> |w| {
     w.tag("chorus-ref")
         .attr_opt("num", &num.unwrap().map(|n| format!("{}", n)))
         .attr(prefix_space)
+        .attr_opt("name", name.unwrap())
 });
 
 xml_write!(struct HtmlTag {
@@ -75,6 +85,7 @@ xml_write!(struct HtmlTag {
 xml_write!(enum Inline |w| {
     Text { text } => { w.write_text(text)?; },
     Chord(c) => { w.write_value(c)?; },
+    ChordSequence(s) => { w.write_value(s)?; },
     Break => { w.tag("br").finish()?; },
     Emph(i) => { w.tag("emph").content()?.many(i)?.finish()?; },
     Strong(i) => { w.tag("strong").content()?.many(i)?.finish()?; },
@@ -82,6 +93,7 @@ xml_write!(enum Inline |w| {
     Image(i) => { w.write_value(i)?; },
     ChorusRef(cr) => { w.write_value(cr)?; },
     HtmlTag(tag) => { w.write_value(tag)?; },
+    Nav { marker } => { w.tag("nav").attr(("marker", marker.to_string())).finish()?; },
 
     Transpose(..) => { unreachable!() },
 });
@@ -89,25 +101,28 @@ xml_write!(enum Inline |w| {
 xml_write!(struct Verse {
     label,
     paragraphs,
+    repeat,
 } -> |w| {
     use VerseLabel::*;
     let label = label.unwrap();
     let label_type = match label {
         Verse(..) => "verse",
-        Chorus(..) => "chorus",
+        Chorus(..) | NamedChorus { .. } => "chorus",
         Custom(..) => "custom",
         None {} => "none",
     };
 
     let label = match label {
         Verse(n) | Chorus(Some(n)) => Some(format!("{}", n)),
-        Custom(s) => Some(s.to_string()),
+        Custom(s) | NamedChorus { name: s, .. } => Some(s.to_string()),
         _ => Option::None,
     };
+    let repeat = repeat.unwrap().map(|n| format!("{}", n));
 
     w.tag("verse")
         .attr(("label-type", label_type))
         .attr_opt("label", &label)
+        .attr_opt("repeat", &repeat)
         .content()?
         .many_tags("p", paragraphs)?
 });
@@ -116,26 +131,98 @@ xml_write!(struct BulletList { items, } -> |w| {
     w.tag("bullet-list").content()?.many_tags("item", items)?
 });
 
+xml_write!(struct Lilypond { source, image, } -> |w| {
+    w.tag("lilypond").content()?.value_opt(image)?.field(source)?
+});
+
 xml_write!(enum Block |w| {
     Verse(verse) => { w.write_value(verse)?; },
     BulletList(l) => { w.write_value(l)?; },
     HorizontalLine => { w.tag("hr").finish()?; },
     Pre { text } => { w.tag("pre").content()?.text(text)?.finish()?; },
     HtmlBlock(i) => { w.tag("html-block").content()?.many(i)?.finish()?; },
+    Lilypond(l) => { w.write_value(l)?; },
 });
 
 xml_write!(struct Song {
     title,
+    slug,
     subtitles,
     blocks,
     notation,
+    index,
+    keep_together,
+    glossary,
+    attribution,
+    link,
+    qr,
+    capo,
+    key,
+    section,
 } -> |w| {
+    let capo = capo.unwrap().map(|capo| format!("{}", capo));
     w.tag("song")
         .attr(title)
+        .attr(slug)
         .attr(notation)
+        .attr(index)
+        .attr(keep_together)
+        .attr_opt("link", link.unwrap())
+        .attr_opt("capo", &capo)
+        .attr_opt("key", key.unwrap())
+        .attr_opt("section", section.unwrap())
         .content()?
         .many_tags("subtitle", subtitles)?
+        .value(attribution)?
+        .value_opt(qr)?
         .many(blocks)?
+        .many(glossary)?
+});
+
+xml_write!(struct Attribution {
+    authors,
+    composers,
+    arrangers,
+    copyright,
+    ccli,
+    license,
+} -> |w| {
+    w.tag("attribution")
+        .attr_opt("ccli", ccli.unwrap())
+        .attr_opt("license", license.unwrap())
+        .content()?
+        .many_tags("author", authors)?
+        .many_tags("composer", composers)?
+        .many_tags("arranger", arrangers)?
+        .many_tags("copyright", copyright)?
+});
+
+xml_write!(struct GlossEntry {
+    term,
+    definition,
+} -> |w| {
+    w.tag("gloss")
+        .attr(term)
+        .attr(definition)
+});
+
+xml_write!(struct Credit {
+    title,
+    idx,
+    attribution,
+} -> |w| {
+    w.tag("credit")
+        .attr(title)
+        .attr(idx)
+        .content()?
+        .value(attribution)?
+});
+
+xml_write!(struct ChordEntry {
+    chord,
+} -> |w| {
+    w.tag("chord")
+        .attr(chord)
 });
 
 xml_write!(struct SongRef {
@@ -1,8 +1,9 @@
-use std::fs;
 use std::path::MAIN_SEPARATOR;
+use std::process::{Command, Stdio};
+use std::{env, fs};
 
 use crate::prelude::*;
-use crate::util::PathBufExt as _;
+use crate::util::{ExitStatusExt as _, PathBufExt as _, TempPath};
 
 /// A filesystem node, either a file (with content), or a directory.
 #[derive(Debug)]
@@ -48,6 +49,11 @@ enum NodeResolved {
     Dir {
         path: PathBuf,
     },
+    /// A file copied from a template directory, see `ProjectTemplate::Dir`.
+    Copy {
+        src: PathBuf,
+        path: PathBuf,
+    },
 }
 
 impl NodeResolved {
@@ -55,12 +61,13 @@ impl NodeResolved {
         match self {
             Self::File { path, .. } => path.as_ref(),
             Self::Dir { path } => path.as_ref(),
+            Self::Copy { path, .. } => path.as_ref(),
         }
     }
 
     fn create(&self) -> Result<()> {
         let dir_path = match self {
-            Self::File { path, .. } => path.parent(),
+            Self::File { path, .. } | Self::Copy { path, .. } => path.parent(),
             Self::Dir { path } => Some(path.as_ref()),
         };
         if let Some(dir_path) = dir_path {
@@ -73,6 +80,11 @@ impl NodeResolved {
                 .with_context(|| format!("Could not initialize file {:?}", path))?;
         }
 
+        if let Self::Copy { src, path } = self {
+            fs::copy(src, path)
+                .with_context(|| format!("Could not copy file {:?} to {:?}", src, path))?;
+        }
+
         Ok(())
     }
 }
@@ -122,6 +134,119 @@ pub const DEFAULT_PROJECT: DefaultProject = DefaultProject {
     ],
 };
 
+/// Built-in project templates by name, usable with `bard init --template <name>`.
+pub static BUILTIN_PROJECTS: &[(&str, &DefaultProject)] = &[("default", &DEFAULT_PROJECT)];
+
+/// Where the files of a new project come from, see `Project::init()`.
+#[derive(Debug)]
+pub enum ProjectTemplate {
+    /// One of the `BUILTIN_PROJECTS`.
+    Builtin(&'static DefaultProject),
+    /// A project directory whose content is copied into the new project.
+    Dir(PathBuf),
+    /// A git repository shallow-cloned into a temporary directory, which is removed on drop.
+    Git(TempPath),
+}
+
+impl ProjectTemplate {
+    /// Parses the `--template` CLI argument, either a built-in project name, a directory path,
+    /// or a git repository URL, which is cloned right away, see `clone_git()`.
+    pub fn from_arg(arg: &str) -> Result<Self> {
+        if let Some((_, project)) = BUILTIN_PROJECTS.iter().find(|(name, _)| *name == arg) {
+            return Ok(Self::Builtin(project));
+        }
+
+        let dir = PathBuf::from(arg);
+        if !dir.is_dir() && Self::is_git_url(arg) {
+            let clone = Self::clone_git(arg)?;
+            if !clone.join("bard.toml").is_file() {
+                bail!(
+                    "Project template repository '{}' doesn't contain a bard.toml file.",
+                    arg
+                );
+            }
+            return Ok(Self::Git(clone));
+        }
+
+        if !dir.join("bard.toml").is_file() {
+            let names: Vec<_> = BUILTIN_PROJECTS.iter().map(|(name, _)| *name).collect();
+            bail!(
+                "Project template '{}' is neither a directory containing a bard.toml file, a git URL, nor a built-in template. Built-in templates: {:?}.",
+                arg,
+                names,
+            );
+        }
+
+        Ok(Self::Dir(dir))
+    }
+
+    /// URLs such as `https://host/repo.git`, `file:///path/repo`, or `git@host:repo.git`.
+    fn is_git_url(arg: &str) -> bool {
+        arg.contains("://") || arg.starts_with("git@") || arg.ends_with(".git")
+    }
+
+    fn clone_git(url: &str) -> Result<TempPath> {
+        let clone = TempPath::make_temp_dir(env::temp_dir().join("bard-template"), true)?;
+        Command::new("git")
+            .args(["clone", "--depth", "1", "--quiet", "--", url])
+            .arg(&*clone)
+            .stdin(Stdio::null())
+            .status()
+            .context("Could not run git")?
+            .into_result()
+            .with_context(|| format!("Could not clone project template repository '{}'", url))?;
+        Ok(clone)
+    }
+
+    pub fn resolve(&self, project_dir: &Path) -> Result<DefaultProjectResolved> {
+        let dir: &Path = match self {
+            Self::Builtin(project) => return Ok(project.resolve(project_dir)),
+            Self::Dir(dir) => dir,
+            Self::Git(clone) => clone,
+        };
+
+        let mut nodes = vec![];
+        Self::dir_nodes(dir, dir, project_dir, &mut nodes)
+            .with_context(|| format!("Could not read project template {:?}", dir))?;
+        Ok(DefaultProjectResolved { nodes })
+    }
+
+    fn dir_nodes(
+        src_root: &Path,
+        src_dir: &Path,
+        project_dir: &Path,
+        nodes: &mut Vec<NodeResolved>,
+    ) -> Result<()> {
+        let mut entries = fs::read_dir(src_dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            // Git metadata of the template isn't part of the project
+            if entry.file_name() == ".git" {
+                continue;
+            }
+
+            let src = entry.path();
+            // NB. Unwrap is ok, the path is prefixed with src_root
+            let path = project_dir.join(src.strip_prefix(src_root).unwrap());
+            if entry.file_type()?.is_dir() {
+                nodes.push(NodeResolved::Dir { path });
+                Self::dir_nodes(src_root, &src, project_dir, nodes)?;
+            } else {
+                nodes.push(NodeResolved::Copy { src, path });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ProjectTemplate {
+    fn default() -> Self {
+        Self::Builtin(&DEFAULT_PROJECT)
+    }
+}
+
 pub struct DefaultProjectResolved {
     nodes: Vec<NodeResolved>,
 }
@@ -142,7 +267,9 @@ impl DefaultProjectResolved {
 
     pub fn files(&self) -> impl Iterator<Item = &Path> {
         self.nodes.iter().filter_map(|node| match node {
-            NodeResolved::File { path, .. } => Some(path.as_path()),
+            NodeResolved::File { path, .. } | NodeResolved::Copy { path, .. } => {
+                Some(path.as_path())
+            }
             NodeResolved::Dir { .. } => None,
         })
     }
@@ -150,7 +277,7 @@ impl DefaultProjectResolved {
     pub fn dirs(&self) -> impl Iterator<Item = &Path> {
         self.nodes.iter().filter_map(|node| match node {
             NodeResolved::Dir { path } => Some(path.as_path()),
-            NodeResolved::File { .. } => None,
+            NodeResolved::File { .. } | NodeResolved::Copy { .. } => None,
         })
     }
 }
@@ -10,12 +10,16 @@
 
 use std::env;
 use std::ffi::OsString;
+use std::fs;
+use std::path::Component;
+use std::time::Duration;
 
 use app::{App, InterruptFlag, MakeOpts, StdioOpts};
 use clap::{CommandFactory as _, Parser as _};
 use serde::Serialize;
 
 pub mod app;
+pub mod bench;
 pub mod book;
 pub mod default_project;
 pub mod music;
@@ -29,8 +33,11 @@ pub mod util;
 pub mod util_cmd;
 pub mod watch;
 
+use crate::default_project::ProjectTemplate;
 use crate::prelude::*;
-use crate::project::{Project, Settings};
+use crate::project::{Project, Settings, Split};
+use crate::render::tex_tools::{TexNotFoundError, TexTools};
+use crate::util::{archive, copy_dir, TempPath};
 use crate::util_cmd::UtilCmd;
 use crate::watch::Watch;
 
@@ -51,6 +58,16 @@ pub const PROGRAM_META: ProgramMeta = ProgramMeta {
     authors: env!("CARGO_PKG_AUTHORS"),
 };
 
+/// Process exit codes returned by `bard()`.
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    pub const ERROR: i32 = 1;
+    /// No TeX distribution could be found, so that scripts can detect this case specifically.
+    pub const TEX_NOT_FOUND: i32 = 3;
+    /// With `make --if-changed`, all outputs were up to date and nothing was built.
+    pub const UP_TO_DATE: i32 = 100;
+}
+
 #[derive(clap::Parser)]
 #[command(
     version = env!("CARGO_PKG_VERSION"),
@@ -95,16 +112,82 @@ enum Command {
     Init {
         #[clap(flatten)]
         opts: StdioOpts,
+        /// Name of a built-in project template, path to a project directory to copy, or a git URL to clone
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Create a new song file in the songs directory of the current project
+    NewSong {
+        #[clap(flatten)]
+        opts: StdioOpts,
+        /// Title of the song, also used to name the file
+        #[arg(long)]
+        title: String,
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+        /// Add the file to the songs list in bard.toml if it isn't matched by it
+        #[arg(long)]
+        add: bool,
     },
     /// Build the current project"
     Make {
         #[clap(flatten)]
         opts: MakeOpts,
+        /// Only build if any output is older than project files, otherwise exit with code 100
+        #[arg(long, conflicts_with = "archive")]
+        if_changed: bool,
+        /// Build the project in a zip or tar archive instead of the current directory
+        #[arg(long, value_name = "FILE")]
+        archive: Option<PathBuf>,
+        /// Directory to copy the outputs of an archived project to, defaults to the current directory
+        #[arg(long, value_name = "DIR", requires = "archive")]
+        archive_output: Option<PathBuf>,
     },
     /// Like make, but keep running and rebuild each time there's a change in project files
     Watch {
         #[clap(flatten)]
         opts: MakeOpts,
+        /// Wait for MS milliseconds without further changes before rebuilding, so that bursts of changes cause one rebuild
+        #[arg(long, value_name = "MS", default_value_t = watch::DEFAULT_DEBOUNCE_MS)]
+        debounce: u64,
+    },
+    /// Load and parse the current project without rendering it, fail if any errors are found
+    Check {
+        #[clap(flatten)]
+        opts: StdioOpts,
+        /// Fail on warnings as well
+        #[arg(long)]
+        deny_warnings: bool,
+    },
+    /// Remove the output files and TeX build directories of the current project
+    Clean {
+        #[clap(flatten)]
+        opts: StdioOpts,
+        /// Only print the files that would be removed, without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that the TeX distribution works and measure how fast it is
+    Doctor {
+        #[clap(flatten)]
+        opts: StdioOpts,
+        /// Only print the TeX distribution and the command line it would be run with, without running it
+        #[arg(long)]
+        print_tex_command: bool,
+    },
+    /// Build the current project several times without TeX and print timings of the build phases
+    Bench {
+        #[clap(flatten)]
+        opts: StdioOpts,
+        /// Number of timed builds, after one warm-up build
+        #[arg(short = 'n', long, default_value_t = 10)]
+        runs: u32,
+    },
+    /// Print a longer explanation of a diagnostic code, eg. B0002
+    Explain {
+        /// The code shown with the diagnostic
+        code: String,
     },
     /// CLI utilities for postprocessing
     #[command(subcommand)]
@@ -116,18 +199,65 @@ enum Command {
 }
 
 impl Command {
-    fn run(self, app: &App) -> Result<()> {
+    fn stdio(&self) -> Option<&StdioOpts> {
         use Command::*;
 
         match self {
-            Init { .. } => bard_init(app),
-            Make { .. } => bard_make(app),
-            Watch { .. } => bard_watch(app),
-            Util(cmd) => cmd.run(app),
+            Init { opts, .. }
+            | NewSong { opts, .. }
+            | Check { opts, .. }
+            | Clean { opts, .. }
+            | Doctor { opts, .. }
+            | Bench { opts, .. } => Some(opts),
+            Make { opts, .. } | Watch { opts, .. } => Some(&opts.stdio),
+            _ => None,
+        }
+    }
+
+    /// Whether the command ends with a summary of warnings and errors, see `App::diag_summary()`.
+    /// If the command fails, the summary is printed after the error.
+    fn diag_summary(&self) -> bool {
+        matches!(
+            self,
+            Command::Make { .. } | Command::Watch { .. } | Command::Check { .. }
+        )
+    }
+
+    /// Returns the process exit code on success, see `exit_code`.
+    fn run(self, app: &App) -> Result<i32> {
+        use Command::*;
+
+        match self {
+            Init { template, .. } => bard_init(app, template.as_deref())?,
+            NewSong {
+                title, force, add, ..
+            } => bard_new_song(app, &title, force, add)?,
+            Make {
+                if_changed: true, ..
+            } => return bard_make_if_changed(app),
+            Make {
+                archive: Some(archive),
+                archive_output,
+                ..
+            } => bard_make_archive(app, &archive, archive_output.as_deref())?,
+            Make { .. } => bard_make(app)?,
+            Watch { debounce, .. } => bard_watch(app, Duration::from_millis(debounce))?,
+            Check { deny_warnings, .. } => bard_check(app, deny_warnings)?,
+            Clean { dry_run, .. } => bard_clean(app, dry_run)?,
+            Doctor {
+                print_tex_command: true,
+                ..
+            } => print!("{}", bard_tex_command_at(app, get_cwd()?)?),
+            Doctor { .. } => bard_doctor(app)?,
+            Bench { runs, .. } => bard_bench(app, runs)?,
+            Explain { code } => bard_explain(&code)?,
+            Util(cmd) => cmd.run(app)?,
 
             #[cfg(feature = "tectonic")]
-            Tectonic(tectonic) => tectonic.run(app),
+            Tectonic(tectonic) => tectonic.run(app)?,
         }
+
+        Ok(exit_code::SUCCESS)
     }
 }
 
@@ -136,22 +266,63 @@ fn get_cwd() -> Result<PathBuf> {
 }
 
 pub fn bard_init_at<P: AsRef<Path>>(app: &App, path: P) -> Result<()> {
+    bard_init_template_at(app, path, &ProjectTemplate::default())
+}
+
+pub fn bard_init_template_at<P: AsRef<Path>>(
+    app: &App,
+    path: P,
+    template: &ProjectTemplate,
+) -> Result<()> {
     let path = path.as_ref();
 
     app.status("Initialize", format!("new project at {:?}", path));
-    Project::init(path).context("Could not initialize a new project")?;
+    Project::init(path, template).context("Could not initialize a new project")?;
     app.success("Done!");
     Ok(())
 }
 
-pub fn bard_init(app: &App) -> Result<()> {
+pub fn bard_init(app: &App, template: Option<&str>) -> Result<()> {
     let cwd = get_cwd()?;
-    bard_init_at(app, cwd)
+    let template = template
+        .map(ProjectTemplate::from_arg)
+        .transpose()?
+        .unwrap_or_default();
+    bard_init_template_at(app, cwd, &template)
+}
+
+/// Creates a new song file in the project at `path`, see `Project::new_song()`.
+pub fn bard_new_song_at<P: AsRef<Path>>(
+    app: &App,
+    path: P,
+    title: &str,
+    force: bool,
+    add: bool,
+) -> Result<PathBuf> {
+    let song = Project::new_song(path.as_ref(), title, force, add)
+        .context("Could not create a new song")?;
+
+    app.status("Created", format!("song file {:?}", song.path));
+    if song.added {
+        app.indent("Added to the 'songs' field in bard.toml");
+    } else if !song.matched {
+        app.warning(format!(
+            "File {:?} is not matched by the 'songs' field in bard.toml, it won't be part of the songbook.\nHint: Use --add to add it to bard.toml, or add it by hand.",
+            song.path
+        ));
+    }
+    Ok(song.path)
+}
+
+pub fn bard_new_song(app: &App, title: &str, force: bool, add: bool) -> Result<()> {
+    let cwd = get_cwd()?;
+    bard_new_song_at(app, cwd, title, force, add)?;
+    Ok(())
 }
 
 pub fn bard_make_at<P: AsRef<Path>>(app: &App, path: P) -> Result<Project> {
     Project::new(app, path.as_ref())
-        .and_then(|project| {
+        .and_then(|mut project| {
             project.render(app)?;
             Ok(project)
         })
@@ -162,17 +333,118 @@ pub fn bard_make(app: &App) -> Result<()> {
     let cwd = get_cwd()?;
 
     bard_make_at(app, cwd)?;
-    app.success("Done!");
+    app.diag_summary();
     Ok(())
 }
 
+/// Builds the project in the zip or tar `archive` in a temporary directory
+/// and copies its output files to `output_dir`, see `util::archive`.
+pub fn bard_make_archive_at(app: &App, archive: &Path, output_dir: &Path) -> Result<()> {
+    app.status("Extracting", format!("{:?}", archive));
+    let dir = TempPath::make_temp_dir(env::temp_dir().join("bard-archive"), true)?;
+    archive::extract(archive, &dir)?;
+    let root = archive::project_root(&dir)?;
+
+    let project = bard_make_at(app, &root)?;
+    copy_outputs(&project, output_dir).with_context(|| {
+        format!(
+            "Could not copy outputs from {:?} to {:?}",
+            project.settings.dir_output(),
+            output_dir
+        )
+    })?;
+    app.indent(format!("Outputs copied to {:?}", output_dir));
+    Ok(())
+}
+
+/// Copies the output files of `project` to `dest`, along with the SyncTeX files of PDF outputs
+/// and the song directories of outputs with `split` set, where they were produced.
+///
+/// Files are placed at their path relative to the output directory, those outside of it directly in `dest`.
+/// Other content of the output directory, such as `.bard-cache`, isn't copied.
+fn copy_outputs(project: &Project, dest: &Path) -> Result<()> {
+    let dir_output = project.settings.dir_output();
+    let mut paths = vec![];
+    for output in project.settings.output.iter() {
+        paths.push(output.file.clone());
+        if output.is_pdf() {
+            paths.push(output.file.with_extension("synctex.gz"));
+        }
+        if output.split != Split::None {
+            paths.push(output.split_dir());
+        }
+    }
+
+    fs::create_dir_all(dest)?;
+    for path in paths.iter().filter(|path| path.exists()) {
+        let relative = path
+            .strip_prefix(dir_output)
+            .ok()
+            .filter(|rel| rel.components().all(|c| matches!(c, Component::Normal(_))));
+        let target = match relative {
+            Some(relative) => dest.join(relative),
+            None => dest.join(path.file_name().unwrap_or_default()),
+        };
+        if path.is_dir() {
+            copy_dir(path, &target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn bard_make_archive(app: &App, archive: &Path, output_dir: Option<&Path>) -> Result<()> {
+    let output_dir = match output_dir {
+        Some(dir) => dir.to_owned(),
+        None => get_cwd()?,
+    };
+
+    bard_make_archive_at(app, archive, &output_dir)?;
+    app.diag_summary();
+    Ok(())
+}
+
+/// Like `bard_make_at()`, but only renders if the project is not up to date,
+/// see `Project::is_up_to_date()`. Returns whether the project was rendered.
+///
+/// TeX is not initialized unless rendering is needed.
+pub fn bard_make_if_changed_at<P: AsRef<Path>>(app: &App, path: P) -> Result<bool> {
+    Project::new(app, path.as_ref())
+        .and_then(|mut project| {
+            if project.is_up_to_date() {
+                return Ok(false);
+            }
+
+            project.render(app)?;
+            Ok(true)
+        })
+        .context("Could not make project")
+}
+
+pub fn bard_make_if_changed(app: &App) -> Result<i32> {
+    let cwd = get_cwd()?;
+
+    if bard_make_if_changed_at(app, cwd)? {
+        app.diag_summary();
+        Ok(exit_code::SUCCESS)
+    } else {
+        app.success("Up to date");
+        Ok(exit_code::UP_TO_DATE)
+    }
+}
+
 pub fn bard_watch_at<P: AsRef<Path>>(app: &App, path: P, mut watch: Watch) -> Result<()> {
     loop {
-        let project = bard_make_at(app, &path)?;
+        let mut watch_set = bard_make_at(app, &path)?.into_watch_set();
+        app.diag_summary();
 
         eprintln!();
         app.status("Watching", "for changes in the project ...");
-        match watch.watch(&project, app.interrupt_flag())? {
+        match watch.watch(&mut watch_set, app.interrupt_flag())? {
             Some(paths) if paths.len() == 1 => {
                 app.indent(format!("Change detected at {:?} ...", paths[0]))
             }
@@ -184,39 +456,198 @@ pub fn bard_watch_at<P: AsRef<Path>>(app: &App, path: P, mut watch: Watch) -> Re
     Ok(())
 }
 
-pub fn bard_watch(app: &App) -> Result<()> {
+pub fn bard_watch(app: &App, debounce: Duration) -> Result<()> {
     let cwd = get_cwd()?;
-    let watch = Watch::new()?;
+    let watch = Watch::new()?.with_debounce(debounce);
     bard_watch_at(app, cwd, watch)
 }
 
+/// Loads and parses the project at `path` without rendering it, so that TeX is not run.
+/// Fails if any errors are reported while loading, or any warnings with `deny_warnings`.
+pub fn bard_check_at<P: AsRef<Path>>(app: &App, path: P, deny_warnings: bool) -> Result<()> {
+    let (warnings_before, errors_before) = app.diag_counts();
+    Project::new(app, path.as_ref()).context("Could not check project")?;
+
+    let (warnings, errors) = app.diag_counts();
+    let (warnings, errors) = (warnings - warnings_before, errors - errors_before);
+    if errors > 0 {
+        bail!("The project has {} error(s).", errors);
+    }
+    if deny_warnings && warnings > 0 {
+        bail!(
+            "The project has {} warning(s), which are denied by --deny-warnings.",
+            warnings
+        );
+    }
+
+    Ok(())
+}
+
+pub fn bard_check(app: &App, deny_warnings: bool) -> Result<()> {
+    let cwd = get_cwd()?;
+    bard_check_at(app, cwd, deny_warnings)?;
+    app.diag_summary();
+    Ok(())
+}
+
+/// Removes the files generated by building the project at `path`, see `Settings::clean_paths()`,
+/// with `dry_run` they're only listed. Returns the paths removed.
+pub fn bard_clean_at<P: AsRef<Path>>(app: &App, path: P, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let settings = Project::load_settings(path.as_ref()).context("Could not load project")?;
+    let paths = settings.clean_paths(app)?;
+
+    for path in paths.iter() {
+        if dry_run {
+            app.status("Would remove", format!("{:?}", path));
+            continue;
+        }
+
+        let res = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        res.with_context(|| format!("Could not remove {:?}", path))?;
+        app.status("Removed", format!("{:?}", path));
+    }
+    if paths.is_empty() {
+        app.success("Nothing to clean");
+    }
+
+    Ok(paths)
+}
+
+pub fn bard_clean(app: &App, dry_run: bool) -> Result<()> {
+    let cwd = get_cwd()?;
+    bard_clean_at(app, cwd, dry_run)?;
+    Ok(())
+}
+
+/// Locates TeX the same way `make` would in a project at `path` (if there's one)
+/// and renders a small built-in document with it.
+pub fn bard_doctor_at<P: AsRef<Path>>(app: &App, path: P) -> Result<()> {
+    let settings = Project::find_settings(path.as_ref())?;
+    TexTools::initialize(app, settings.as_ref().and_then(Settings::tex))?;
+
+    let tex = TexTools::get();
+    app.status("Checking", format!("TeX distribution '{}'", tex.config()));
+    match tex.self_test(app) {
+        Ok(Some(time)) => app.success(format!(
+            "TeX OK, sample document rendered in {:.2} s",
+            time.as_secs_f64()
+        )),
+        Ok(None) => app.warning("TeX is disabled (tex = \"none\"), PDF files won't be rendered."),
+        Err(err) => {
+            let hints = tex.self_test_hints().join("\n");
+            return Err(err.context(format!(
+                "TeX could not render the sample document.\n{}",
+                hints
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Locates TeX the same way `make` would in a project at `path` (if there's one)
+/// and describes the configuration found, see `TexTools::describe()`.
+pub fn bard_tex_command_at<P: AsRef<Path>>(app: &App, path: P) -> Result<String> {
+    let settings = Project::find_settings(path.as_ref())?;
+    TexTools::initialize(app, settings.as_ref().and_then(Settings::tex))?;
+    TexTools::get().describe()
+}
+
+pub fn bard_doctor(app: &App) -> Result<()> {
+    let cwd = get_cwd()?;
+    bard_doctor_at(app, cwd)
+}
+
+/// Benchmarks the project at `path`, see `bench::bench()`.
+pub fn bard_bench_at<P: AsRef<Path>>(app: &App, path: P, runs: u32) -> Result<bench::BenchReport> {
+    bench::bench(app, path.as_ref(), runs)
+}
+
+pub fn bard_bench(app: &App, runs: u32) -> Result<()> {
+    let cwd = get_cwd()?;
+    let report = bard_bench_at(app, cwd, runs)?;
+    print!("{}", report);
+    Ok(())
+}
+
+pub fn bard_explain(code: &str) -> Result<()> {
+    let explanation = parser::explain::explain(code)?;
+    println!("{}\n\n{}", explanation.code, explanation.text);
+    Ok(())
+}
+
 pub fn bard(args: &[OsString], interrupt: InterruptFlag) -> i32 {
     let cli = Cli::parse_from(args);
     if cli.print_version() {
-        return 0;
+        return exit_code::SUCCESS;
     }
 
     let cmd = if let Some(cmd) = cli.cmd {
         cmd
     } else {
         let _ = Cli::command().print_help();
-        return 0;
+        return exit_code::SUCCESS;
     };
 
     let app = match &cmd {
-        Command::Init { opts } => App::new(&opts.clone().into(), interrupt),
-        Command::Make { opts } => App::new(opts, interrupt),
-        Command::Watch { opts } => App::new(opts, interrupt),
-        Command::Util(_) => App::new(&Default::default(), interrupt),
+        Command::Init { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::NewSong { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::Make { opts, .. } => App::new(opts, interrupt),
+        Command::Watch { opts, .. } => App::new(opts, interrupt),
+        Command::Check { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::Clean { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::Doctor { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::Bench { opts, .. } => {
+            // Status messages of each build would bury the results
+            let opts = MakeOpts {
+                no_postprocess: true,
+                stdio: StdioOpts {
+                    quiet: !opts.verbose,
+                    ..opts.clone()
+                },
+                ..Default::default()
+            };
+            App::new(&opts, interrupt)
+                .with_timings()
+                .with_tex_disabled()
+        }
+        Command::Explain { .. } | Command::Util(_) => App::new(&Default::default(), interrupt),
 
         #[cfg(feature = "tectonic")]
         Command::Tectonic(_) => App::new_as_tectonic(interrupt),
     };
+    let app = match cmd
+        .stdio()
+        .and_then(|opts| Some((opts.log_file.as_ref()?, opts.append)))
+    {
+        Some((path, append)) => match app.clone().with_log_file(path, append) {
+            Ok(app) => app,
+            Err(err) => {
+                app.error(err);
+                return exit_code::ERROR;
+            }
+        },
+        None => app,
+    };
 
-    if let Err(err) = cmd.run(&app) {
-        app.error(err);
-        1
-    } else {
-        0
+    let diag_summary = cmd.diag_summary();
+    match cmd.run(&app) {
+        Ok(code) => code,
+        Err(err) => {
+            let code = if err.chain().any(|e| e.is::<TexNotFoundError>()) {
+                exit_code::TEX_NOT_FOUND
+            } else {
+                exit_code::ERROR
+            };
+            app.error(err);
+            if diag_summary {
+                app.diag_summary();
+            }
+            code
+        }
     }
 }
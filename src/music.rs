@@ -1,10 +1,11 @@
+use std::borrow::Cow;
 use std::fmt;
 use std::ops;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 /// Musical note notation convention
 /// Variant naming follows <https://en.wikipedia.org/wiki/Musical_note#12-tone_chromatic_scale>
@@ -42,6 +43,23 @@ impl FromStr for Notation {
     }
 }
 
+impl Notation {
+    /// The Nashville and Roman notations write chords as degrees relative to the key of a song.
+    pub fn is_relative(self) -> bool {
+        matches!(self, Notation::Nashville | Notation::Roman)
+    }
+
+    /// Notation of song keys, see `parse_key()`. Keys of songs written in a relative notation
+    /// can't be relative themselves, they're written in the English notation.
+    pub fn key_notation(self) -> Notation {
+        if self.is_relative() {
+            Notation::English
+        } else {
+            self
+        }
+    }
+}
+
 impl fmt::Display for Notation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let name = match self {
@@ -54,6 +72,53 @@ impl fmt::Display for Notation {
     }
 }
 
+/// Which accidentals are used to spell tones of black keys when transposing, see `transpose_spelled()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Spelling {
+    /// The more common spelling of each tone, ie. `C#`, `Eb`, `F#`, `Ab`, and `Bb`.
+    Mixed,
+    Sharps,
+    Flats,
+}
+
+impl Spelling {
+    /// Sharps when transposing up and flats when transposing down, the spelling
+    /// musicians usually expect, eg. `C` goes to `C#` a semitone up, but `D` to `Db` a semitone down.
+    pub fn for_direction(by: i32) -> Self {
+        if by > 0 {
+            Self::Sharps
+        } else if by < 0 {
+            Self::Flats
+        } else {
+            Self::Mixed
+        }
+    }
+
+    /// Spelling of the key signature of the key with the `tonic`, eg. flats in `F` and `Dm`,
+    /// sharps in `D` and `Bm`, and `Mixed` in `C` and `Am`. A black-key tonic is spelled with its own accidental,
+    /// eg. sharps in `F#` and flats in `Eb`. Returns `None` if `tonic` isn't a single tone in `notation`.
+    pub fn for_key(tonic: &str, minor: bool, notation: Notation) -> Option<Self> {
+        let tonic = tonic.trim();
+        let (tone, size) = Chromatic::parse_span(tonic, notation)?;
+        if size != tonic.len() {
+            return None;
+        }
+        if tone.is_black_key() {
+            let sharp = tonic.contains(['#', '♯']);
+            return Some(if sharp { Self::Sharps } else { Self::Flats });
+        }
+
+        let major = if minor { tone + 3.into() } else { tone };
+        let spelling = match major.num() {
+            0 => Self::Mixed,
+            5 => Self::Flats,
+            _ if major.is_black_key() => Self::Flats,
+            _ => Self::Sharps,
+        };
+        Some(spelling)
+    }
+}
+
 /// Represents a half-tone in a 12-tone chromatic scale in equal temperament
 /// tuning, starting from C (ie. C = 0, C# = 1, ...)
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
@@ -153,6 +218,10 @@ impl Chromatic {
         self.0
     }
 
+    fn is_black_key(self) -> bool {
+        matches!(self.0, 1 | 3 | 6 | 8 | 10)
+    }
+
     fn parse_halftone(from: &str, base: Chromatic, base_size: usize) -> (Chromatic, usize) {
         let c = from[base_size..].chars().next();
         let (delta, size) = match c {
@@ -307,6 +376,25 @@ impl Chromatic {
         }
     }
 
+    /// Like `as_str()`, but black keys are spelled according to `spelling`.
+    /// The German `B` is kept as it is with flats.
+    fn as_str_spelled(
+        &self,
+        notation: Notation,
+        uppercase: bool,
+        spelling: Spelling,
+    ) -> Cow<'static, str> {
+        let black_key = self.is_black_key();
+        let german_b = notation == Notation::German && self.0 == 10;
+        let (natural, accidental) = match spelling {
+            Spelling::Sharps if black_key => (*self - 1.into(), '#'),
+            Spelling::Flats if black_key && !german_b => (*self + 1.into(), 'b'),
+            _ => return self.as_str(notation, uppercase).into(),
+        };
+
+        format!("{}{}", natural.as_str(notation, uppercase), accidental).into()
+    }
+
     pub fn transposed<C>(self, by: C) -> Chromatic
     where
         C: Into<Chromatic>,
@@ -325,17 +413,28 @@ impl fmt::Display for Chromatic {
 struct Chord<'s> {
     base: Chromatic,
     uppercase: bool,
-    suffix: &'s str,
+    suffix: Cow<'s, str>,
 }
 
 impl<'s> Chord<'s> {
     fn parse(src: &'s str, notation: Notation) -> Result<Self, &'s str> {
         let (base, base_size) = Chromatic::parse_span(src, notation).ok_or(src)?;
+        let mut suffix = Cow::Borrowed(&src[base_size..]);
+        // Nashville sevenths etc. written as `5(7)`, see `Chord::suffix()`
+        if notation == Notation::Nashville && suffix.starts_with('(') {
+            if let Some(end) = suffix.find(')') {
+                let digits = &suffix[1..end];
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    suffix = format!("{}{}", digits, &suffix[end + 1..]).into();
+                }
+            }
+        }
 
         Ok(Self {
             base,
-            uppercase: src.chars().next().unwrap().is_uppercase(),
-            suffix: &src[base_size..],
+            // Nashville numbers are always uppercase
+            uppercase: !src.chars().next().unwrap().is_lowercase(),
+            suffix,
         })
     }
 
@@ -347,14 +446,32 @@ impl<'s> Chord<'s> {
         }
     }
 
-    fn str_len(&self, notation: Notation) -> usize {
-        self.base.as_str(notation, self.uppercase).len() + self.suffix.len()
+    /// The suffix as written in the target `notation`. In the Nashville notation, a suffix
+    /// starting with a digit is put in parentheses, eg. `5(7)`, to not run into the number.
+    fn suffix(&self, notation: Notation) -> Cow<'_, str> {
+        let starts_with_digit = self.suffix.starts_with(|c: char| c.is_ascii_digit());
+        if notation == Notation::Nashville && starts_with_digit {
+            let end = self
+                .suffix
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(self.suffix.len());
+            format!("({}){}", &self.suffix[..end], &self.suffix[end..]).into()
+        } else {
+            Cow::Borrowed(&self.suffix)
+        }
+    }
+
+    fn str_len(&self, notation: Notation, spelling: Spelling) -> usize {
+        self.base
+            .as_str_spelled(notation, self.uppercase, spelling)
+            .len()
+            + self.suffix(notation).len()
     }
 
-    fn write_string(&self, mut to: String, notation: Notation) -> String {
-        let base = self.base.as_str(notation, self.uppercase);
-        to.push_str(base);
-        to.push_str(self.suffix);
+    fn write_string(&self, mut to: String, notation: Notation, spelling: Spelling) -> String {
+        let base = self.base.as_str_spelled(notation, self.uppercase, spelling);
+        to.push_str(&base);
+        to.push_str(&self.suffix(notation));
         to
     }
 }
@@ -380,12 +497,9 @@ impl<'s> ChordIter<'s> {
             notation: src_notation,
         }
     }
-}
-
-impl<'s> Iterator for ChordIter<'s> {
-    type Item = Result<Chord<'s>, &'s str>;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Splits off the next chord source including its trailing separators.
+    fn next_chunk(&mut self) -> Option<&'s str> {
         if self.rest.is_empty() {
             return None;
         }
@@ -408,7 +522,16 @@ impl<'s> Iterator for ChordIter<'s> {
         let (next, rest) = self.rest.split_at(split);
         self.rest = rest;
 
-        Some(Chord::parse(next, self.notation))
+        Some(next)
+    }
+}
+
+impl<'s> Iterator for ChordIter<'s> {
+    type Item = Result<Chord<'s>, &'s str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk()
+            .map(|chunk| Chord::parse(chunk, self.notation))
     }
 }
 
@@ -417,6 +540,17 @@ pub fn transpose(
     by: impl Into<Chromatic>,
     src_notation: Notation,
     to_notation: Notation,
+) -> Result<String, &str> {
+    transpose_spelled(chord_set, by, src_notation, to_notation, Spelling::Mixed)
+}
+
+/// Like `transpose()`, with black keys in the result spelled according to `spelling`.
+pub fn transpose_spelled(
+    chord_set: &str,
+    by: impl Into<Chromatic>,
+    src_notation: Notation,
+    to_notation: Notation,
+    spelling: Spelling,
 ) -> Result<String, &str> {
     let by = by.into();
 
@@ -429,17 +563,240 @@ pub fn transpose(
     // Compute the resulting string's length
     let mut transposed_len = prefix.len();
     for chord in ChordIter::new(rest, src_notation) {
-        transposed_len += chord?.transposed(by).str_len(to_notation);
+        transposed_len += chord?.transposed(by).str_len(to_notation, spelling);
     }
 
     // Render the resulting string
     let mut res = String::with_capacity(transposed_len);
     res.push_str(prefix);
     Ok(ChordIter::new(rest, src_notation).fold(res, |res, chord| {
-        chord.unwrap().transposed(by).write_string(res, to_notation)
+        chord
+            .unwrap()
+            .transposed(by)
+            .write_string(res, to_notation, spelling)
     }))
 }
 
+/// Chord shapes fingered with a capo at the `capo` fret to sound as `chord_set`,
+/// ie. the chords transposed down by `capo` semitones, spelled with flats.
+pub fn capo_shapes(chord_set: &str, capo: u32, notation: Notation) -> Result<String, &str> {
+    let by = -((capo % 12) as i32);
+    transpose_spelled(
+        chord_set,
+        by,
+        notation,
+        notation,
+        Spelling::for_direction(by),
+    )
+}
+
+/// Parses the key of a song written in `notation`, such as `G` or `Em`, returning its tonic.
+/// The key is written in `notation.key_notation()`.
+pub fn parse_key(key: &str, notation: Notation) -> Option<Chromatic> {
+    let key = key.trim();
+    match Chromatic::parse_span(key, notation.key_notation()) {
+        Some((tonic, size)) if matches!(&key[size..], "" | "m") => Some(tonic),
+        _ => None,
+    }
+}
+
+/// Split a chord set into individual chords, returning each chord's base tone and its text.
+///
+/// Slash chords such as `G/B` are kept whole, parts which can't be parsed
+/// as chords in the `notation` are skipped.
+pub fn split_chords(chord_set: &str, notation: Notation) -> Vec<(Chromatic, &str)> {
+    let prefix_at = chord_set
+        .find(|c: char| !is_chord_separator(c))
+        .unwrap_or(0);
+
+    let mut res: Vec<(Chromatic, &str)> = vec![];
+    // Start of the last chord in `res` if it's followed by a single slash
+    let mut slash_start = None;
+    let mut pos = prefix_at;
+    let mut chunks = ChordIter::new(&chord_set[prefix_at..], notation);
+    while let Some(chunk) = chunks.next_chunk() {
+        let start = pos;
+        pos += chunk.len();
+        let name = chunk.trim_end_matches(is_chord_separator);
+
+        let chord = match Chord::parse(name, notation) {
+            Ok(chord) => chord,
+            Err(_) => {
+                slash_start = None;
+                continue;
+            }
+        };
+        let chord_start = match slash_start.take() {
+            Some(slash_start) => {
+                // The chord is a bass note, extend the previous chord
+                res.last_mut().unwrap().1 = &chord_set[slash_start..start + name.len()];
+                slash_start
+            }
+            None => {
+                res.push((chord.base, name));
+                start
+            }
+        };
+        if &chunk[name.len()..] == "/" {
+            slash_start = Some(chord_start);
+        }
+    }
+
+    res
+}
+
+/// Replace ASCII accidentals `#` and `b` with the `♯` and `♭` glyphs in a chord set.
+///
+/// Only accidentals are replaced, ie. those following a chord's base tone
+/// and those in the suffix followed by a digit (as in `C7b9` or `C7#11`).
+/// Chords which can't be parsed in the `notation` are left as they are.
+pub fn unicode_accidentals(chord_set: &str, notation: Notation) -> String {
+    fn accidental(c: char) -> Option<char> {
+        match c {
+            'b' => Some('♭'),
+            '#' => Some('♯'),
+            _ => None,
+        }
+    }
+
+    let prefix_at = chord_set
+        .find(|c: char| !is_chord_separator(c))
+        .unwrap_or(0);
+    let (prefix, rest) = chord_set.split_at(prefix_at);
+
+    let mut res = String::with_capacity(chord_set.len() + 8);
+    res.push_str(prefix);
+    let mut chunks = ChordIter::new(rest, notation);
+    while let Some(chunk) = chunks.next_chunk() {
+        let base_size = match Chromatic::parse_span(chunk, notation) {
+            Some((_, base_size)) => base_size,
+            None => {
+                res.push_str(chunk);
+                continue;
+            }
+        };
+
+        // The base tone's accidental, if any, is its last character,
+        // but the base may also consist of a single 'b' tone name.
+        let (base, suffix) = chunk.split_at(base_size);
+        let mut base_chars = base.chars();
+        match base_chars.next_back().map(|c| (c, accidental(c))) {
+            Some((_, Some(acc))) if !base_chars.as_str().is_empty() => {
+                res.push_str(base_chars.as_str());
+                res.push(acc);
+            }
+            _ => res.push_str(base),
+        }
+
+        let mut suffix = suffix.chars().peekable();
+        while let Some(c) = suffix.next() {
+            match accidental(c) {
+                Some(acc) if suffix.peek().map_or(false, char::is_ascii_digit) => res.push(acc),
+                _ => res.push(c),
+            }
+        }
+    }
+
+    res
+}
+
+/// Replace the `♯` and `♭` glyphs with ASCII `#` and `b`, the inverse of `unicode_accidentals()`.
+pub fn ascii_accidentals(chord_set: &str) -> String {
+    chord_set.replace('♯', "#").replace('♭', "b")
+}
+
+/// An instrument for which a book is made, see `Instrument::plays()`.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Instrument {
+    Guitar,
+    Ukulele,
+}
+
+impl Instrument {
+    /// Chord qualities with a common fingering on the instrument in all keys.
+    fn qualities(self) -> &'static [&'static str] {
+        const UKULELE: &[&str] = &[
+            "", "m", "5", "6", "m6", "7", "m7", "maj7", "9", "add9", "sus2", "sus4", "7sus4",
+            "dim", "dim7", "m7b5", "aug",
+        ];
+        const GUITAR: &[&str] = &[
+            "", "m", "5", "6", "m6", "7", "m7", "maj7", "9", "add9", "sus2", "sus4", "7sus4",
+            "dim", "dim7", "m7b5", "aug", "madd9", "mmaj7", "m9", "maj9", "7sus2", "9sus4", "11",
+            "m11", "add11", "13", "7b5", "7#5", "7b9", "7#9",
+        ];
+
+        match self {
+            Self::Guitar => GUITAR,
+            Self::Ukulele => UKULELE,
+        }
+    }
+
+    /// Returns whether `chord`, a single chord as returned by `split_chords()`,
+    /// is in the instrument's built-in chord set.
+    ///
+    /// Common spellings of chord qualities are recognized, eg. `Cmin`, `C-` and `c` are all C minor.
+    /// The bass note of slash chords is not checked.
+    pub fn plays(self, chord: &str, notation: Notation) -> bool {
+        const ALIASES: &[(&str, &str)] = &[
+            ("(", ""),
+            (")", ""),
+            ("min", "m"),
+            ("Maj", "maj"),
+            ("M7", "maj7"),
+            ("M9", "maj9"),
+            ("Δ9", "maj9"),
+            ("Δ7", "maj7"),
+            ("Δ", "maj7"),
+            ("°7", "dim7"),
+            ("°", "dim"),
+            ("ø7", "m7b5"),
+            ("ø", "m7b5"),
+            ("+", "aug"),
+        ];
+
+        let chord = ascii_accidentals(chord);
+        let chord = chord.split('/').next().unwrap_or_default();
+        let lowercase = chord.starts_with(char::is_lowercase);
+        let chord = match Chord::parse(chord, notation) {
+            Ok(chord) => chord,
+            Err(_) => return false,
+        };
+
+        let mut quality = ALIASES
+            .iter()
+            .fold(chord.suffix.to_string(), |quality, (alias, to)| {
+                quality.replace(alias, to)
+            });
+        if let Some(minor) = quality.strip_prefix('-') {
+            quality = format!("m{}", minor);
+        }
+        if quality.ends_with("sus") {
+            quality.push('4');
+        }
+        if quality == "maj" {
+            quality.clear();
+        }
+        // A lowercase base tone denotes a minor chord, eg. `am` or `ii7`
+        let minor = quality.starts_with('m') && !quality.starts_with("maj");
+        if lowercase && !minor {
+            quality.insert(0, 'm');
+        }
+
+        self.qualities().contains(&quality.as_str())
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Instrument::Guitar => "guitar",
+            Instrument::Ukulele => "ukulele",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -461,7 +818,7 @@ mod tests {
         // Test from_str:
         let parsed: Vec<_> = names
             .iter()
-            .map(|s| Notation::from_str(*s).unwrap())
+            .map(|s| Notation::from_str(s).unwrap())
             .collect();
         assert_eq!(&parsed, &expected);
 
@@ -634,10 +991,46 @@ mod tests {
         assert_eq!(t, "IV/V,VI");
     }
 
+    #[test]
+    fn parse_keys() {
+        assert_eq!(parse_key(" G ", English), Some(Chromatic::new(7)));
+        assert_eq!(parse_key("F#m", English), Some(Chromatic::new(6)));
+        assert_eq!(parse_key("Bb", Nashville), Some(Chromatic::new(10)));
+        assert_eq!(parse_key("B", German), Some(Chromatic::new(10)));
+        assert_eq!(parse_key("G7", English), None);
+        assert_eq!(parse_key("", English), None);
+        assert_eq!(parse_key("V", Roman), None);
+    }
+
+    #[test]
+    fn key_spelling() {
+        let spelling = |tonic, minor, notation| Spelling::for_key(tonic, minor, notation).unwrap();
+        assert_eq!(spelling("C", false, English), Spelling::Mixed);
+        assert_eq!(spelling("A", true, English), Spelling::Mixed);
+        assert_eq!(spelling("G", false, English), Spelling::Sharps);
+        assert_eq!(spelling("B", true, English), Spelling::Sharps);
+        assert_eq!(spelling("F", false, English), Spelling::Flats);
+        assert_eq!(spelling("G", true, English), Spelling::Flats);
+        assert_eq!(spelling("F#", false, English), Spelling::Sharps);
+        assert_eq!(spelling("Eb", true, English), Spelling::Flats);
+        assert_eq!(spelling("B", false, German), Spelling::Flats);
+        assert_eq!(spelling("H", false, German), Spelling::Sharps);
+        assert_eq!(Spelling::for_key("G7", false, English), None);
+    }
+
     #[test]
     fn transpose_nashville() {
         let t = transpose("I/II,III", 0, Roman, Nashville).unwrap();
         assert_eq!(t, "1/2,3");
+
+        let t = transpose("G7/B,Em7,Cmaj7,D", -7, English, Nashville).unwrap();
+        assert_eq!(t, "1(7)/3,6m7,4maj7,5");
+        let t = transpose("C9sus4", 0, English, Nashville).unwrap();
+        assert_eq!(t, "1(9)sus4");
+        let t = transpose("1(7)/3,5(9)sus4,57", 0, Nashville, Nashville).unwrap();
+        assert_eq!(t, "1(7)/3,5(9)sus4,5(7)");
+        let t = transpose("1(7)/3,6m", 7, Nashville, English).unwrap();
+        assert_eq!(t, "G7/B,Em");
     }
 
     #[test]
@@ -648,4 +1041,104 @@ mod tests {
         let t = transpose("c,d,e,", 2, English, Roman).unwrap();
         assert_eq!(t, "ii,iii,iv#,");
     }
+
+    #[test]
+    fn transpose_spelling() {
+        let up = Spelling::for_direction(1);
+        let down = Spelling::for_direction(-1);
+        assert_eq!(up, Spelling::Sharps);
+        assert_eq!(down, Spelling::Flats);
+        assert_eq!(Spelling::for_direction(0), Spelling::Mixed);
+
+        let t = transpose_spelled("C/E,Dm7", 1, English, English, up).unwrap();
+        assert_eq!(t, "C#/F,D#m7");
+        let t = transpose_spelled("D/F#,em", -1, English, English, down).unwrap();
+        assert_eq!(t, "Db/F,ebm");
+        let t = transpose_spelled("Bb", 0, English, English, Spelling::Mixed).unwrap();
+        assert_eq!(t, "Bb");
+
+        let t = transpose_spelled("H,c", 1, German, German, up).unwrap();
+        assert_eq!(t, "C,c#");
+        let t = transpose_spelled("C,H,a", -1, German, German, down).unwrap();
+        assert_eq!(t, "H,B,ab");
+        let t = transpose_spelled("A", 1, German, German, up).unwrap();
+        assert_eq!(t, "A#");
+
+        let t = transpose_spelled("1,4", -1, Nashville, Nashville, down).unwrap();
+        assert_eq!(t, "7,3");
+        let t = transpose_spelled("IV,ii", 1, Roman, Roman, up).unwrap();
+        assert_eq!(t, "IV#,ii#");
+    }
+
+    #[test]
+    fn capo_shapes_basic() {
+        assert_eq!(capo_shapes("C/G,Am7", 3, English).unwrap(), "A/E,Gbm7");
+        assert_eq!(capo_shapes("Eb", 1, English).unwrap(), "D");
+        assert_eq!(capo_shapes("D", 1, English).unwrap(), "Db");
+        assert_eq!(capo_shapes("C", 2, German).unwrap(), "B");
+        assert_eq!(capo_shapes("C", 1, German).unwrap(), "H");
+        assert_eq!(capo_shapes("G", 12, English).unwrap(), "G");
+        assert_eq!(capo_shapes("N.C.", 2, English), Err("N.C."));
+    }
+
+    #[test]
+    fn unicode_accidentals_basic() {
+        assert_eq!(unicode_accidentals("Bb", English), "B♭");
+        assert_eq!(unicode_accidentals("C#m7", English), "C♯m7");
+        assert_eq!(unicode_accidentals("F#", English), "F♯");
+        assert_eq!(unicode_accidentals("bb", English), "b♭");
+        assert_eq!(unicode_accidentals("b", English), "b");
+        assert_eq!(unicode_accidentals("Bbm/Ab", English), "B♭m/A♭");
+        assert_eq!(
+            unicode_accidentals(" C7b9, Cadd#11 ", English),
+            " C7♭9, Cadd♯11 "
+        );
+        assert_eq!(unicode_accidentals("IIIb", Roman), "III♭");
+        assert_eq!(unicode_accidentals("N.C.", English), "N.C.");
+    }
+
+    #[test]
+    fn ascii_accidentals_basic() {
+        assert_eq!(ascii_accidentals("B♭m/A♭"), "Bbm/Ab");
+        assert_eq!(ascii_accidentals(" C7♭9, Cadd♯11 "), " C7b9, Cadd#11 ");
+        assert_eq!(ascii_accidentals("Bbm/Ab"), "Bbm/Ab");
+    }
+
+    #[test]
+    fn instrument_plays() {
+        use Instrument::*;
+
+        for chord in [
+            "C", "Am7", "Bbmaj7", "F#m", "Dsus", "Emin", "G-", "a", "C°", "G/B",
+        ] {
+            assert!(Ukulele.plays(chord, English), "{}", chord);
+        }
+        assert!(!Ukulele.plays("C7#9", English));
+        assert!(Guitar.plays("C7#9", English));
+        assert!(Guitar.plays("C7(♯9)", English));
+        assert!(!Guitar.plays("Cmaj13#11", English));
+
+        assert!(Ukulele.plays("H7", German));
+        assert!(Ukulele.plays("ii7", Roman));
+        assert!(Ukulele.plays("4maj7", Nashville));
+        assert!(!Ukulele.plays("N.C.", English));
+    }
+
+    #[test]
+    fn split_chords_basic() {
+        let names = |chord_set| {
+            split_chords(chord_set, English)
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("C"), ["C"]);
+        assert_eq!(names(" Am7, D | G/B  Em "), ["Am7", "D", "G/B", "Em"]);
+        assert_eq!(names("N.C. C//D"), ["C", "D"]);
+        assert_eq!(names("C/N.C. D"), ["C", "D"]);
+
+        let (base, name) = split_chords("H7", German)[0];
+        assert_eq!(base, Chromatic::parse("B", English).unwrap());
+        assert_eq!(name, "H7");
+    }
 }
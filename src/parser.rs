@@ -22,6 +22,8 @@ use crate::music::{self, Notation};
 use crate::prelude::*;
 use crate::util::{BStr, StrExt};
 
+pub mod chordpro;
+pub mod explain;
 pub mod html;
 
 type AstRef<'a> = &'a AstNode<'a>;
@@ -30,6 +32,20 @@ type Arena<'a> = comrak::Arena<AstNode<'a>>;
 const FALLBACK_TITLE: &str = "[Untitled]";
 
 static EXTENSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)(!+)(\S+)").unwrap());
+static NAV_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{([^{}]*)\}|\|:|:\|").unwrap());
+static GLOSS_ANNOTATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^\s*gloss\s*:([^=]*)(?:=(.*))?$").unwrap());
+static ATTRIBUTION_ANNOTATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(author|composer|arranger|copyright|ccli|license)\s*:(.*)$").unwrap()
+});
+static LINK_ANNOTATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^\s*(?:link|audio)\s*:(.*)$").unwrap());
+static CAPO_ANNOTATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^\s*capo\s*:(.*)$").unwrap());
+static KEY_ANNOTATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)^\s*key\s*:(.*)$").unwrap());
+static CHORUS_ANNOTATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^\s*chorus\s*:(.*)$").unwrap());
+static REPEAT_ANNOTATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)^\s*(?:repeat\s*:(.*)|[x×]\s*(\d+)\s*)$").unwrap());
 
 #[derive(Error, PartialEq, Eq, Clone, Debug)]
 pub enum DiagKind {
@@ -41,6 +57,28 @@ pub enum DiagKind {
     HtmlReservedTag { tag: BStr },
     #[error("Text in HTML block ignored: \"{text}\"\nYou may need a blank line between the HTML block and the following text.")]
     HtmlIgnoredText { text: BStr },
+    #[error("Unrecognized navigation marker: {marker}")]
+    NavMarker { marker: BStr },
+    #[error(
+        "Invalid glossary annotation: {annotation}\nExpected form: {{gloss: term = definition}}"
+    )]
+    GlossAnnotation { annotation: BStr },
+    #[error("Empty attribution annotation: {annotation}\nExpected form: {{author: name}}")]
+    AttributionAnnotation { annotation: BStr },
+    #[error("Empty verse left out")]
+    EmptyVerse,
+    #[error("Empty link annotation: {annotation}\nExpected form: {{link: https://example.com/recording}}")]
+    LinkAnnotation { annotation: BStr },
+    #[error("Invalid repeat annotation: {annotation}\nExpected form: {{repeat: 2}} or {{x2}}")]
+    RepeatAnnotation { annotation: BStr },
+    #[error("Invalid capo annotation: {annotation}\nExpected form: {{capo: 2}}")]
+    CapoAnnotation { annotation: BStr },
+    #[error("Invalid key annotation: {annotation}\nExpected form: {{key: G}} or {{key: Em}}")]
+    KeyAnnotation { annotation: BStr },
+    #[error("Unsupported ChordPro directive left out: {directive}")]
+    ChordProDirective { directive: BStr },
+    #[error("Invalid chorus annotation: {annotation}\nExpected form: {{chorus: Refrain}} inside a chorus")]
+    ChorusAnnotation { annotation: BStr },
 }
 
 impl DiagKind {
@@ -50,9 +88,45 @@ impl DiagKind {
             Self::Transposition { .. } => true,
             Self::HtmlReservedTag { .. } => true,
             Self::HtmlIgnoredText { .. } => false,
+            Self::NavMarker { .. } => false,
+            Self::GlossAnnotation { .. } => false,
+            Self::AttributionAnnotation { .. } => false,
+            Self::EmptyVerse => false,
+            Self::LinkAnnotation { .. } => false,
+            Self::RepeatAnnotation { .. } => false,
+            Self::CapoAnnotation { .. } => false,
+            Self::KeyAnnotation { .. } => false,
+            Self::ChordProDirective { .. } => false,
+            Self::ChorusAnnotation { .. } => false,
         }
     }
 
+    /// Stable code of the diagnostic, the explanation is printed by `bard explain <code>`,
+    /// see `explain::EXPLANATIONS`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ControlChar { .. } => "B0001",
+            Self::Transposition { .. } => "B0002",
+            Self::HtmlReservedTag { .. } => "B0003",
+            Self::HtmlIgnoredText { .. } => "B0004",
+            Self::NavMarker { .. } => "B0005",
+            Self::GlossAnnotation { .. } => "B0006",
+            Self::AttributionAnnotation { .. } => "B0007",
+            Self::EmptyVerse => "B0008",
+            Self::LinkAnnotation { .. } => "B0009",
+            Self::RepeatAnnotation { .. } => "B0010",
+            Self::CapoAnnotation { .. } => "B0011",
+            Self::KeyAnnotation { .. } => "B0012",
+            Self::ChordProDirective { .. } => "B0013",
+            Self::ChorusAnnotation { .. } => "B0014",
+        }
+    }
+
+    /// Notices are only informative and only shown in verbose mode.
+    pub fn is_notice(&self) -> bool {
+        matches!(self, Self::EmptyVerse)
+    }
+
     fn html_ignored_text(text: &str) -> Self {
         const MAX_LEN: usize = 32;
         let mut truncated = String::with_capacity(text.len().min(MAX_LEN + 5));
@@ -72,7 +146,7 @@ impl DiagKind {
 /// Reports kind of diagnostic (error or warning), filename, line number and containts the specific error/warning.
 /// The line number is 1-indexed.
 #[derive(Error, PartialEq, Eq, Clone, Debug)]
-#[error("{file}:{line}: {kind}")]
+#[error("{file}:{line}: [{code}] {kind}", code = kind.code())]
 pub struct Diagnostic {
     pub file: PathBuf,
     pub line: usize,
@@ -84,8 +158,44 @@ impl Diagnostic {
     pub fn is_error(&self) -> bool {
         self.kind.is_error()
     }
+
+    #[inline]
+    pub fn is_notice(&self) -> bool {
+        self.kind.is_notice()
+    }
+
+    #[inline]
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn severity(&self) -> &'static str {
+        if self.is_error() {
+            "error"
+        } else if self.is_notice() {
+            "notice"
+        } else {
+            "warning"
+        }
+    }
+
+    /// The diagnostic as printed with `--diagnostics-format json`.
+    /// Only lines are tracked by the parser, so `column` is always `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "path": self.file,
+            "line": self.line,
+            "column": null,
+            "severity": self.severity(),
+            "code": self.code(),
+            "message": self.kind.to_string(),
+        })
+    }
 }
 
+/// Receiver of parser diagnostics, see `Project::with_diag_sink()`.
+///
+/// Implemented for closures, `mpsc::Sender`, and `App`, which prints the diagnostics.
 pub trait DiagSink {
     fn report(&self, diagnostic: Diagnostic);
 }
@@ -157,6 +267,26 @@ impl Extension {
         None
     }
 
+    fn try_parse_song_meta(&self, meta: &mut SongMeta) -> bool {
+        if self.num_excls != 1 {
+            return false;
+        }
+
+        match self.content.split_once('=') {
+            Some(("index", value)) => match value.parse() {
+                Ok(index) => meta.index = index,
+                Err(_) => return false,
+            },
+            Some(("keep_together", value)) => match value.parse() {
+                Ok(keep_together) => meta.keep_together = keep_together,
+                Err(_) => return false,
+            },
+            _ => return false,
+        }
+
+        true
+    }
+
     fn try_parse_chorus_ref(&self) -> Option<ChorusRef> {
         if self.num_excls == 1 && self.content.chars().all(|c| c == '>') {
             let num = self.content.len() as _;
@@ -457,6 +587,7 @@ impl<'a> NodeExt<'a> for AstNode<'a> {
 struct ChordBuilder {
     chord: BStr,
     alt_chord: Option<BStr>,
+    alternate: Option<ChordAlternate>,
     backticks: usize,
     baseline: bool,
     inlines: Vec<Inline>,
@@ -465,10 +596,21 @@ struct ChordBuilder {
 impl ChordBuilder {
     fn new(code: &NodeCode) -> Self {
         let (chord, baseline) = Self::preprocess_chord_set(&code.literal);
+        let (chord, alternate) = match Self::split_alternate(&chord) {
+            Some((chord, alternate)) => (
+                chord.into(),
+                Some(ChordAlternate {
+                    chord: alternate.into(),
+                    alt_chord: None,
+                }),
+            ),
+            None => (chord, None),
+        };
 
         Self {
             chord,
             alt_chord: None,
+            alternate,
             backticks: code.num_backticks,
             baseline,
             inlines: vec![],
@@ -513,6 +655,14 @@ impl ChordBuilder {
         (res.into(), baseline)
     }
 
+    /// Splits a chord set written as `[primary|alternate]` into the two parts.
+    /// The alternate follows the last `|`, so that the primary chord set may contain bars.
+    fn split_alternate(chord: &str) -> Option<(&str, &str)> {
+        let inner = chord.trim().strip_prefix('[')?.strip_suffix(']')?;
+        let (chord, alternate) = inner.rsplit_once('|')?;
+        Some((chord.trim(), alternate.trim()))
+    }
+
     fn inlines_mut(&mut self) -> &mut Vec<Inline> {
         &mut self.inlines
     }
@@ -529,12 +679,19 @@ impl ChordBuilder {
             let delta = xp.alt_xpose.unwrap_or(0);
             let to_nt = xp.alt_notation.unwrap_or(src_nt);
             self.alt_chord = Some(music::transpose(&self.chord, delta, src_nt, to_nt)?.into());
+            if let Some(alternate) = self.alternate.as_mut() {
+                alternate.alt_chord =
+                    Some(music::transpose(&alternate.chord, delta, src_nt, to_nt)?.into());
+            }
         }
 
         if xp.xpose.is_some() || xp.notation.is_some() {
             let delta = xp.xpose.unwrap_or(0);
             let to_nt = xp.notation.unwrap_or(src_nt);
             self.chord = music::transpose(&self.chord, delta, src_nt, to_nt)?.into();
+            if let Some(alternate) = self.alternate.as_mut() {
+                alternate.chord = music::transpose(&alternate.chord, delta, src_nt, to_nt)?.into();
+            }
         }
 
         Ok(())
@@ -544,6 +701,7 @@ impl ChordBuilder {
         let chord = Chord::new(
             self.chord,
             self.alt_chord,
+            self.alternate,
             self.backticks,
             self.baseline,
             self.inlines,
@@ -556,30 +714,36 @@ impl ChordBuilder {
 struct VerseBuilder<'a> {
     label: VerseLabel,
     paragraphs: Vec<Paragraph>,
+    /// Set by a repeat annotation, the last one in a verse is used.
+    repeat: Option<u32>,
+    /// Source line where the verse starts.
+    line: usize,
     ctx: &'a ParserCtx<'a>,
 }
 
 impl<'a> VerseBuilder<'a> {
-    fn new(label: VerseLabel, ctx: &'a ParserCtx<'a>) -> Self {
+    fn new(label: VerseLabel, line: usize, ctx: &'a ParserCtx<'a>) -> Self {
         Self {
             label,
             paragraphs: vec![],
+            repeat: None,
+            line,
             ctx,
         }
     }
 
-    fn with_p_nodes<I>(label: VerseLabel, ctx: &'a ParserCtx<'a>, nodes: I) -> Self
+    fn with_p_nodes<I>(label: VerseLabel, line: usize, ctx: &'a ParserCtx<'a>, nodes: I) -> Self
     where
         I: Iterator<Item = AstRef<'a>>,
     {
-        nodes.fold(Self::new(label, ctx), |mut this, node| {
+        nodes.fold(Self::new(label, line, ctx), |mut this, node| {
             this.add_p_node(node);
             this
         })
     }
 
     /// Parse a text node. It may parse into a series of `Inline`s
-    /// since extension parsing is handled here.
+    /// since navigation markers, glossary, attribution, link, capo, key, chorus, and repeat annotations and extension parsing is handled here.
     fn parse_text(&mut self, node: AstRef, target: &mut Vec<Inline>) {
         let data = node.data.borrow();
         let text = data
@@ -587,13 +751,166 @@ impl<'a> VerseBuilder<'a> {
             .text()
             .unwrap_or_else(|| unreachable!("Unexpected element: {:?}", &data.value));
 
+        let mut pos = 0;
+        for caps in NAV_MARKER.captures_iter(text) {
+            let hit = caps.get(0).unwrap();
+            let marker = caps.get(1).unwrap_or(hit).as_str();
+            if let Some(gloss) = GLOSS_ANNOTATION.captures(marker) {
+                // Glossary annotations are removed from the text
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                let definition = gloss.get(2).map_or("", |m| m.as_str());
+                match (gloss[1].trim(), definition.trim()) {
+                    ("", _) | (_, "") => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::GlossAnnotation { annotation },
+                        );
+                    }
+                    (term, definition) => self
+                        .ctx
+                        .glossary
+                        .borrow_mut()
+                        .push(GlossEntry::new(term, definition)),
+                }
+            } else if let Some(attribution) = ATTRIBUTION_ANNOTATION.captures(marker) {
+                // Attribution annotations are removed from the text as well
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                match attribution[2].trim() {
+                    "" => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::AttributionAnnotation { annotation },
+                        );
+                    }
+                    value => {
+                        let field = attribution[1].to_lowercase();
+                        self.ctx.attribution.borrow_mut().add(&field, value);
+                    }
+                }
+            } else if let Some(link) = LINK_ANNOTATION.captures(marker) {
+                // Link annotations too, the last one in a song is used
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                match link[1].trim() {
+                    "" => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::LinkAnnotation { annotation },
+                        );
+                    }
+                    value => {
+                        self.ctx.link.replace(Some(value.into()));
+                    }
+                }
+            } else if let Some(capo) = CAPO_ANNOTATION.captures(marker) {
+                // Capo annotations as well, the last one in a song is used
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                match capo[1].trim().parse::<u32>() {
+                    Ok(capo) if capo < 12 => {
+                        self.ctx.capo.replace(Some(capo));
+                    }
+                    _ => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::CapoAnnotation { annotation },
+                        );
+                    }
+                }
+            } else if let Some(key) = KEY_ANNOTATION.captures(marker) {
+                // Key annotations as well, the last one in a song is used
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                let key = key[1].trim();
+                if music::parse_key(key, self.ctx.xp().src_notation).is_some() {
+                    self.ctx.key.replace(Some(key.into()));
+                } else {
+                    let annotation = hit.as_str().into();
+                    self.ctx
+                        .report_diag(node.source_line(), DiagKind::KeyAnnotation { annotation });
+                }
+            } else if let Some(chorus) = CHORUS_ANNOTATION.captures(marker) {
+                // Chorus annotations name the chorus they're in
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                match (&self.label, chorus[1].trim()) {
+                    (VerseLabel::Chorus(num) | VerseLabel::NamedChorus { num, .. }, name)
+                        if !name.is_empty() =>
+                    {
+                        self.label = VerseLabel::NamedChorus {
+                            num: *num,
+                            name: name.into(),
+                        };
+                    }
+                    _ => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::ChorusAnnotation { annotation },
+                        );
+                    }
+                }
+            } else if let Some(repeat) = REPEAT_ANNOTATION.captures(marker) {
+                // Repeat annotations apply to the verse they're in
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                pos = hit.end();
+                let count = repeat.get(1).or_else(|| repeat.get(2)).unwrap().as_str();
+                match count.trim().parse::<u32>() {
+                    Ok(count) if count > 0 => self.repeat = (count > 1).then_some(count),
+                    _ => {
+                        let annotation = hit.as_str().into();
+                        self.ctx.report_diag(
+                            node.source_line(),
+                            DiagKind::RepeatAnnotation { annotation },
+                        );
+                    }
+                }
+            } else if let Some(marker) = NavMarker::parse(marker) {
+                self.parse_text_extensions(&text[pos..hit.start()], target);
+                target.push(Inline::Nav { marker });
+                pos = hit.end();
+            } else {
+                // Unrecognized markers are left in as regular text
+                let marker = hit.as_str().into();
+                self.ctx
+                    .report_diag(node.source_line(), DiagKind::NavMarker { marker });
+            }
+        }
+
+        self.parse_text_extensions(&text[pos..], target);
+    }
+
+    /// Parse text that may contain bard MD extensions.
+    fn parse_text_extensions(&mut self, text: &str, target: &mut Vec<Inline>) {
+        if text.is_empty() {
+            return;
+        }
+
         let mut pos = 0;
         for caps in EXTENSION.captures_iter(text) {
             let hit = caps.get(0).unwrap();
 
             // Try parsing an extension
             let ext = Extension::from(caps);
-            if let Some(inline) = ext.try_parse() {
+            if ext.try_parse_song_meta(&mut self.ctx.song_meta.borrow_mut()) {
+                // Song settings are applied to the song as a whole, nothing is added to the AST
+                let preceding = &text[pos..hit.start()];
+                if !preceding.is_empty() {
+                    target.push(Inline::text(preceding));
+                }
+
+                pos = if !ext.prefix_space && hit.end() < text.len() {
+                    hit.end() + 1
+                } else {
+                    hit.end()
+                };
+            } else if let Some(inline) = ext.try_parse() {
                 // First see if there's regular text preceding the extension
                 let preceding = &text[pos..hit.start()];
                 if !preceding.is_empty() {
@@ -761,8 +1078,22 @@ impl<'a> VerseBuilder<'a> {
         }
     }
 
-    fn finalize(self) -> Verse {
-        Verse::new(self.label, self.paragraphs)
+    /// Returns `None` if the verse turns out empty after whitespace normalization,
+    /// see `Verse::normalize_whitespace()`.
+    fn finalize(self) -> Option<Verse> {
+        let mut verse = Verse::new(self.label, self.paragraphs).with_repeat(self.repeat);
+        if self.ctx.preserve_whitespace {
+            return Some(verse);
+        }
+
+        verse.normalize_whitespace();
+        verse.group_chord_lines();
+        if verse.paragraphs.is_empty() && verse.label.is_some() {
+            self.ctx.report_diag(self.line, DiagKind::EmptyVerse);
+            return None;
+        }
+
+        Some(verse)
     }
 }
 
@@ -794,6 +1125,12 @@ impl<'a> SongBuilder<'a> {
 
         // Shift nodes to the song content
         let nodes = &nodes[subtitles.len()..];
+        ctx.song_meta.replace(ctx.song_meta_default.clone());
+        ctx.glossary.take();
+        ctx.attribution.take();
+        ctx.link.take();
+        ctx.capo.take();
+        ctx.key.take();
 
         Self {
             nodes,
@@ -812,17 +1149,17 @@ impl<'a> SongBuilder<'a> {
         self.verse_num
     }
 
-    fn verse_mut(&mut self) -> &mut VerseBuilder<'a> {
+    fn verse_mut(&mut self, line: usize) -> &mut VerseBuilder<'a> {
         if self.verse.is_none() {
-            self.verse = Some(VerseBuilder::new(VerseLabel::None {}, self.ctx));
+            self.verse = Some(VerseBuilder::new(VerseLabel::None {}, line, self.ctx));
         }
 
         self.verse.as_mut().unwrap()
     }
 
     fn verse_finalize(&mut self) {
-        if let Some(verse) = self.verse.take() {
-            self.blocks.push(Block::Verse(verse.finalize()));
+        if let Some(verse) = self.verse.take().and_then(VerseBuilder::finalize) {
+            self.blocks.push(Block::Verse(verse));
         }
     }
 
@@ -843,11 +1180,11 @@ impl<'a> SongBuilder<'a> {
 
                 if self.verse.is_none() {
                     let label = VerseLabel::Chorus(Some(level));
-                    let verse = VerseBuilder::new(label, self.ctx);
+                    let verse = VerseBuilder::new(label, c.source_line(), self.ctx);
                     self.verse = Some(verse);
                 }
 
-                self.verse_mut().add_p_node(c);
+                self.verse_mut(c.source_line()).add_p_node(c);
             }
         }
     }
@@ -859,7 +1196,7 @@ impl<'a> SongBuilder<'a> {
             }
 
             match &node.data.borrow().value {
-                NodeValue::Paragraph => self.verse_mut().add_p_node(node),
+                NodeValue::Paragraph => self.verse_mut(node.source_line()).add_p_node(node),
 
                 NodeValue::List(list) if matches!(list.list_type, ListType::Ordered) => {
                     for item in node.children() {
@@ -867,7 +1204,12 @@ impl<'a> SongBuilder<'a> {
                         self.verse_finalize();
 
                         let label = VerseLabel::Verse(self.next_verse_num());
-                        let verse = VerseBuilder::with_p_nodes(label, self.ctx, item.children());
+                        let verse = VerseBuilder::with_p_nodes(
+                            label,
+                            item.source_line(),
+                            self.ctx,
+                            item.children(),
+                        );
                         self.verse = Some(verse);
                     }
                 }
@@ -887,13 +1229,17 @@ impl<'a> SongBuilder<'a> {
 
                 NodeValue::Heading(h) if h.level >= 3 => {
                     let label = VerseLabel::Custom(node.as_plaintext().into());
-                    self.verse = Some(VerseBuilder::new(label, self.ctx));
+                    self.verse = Some(VerseBuilder::new(label, node.source_line(), self.ctx));
                 }
 
                 NodeValue::ThematicBreak => {
                     self.blocks.push(Block::HorizontalLine);
                 }
 
+                NodeValue::CodeBlock(cb) if cb.info.trim() == "lilypond" => self
+                    .blocks
+                    .push(Block::Lilypond(Lilypond::new(cb.literal.clone_bstr()))),
+
                 NodeValue::CodeBlock(cb) => self.blocks.push(Block::Pre {
                     text: cb.literal.clone_bstr(),
                 }),
@@ -925,15 +1271,38 @@ impl<'a> SongBuilder<'a> {
             .map(|b| b.chorus_num().unwrap_or(0))
             .max()
             .unwrap_or(0);
+        // References to choruses named by a chorus annotation show the name instead.
+        let names: Vec<(u32, BStr)> = self
+            .blocks
+            .iter()
+            .filter_map(Block::chorus_name)
+            .map(|(num, name)| (num, name.clone()))
+            .collect();
+        if !names.is_empty() {
+            self.blocks
+                .iter_mut()
+                .for_each(|block| block.name_chorus_refs(&names));
+        }
         if max_chorus < 2 {
             self.blocks.iter_mut().for_each(Block::remove_chorus_num);
         }
 
+        let slug = slug(&self.title).into();
         let mut song = Song {
             title: self.title.into(),
+            slug,
             subtitles: self.subtitles.into(),
             blocks: self.blocks,
             notation: self.ctx.xp().src_notation,
+            index: self.ctx.song_meta.borrow().index,
+            keep_together: self.ctx.song_meta.borrow().keep_together,
+            glossary: self.ctx.glossary.take(),
+            attribution: self.ctx.attribution.take(),
+            link: self.ctx.link.take(),
+            qr: None,
+            capo: self.ctx.capo.take(),
+            key: self.ctx.key.take(),
+            section: None,
         };
 
         song.postprocess();
@@ -982,12 +1351,16 @@ impl<'s, 'a> Iterator for SongsIter<'s, 'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Hash, Debug)]
 pub struct ParserConfig {
     pub notation: Notation,
     pub fallback_title: String,
     pub xp_disabled: bool,
     pub smart_punctuation: bool,
+    /// Default of the `keep_together` song setting.
+    pub keep_together: bool,
+    /// Keep whitespace and empty verses as written, see `Verse::normalize_whitespace()`.
+    pub preserve_whitespace: bool,
 }
 
 impl ParserConfig {
@@ -997,6 +1370,8 @@ impl ParserConfig {
             fallback_title: FALLBACK_TITLE.into(),
             xp_disabled: false,
             smart_punctuation,
+            keep_together: false,
+            preserve_whitespace: false,
         }
     }
 
@@ -1004,6 +1379,16 @@ impl ParserConfig {
         self.xp_disabled = xp_disabled;
         self
     }
+
+    pub fn keep_together(mut self, keep_together: bool) -> Self {
+        self.keep_together = keep_together;
+        self
+    }
+
+    pub fn preserve_whitespace(mut self, preserve_whitespace: bool) -> Self {
+        self.preserve_whitespace = preserve_whitespace;
+        self
+    }
 }
 
 impl Default for ParserConfig {
@@ -1013,6 +1398,8 @@ impl Default for ParserConfig {
             fallback_title: FALLBACK_TITLE.into(),
             xp_disabled: false,
             smart_punctuation: true,
+            keep_together: false,
+            preserve_whitespace: false,
         }
     }
 }
@@ -1020,21 +1407,49 @@ impl Default for ParserConfig {
 struct ParserCtx<'d> {
     fallback_title: String,
     xp: RefCell<Transposition>,
+    /// Settings of the song currently being parsed.
+    song_meta: RefCell<SongMeta>,
+    /// Settings each song starts with, see `ParserConfig`.
+    song_meta_default: SongMeta,
+    /// Glossary entries of the song currently being parsed.
+    glossary: RefCell<Vec<GlossEntry>>,
+    /// Attribution of the song currently being parsed.
+    attribution: RefCell<Attribution>,
+    /// Link of the song currently being parsed.
+    link: RefCell<Option<BStr>>,
+    /// Capo position of the song currently being parsed.
+    capo: RefCell<Option<u32>>,
+    /// Key of the song currently being parsed.
+    key: RefCell<Option<BStr>>,
     input_file: PathBuf,
     diag_sink: Box<dyn DiagSink + 'd>,
     error_seen: Cell<bool>,
     smart_punctuation: bool,
+    preserve_whitespace: bool,
 }
 
 impl<'d> ParserCtx<'d> {
     fn new(config: ParserConfig, input_file: &Path, diag_sink: Box<dyn DiagSink + 'd>) -> Self {
+        let song_meta_default = SongMeta {
+            keep_together: config.keep_together,
+            ..SongMeta::default()
+        };
+
         Self {
             fallback_title: config.fallback_title,
             xp: RefCell::new(Transposition::new(config.notation, config.xp_disabled)),
+            song_meta: RefCell::new(song_meta_default.clone()),
+            song_meta_default,
+            glossary: RefCell::new(vec![]),
+            attribution: RefCell::new(Attribution::default()),
+            link: RefCell::new(None),
+            capo: RefCell::new(None),
+            key: RefCell::new(None),
             input_file: input_file.to_owned(),
             diag_sink,
             error_seen: Cell::new(false),
             smart_punctuation: config.smart_punctuation,
+            preserve_whitespace: config.preserve_whitespace,
         }
     }
 
@@ -0,0 +1,270 @@
+//! Import of songs in the ChordPro format, see `to_markdown()`.
+//!
+//! ChordPro songs are converted to bard Markdown, which is then parsed as usual,
+//! so that they produce the same AST. Supported are the `title`, `subtitle`,
+//! `start_of_chorus`/`end_of_chorus`, `start_of_verse`/`end_of_verse`, `start_of_tab`/`end_of_tab`,
+//! `new_song`, and `comment` directives
+//! and their short forms (a comment such as `{c: x2}` right after a verse is its repeat annotation), the `key`, `capo`, `artist`, `composer`, and `copyright` directives,
+//! which are converted to bard annotations, and chords written inline as `[C]`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{DiagKind, DiagSink, Diagnostic};
+use crate::prelude::*;
+
+/// Extensions of files loaded as ChordPro songs.
+pub const EXTENSIONS: &[&str] = &["cho", "crd", "chopro", "chordpro", "pro"];
+
+static DIRECTIVE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\{\s*([A-Za-z_-]+)(?:\s*[:\s]\s*(.*?))?\s*\}$").unwrap());
+static REPEAT_COMMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[x×]\s*\d+$").unwrap());
+
+pub fn is_chordpro(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            EXTENSIONS
+                .iter()
+                .any(|chordpro| ext.eq_ignore_ascii_case(chordpro))
+        })
+}
+
+/// Result of `to_markdown()`.
+#[derive(Debug)]
+pub struct Converted {
+    pub markdown: String,
+    /// Line numbers in the ChordPro source of each line of `markdown`.
+    lines: Vec<usize>,
+}
+
+impl Converted {
+    /// Maps a 1-based line number of `markdown` to the ChordPro source line it was converted from.
+    pub fn source_line(&self, line: usize) -> usize {
+        line.checked_sub(1)
+            .and_then(|idx| self.lines.get(idx))
+            .or_else(|| self.lines.last())
+            .copied()
+            .unwrap_or(line)
+    }
+}
+
+/// Converts a ChordPro `source` to bard Markdown. Unknown directives are left out
+/// and reported to `diag_sink` as `DiagKind::ChordProDirective`.
+pub fn to_markdown(source: &str, file: &Path, diag_sink: &dyn DiagSink) -> Converted {
+    let mut converter = Converter::default();
+    for (i, line) in source.lines().enumerate() {
+        converter.line = i + 1;
+        let line = line.trim_end();
+        let trimmed = line.trim_start();
+
+        if converter.tab {
+            if matches!(trimmed, "{end_of_tab}" | "{eot}") {
+                converter.push("```");
+                converter.push("");
+                converter.tab = false;
+            } else {
+                converter.push(line);
+            }
+            continue;
+        } else if trimmed.starts_with('#') {
+            // A ChordPro comment
+            continue;
+        } else if trimmed.is_empty() {
+            converter.blank();
+            continue;
+        }
+
+        let directive = match DIRECTIVE.captures(trimmed) {
+            Some(directive) => directive,
+            None => {
+                converter.lyrics(line);
+                continue;
+            }
+        };
+
+        let value = directive.get(2).map_or("", |value| value.as_str());
+        match directive[1].to_ascii_lowercase().as_str() {
+            "title" | "t" => {
+                converter.end_block();
+                converter.last_lyrics = None;
+                converter.push(&format!("# {}", escape(value, false)));
+            }
+            "subtitle" | "st" => converter.push(&format!("## {}", escape(value, false))),
+            "start_of_chorus" | "soc" => {
+                converter.end_block();
+                converter.chorus = true;
+            }
+            "end_of_chorus" | "eoc" => {
+                converter.end_block();
+                converter.chorus = false;
+            }
+            "start_of_verse" | "sov" | "end_of_verse" | "eov" | "new_song" | "ns" => {
+                converter.end_block()
+            }
+            "start_of_tab" | "sot" => {
+                converter.end_block();
+                converter.push("```");
+                converter.tab = true;
+                converter.last_lyrics = None;
+            }
+            "comment" | "c" | "comment_italic" | "ci" | "comment_box" | "cb" => {
+                if !converter.repeat(value) {
+                    converter.end_block();
+                    converter.push(&format!("*{}*", escape(value, false)));
+                    converter.end_block();
+                }
+            }
+            name @ ("key" | "capo" | "composer" | "copyright") => converter.annotation(name, value),
+            "artist" => converter.annotation("author", value),
+            _ => diag_sink.report(Diagnostic {
+                file: file.to_owned(),
+                line: converter.line,
+                kind: DiagKind::ChordProDirective {
+                    directive: trimmed.into(),
+                },
+            }),
+        }
+    }
+
+    Converted {
+        markdown: converter.markdown,
+        lines: converter.lines,
+    }
+}
+
+#[derive(Default, Debug)]
+struct Converter {
+    markdown: String,
+    lines: Vec<usize>,
+    /// The ChordPro source line being converted.
+    line: usize,
+    chorus: bool,
+    /// Inside a `{start_of_tab}` block, which is converted to a code block as is.
+    tab: bool,
+    /// Whether a verse or a chorus has been started by lyrics since the last blank line.
+    in_block: bool,
+    /// Position in `markdown` of the end of the last lyrics line, where a repeat annotation goes.
+    last_lyrics: Option<usize>,
+}
+
+impl Converter {
+    fn push(&mut self, line: &str) {
+        self.markdown.push_str(line);
+        self.markdown.push('\n');
+        self.lines.push(self.line);
+    }
+
+    fn end_block(&mut self) {
+        if self.lines.is_empty() || self.markdown.ends_with("\n\n") {
+            return;
+        }
+
+        self.push("");
+        self.in_block = false;
+    }
+
+    /// Blank lines separate verses, or paragraphs within a chorus.
+    fn blank(&mut self) {
+        if self.chorus && self.in_block {
+            self.push(">");
+        } else {
+            self.end_block();
+        }
+    }
+
+    fn lyrics(&mut self, line: &str) {
+        let prefix = match (self.chorus, self.in_block) {
+            (true, _) => "> ",
+            (false, false) => "1. ",
+            (false, true) => "",
+        };
+        self.in_block = true;
+        self.push(&format!("{}{}", prefix, convert_chords(line.trim_start())));
+        self.last_lyrics = Some(self.markdown.len() - 1);
+    }
+
+    /// A comment such as `{comment: x2}` is converted to a repeat annotation of the last verse.
+    fn repeat(&mut self, comment: &str) -> bool {
+        match self.last_lyrics.take() {
+            Some(pos) if REPEAT_COMMENT.is_match(comment) => {
+                self.markdown.insert_str(pos, &format!(" {{{}}}", comment));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn annotation(&mut self, name: &str, value: &str) {
+        self.end_block();
+        self.push(&format!("{{{}: {}}}", name, value));
+        self.end_block();
+    }
+}
+
+/// Escapes characters with a meaning in Markdown, at the `line_start` also block markers such as `-` or `1.`.
+fn escape(text: &str, line_start: bool) -> String {
+    let mut res = String::with_capacity(text.len() + 2);
+    let mut rest = text;
+    if line_start {
+        let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if text.starts_with(&['#', '>', '-', '+', '='][..]) {
+            res.push('\\');
+        } else if digits > 0 && text[digits..].starts_with(&['.', ')'][..]) {
+            res.push_str(&text[..digits]);
+            res.push('\\');
+            rest = &text[digits..];
+        }
+    }
+
+    for c in rest.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '<' | '[' | ']') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Converts inline ChordPro chords such as `[C]` to bard chords, the rest of `line` is escaped.
+fn convert_chords(line: &str) -> String {
+    let mut res = String::with_capacity(line.len() + 8);
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let end = match rest[start..].find(']') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        res.push_str(&escape(&rest[..start], res.is_empty()));
+        let chord = rest[start + 1..end].trim();
+        if !chord.is_empty() {
+            res.push('`');
+            res.push_str(chord);
+            res.push('`');
+        }
+        rest = &rest[end + 1..];
+    }
+
+    res.push_str(&escape(rest, res.is_empty()));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_chords_inline() {
+        assert_eq!(
+            convert_chords("Oh the [G]summer time is [G/B]coming"),
+            "Oh the `G`summer time is `G/B`coming"
+        );
+        assert_eq!(convert_chords("[Am] [C]"), "`Am` `C`");
+        assert_eq!(
+            convert_chords("1. *Not* a list [x"),
+            "1\\. \\*Not\\* a list \\[x"
+        );
+        assert_eq!(convert_chords("- dash [D]- more"), "\\- dash `D`- more");
+    }
+}
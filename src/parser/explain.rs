@@ -0,0 +1,226 @@
+//! Longer explanations of parser diagnostics, printed by `bard explain <code>`.
+//!
+//! Each `DiagKind` has a stable code, see `DiagKind::code()`.
+
+use crate::prelude::*;
+
+#[derive(Debug)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub text: &'static str,
+}
+
+impl Explanation {
+    const fn new(code: &'static str, text: &'static str) -> Self {
+        Self { code, text }
+    }
+}
+
+pub static EXPLANATIONS: &[Explanation] = &[
+    Explanation::new(
+        "B0001",
+        r#"A control character was found in a song file.
+
+Control characters, such as the null character or escape, are not allowed in songs,
+as they can't be rendered and typically get into a file by accident, eg. when copying text
+from a PDF or a word processor. Tabs and line breaks are fine.
+
+To fix this, find the character on the reported line and remove it. Many editors can show
+invisible characters, or you can retype the line."#,
+    ),
+    Explanation::new(
+        "B0002",
+        r#"A chord could not be transposed or converted to another notation.
+
+Chords are only recognized when transposition (`!+N`) or a notation change (`!!notation`)
+is applied. Each chord in a chord span has to start with a tone in the song's notation,
+for example:
+
+    !+2
+    1. `Am`Lyrics `Hm`lyrics
+
+Here `Hm` is not a chord in the default english notation, where it would be `Bm`.
+
+To fix this, correct the chord, or set the notation the song is written in with the
+'notation' setting in bard.toml or with a `!!notation` extension, eg. `!!german`."#,
+    ),
+    Explanation::new(
+        "B0003",
+        r#"An HTML tag reserved for future use was found in a song.
+
+Some HTML tags, such as <html> or <tex>, have a special meaning in bard or are reserved
+for it. They can't be used in songs, for example:
+
+    1. Lyrics <tex>\bigskip</tex>
+
+To fix this, use a different tag name. Custom tags can be rendered with inline partials
+in templates, see the Templates chapter of the bard book."#,
+    ),
+    Explanation::new(
+        "B0004",
+        r#"Text in an HTML block was ignored.
+
+In Markdown, an HTML block continues until the next blank line, and text inside it is not
+treated as lyrics. This typically happens when lyrics directly follow an HTML tag on its
+own line, for example:
+
+    <div class="note">
+    1. Lyrics
+
+To fix this, put a blank line between the HTML block and the following text."#,
+    ),
+    Explanation::new(
+        "B0005",
+        r#"A navigation marker was not recognized.
+
+Navigation markers are written in curly braces in the lyrics, for example `{segno}`,
+`{coda}`, `{D.S. al coda}`, or as `|:` and `:|` repeat signs. Text in curly braces which
+is not a known marker is kept as it is, for example:
+
+    1. Lyrics {sengo}
+
+To fix this, correct the marker's name, see the Writing Songs chapter of the bard book
+for the list of supported markers."#,
+    ),
+    Explanation::new(
+        "B0006",
+        r#"A glossary annotation is not valid.
+
+Glossary annotations define a term and its definition, which are collected into the
+book's glossary, for example:
+
+    {gloss: capo = a clamp shortening the strings of a guitar}
+
+The annotation is ignored if the term or the definition is missing, or the `=` is left out.
+
+To fix this, write the annotation in the `{gloss: term = definition}` form."#,
+    ),
+    Explanation::new(
+        "B0007",
+        r#"An attribution annotation is empty.
+
+Attribution annotations, such as `{author: ...}`, `{composer: ...}`, or `{copyright: ...}`,
+need a value, for example:
+
+    {author: Robert Tannahill}
+
+An annotation without a value, such as `{author:}`, is ignored.
+
+To fix this, fill in the value or remove the annotation."#,
+    ),
+    Explanation::new(
+        "B0008",
+        r#"An empty verse was left out.
+
+A verse with no lyrics and no chords, such as a verse label left over from copying
+a song, is not rendered. This is only a notice shown with `--verbose`.
+
+To keep the verse, add some content to it, or set 'preserve_whitespace' in bard.toml,
+which keeps songs' whitespace and empty verses as written."#,
+    ),
+    Explanation::new(
+        "B0009",
+        r#"A link annotation is empty.
+
+Link annotations, `{link: ...}` or `{audio: ...}`, give a song a link to a recording
+or a related page, which outputs with 'qr_codes' set render as a QR code, for example:
+
+    {link: https://example.com/recordings/wild-mountain-thyme.mp3}
+
+An annotation without a value, such as `{link:}`, is ignored.
+
+To fix this, fill in the URL or remove the annotation."#,
+    ),
+    Explanation::new(
+        "B0010",
+        r#"A repeat annotation doesn't contain a valid count.
+
+Repeat annotations, `{repeat: N}` or the shorter `{xN}`, mark a verse or chorus
+to be sung N times, outputs render them as an indicator such as (×2), for example:
+
+    > Will ye go, lassie, go? {x2}
+
+The count has to be a positive whole number. An invalid annotation, such as `{repeat: twice}`,
+is ignored and the verse is shown without the indicator.
+
+To fix this, correct the count or remove the annotation."#,
+    ),
+    Explanation::new(
+        "B0011",
+        r#"A capo annotation doesn't contain a valid fret number.
+
+A capo annotation, `{capo: N}`, gives the fret a song is played with a capo at, for example:
+
+    # Wild Mountain Thyme
+    {capo: 2}
+
+Outputs then show the chords as played with the capo, ie. transposed down by N semitones,
+with a "Capo N" note at the top of the song. The fret has to be a whole number from 0 to 11,
+`{capo: 0}` means no capo, even if the output sets one with its 'capo' setting.
+An invalid annotation, such as `{capo: II}`, is ignored.
+
+To fix this, correct the fret number or remove the annotation."#,
+    ),
+    Explanation::new(
+        "B0012",
+        r#"A key annotation doesn't contain a valid key.
+
+A key annotation, `{key: K}`, gives the key of a song, for example:
+
+    # Wild Mountain Thyme
+    {key: G}
+
+Outputs with the 'notation' setting set to "nashville" or "roman" show the chords
+as degrees relative to the key, eg. `G/B` as `1/3` in the key of G.
+The key is a tone in the notation of the song, optionally followed by `m` for minor keys,
+such as `{key: Em}`. Songs written in the Nashville or Roman notation give the key
+in the English notation. An invalid annotation, such as `{key: G7}`, is ignored.
+
+To fix this, correct the key or remove the annotation."#,
+    ),
+    Explanation::new(
+        "B0013",
+        r#"A ChordPro song contains a directive bard doesn't support.
+
+Songs in the ChordPro format, ie. files with the .cho, .crd, .chopro, .chordpro, or .pro extension,
+are converted to bard songs. Supported are the title, subtitle, start_of_chorus, end_of_chorus,
+start_of_verse, end_of_verse, and comment directives and their short forms, such as {t: ...} or {soc},
+and the key, capo, artist, composer, and copyright directives, which are converted to bard annotations.
+Other directives, such as {textsize: 12} or {define: ...}, are left out and the rest of the song is built as usual.
+
+To fix this, remove the directive, or convert the song to Markdown to use bard's own features instead."#,
+    ),
+    Explanation::new(
+        "B0014",
+        r#"A chorus annotation is empty or not inside a chorus.
+
+A chorus annotation, `{chorus: name}`, names the chorus it's in, for example:
+
+    > And we'll all go together to pull wild mountain thyme {chorus: Refrain 1}
+
+The name is shown instead of the book's chorus_label, also in references
+to the chorus, such as `!>`. Only choruses, started with `>`, `>>`, etc., can be named.
+An invalid annotation, such as one in a numbered verse, is ignored.
+
+To fix this, move the annotation into a chorus, or use a custom verse label, eg. `### Bridge`."#,
+    ),
+];
+
+/// Looks up the explanation of a diagnostic `code`, eg. `B0002`.
+/// The code is case-insensitive and the `B` prefix may be left out.
+pub fn explain(code: &str) -> Result<&'static Explanation> {
+    let code = code.trim();
+    let num = code.strip_prefix(|c| c == 'B' || c == 'b').unwrap_or(code);
+
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code[1..] == *num)
+        .ok_or_else(|| {
+            let codes: Vec<_> = EXPLANATIONS.iter().map(|e| e.code).collect();
+            anyhow!(
+                "Unknown diagnostic code: {}\nKnown codes are: {}",
+                code,
+                codes.join(", ")
+            )
+        })
+}
@@ -35,9 +35,9 @@ impl<'a> TetsParser<'a> {
 
     fn parse_one_para(self) -> Paragraph {
         let blocks = self.parse_one().blocks;
-        let block = Vec::from(blocks).drain(..).next().unwrap();
+        let block = blocks.into_iter().next().unwrap();
         match block {
-            Block::Verse(v) => Vec::from(v.paragraphs).drain(..).next().unwrap(),
+            Block::Verse(v) => v.paragraphs.into_iter().next().unwrap(),
             _ => panic!("First block in this Song isn't a Verse"),
         }
     }
@@ -92,9 +92,22 @@ fn song(
 
     json!({
         "title": title.as_ref(),
+        "slug": slug(title.as_ref()),
         "subtitles": subtitles,
         "notation": notation.as_ref(),
         "blocks": blocks.into_iter().collect::<Vec<_>>(),
+        "index": true,
+        "keep_together": false,
+        "glossary": [],
+        "attribution": {
+            "authors": [],
+            "composers": [],
+            "arrangers": [],
+            "copyright": [],
+            "ccli": null,
+            "license": null,
+        },
+        "link": null,
     })
 }
 
@@ -144,6 +157,14 @@ fn b_pre(text: &str) -> Json {
     })
 }
 
+fn b_lilypond(source: &str) -> Json {
+    json!({
+        "type": "b-lilypond",
+        "source": source,
+        "image": Null,
+    })
+}
+
 fn b_html(inlines: impl IntoIterator<Item = Json>) -> Json {
     json!({
         "type": "b-html-block",
@@ -259,7 +280,7 @@ Lyrics lyrics...
 Lyrics lyrics...
     "#;
 
-    let songs = parse(&input, false);
+    let songs = parse(input, false);
 
     assert_eq!(songs.len(), 3);
     assert_eq!(&*songs[0].title, FALLBACK_TITLE);
@@ -471,6 +492,74 @@ fn parse_chords_baseline() {
     ]));
 }
 
+#[test]
+fn parse_chord_sequence() {
+    let input = r#"
+# Song
+1. `Am` `C`  ``G_``
+`D`Sailing
+`D` (2x)
+
+`Em`
+"#;
+    let i_chord_seq = |chords: Vec<Json>| {
+        let chords: Vec<_> = chords
+            .into_iter()
+            .map(|mut chord| {
+                chord.as_object_mut().unwrap().remove("type");
+                chord
+            })
+            .collect();
+        json!({ "type": "i-chord-sequence", "chords": chords })
+    };
+
+    let song = parse_one(input);
+    let verse = song.get_verse(0);
+    verse.paragraphs[0].assert_json_eq(json!([
+        i_chord_seq(vec![
+            i_chord("Am", Null, 1, []),
+            i_chord("C", Null, 1, []),
+            i_chord("G", Null, 2, Baseline),
+        ]),
+        i_break(),
+        i_chord("D", Null, 1, [i_text("Sailing")]),
+        i_break(),
+        i_chord("D", Null, 1, [i_text(" (2x)")]),
+    ]));
+    verse.paragraphs[1].assert_json_eq(json!([i_chord_seq(vec![i_chord("Em", Null, 1, [])])]));
+}
+
+#[test]
+fn parse_chord_alternates() {
+    let input = r#"
+# Song
+1. `[Cmaj7|C]`Sailing `[ Am | D|D ]`round `[G]`the `Em|D`sea.
+
+!+2
+!!nashville
+
+2. `[Cmaj7|C]`Sailing
+"#;
+    let song = parse_one(input);
+    let verse = |num| song.get_verse(num).paragraphs[0].clone();
+
+    let chord_alt = |chord, alternate, inlines| {
+        let mut chord = i_chord(chord, Null, 1, inlines);
+        chord["alternate"] = json!({ "chord": alternate, "alt_chord": Null });
+        chord
+    };
+    verse(0).assert_json_eq(json!([
+        chord_alt("Cmaj7", "C", [i_text("Sailing ")]),
+        chord_alt("Am | D", "D", [i_text("round ")]),
+        i_chord("[G]", Null, 1, [i_text("the ")]),
+        i_chord("Em|D", Null, 1, [i_text("sea.")]),
+    ]));
+
+    let mut chord = i_chord("Dmaj7", "1maj7", 1, [i_text("Sailing")]);
+    chord["alternate"] = json!({ "chord": "D", "alt_chord": "1" });
+    verse(1).assert_json_eq(json!([chord]));
+}
+
 #[test]
 fn parse_inlines() {
     let input = r#"
@@ -855,6 +944,392 @@ fn parse_punctuation() {
     output2.assert_json_eq(json!([i_text(r#""Hello", 'World!' ..."#),]));
 }
 
+#[test]
+fn parse_nav_markers() {
+    let input = r#"# Song
+
+1. |: Hello {Segno} world :| {D.S. al Coda}
+{coda} Bye {whatever}
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let nav = |marker: &str| json!({ "type": "i-nav", "marker": marker });
+    songs.unwrap()[0].blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([
+            nav("repeat-start"),
+            i_text(" Hello "),
+            nav("segno"),
+            i_text(" world "),
+            nav("repeat-end"),
+            i_text(" "),
+            nav("dal-segno-al-coda"),
+            i_break(),
+            nav("coda"),
+            i_text(" Bye {whatever}"),
+        ])]
+    )]));
+
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::NavMarker {
+            marker: "{whatever}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_gloss_annotations() {
+    let input = r#"# Song
+
+1. Where art {gloss: art = are} thou{Gloss:thou=you}
+{gloss: thee} {gloss: = you}
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let song = &songs.unwrap()[0];
+    song.blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([i_text("Where art "), i_text(" thou"),])]
+    )]));
+    assert_eq!(
+        song.glossary,
+        [
+            GlossEntry::new("art", "are"),
+            GlossEntry::new("thou", "you")
+        ]
+    );
+
+    assert_eq!(diags.len(), 2);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::GlossAnnotation {
+            annotation: "{gloss: thee}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_attribution_annotations() {
+    let input = r#"# Song 1
+
+{author: John Lennon} {Author: Paul McCartney}
+{composer:John Lennon}
+{copyright: © 1965 Northern Songs} {arranger: }
+{CCLI: 12345} {license: CCLI}
+
+1. Lyrics.
+
+# Song 2
+
+1. Lyrics.
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    songs[0]
+        .blocks
+        .assert_json_eq(json!([ver_verse(1, [p([i_text("Lyrics.")])])]));
+    assert_eq!(
+        songs[0].attribution,
+        Attribution {
+            authors: vec!["John Lennon".into(), "Paul McCartney".into()],
+            composers: vec!["John Lennon".into()],
+            arrangers: vec![],
+            copyright: vec!["© 1965 Northern Songs".into()],
+            ccli: Some("12345".into()),
+            license: Some("CCLI".into()),
+        }
+    );
+    assert!(songs[1].attribution.is_empty());
+
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::AttributionAnnotation {
+            annotation: "{arranger: }".into()
+        }
+    );
+}
+
+#[test]
+fn parse_link_annotation() {
+    let input = r#"
+# Song 1
+{link: https://example.com/song-1.mp3}
+
+1. Lyrics. {audio:}
+
+# Song 2
+
+1. Lyrics. {Audio: https://example.com/song-2.mp3}
+{link: https://example.com/song-2}
+
+# Song 3
+
+1. Lyrics.
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    // Annotations are removed from the text
+    let blocks = serde_json::to_string(&songs[1].blocks).unwrap();
+    assert!(!blocks.contains("example.com"));
+    assert_eq!(
+        songs[0].link.as_deref(),
+        Some("https://example.com/song-1.mp3")
+    );
+    assert_eq!(songs[1].link.as_deref(), Some("https://example.com/song-2"));
+    assert_eq!(songs[2].link, None);
+
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::LinkAnnotation {
+            annotation: "{audio:}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_capo_annotation() {
+    let input = r#"
+# Song 1
+{capo: 3}
+
+1. Lyrics.
+
+# Song 2
+{Capo: II}
+
+1. Lyrics. {capo: 0}
+
+# Song 3
+
+1. Lyrics. {capo: 12}
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    let capos: Vec<_> = songs.iter().map(|song| song.capo).collect();
+    assert_eq!(capos, [Some(3), Some(0), None]);
+    // Annotations are removed from the text
+    let blocks = serde_json::to_string(&songs[1].blocks).unwrap();
+    assert!(!blocks.contains("capo"));
+
+    assert_eq!(diags.len(), 2);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::CapoAnnotation {
+            annotation: "{Capo: II}".into()
+        }
+    );
+    assert_eq!(
+        diags[1].kind,
+        DiagKind::CapoAnnotation {
+            annotation: "{capo: 12}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_repeat_annotation() {
+    let input = r#"
+# Song
+
+1. Lyrics.
+
+> Chorus. {x2}
+Chorus.
+
+2. Lyrics. {Repeat: 3}
+
+3. Lyrics. {repeat: twice}
+
+4. Lyrics. {×0}
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    let repeats: Vec<_> = (0..5).map(|i| songs[0].get_verse(i).repeat).collect();
+    assert_eq!(repeats, [None, Some(2), Some(3), None, None]);
+    // Annotations are removed from the text
+    let blocks = serde_json::to_string(&songs[0].blocks).unwrap();
+    assert!(!blocks.contains("x2") && !blocks.contains("Repeat"));
+    assert!(blocks.contains(r#""text":"Chorus.""#));
+
+    assert_eq!(diags.len(), 2);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::RepeatAnnotation {
+            annotation: "{repeat: twice}".into()
+        }
+    );
+    assert_eq!(
+        diags[1].kind,
+        DiagKind::RepeatAnnotation {
+            annotation: "{×0}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_key_annotation() {
+    let input = r#"
+# Song
+{key: G}
+
+1. Lyrics. {Key: F#m}
+
+# Song 2
+
+1. Lyrics. {key: H7}
+
+# Song 3
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    let keys: Vec<_> = songs.iter().map(|song| song.key.clone()).collect();
+    assert_eq!(keys, [Some("F#m".into()), None, None]);
+
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::KeyAnnotation {
+            annotation: "{key: H7}".into()
+        }
+    );
+}
+
+#[test]
+fn parse_chorus_annotation() {
+    let input = r#"
+# Song
+
+> Chorus one. {chorus: Refrain 1}
+
+>> Chorus two.
+
+1. Lyrics !> !>> {chorus: Verse}
+
+# Song 2
+
+> Chorus. {chorus:}
+
+1. Lyrics !>
+"#;
+    let (songs, diags) = try_parse(input, false);
+    let songs = songs.unwrap();
+    songs[0].blocks.assert_json_eq(json!([
+        b_verse(
+            "named_chorus",
+            json!({ "num": 1, "name": "Refrain 1" }),
+            [p([i_text("Chorus one.")])]
+        ),
+        ver_chorus(2, [p([i_text("Chorus two.")])]),
+        ver_verse(
+            1,
+            [p([
+                i_text("Lyrics"),
+                json!({ "type": "i-chorus-ref", "num": 1, "prefix_space": " ", "name": "Refrain 1" }),
+                i_chorus_ref(2, " "),
+            ])]
+        ),
+    ]));
+    songs[1].blocks.assert_json_eq(json!([
+        ver_chorus(Null, [p([i_text("Chorus.")])]),
+        ver_verse(1, [p([i_text("Lyrics"), i_chorus_ref(Null, " ")])]),
+    ]));
+
+    let annotations: Vec<_> = diags
+        .iter()
+        .map(|diag| match &diag.kind {
+            DiagKind::ChorusAnnotation { annotation } => annotation.clone(),
+            kind => panic!("Unexpected diagnostic: {:?}", kind),
+        })
+        .collect();
+    assert_eq!(annotations, ["{chorus: Verse}".into(), "{chorus:}".into()]);
+}
+
+#[test]
+fn parse_whitespace_normalization() {
+    let input = "# Song
+
+1. Trailing spaces\u{a0} \\
+   `C`Chord at the end \\
+   \u{a0}
+   Next paragraph
+
+2.
+
+> Chorus
+
+### Bridge
+\\
+Lyrics
+";
+    let (songs, diags) = try_parse(input, false);
+    songs.unwrap()[0].blocks.assert_json_eq(json!([
+        ver_verse(
+            1,
+            [
+                p([
+                    i_text("Trailing spaces"),
+                    i_break(),
+                    i_chord("C", Null, 1, [i_text("Chord at the end")]),
+                ]),
+                p([i_text("Next paragraph")]),
+            ]
+        ),
+        ver_chorus(Null, [p([i_text("Chorus")])]),
+        ver_custom("Bridge", [p([i_break(), i_text("Lyrics")])]),
+    ]));
+
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].kind, DiagKind::EmptyVerse);
+    assert_eq!(diags[0].line, 8);
+}
+
+#[test]
+fn parse_whitespace_preserved() {
+    let input = "# Song
+
+1. Trailing space\u{a0}
+   \u{a0}
+
+2.
+";
+    let config = ParserConfig::default().preserve_whitespace(true);
+    let (songs, diags) = TetsParser::new(input, config).parse();
+    songs.unwrap()[0].blocks.assert_json_eq(json!([
+        ver_verse(
+            1,
+            [p([
+                i_text("Trailing space\u{a0}"),
+                i_break(),
+                i_text("\u{a0}"),
+            ])]
+        ),
+        ver_verse(2, []),
+    ]));
+    assert!(diags.is_empty());
+}
+
+#[test]
+fn parse_song_index() {
+    let input = r#"# Song 1
+
+!index=false
+1. Lyrics !index=false
+
+# Song 2
+
+1. Lyrics.
+"#;
+
+    let songs = parse(input, false);
+    assert!(!songs[0].index);
+    assert!(songs[1].index);
+    songs[0]
+        .blocks
+        .assert_json_eq(json!([ver_verse(1, [p([i_text("Lyrics")])])]));
+}
+
 #[test]
 fn parse_crlf() {
     let input = b"# Song\r\n\r\n1. First verse.\r\n\r\n```\r\npre1\r\npre2\r\n```";
@@ -871,6 +1346,18 @@ fn parse_crlf() {
     ));
 }
 
+#[test]
+fn parse_lilypond() {
+    let input = "# Song\n\n```lilypond\n\\relative c' { c4 d e f }\n```\n\n```\npre\n```";
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [b_lilypond("\\relative c' { c4 d e f }\n"), b_pre("pre\n")],
+    ));
+}
+
 #[test]
 fn parse_crlf_html() {
     let input = b"# Song\r\n\r\n<foo>\r\nline1\r\nline2\r\n</foo>\r\n";
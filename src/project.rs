@@ -1,17 +1,28 @@
+use std::cell::Cell;
 use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs;
-use std::iter;
 use std::process::Command;
 use std::process::Stdio;
 use std::str;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use regex::Regex;
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer};
 
-use crate::app::App;
-use crate::book::{self, Book, Song, SongRef};
-use crate::default_project::DEFAULT_PROJECT;
-use crate::music::Notation;
+use crate::app::{App, InterruptError};
+use crate::book::{
+    self, Book, EmptySongs, GlossEntry, MissingCopyright, Song, SongRef, SortKey, UnplayableChords,
+};
+use crate::default_project::ProjectTemplate;
+use crate::music::{self, Instrument, Notation};
+use crate::parser::chordpro;
+use crate::parser::DiagSink;
 use crate::parser::Diagnostic;
 use crate::parser::Parser;
 use crate::parser::ParserConfig;
@@ -19,19 +30,35 @@ use crate::prelude::*;
 use crate::render::tex_tools::TexConfig;
 use crate::render::tex_tools::TexTools;
 use crate::render::Renderer;
-use crate::util::ExitStatusExt;
+use crate::util::collation::Collator;
+use crate::util::lilypond;
+use crate::util::{ExitStatusExt, FileLock, TempPath};
 
 pub use toml::Value;
 
 mod input;
-use input::{InputSet, SongsGlobs};
+use input::{SongFile, SongsGlobs};
 mod output;
-pub use output::{Format, Output};
+mod parse_cache;
+pub(crate) use output::build_date;
+use output::{render_order, render_waves};
+pub use output::{AlternateChords, CapoChords, Format, Output, PageNumbering, Split, TexRuns};
+pub use parse_cache::ParseCache;
+mod watch_set;
+pub use watch_set::{WatchChange, WatchSet};
 
 pub type Metadata = BTreeMap<Box<str>, Value>;
 
 type TomlMap = toml::map::Map<String, Value>;
 
+fn default_max_song_files() -> usize {
+    10_000
+}
+
+fn default_tex_timeout() -> u64 {
+    300
+}
+
 fn dir_songs() -> PathBuf {
     "songs".into()
 }
@@ -44,7 +71,7 @@ fn dir_output() -> PathBuf {
     "output".into()
 }
 
-fn meta_default_chorus_label<'de, D>(de: D) -> Result<Metadata, D::Error>
+fn meta_default_labels<'de, D>(de: D) -> Result<Metadata, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -52,9 +79,17 @@ where
     if !meta.contains_key("chorus_label") {
         meta.insert("chorus_label".into(), "Ch".into());
     }
+    if !meta.contains_key("capo_label") {
+        meta.insert("capo_label".into(), "Capo".into());
+    }
     Ok(meta)
 }
 
+/// Collator for sorting according to the `locale` field of a `[book]` section.
+pub fn book_collator(book: &Metadata) -> Collator {
+    Collator::new(book.get("locale").and_then(Value::as_str))
+}
+
 fn pathbuf_relative_only<'de, D>(de: D) -> Result<PathBuf, D::Error>
 where
     D: Deserializer<'de>,
@@ -71,6 +106,14 @@ where
     }
 }
 
+fn is_interrupt(err: &Error) -> bool {
+    err.chain().any(|err| err.is::<InterruptError>())
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
 fn default_smart_punctuation() -> bool {
     true
 }
@@ -78,6 +121,9 @@ fn default_smart_punctuation() -> bool {
 #[derive(Deserialize, Debug)]
 pub struct Settings {
     songs: SongsGlobs,
+    /// Song files placed at the start of the book, see `pin_inputs()`.
+    #[serde(default)]
+    pinned: Vec<PathBuf>,
 
     #[serde(default = "dir_songs", deserialize_with = "pathbuf_relative_only")]
     dir_songs: PathBuf,
@@ -90,11 +136,44 @@ pub struct Settings {
     pub notation: Notation,
     #[serde(default = "default_smart_punctuation")]
     pub smart_punctuation: bool,
+    /// Default of the `unicode_accidentals` output setting.
+    #[serde(default)]
+    pub unicode_accidentals: bool,
+    /// Default of the `!keep_together` song setting.
+    #[serde(default)]
+    pub keep_together: bool,
+    /// Keep whitespace in songs as written, see `Verse::normalize_whitespace()`.
+    #[serde(default)]
+    pub preserve_whitespace: bool,
+    /// What to do with songs without content.
+    #[serde(default)]
+    pub empty_songs: EmptySongs,
+    /// What to do with songs without a copyright notice.
+    #[serde(default)]
+    pub missing_copyright: MissingCopyright,
+    /// Instrument to check the songs' chords against, see `Book::check_chords()`.
+    #[serde(default)]
+    pub instrument: Option<Instrument>,
+    /// What to do with chords not playable on the `instrument`.
+    #[serde(default)]
+    pub unplayable_chords: UnplayableChords,
+    /// Maximum number of song files, `0` for no limit, see `InputSet::finalize()`.
+    #[serde(default = "default_max_song_files")]
+    pub max_song_files: usize,
     tex: Option<TexConfig>,
+    /// Time limit of a TeX run in seconds, `0` for no limit, see `Settings::tex_timeout()`.
+    #[serde(default = "default_tex_timeout")]
+    tex_timeout: u64,
 
     pub output: Vec<Output>,
-    #[serde(deserialize_with = "meta_default_chorus_label")]
+    #[serde(deserialize_with = "meta_default_labels")]
     pub book: Metadata,
+
+    /// Indices into `output` in the order in which they are rendered, see `render_order()`.
+    #[serde(skip)]
+    render_order: Vec<usize>,
+    #[serde(skip)]
+    project_dir: PathBuf,
 }
 
 impl Settings {
@@ -103,14 +182,67 @@ impl Settings {
         major.parse().unwrap()
     }
 
-    pub fn from_file(path: &Path, project_dir: &Path) -> Result<Settings> {
+    /// Sets the `version` field in the project file at `path`, adding it if missing.
+    ///
+    /// Only the top-level table is searched, ie. the lines before the first `[table]` header,
+    /// so that eg. a `version` field in the `[book]` table is kept as it is.
+    fn stamp_version(path: &Path, version: u32) -> Result<()> {
+        static VERSION_LINE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"(?m)^\s*version\s*=.*$").unwrap());
+        static TABLE_HEADER: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?m)^\s*\[").unwrap());
+
         let contents = fs::read_to_string(path)
             .with_context(|| format!("Failed to read project file {:?}", path))?;
+        let top_level_end = TABLE_HEADER
+            .find(&contents)
+            .map_or(contents.len(), |header| header.start());
+        let version_line = format!("version = {}", version);
+        let contents = match VERSION_LINE.find(&contents[..top_level_end]) {
+            Some(line) => format!(
+                "{}{}{}",
+                &contents[..line.start()],
+                version_line,
+                &contents[line.end()..]
+            ),
+            None => format!("{}\n\n{}", version_line, contents),
+        };
+
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write project file {:?}", path))
+    }
+
+    /// Adds `glob` to the `songs` field in the project file at `path`, see `SongsGlobs::push()`.
+    ///
+    /// The file is written back from its parsed form, so comments and formatting are not kept.
+    fn add_songs_glob(path: &Path, glob: &str) -> Result<()> {
+        let contents = Self::read_file(path)?;
+        let mut toml: TomlMap = toml::from_str(&contents)
+            .map_err(|err| Self::toml_error(err, &contents))
+            .with_context(|| format!("Could not parse project file {:?}", path))?;
+
+        let mut songs: SongsGlobs = match toml.remove("songs") {
+            Some(songs) => songs
+                .try_into()
+                .context("Could not parse the 'songs' field")?,
+            None => SongsGlobs::default(),
+        };
+        songs.push(glob.to_string());
+        toml.insert("songs".into(), Value::try_from(&songs)?);
+
+        let contents = toml::to_string_pretty(&toml)?;
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write project file {:?}", path))
+    }
+
+    pub fn from_file(path: &Path, project_dir: &Path) -> Result<Settings> {
+        let contents = Self::read_file(path)?;
 
         let parse_err = || format!("Could not parse project file {:?}", path);
 
         // Check version
-        let settings: TomlMap = toml::from_str(&contents).with_context(parse_err)?;
+        let settings: TomlMap = toml::from_str(&contents)
+            .map_err(|err| Self::toml_error(err, &contents))
+            .with_context(parse_err)?;
         let version = settings.get("version").unwrap_or(&Value::Integer(1));
         let version = version
             .as_integer()
@@ -125,31 +257,414 @@ impl Settings {
             bail!("This project was created with a newer version {}.x of bard, the project cannot be built by bard {}.x", version, self_ver);
         }
 
-        let mut settings: Settings = toml::from_str(&contents).with_context(parse_err)?;
+        let mut settings: Settings = toml::from_str(&contents)
+            .map_err(|err| Self::toml_error(err, &contents))
+            .with_context(parse_err)?;
 
         settings.resolve(project_dir)?;
         Ok(settings)
     }
 
+    /// Adds a hint on how to fix the project file to TOML errors caused by common mistakes,
+    /// see `toml_hint()`.
+    fn toml_error(err: toml::de::Error, contents: &str) -> Error {
+        match Self::toml_hint(&err, contents) {
+            Some(hint) => anyhow!("{}\nHint: {}", err.to_string().trim_end(), hint),
+            None => err.into(),
+        }
+    }
+
+    fn toml_hint(err: &toml::de::Error, contents: &str) -> Option<String> {
+        let message = err.message();
+        let span = err.span().unwrap_or_default();
+        let at = contents.get(span.start..).unwrap_or_default();
+        let line_start = contents[..span.start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line = contents[line_start..]
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim();
+
+        // Name of a table in a `[name]` or `[[name]]` header
+        let header_name = || {
+            let name = line.trim_start_matches('[').split(']').next()?.trim();
+            (!name.is_empty() && line.starts_with('[')).then_some(name)
+        };
+
+        let hint = if message.contains("expected a sequence") || message.contains("duplicate key") {
+            let name = header_name().filter(|_| !line.starts_with("[["))?;
+            format!(
+                "Did you mean `[[{}]]`? Each item of a list of tables is written as a section with double brackets.",
+                name
+            )
+        } else if message.contains("expected a map") {
+            let name = header_name().filter(|_| line.starts_with("[["))?;
+            format!(
+                "Did you mean `[{}]`? Double brackets are only used for lists of tables, such as `[[output]]`.",
+                name
+            )
+        } else if message.starts_with("invalid inline table") {
+            "Inline tables `{ ... }` have to be written on a single line and can't have a trailing comma. \
+            Consider using sections such as `[[output]]` instead."
+                .into()
+        } else if message.starts_with("invalid string") {
+            "Text values have to be quoted, eg. `file = \"songbook.pdf\"`.".into()
+        } else if message.starts_with("expected `.`, `=`") && at.starts_with(':') {
+            "Values are assigned with `=`, eg. `key = value`.".into()
+        } else if message.starts_with("invalid escape") || message.starts_with("invalid unicode") {
+            "Backslashes in double-quoted strings start escape sequences. \
+            Use single quotes or forward slashes for paths, eg. 'C:\\Songs' or \"C:/Songs\"."
+                .into()
+        } else if message.starts_with("invalid array") {
+            "Items of an array have to be separated by commas, eg. `[\"a.md\", \"b.md\"]`.".into()
+        } else {
+            return None;
+        };
+
+        Some(hint)
+    }
+
+    /// Reads the project file as UTF-8 text, a byte-order mark is skipped.
+    /// Other encodings, such as UTF-16 saved by some editors on Windows, are reported as such.
+    fn read_file(path: &Path) -> Result<String> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Failed to read project file {:?}", path))?;
+        let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&bytes);
+
+        let encoding = match bytes {
+            [0xff, 0xfe, 0, 0, ..] | [0, 0, 0xfe, 0xff, ..] => Some("UTF-32"),
+            [0xff, 0xfe, ..] | [0xfe, 0xff, ..] => Some("UTF-16"),
+            // ASCII text in UTF-16 without BOM contains zero bytes, which UTF-8 text doesn't
+            _ if bytes.contains(&0) => Some("UTF-16"),
+            _ => None,
+        };
+        let hint = "Hint: Re-save the file with the UTF-8 encoding in your text editor.";
+        if let Some(encoding) = encoding {
+            bail!(
+                "Project file {:?} appears to be encoded as {}, but only UTF-8 is supported.\n{}",
+                path,
+                encoding,
+                hint
+            );
+        }
+
+        String::from_utf8(bytes.to_vec()).map_err(|err| {
+            anyhow!(
+                "Project file {:?} is not valid UTF-8 text (invalid data at byte {}).\n{}",
+                path,
+                err.utf8_error().valid_up_to(),
+                hint
+            )
+        })
+    }
+
     pub fn dir_songs(&self) -> &Path {
         self.dir_songs.as_ref()
     }
 
+    pub fn dir_templates(&self) -> &Path {
+        self.dir_templates.as_ref()
+    }
+
     pub fn dir_output(&self) -> &Path {
         self.dir_output.as_ref()
     }
 
+    /// Returns the existing files generated by building the project, to be removed by `bard clean`,
+    /// ie. the output files, SyncTeX files of PDF outputs, directories of split outputs,
+    /// TeX build directories left behind (see `TexRenderJob::new()`), QR code and LilyPond images,
+    /// and the parse cache. Files in the songs and templates directories are never returned.
+    ///
+    /// Fails if the output directory is not inside the project directory,
+    /// so that a misconfigured `output` setting can't make bard remove unrelated files.
+    /// Likewise, paths which resolve outside of the output directory, eg. output files
+    /// set to an absolute path or with `..`, are skipped with a warning.
+    pub fn clean_paths(&self, app: &App) -> Result<Vec<PathBuf>> {
+        let dir_output = self.dir_output();
+        let canonical = |path: &Path| {
+            path.canonicalize()
+                .with_context(|| format!("Could not resolve path {:?}", path))
+        };
+        if !dir_output.exists() {
+            return Ok(vec![]);
+        }
+        let dir_output_canonical = canonical(dir_output)?;
+        if !dir_output_canonical.starts_with(canonical(&self.project_dir)?) {
+            bail!(
+                "Output directory {:?} is not inside the project directory {:?}, refusing to remove files in it.",
+                dir_output,
+                self.project_dir
+            );
+        }
+
+        let entries: Vec<_> = fs::read_dir(dir_output)
+            .and_then(|dir| dir.map(|entry| entry.map(|e| e.path())).collect())
+            .with_context(|| format!("Could not read directory {:?}", dir_output))?;
+        let mut candidates = vec![];
+        for output in self.output.iter() {
+            let file = output.file.as_path();
+            if file.is_file() {
+                candidates.push(file.to_owned());
+            }
+            if output.is_pdf() {
+                candidates.push(file.with_extension("synctex.gz"));
+            }
+            if output.split != Split::None {
+                candidates.push(output.split_dir());
+            }
+            candidates.extend(
+                entries
+                    .iter()
+                    .filter(|path| TempPath::is_temp_dir(path, file))
+                    .cloned(),
+            );
+        }
+        candidates.extend(
+            [
+                Song::QR_DIR,
+                lilypond::DIR,
+                ParseCache::DIR,
+                WatchSet::RECORD,
+            ]
+            .map(|name| dir_output.join(name)),
+        );
+
+        let kept_dirs: Vec<_> = [self.dir_songs(), self.dir_templates()]
+            .iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+        let mut paths = vec![];
+        for path in candidates {
+            if !path.exists() {
+                continue;
+            }
+            let resolved = canonical(&path)?;
+            if resolved == dir_output_canonical || !resolved.starts_with(&dir_output_canonical) {
+                app.warning(format!(
+                    "{:?} is not inside the output directory {:?}, it is not removed.",
+                    path, dir_output
+                ));
+                continue;
+            }
+            if !kept_dirs.iter().any(|dir| resolved.starts_with(dir)) {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
     fn resolve(&mut self, project_dir: &Path) -> Result<()> {
+        self.project_dir = project_dir.to_owned();
         self.dir_songs.resolve(project_dir);
         self.dir_templates.resolve(project_dir);
         self.dir_output.resolve(project_dir);
 
         for output in self.output.iter_mut() {
-            output.resolve(&self.dir_templates, &self.dir_output)?;
+            output.resolve(&self.dir_templates, &self.dir_output, &self.book)?;
+
+            let notation = output.notation.unwrap_or(self.notation);
+            if let Some(key) = output.to_key.as_deref() {
+                if music::parse_key(key, notation).is_none() {
+                    bail!(
+                        "Output {:?} sets 'to_key' to {:?}, which is not a key in the {} notation.\nHint: Write the key like the {{key: ...}} annotations of songs, eg. 'G' or 'Em'.",
+                        output.file,
+                        key,
+                        notation.key_notation()
+                    );
+                }
+            }
         }
 
+        self.render_order = render_order(&self.output)?;
         Ok(())
     }
+
+    pub fn tex(&self) -> Option<&TexConfig> {
+        self.tex.as_ref()
+    }
+
+    /// Time limit of a TeX run, `None` if disabled with `tex_timeout = 0`.
+    pub fn tex_timeout(&self) -> Option<Duration> {
+        (self.tex_timeout > 0).then(|| Duration::from_secs(self.tex_timeout))
+    }
+
+    /// Moves the `pinned` files to the start of `inputs` in the order they're listed,
+    /// returns the number of pinned files. Pinned files have to be matched by `songs`
+    /// and they don't belong to any section.
+    fn pin_inputs(&self, inputs: &mut Vec<SongFile>) -> Result<usize> {
+        let mut pinned: Vec<SongFile> = Vec::with_capacity(self.pinned.len());
+        for file in self.pinned.iter() {
+            let path = self.dir_songs.join(file);
+            match inputs.iter().position(|input| input.path == path) {
+                Some(pos) => pinned.push(SongFile {
+                    section: None,
+                    ..inputs.remove(pos)
+                }),
+                None if pinned.iter().any(|input| input.path == path) => {
+                    bail!("File {:?} is listed in 'pinned' more than once.", file)
+                }
+                None => bail!(
+                    "Pinned file {:?} is not matched by the 'songs' setting.\nHint: Pinned files have to be listed in 'songs' as well, or matched by one of its patterns.",
+                    file
+                ),
+            }
+        }
+
+        let count = pinned.len();
+        inputs.splice(0..0, pinned);
+        Ok(count)
+    }
+
+    fn parser_config(&self) -> ParserConfig {
+        ParserConfig::new(self.notation, self.smart_punctuation)
+            .keep_together(self.keep_together)
+            .preserve_whitespace(self.preserve_whitespace)
+    }
+
+    /// Returns the effective settings in TOML, ie. with defaults filled in.
+    ///
+    /// Paths are relative in the same way as in `bard.toml` (eg. output files
+    /// relative to `dir_output`), so that the result can be used as a project file.
+    pub fn to_effective_toml(&self) -> Result<String> {
+        fn rel(path: &Path, base: &Path) -> Value {
+            path.strip_prefix(base)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned()
+                .into()
+        }
+
+        let mut toml = TomlMap::new();
+        toml.insert("version".into(), Value::Integer(Self::version() as _));
+        toml.insert("songs".into(), Value::try_from(&self.songs)?);
+        toml.insert("pinned".into(), Value::try_from(&self.pinned)?);
+        toml.insert("dir_songs".into(), rel(&self.dir_songs, &self.project_dir));
+        toml.insert(
+            "dir_templates".into(),
+            rel(&self.dir_templates, &self.project_dir),
+        );
+        toml.insert(
+            "dir_output".into(),
+            rel(&self.dir_output, &self.project_dir),
+        );
+        toml.insert("notation".into(), Value::try_from(self.notation)?);
+        toml.insert("smart_punctuation".into(), self.smart_punctuation.into());
+        toml.insert(
+            "unicode_accidentals".into(),
+            self.unicode_accidentals.into(),
+        );
+        toml.insert("keep_together".into(), self.keep_together.into());
+        toml.insert(
+            "preserve_whitespace".into(),
+            self.preserve_whitespace.into(),
+        );
+        toml.insert("empty_songs".into(), Value::try_from(self.empty_songs)?);
+        toml.insert(
+            "missing_copyright".into(),
+            Value::try_from(self.missing_copyright)?,
+        );
+        if let Some(instrument) = self.instrument {
+            toml.insert("instrument".into(), instrument.to_string().into());
+        }
+        toml.insert(
+            "unplayable_chords".into(),
+            Value::try_from(self.unplayable_chords)?,
+        );
+        toml.insert(
+            "max_song_files".into(),
+            Value::Integer(self.max_song_files as _),
+        );
+        if let Some(tex) = self.tex.as_ref() {
+            toml.insert("tex".into(), Value::try_from(tex)?);
+        }
+        toml.insert("tex_timeout".into(), Value::Integer(self.tex_timeout as _));
+
+        let outputs = self
+            .output
+            .iter()
+            .map(|output| {
+                let mut table = match Value::try_from(output)? {
+                    Value::Table(table) => table,
+                    _ => unreachable!(),
+                };
+                table.insert("file".into(), rel(&output.file, &self.dir_output));
+                if let Some(template) = output.template.as_ref() {
+                    table.insert("template".into(), rel(template, &self.dir_templates));
+                }
+                table.insert("dpi".into(), Value::Float(output.dpi() as _));
+                if let Some(after) = output.page_start_after.as_ref() {
+                    table.insert("page_start_after".into(), rel(after, &self.dir_output));
+                }
+                if !output.merge.is_empty() {
+                    let merge = output
+                        .merge
+                        .iter()
+                        .map(|file| rel(file, &self.dir_output))
+                        .collect();
+                    table.insert("merge".into(), Value::Array(merge));
+                }
+                if !output.book_overrides.is_empty() {
+                    table.insert("book".into(), Value::try_from(&output.book_overrides)?);
+                }
+                Ok(Value::Table(table))
+            })
+            .collect::<Result<_>>()?;
+        toml.insert("output".into(), Value::Array(outputs));
+        toml.insert("book".into(), Value::try_from(&self.book)?);
+
+        Ok(toml::to_string(&toml)?)
+    }
+
+    /// Iterates outputs in the order in which they should be rendered,
+    /// ie. merged outputs come before the output that merges them.
+    pub fn outputs_render_order(&self) -> impl Iterator<Item = &Output> {
+        self.render_order.iter().map(move |&i| &self.output[i])
+    }
+
+    /// Groups of outputs which may be rendered in parallel, in the order in which the groups
+    /// should be rendered, see `render_waves()`.
+    pub fn outputs_render_waves(&self) -> Vec<Vec<&Output>> {
+        render_waves(&self.output, &self.render_order)
+            .into_iter()
+            .map(|wave| wave.into_iter().map(|i| &self.output[i]).collect())
+            .collect()
+    }
+}
+
+/// Parses songs in the file at `path`, which is reported relative to `project_dir`.
+fn parse_song_file(
+    path: &Path,
+    project_dir: &Path,
+    config: ParserConfig,
+    diag_sink: impl DiagSink,
+) -> Result<Vec<Song>> {
+    let source = fs::read_to_string(path)?;
+    parse_song_source(&source, path, project_dir, config, diag_sink)
+}
+
+/// Parses `source` read from the song file at `path`, see `parse_song_file()`.
+fn parse_song_source(
+    source: &str,
+    path: &Path,
+    project_dir: &Path,
+    config: ParserConfig,
+    diag_sink: impl DiagSink,
+) -> Result<Vec<Song>> {
+    let rel_path = path.strip_prefix(project_dir).unwrap_or(path);
+    let songs = if chordpro::is_chordpro(path) {
+        // ChordPro songs are converted to Markdown, diagnostics refer to the ChordPro source lines
+        let converted = chordpro::to_markdown(source, rel_path, &diag_sink);
+        let diag_sink = |mut diag: Diagnostic| {
+            diag.line = converted.source_line(diag.line);
+            diag_sink.report(diag);
+        };
+        let songs = Parser::new(&converted.markdown, rel_path, config, diag_sink).parse();
+        songs
+    } else {
+        Parser::new(source, rel_path, config, diag_sink).parse()
+    };
+    songs.map_err(|_| anyhow!("Could not parse file {:?}", path))
 }
 
 #[cfg(unix)]
@@ -157,46 +672,87 @@ static SCRIPT_EXT: &str = "sh";
 #[cfg(windows)]
 static SCRIPT_EXT: &str = "bat";
 
+/// A song file created by `Project::new_song()`.
+#[derive(Debug)]
+pub struct NewSong {
+    pub path: PathBuf,
+    /// Whether the file was added to the `songs` field of `bard.toml`.
+    pub added: bool,
+    /// Whether the file is matched by the `songs` field, ie. whether it is part of the book.
+    pub matched: bool,
+}
+
 #[derive(Debug)]
 pub struct Project {
     pub project_dir: PathBuf,
     pub settings: Settings,
     pub book: Book,
 
-    project_file: PathBuf,
-    input_paths: Vec<PathBuf>,
+    watch_set: WatchSet,
 }
 
 impl Project {
+    /// Name of the lock file in the project directory held while rendering, see `lock_build()`.
+    pub const LOCK_FILE: &'static str = ".bard.lock";
+
+    /// Loads the project in `cwd` or its parent directories, parser diagnostics are reported to `app`.
     pub fn new<P: AsRef<Path>>(app: &App, cwd: P) -> Result<Project> {
-        let cwd = cwd.as_ref();
-        let (project_file, project_dir) = Self::find_in_parents(cwd).ok_or_else(|| {
-            anyhow!(
-                "Could not find bard.toml file in current or parent directories\nCurrent directory: {:?}",
-                cwd,
-            )
-        })?;
+        Self::with_diag_sink(app, cwd, app)
+    }
+
+    /// Like `new()`, but parser diagnostics are reported to `diag_sink` instead of `app`,
+    /// eg. to collect them when bard is used as a library.
+    ///
+    /// Other messages, such as warnings about empty songs, still go through `app`.
+    pub fn with_diag_sink<P: AsRef<Path>>(
+        app: &App,
+        cwd: P,
+        diag_sink: &dyn DiagSink,
+    ) -> Result<Project> {
+        let (project_file, project_dir) = Self::find_project_file(cwd.as_ref())?;
 
         app.status("Loading", format!("project at {:?}", project_dir));
 
         let settings = Settings::from_file(&project_file, &project_dir)?;
         let book = Book::new(&settings);
+        let watch_set = WatchSet::from_settings(project_file, &settings);
 
         let mut project = Project {
-            project_file,
             project_dir,
             settings,
-            input_paths: vec![],
             book,
+            watch_set,
         };
 
         project
-            .load_md_files(app)
+            .load_md_files(app, diag_sink)
             .context("Failed to load input files")?;
 
         Ok(project)
     }
 
+    /// Loads only the settings of the project in `cwd` or its parent directories, if any.
+    pub fn find_settings(cwd: &Path) -> Result<Option<Settings>> {
+        Self::find_in_parents(cwd)
+            .map(|(project_file, project_dir)| Settings::from_file(&project_file, &project_dir))
+            .transpose()
+    }
+
+    /// Loads the settings of the project in `cwd` or its parent directories, without loading the songs.
+    pub fn load_settings(cwd: &Path) -> Result<Settings> {
+        let (project_file, project_dir) = Self::find_project_file(cwd)?;
+        Settings::from_file(&project_file, &project_dir)
+    }
+
+    fn find_project_file(cwd: &Path) -> Result<(PathBuf, PathBuf)> {
+        Self::find_in_parents(cwd).ok_or_else(|| {
+            anyhow!(
+                "Could not find bard.toml file in current or parent directories\nCurrent directory: {:?}",
+                cwd,
+            )
+        })
+    }
+
     fn find_in_parents(start_dir: &Path) -> Option<(PathBuf, PathBuf)> {
         assert!(start_dir.is_dir());
 
@@ -211,39 +767,130 @@ impl Project {
         }
     }
 
-    fn load_md_files(&mut self, app: &App) -> Result<()> {
-        let input_set = InputSet::new(&self.settings.dir_songs)?;
-        self.input_paths = self
-            .settings
-            .songs
-            .iter()
-            .try_fold(input_set, InputSet::apply_glob)?
-            .finalize()?;
+    fn load_md_files(&mut self, app: &App, diag_sink: &dyn DiagSink) -> Result<()> {
+        let mut input_paths = self.watch_set.resolve_inputs()?;
+        let num_pinned = self.settings.pin_inputs(&mut input_paths)?;
 
         let diag_sink = move |diag: Diagnostic| {
-            app.parser_diag(diag);
+            diag_sink.report(diag);
         };
 
-        for path in self.input_paths.iter() {
-            app.check_interrupted()?;
-            let source = fs::read_to_string(path)?;
-            let config = ParserConfig::new(self.settings.notation, self.settings.smart_punctuation);
-            let rel_path = path.strip_prefix(&self.project_dir).unwrap_or(path);
-            let mut parser = Parser::new(&source, rel_path, config, diag_sink);
-            let songs = parser
-                .parse()
-                .map_err(|_| anyhow!("Could not parse file {:?}", path))?;
-            self.book.add_songs(songs);
-        }
+        let config = self.settings.parser_config();
+        let cache = ParseCache::new(&self.settings.dir_output, &config);
+
+        app.time("parse", || -> Result<()> {
+            // Files are still parsed after one fails, so that diagnostics of all of them are reported
+            let mut failed = None;
+            for (i, input) in input_paths.iter().enumerate() {
+                app.check_interrupted()?;
+                let source = match fs::read_to_string(&input.path) {
+                    Ok(source) => source,
+                    Err(err) => {
+                        failed.get_or_insert(err.into());
+                        continue;
+                    }
+                };
+                let key = cache.key(&input.path, &source);
+                let cached = key.as_ref().and_then(|key| cache.get(&input.path, key));
+                let songs = match cached {
+                    Some(songs) => songs,
+                    None => {
+                        let reported = Cell::new(false);
+                        let diag_sink = |diag: Diagnostic| {
+                            reported.set(true);
+                            diag_sink(diag);
+                        };
+                        let songs = match parse_song_source(
+                            &source,
+                            &input.path,
+                            &self.project_dir,
+                            config.clone(),
+                            diag_sink,
+                        ) {
+                            Ok(songs) => songs,
+                            Err(err) => {
+                                failed.get_or_insert(err);
+                                continue;
+                            }
+                        };
+                        if let (Some(key), false) = (key, reported.get()) {
+                            cache.put(&input.path, key, &songs);
+                        }
+                        songs
+                    }
+                };
+                self.watch_set.add_input(&input.path, &songs);
+                self.book.add_songs(songs, input.section.as_deref());
+                if i + 1 == num_pinned {
+                    self.book.pinned = self.book.songs.len();
+                }
+            }
+            let paths: Vec<_> = input_paths
+                .iter()
+                .map(|input| input.path.as_path())
+                .collect();
+            cache.prune(&paths);
+            failed.map_or(Ok(()), Err)
+        })?;
 
-        self.book
-            .postprocess(&self.settings.dir_output, app.img_cache())?;
+        app.time("postprocess", || {
+            self.book.postprocess(app, &self.settings.dir_output)
+        })?;
 
         Ok(())
     }
 
-    pub fn init<P: AsRef<Path>>(project_dir: P) -> Result<()> {
-        DEFAULT_PROJECT.resolve(project_dir.as_ref()).create()
+    /// Creates a new project from `template`, its `bard.toml` is stamped with the current `Settings::version()`.
+    pub fn init<P: AsRef<Path>>(project_dir: P, template: &ProjectTemplate) -> Result<()> {
+        let project_dir = project_dir.as_ref();
+        template.resolve(project_dir)?.create()?;
+        Settings::stamp_version(&project_dir.join("bard.toml"), Settings::version())
+    }
+
+    /// Creates a song file with a title and an empty verse in the songs directory of the project
+    /// in `cwd` or its parent directories, named after the `title`'s slug, see `book::slug()`.
+    ///
+    /// An existing file is only overwritten with `force`. With `add`, the file is added
+    /// to the `songs` field of `bard.toml`, unless it's already matched by it.
+    pub fn new_song(cwd: &Path, title: &str, force: bool, add: bool) -> Result<NewSong> {
+        let title = title.trim();
+        if title.is_empty() {
+            bail!("The song title is empty.");
+        }
+
+        let (project_file, project_dir) = Self::find_project_file(cwd)?;
+        let settings = Settings::from_file(&project_file, &project_dir)?;
+
+        let filename = format!("{}.md", book::slug(title));
+        let dir_songs = settings.dir_songs();
+        let path = dir_songs.join(&filename);
+        if path.exists() && !force {
+            bail!(
+                "File {:?} already exists.\nHint: Use --force to overwrite it.",
+                path
+            );
+        }
+
+        fs::create_dir_all(dir_songs)
+            .with_context(|| format!("Could not create directory {:?}", dir_songs))?;
+        fs::write(&path, format!("# {}\n\n1. \n", title))
+            .with_context(|| format!("Could not write file {:?}", path))?;
+
+        let matched = settings
+            .songs
+            .resolve(dir_songs, settings.max_song_files)?
+            .iter()
+            .any(|input| input.path == path);
+        let added = add && !matched;
+        if added {
+            Settings::add_songs_glob(&project_file, &filename)?;
+        }
+
+        Ok(NewSong {
+            path,
+            added,
+            matched: matched || added,
+        })
     }
 
     pub fn book_section(&self) -> &Metadata {
@@ -254,10 +901,27 @@ impl Project {
         &self.book.songs
     }
 
+    /// Songs with `index` set, sorted by title, see `SortKey::Title`.
+    /// Pinned songs come first.
     pub fn songs_sorted(&self) -> &[SongRef] {
         &self.book.songs_sorted
     }
 
+    /// Number of songs at the start of `songs()` loaded from pinned files.
+    pub fn pinned(&self) -> usize {
+        self.book.pinned
+    }
+
+    /// Glossary entries of all songs, see `Book::glossary`.
+    pub fn glossary(&self) -> &[GlossEntry] {
+        &self.book.glossary
+    }
+
+    /// See `Book::songs_ordered_by()`.
+    pub fn songs_ordered_by(&self, key: SortKey) -> Vec<SongRef> {
+        self.book.songs_ordered_by(key)
+    }
+
     fn run_script(&self, app: &App, output: &Output) -> Result<()> {
         let script_fn = match output.script.as_deref() {
             Some(s) => format!("{}.{}", s, SCRIPT_EXT),
@@ -272,6 +936,14 @@ impl Project {
             );
         }
 
+        let mut input_files = OsString::new();
+        for (i, path) in self.input_paths().iter().enumerate() {
+            if i > 0 {
+                input_files.push("\n");
+            }
+            input_files.push(path);
+        }
+
         app.status("Running", format!("script '{}'", script_fn));
         let mut child = Command::new(script_path)
             .current_dir(self.settings.dir_output())
@@ -283,15 +955,41 @@ impl Project {
             .env("OUTPUT_STEM", output.file.file_stem().unwrap()) // NB. unwrap is fine here, there's always a stem
             .env("PROJECT_DIR", self.project_dir.as_os_str())
             .env("OUTPUT_DIR", self.settings.dir_output().as_os_str())
+            .env("SONG_COUNT", self.book.songs.len().to_string())
+            .env("INPUT_FILES", input_files)
             .spawn()?;
-        app.child_wait(&mut child)?.into_result()?;
+        app.child_wait(&mut child, None)?.into_result()?;
 
         Ok(())
     }
 
-    pub fn render(&self, app: &App) -> Result<()> {
+    /// Takes the lock preventing concurrent builds of the project,
+    /// which is released once the returned value is dropped.
+    fn lock_build(&self) -> Result<FileLock> {
+        let path = self.project_dir.join(Self::LOCK_FILE);
+        FileLock::try_lock(&path)
+            .with_context(|| format!("Could not lock file {:?}", path))?
+            .ok_or_else(|| {
+                anyhow!(
+                    "Another bard build is in progress in project {:?}.\nHint: Wait for it to finish, or stop it, eg. if it is running in 'bard watch'.",
+                    self.project_dir
+                )
+            })
+    }
+
+    pub fn render(&mut self, app: &App) -> Result<()> {
+        if app.dry_run() {
+            return self.render_dry_run(app);
+        }
+
+        let _lock = self.lock_build()?;
         fs::create_dir_all(&self.settings.dir_output)?;
 
+        app.time("lilypond", || {
+            self.book
+                .render_lilypond(app, &self.settings.dir_output, self.settings.tex_timeout())
+        })?;
+
         if self.settings.output.iter().any(|o| o.is_pdf()) {
             // Initialize Tex tools ahead of actual rendering so that
             // errors are reported early...
@@ -299,70 +997,283 @@ impl Project {
                 .context("Could not initialize TeX tools.")?;
         }
 
-        self.settings.output.iter().try_for_each(|output| {
-            app.check_interrupted()?;
-            app.status("Rendering", output.output_filename());
-            let context = || {
-                format!(
-                    "Could not render output file {:?}",
-                    output.file.file_name().unwrap()
-                )
-            };
-
-            let renderer = Renderer::new(self, output, app.img_cache()).with_context(context)?;
-            let tpl_version = renderer.version();
-
-            let res = renderer.render(app).with_context(context).and_then(|_| {
-                if app.post_process() {
-                    self.run_script(app, output).with_context(|| {
-                        format!(
-                            "Could not run script for output file {:?}",
-                            output.file.file_name().unwrap()
-                        )
-                    })
-                } else {
-                    Ok(())
+        // With App::keep_going(), outputs are rendered after one fails, except those depending on it
+        let mut failed: Vec<(&Output, Error)> = vec![];
+        let stop = |failed: &[(&Output, Error)]| {
+            failed
+                .iter()
+                .any(|(_, err)| !app.keep_going() || is_interrupt(err))
+        };
+        if app.jobs() <= 1 {
+            for output in self.settings.outputs_render_order() {
+                if let Err(err) = Self::check_dependencies(output, &failed)
+                    .and_then(|_| self.render_output(app, output))
+                {
+                    failed.push((output, err));
+                }
+                if stop(&failed) {
+                    break;
+                }
+            }
+        } else {
+            for wave in self.settings.outputs_render_waves() {
+                let mut outputs = vec![];
+                for output in wave {
+                    match Self::check_dependencies(output, &failed) {
+                        Ok(()) => outputs.push(output),
+                        Err(err) => failed.push((output, err)),
+                    }
                 }
-            });
+                failed.extend(self.render_parallel(app, &outputs));
+                if stop(&failed) {
+                    break;
+                }
+            }
+        }
+        Self::report_failed(app, failed)?;
+        self.watch_set.record_build();
+
+        if app.verify_fresh() {
+            self.verify_fresh(app)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the outputs `render()` would render, in order, and the TeX distribution used for PDFs,
+    /// without rendering them, see `MakeOpts::dry_run`.
+    fn render_dry_run(&self, app: &App) -> Result<()> {
+        let mut tex_runs = false;
+        if self.settings.output.iter().any(|o| o.is_pdf()) {
+            // Initialized as in a build so that configuration errors are reported
+            TexTools::initialize(app, self.settings.tex.as_ref())
+                .context("Could not initialize TeX tools.")?;
+            let tex = TexTools::get();
+            app.status(
+                "TeX",
+                format!("{}, configured from {}", tex.config(), tex.source()),
+            );
+            tex_runs = app.post_process() && !tex.config().is_none();
+        }
+
+        for output in self.settings.outputs_render_order() {
+            let mut plan = format!("{} ({})", output.output_filename(), output.format());
+            if let Some(template) = output.template_path() {
+                let template = template.strip_prefix(&self.project_dir).unwrap_or(template);
+                plan.push_str(&format!(", template {:?}", template));
+            }
+            if output.is_pdf() && tex_runs && output.tex_runs.max() > 0 {
+                plan.push_str(&format!(", TeX runs: {}", output.tex_runs));
+            }
+            app.status("Would render", plan);
+        }
+
+        Ok(())
+    }
 
-            // Perform version check of the template (if the Render supports it and there is a template file).
-            // This is done after rendering and preprocessing so that the CLI messages are at the bottom of the log.
-            // Otherwise they tend to be far behind eg. TeX output etc.
-            if let Some((tpl_version, tpl_path)) = tpl_version.zip(output.template.as_ref()) {
-                book::version::compat_check(app, tpl_path, &tpl_version);
+    fn render_output(&self, app: &App, output: &Output) -> Result<()> {
+        app.check_interrupted()?;
+        app.status("Rendering", output.output_filename());
+        let context = || {
+            format!(
+                "Could not render output file {:?}",
+                output.file.file_name().unwrap()
+            )
+        };
+
+        let renderer = app
+            .time("templates", || Renderer::new(self, output, app.img_cache()))
+            .with_context(context)?;
+        let tpl_version = renderer.version();
+
+        let res = app.time("render", || renderer.render(app));
+        let res = res.with_context(context).and_then(|_| {
+            if app.post_process() {
+                self.run_script(app, output).with_context(|| {
+                    format!(
+                        "Could not run script for output file {:?}",
+                        output.file.file_name().unwrap()
+                    )
+                })
+            } else {
+                Ok(())
             }
+        });
+
+        // Perform version check of the template (if the Render supports it and there is a template file).
+        // This is done after rendering and preprocessing so that the CLI messages are at the bottom of the log.
+        // Otherwise they tend to be far behind eg. TeX output etc.
+        if let Some((tpl_version, tpl_path)) = tpl_version.zip(output.template.as_ref()) {
+            book::version::compat_check(app, tpl_path, &tpl_version);
+            book::version::deprecation_check(app, tpl_path);
+        }
 
-            res
-        })
+        res
+    }
+
+    /// Fails if an output `output` depends on is among the `failed` ones, in which case it's not rendered.
+    fn check_dependencies(output: &Output, failed: &[(&Output, Error)]) -> Result<()> {
+        match output
+            .dependencies()
+            .find(|dep| failed.iter().any(|(failed, _)| &failed.file == *dep))
+        {
+            Some(dep) => bail!(
+                "Output {:?} was not rendered, because {:?} could not be rendered.",
+                output.output_filename(),
+                dep.file_name().unwrap_or(dep.as_os_str()),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the error of the first `failed` output. With `App::keep_going()` and more failed outputs,
+    /// the errors are printed and an error listing the outputs is returned instead.
+    fn report_failed(app: &App, mut failed: Vec<(&Output, Error)>) -> Result<()> {
+        if let Some(i) = failed.iter().position(|(_, err)| is_interrupt(err)) {
+            return Err(failed.swap_remove(i).1);
+        }
+        if failed.len() <= 1 || !app.keep_going() {
+            return failed
+                .into_iter()
+                .next()
+                .map_or(Ok(()), |(_, err)| Err(err));
+        }
+
+        let names: Vec<_> = failed
+            .iter()
+            .map(|(output, _)| format!("{:?}", output.output_filename()))
+            .collect();
+        for (_, err) in failed {
+            app.error(err);
+        }
+        bail!(
+            "{} outputs could not be rendered: {}",
+            names.len(),
+            names.join(", ")
+        );
+    }
+
+    /// Renders `outputs` with up to `App::jobs()` threads, TeX runs are still serialized by `TexTools`.
+    /// Returns the errors of failed outputs in the order of `outputs`. After an error, no further outputs
+    /// are started, unless with `App::keep_going()`.
+    fn render_parallel<'o>(&self, app: &App, outputs: &[&'o Output]) -> Vec<(&'o Output, Error)> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results: Mutex<Vec<(usize, Error)>> = Mutex::new(vec![]);
+
+        thread::scope(|scope| {
+            for _ in 0..app.jobs().min(outputs.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= outputs.len() || failed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    // Each output's messages are printed together once it's done
+                    let app = app.buffered();
+                    let res = self.render_output(&app, outputs[i]);
+                    app.flush_buffer();
+                    if let Err(err) = res {
+                        if !app.keep_going() || is_interrupt(&err) {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        results.lock().push((i, err));
+                    }
+                });
+            }
+        });
+
+        let mut results = results.into_inner();
+        results.sort_by_key(|(i, _)| *i);
+        results
+            .into_iter()
+            .map(|(i, err)| (outputs[i], err))
+            .collect()
+    }
+
+    /// Checks that each output file rendered by `render()` exists and is newer than all input files,
+    /// ie. those returned by `watch_paths()`.
+    ///
+    /// This is a sanity check against outputs which were not rebuilt, see `MakeOpts::verify_fresh`.
+    /// For PDF outputs which are not processed by TeX the TeX file is checked instead,
+    /// with `MakeOpts::preview` the preview files are checked.
+    pub fn verify_fresh(&self, app: &App) -> Result<()> {
+        let inputs: Vec<_> = self
+            .watch_paths()
+            .filter_map(|path| mtime(path).map(|mtime| (path, mtime)))
+            .collect();
+
+        for output in self.settings.output.iter() {
+            let tex_runs = output.is_pdf()
+                && output.tex_runs.max() > 0
+                && app.post_process()
+                && !TexTools::get().config().is_none();
+            let file = match (output.is_pdf(), app.preview().is_some(), tex_runs) {
+                (true, true, true) => output.file.with_extension("preview.pdf"),
+                (true, true, false) => output.file.with_extension("preview.tex"),
+                (true, false, false) => output.file.with_extension("tex"),
+                _ => output.file.clone(),
+            };
+
+            let output_mtime = mtime(&file)
+                .ok_or_else(|| anyhow!("Output file {:?} was not produced by the build.", file))?;
+            let newer_input = inputs
+                .iter()
+                .filter(|(_, mtime)| *mtime > output_mtime)
+                .max_by_key(|(_, mtime)| *mtime);
+            if let Some((input, _)) = newer_input {
+                bail!(
+                    "Output file {:?} is older than its input {:?} after the build.",
+                    file,
+                    input
+                );
+            }
+        }
+
+        Ok(())
     }
 
-    pub fn input_paths(&self) -> &Vec<PathBuf> {
-        &self.input_paths
+    pub fn input_paths(&self) -> &[PathBuf] {
+        self.watch_set.inputs()
     }
 
     pub fn output_paths(&self) -> impl Iterator<Item = &Path> {
         self.settings.output.iter().map(|o| o.file.as_path())
     }
 
-    pub fn watch_paths(&self) -> impl Iterator<Item = &Path> {
-        // Input MD files:
-        // TODO: this won't work for wildcards
-        let inputs = self.input_paths.iter().map(PathBuf::as_ref);
+    /// Returns `true` if all output files exist and are newer than all input files,
+    /// ie. those returned by `watch_paths()`, and the set of input files is the same
+    /// as in the last build, in which case there's nothing to rebuild.
+    pub fn is_up_to_date(&self) -> bool {
+        if self.watch_set.changed_since_build() {
+            return false;
+        }
 
-        // Templates:
-        let templates = self
-            .settings
-            .output
-            .iter()
-            .filter_map(Output::template_path);
+        // Missing inputs are considered changed
+        let newest_input = match self.watch_paths().map(mtime).collect::<Option<Vec<_>>>() {
+            Some(mtimes) => mtimes.into_iter().max(),
+            None => return false,
+        };
+        let oldest_output = match self.output_paths().map(mtime).collect::<Option<Vec<_>>>() {
+            Some(mtimes) => mtimes.into_iter().min(),
+            None => return false,
+        };
 
-        // Images:
-        let images = self.book.iter_images().map(|i| i.full_path());
+        match (newest_input, oldest_output) {
+            (Some(input), Some(output)) => output >= input,
+            _ => false,
+        }
+    }
+
+    pub fn watch_paths(&self) -> impl Iterator<Item = &Path> {
+        self.watch_set.paths()
+    }
+
+    pub fn watch_set(&self) -> &WatchSet {
+        &self.watch_set
+    }
 
-        // bard.toml:
-        iter::once(self.project_file.as_path())
-            .chain(inputs)
-            .chain(templates)
-            .chain(images)
+    /// Drops the loaded book and keeps only its dependencies, eg. for watching them for changes.
+    pub fn into_watch_set(self) -> WatchSet {
+        self.watch_set
     }
 }
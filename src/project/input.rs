@@ -1,26 +1,80 @@
-use std::slice;
+use std::{fs, io, mem, slice};
 
-use globset::Glob;
-use serde::Deserialize;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::*;
 use crate::util::{read_dir_all, sort_paths_lexical};
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum SongsGlobs {
     One(String),
     Many(Vec<String>),
+    /// Songs grouped in labelled parts of the book, see `SongsSection`.
+    Sections(Vec<SongsSection>),
+}
+
+/// Songs of a labelled part of the book, written as `{ section = "Ballads", globs = [...] }`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SongsSection {
+    pub section: String,
+    pub globs: Vec<String>,
+}
+
+/// A song file matched by `SongsGlobs` along with the label of its section, if any.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SongFile {
+    pub path: PathBuf,
+    pub section: Option<String>,
 }
 
 impl SongsGlobs {
     pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
-        let items = match self {
-            Self::One(one) => slice::from_ref(one),
-            Self::Many(many) => many.as_slice(),
+        let (items, sections): (&[String], &[SongsSection]) = match self {
+            Self::One(one) => (slice::from_ref(one), &[]),
+            Self::Many(many) => (many.as_slice(), &[]),
+            Self::Sections(sections) => (&[], sections.as_slice()),
         };
 
-        (0..).map_while(move |i| items.get(i).map(move |s| s.as_str()))
+        items
+            .iter()
+            .chain(sections.iter().flat_map(|section| section.globs.iter()))
+            .map(String::as_str)
+    }
+
+    /// Whether any of the patterns is a glob, in which case the matched files may change
+    /// as files are added to or removed from the songs directory.
+    pub fn has_globs(&self) -> bool {
+        self.iter().any(InputSet::is_globlike)
+    }
+
+    /// Appends `glob` to the patterns, to the last section if the songs are split into sections.
+    pub fn push(&mut self, glob: String) {
+        match self {
+            Self::One(one) => *self = Self::Many(vec![mem::take(one), glob]),
+            Self::Many(many) => many.push(glob),
+            Self::Sections(sections) => match sections.last_mut() {
+                Some(section) => section.globs.push(glob),
+                None => *self = Self::Many(vec![glob]),
+            },
+        }
+    }
+
+    /// Returns the song files in `dir_songs` matched by the patterns, see `InputSet`.
+    pub fn resolve(&self, dir_songs: &Path, limit: usize) -> Result<Vec<SongFile>> {
+        let input_set = InputSet::new(dir_songs)?;
+        let input_set = match self {
+            Self::Sections(sections) => sections.iter().try_fold(input_set, |set, section| {
+                section
+                    .globs
+                    .iter()
+                    .map(String::as_str)
+                    .try_fold(set.begin_section(&section.section), InputSet::apply_glob)
+            })?,
+            _ => self.iter().try_fold(input_set, InputSet::apply_glob)?,
+        };
+        input_set.finalize(limit)
     }
 }
 
@@ -30,26 +84,125 @@ impl Default for SongsGlobs {
     }
 }
 
+/// A rule from `.bardignore`, see `IgnoreList`.
+#[derive(Debug)]
+struct IgnoreRule {
+    set: GlobSet,
+    negated: bool,
+}
+
+/// Patterns loaded from the `.bardignore` file in the songs directory.
+///
+/// The file uses the gitignore syntax, ie. one pattern per line,
+/// `#` comments, `!` to re-include previously excluded files,
+/// a trailing `/` to only match directories, and patterns containing a `/`
+/// are relative to the songs directory, while others match at any depth.
+#[derive(Debug, Default)]
+struct IgnoreList {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreList {
+    const FILENAME: &'static str = ".bardignore";
+
+    fn load(dir_songs: &Path) -> Result<Self> {
+        let path = dir_songs.join(Self::FILENAME);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err).with_context(|| format!("Could not read file {:?}", path)),
+        };
+
+        let mut rules = vec![];
+        for (i, line) in content.lines().enumerate() {
+            let rule = Self::parse_rule(line)
+                .with_context(|| format!("Invalid pattern in file {:?} on line {}", path, i + 1))?;
+            rules.extend(rule);
+        }
+
+        Ok(Self { rules })
+    }
+
+    fn parse_rule(line: &str) -> Result<Option<IgnoreRule>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+        // A backslash escapes a leading '#' or '!'
+        let pattern = pattern.strip_prefix('\\').unwrap_or(pattern);
+        let (dir_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+
+        let glob = |pattern: &str| {
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob pattern: '{}'", pattern))
+        };
+        let mut set = GlobSetBuilder::new();
+        if !dir_only {
+            set.add(glob(&pattern)?);
+        }
+        set.add(glob(&format!("{}/**", pattern))?);
+
+        Ok(Some(IgnoreRule {
+            set: set.build()?,
+            negated,
+        }))
+    }
+
+    /// `rel_path` is relative to the songs directory. The last matching rule wins.
+    fn is_ignored(&self, rel_path: &Path) -> bool {
+        if rel_path == Path::new(Self::FILENAME) {
+            return true;
+        }
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.set.is_match(rel_path))
+            .map_or(false, |rule| !rule.negated)
+    }
+}
+
 #[derive(Debug)]
 pub struct InputSet<'a> {
     dir_songs: &'a Path,
     all_files: Vec<PathBuf>,
     match_set: Vec<PathBuf>,
+    /// Indices into `match_set` where sections start and their labels, see `begin_section()`.
+    sections: Vec<(usize, String)>,
 }
 
 impl<'a> InputSet<'a> {
     pub fn new(dir_songs: &'a Path) -> Result<Self> {
-        let all_files = read_dir_all(dir_songs)
+        let ignore = IgnoreList::load(dir_songs)?;
+        let mut all_files = read_dir_all(dir_songs)
             .with_context(|| format!("Could not read directory {:?}", dir_songs))?;
+        // NB. Unwrap should be ok here as the paths will all be prefixed by dir_songs
+        all_files.retain(|path| !ignore.is_ignored(path.strip_prefix(dir_songs).unwrap()));
 
         Ok(Self {
             dir_songs,
             all_files,
             match_set: vec![],
+            sections: vec![],
         })
     }
 
-    fn is_globlike<S: AsRef<str>>(s: S) -> bool {
+    pub fn is_globlike<S: AsRef<str>>(s: S) -> bool {
         s.as_ref().contains(&['*', '?', '{', '}'][..])
     }
 
@@ -102,7 +255,47 @@ impl<'a> InputSet<'a> {
         Ok(self)
     }
 
-    pub fn finalize(self) -> Result<Vec<PathBuf>> {
-        Ok(self.match_set)
+    /// Files matched from now on belong to the section labelled `section`.
+    /// The order of files is preserved across sections the same way as across globs.
+    pub fn begin_section(mut self, section: &str) -> Self {
+        self.sections
+            .push((self.match_set.len(), section.to_string()));
+        self
+    }
+
+    /// Returns the matched files, fails if there are more than `limit` of them (unless `limit` is `0`).
+    pub fn finalize(self, limit: usize) -> Result<Vec<SongFile>> {
+        if limit > 0 && self.match_set.len() > limit {
+            let examples: Vec<_> = self
+                .match_set
+                .iter()
+                .take(5)
+                .map(|path| format!("  {:?}", path.strip_prefix(self.dir_songs).unwrap_or(path)))
+                .collect();
+            bail!(
+                "The songs setting matches {} files, more than the limit of {}. The first files matched are:\n{}\nHint: Check the songs patterns in bard.toml, or raise the limit with the max_song_files setting.",
+                self.match_set.len(),
+                limit,
+                examples.join("\n"),
+            );
+        }
+
+        let mut sections = self.sections.into_iter().peekable();
+        let mut section = None;
+        let files = self
+            .match_set
+            .into_iter()
+            .enumerate()
+            .map(|(i, path)| {
+                while let Some((_, label)) = sections.next_if(|(start, _)| *start <= i) {
+                    section = Some(label);
+                }
+                SongFile {
+                    path,
+                    section: section.clone(),
+                }
+            })
+            .collect();
+        Ok(files)
     }
 }
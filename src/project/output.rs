@@ -1,10 +1,16 @@
 use std::borrow::Cow;
+use std::fmt;
+use std::time::SystemTime;
 
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use strum::{Display, EnumVariantNames, VariantNames};
 
+use crate::app::App;
+use crate::music::Notation;
 use crate::prelude::*;
-use crate::project::Metadata;
+use crate::project::{Metadata, Value};
+use crate::render::merge::pdf_page_count;
 use crate::util::PathBufExt;
 
 #[derive(Serialize, Deserialize, Display, EnumVariantNames, PartialEq, Eq, Clone, Copy, Debug)]
@@ -14,8 +20,12 @@ pub enum Format {
     Pdf,
     Html,
     Hovorka,
+    Epub,
     Json,
     Xml,
+    Merge,
+    Licenses,
+    ChordPro,
 }
 
 impl Format {
@@ -41,8 +51,11 @@ impl Format {
         Ok(match ext.to_str().unwrap_or("") {
             "pdf" => Self::Pdf,
             "html" => Self::Html,
+            "epub" => Self::Epub,
             "json" => Self::Json,
             "xml" => Self::Xml,
+            "csv" => Self::Licenses,
+            "cho" | "chopro" | "chordpro" | "pro" => Self::ChordPro,
             _ => bail!(
                 "Could not detect format based file on extension for: {:?}\n{}",
                 path,
@@ -53,12 +66,69 @@ impl Format {
 
     fn default_dpi(self) -> f32 {
         match self {
-            Self::Html => 1.0,
+            Self::Html | Self::Epub => 1.0,
             _ => 144.0,
         }
     }
 }
 
+/// Page numbering of the front matter (title page and ToC) in PDF outputs.
+///
+/// The variant names are the LaTeX `\pagenumbering` styles.
+#[derive(Serialize, Deserialize, Display, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum PageNumbering {
+    /// Front matter pages are not numbered and count towards song page numbers.
+    #[default]
+    None,
+    /// Lowercase roman numerals, song pages are numbered separately.
+    Roman,
+    /// Arabic numerals, song pages are numbered separately.
+    Arabic,
+}
+
+/// Which chords are rendered for chords with an alternate, see `Chord::alternate`.
+#[derive(Serialize, Deserialize, Display, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum AlternateChords {
+    /// The chords as written, alternates are available to templates.
+    #[default]
+    Primary,
+    /// The alternates, eg. for a beginner edition.
+    Alternate,
+    /// The chords followed by their alternates in parentheses.
+    Both,
+}
+
+/// Which chords are rendered for songs played with a capo, see `Song::with_capo()`.
+#[derive(Serialize, Deserialize, Display, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum CapoChords {
+    /// The shapes fingered with the capo.
+    #[default]
+    Shapes,
+    /// The sounding chords as written.
+    Sounding,
+    /// The sounding chords followed by the shapes in parentheses.
+    Both,
+}
+
+/// Whether an output is rendered into one file or split into a file per song.
+#[derive(Serialize, Deserialize, Display, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum Split {
+    /// All songs are rendered into the output file.
+    #[default]
+    None,
+    /// Each song is rendered into a file named by its slug in a directory named
+    /// after the output file, which becomes an index, see `Output::split_dir()`.
+    PerSong,
+}
+
 fn default_font_size() -> u32 {
     12
 }
@@ -67,8 +137,66 @@ fn default_toc_sort_key() -> String {
     "numberline\\s+\\{[^}]*}([^}]+)".to_string()
 }
 
-fn default_tex_runs() -> u32 {
-    3
+fn default_tex_runs() -> TexRuns {
+    TexRuns::Fixed(3)
+}
+
+/// Number of TeX runs of a PDF output, a number or `"auto"`, see `Output::tex_runs`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TexRuns {
+    Fixed(u32),
+    /// TeX is rerun until the `.toc` and `.aux` files don't change, up to `AUTO_MAX` runs in total.
+    Auto,
+}
+
+impl TexRuns {
+    pub const AUTO_MAX: u32 = 5;
+
+    /// The maximum number of runs, `0` means TeX doesn't run.
+    pub fn max(self) -> u32 {
+        match self {
+            Self::Fixed(runs) => runs,
+            Self::Auto => Self::AUTO_MAX,
+        }
+    }
+}
+
+impl fmt::Display for TexRuns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(runs) => write!(f, "{}", runs),
+            Self::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+impl Serialize for TexRuns {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Fixed(runs) => serializer.serialize_u32(*runs),
+            Self::Auto => serializer.serialize_str("auto"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TexRuns {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Runs(u32),
+            Mode(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Runs(runs) => Ok(Self::Fixed(runs)),
+            Repr::Mode(mode) if mode == "auto" => Ok(Self::Auto),
+            Repr::Mode(mode) => Err(D::Error::custom(format!(
+                "invalid tex_runs value {:?}, expected a number or \"auto\"",
+                mode
+            ))),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -86,36 +214,185 @@ pub struct Output {
     pub toc_sort: bool,
     #[serde(default = "default_toc_sort_key")]
     pub toc_sort_key: String,
+    /// Show songs' `{key: K}` annotations in the table of contents of the default templates.
+    #[serde(default)]
+    pub toc_keys: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dpi: Option<f32>,
     #[serde(default = "default_tex_runs")]
-    pub tex_runs: u32,
+    pub tex_runs: TexRuns,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub script: Option<String>,
+    /// Render only chords and song structure, without lyrics.
+    #[serde(default)]
+    pub chords_only: bool,
+    /// Render chord accidentals, navigation markers, and punctuation as plain ASCII,
+    /// see `Song::ascii_only()`.
+    #[serde(default)]
+    pub ascii_only: bool,
+    /// Transpose chords by this many semitones, see `Song::transposed()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transpose: Option<i32>,
+    /// Transpose each song from its `{key: ...}` to this key, see `Song::transposed_to()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_key: Option<String>,
+    /// Show chords relative to a capo at this fret, unless a song has its own `{capo: N}`, see `Song::with_capo()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capo: Option<u32>,
+    /// Which chords to render for songs played with a capo.
+    #[serde(default)]
+    pub capo_chords: CapoChords,
+    /// Render chords in this notation instead of the project's one, see `Song::with_notation()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notation: Option<Notation>,
+    /// Render chord accidentals as `♯` and `♭` instead of the project's `unicode_accidentals`,
+    /// see `Song::with_unicode_accidentals()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unicode_accidentals: Option<bool>,
+    /// Which of chords and their alternates to render, see `Song::with_alternate_chords()`.
+    #[serde(default)]
+    pub alternate_chords: AlternateChords,
+    /// Render a file per song in addition to the index, see `Split`.
+    #[serde(default)]
+    pub split: Split,
+    /// Render QR codes of songs' links, see `Song::with_qr()`.
+    #[serde(default)]
+    pub qr_codes: bool,
+    /// Provide the index of chords used in the book to the template, see `book::chord_index()`.
+    #[serde(default)]
+    pub chord_index: bool,
+    /// Normalize whitespace of generated TeX source, see `RPdf`.
+    #[serde(default)]
+    pub tex_normalize: bool,
+    /// Write SyncTeX data next to PDF outputs, see `TexRenderJob::move_pdf()`.
+    #[serde(default)]
+    pub synctex: bool,
+    /// Page numbering style of the front matter in PDF outputs.
+    #[serde(default)]
+    pub front_matter_numbering: PageNumbering,
+    /// Page number of the first page of songs in PDF outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_start: Option<u32>,
+    /// File of another PDF output after which page numbering continues, see `page_start()`.
+    #[serde(default, skip_serializing)]
+    pub page_start_after: Option<PathBuf>,
+    /// Files of other outputs concatenated into this one, see `Format::Merge`.
+    #[serde(default, skip_serializing)]
+    pub merge: Vec<PathBuf>,
+    /// External PDF files, eg. a cover, placed before the pages rendered by TeX, see `TexRenderJob`.
+    #[serde(default, skip_serializing)]
+    pub prepend_pdf: Vec<PathBuf>,
+    /// External PDF files placed after the pages rendered by TeX.
+    #[serde(default, skip_serializing)]
+    pub append_pdf: Vec<PathBuf>,
 
     #[serde(rename = "book", default, skip_serializing)]
     pub book_overrides: Metadata,
 }
 
 impl Output {
-    pub fn resolve(&mut self, dir_templates: &Path, dir_output: &Path) -> Result<()> {
+    pub fn resolve(
+        &mut self,
+        dir_templates: &Path,
+        dir_output: &Path,
+        project_book: &Metadata,
+    ) -> Result<()> {
+        let book = self.override_book_section(project_book);
+        self.file = interpolate_file(&self.file, &book)?;
+        for file in self
+            .merge
+            .iter_mut()
+            .chain(self.page_start_after.iter_mut())
+        {
+            *file = interpolate_file(file, project_book)?;
+        }
+
         if let Some(template) = self.template.as_mut() {
             template.resolve(dir_templates);
         }
 
         if self.format.is_none() {
-            self.format = Some(Format::try_from_ext(&self.file)?);
+            self.format = Some(if self.merge.is_empty() {
+                Format::try_from_ext(&self.file)?
+            } else {
+                Format::Merge
+            });
+        }
+
+        match (self.format(), self.merge.is_empty()) {
+            (Format::Merge, true) => bail!(
+                "Output {:?} has the 'merge' format, but no 'merge' list of outputs to concatenate.",
+                self.file
+            ),
+            (Format::Merge, false) | (_, true) => {}
+            (format, false) => bail!(
+                "Output {:?} has a 'merge' list, but its format is '{}'. Only PDF files can be merged.",
+                self.file,
+                format
+            ),
+        }
+
+        if self.split != Split::None
+            && !matches!(self.format(), Format::Html | Format::Json | Format::Xml)
+        {
+            bail!(
+                "Output {:?} sets 'split = \"{}\"', but its format is '{}'. Only HTML, JSON, and XML outputs can be split.",
+                self.file,
+                self.split,
+                self.format()
+            );
+        }
+
+        let includes_pdfs = !self.prepend_pdf.is_empty() || !self.append_pdf.is_empty();
+        if includes_pdfs && !self.is_pdf() {
+            bail!(
+                "Output {:?} sets 'prepend_pdf' or 'append_pdf', but its format is '{}'. Only PDF outputs can include PDF files.",
+                self.file,
+                self.format()
+            );
+        }
+
+        if self.transpose.is_some() && self.to_key.is_some() {
+            bail!(
+                "Output {:?} sets both 'transpose' and 'to_key', only one may be used.",
+                self.file
+            );
+        }
+
+        if self.page_start.is_some() && self.page_start_after.is_some() {
+            bail!(
+                "Output {:?} sets both 'page_start' and 'page_start_after', only one may be used.",
+                self.file
+            );
         }
 
         self.file.resolve(dir_output);
+        for file in self.merge.iter_mut() {
+            file.resolve(dir_output);
+        }
+        if let Some(file) = self.page_start_after.as_mut() {
+            file.resolve(dir_output);
+        }
+        for file in self
+            .prepend_pdf
+            .iter_mut()
+            .chain(self.append_pdf.iter_mut())
+        {
+            file.resolve(dir_output);
+        }
         Ok(())
     }
 
+    /// Directory of per-song files of a split output, the output file without extension.
+    pub fn split_dir(&self) -> PathBuf {
+        self.file.with_extension("")
+    }
+
     pub fn format(&self) -> Format {
         self.format.unwrap()
     }
 
-    pub fn output_filename(&self) -> Cow<str> {
+    pub fn output_filename(&self) -> Cow<'_, str> {
         self.file
             .file_name()
             .expect("OutputSpec: Invalid filename")
@@ -124,8 +401,10 @@ impl Output {
 
     pub fn template_path(&self) -> Option<&Path> {
         match self.format() {
-            Format::Pdf | Format::Html | Format::Hovorka => self.template.as_deref(),
-            Format::Json | Format::Xml => None,
+            Format::Pdf | Format::Html | Format::Hovorka | Format::Epub => self.template.as_deref(),
+            Format::Json | Format::Xml | Format::Merge | Format::Licenses | Format::ChordPro => {
+                None
+            }
         }
     }
 
@@ -133,13 +412,50 @@ impl Output {
         self.format() == Format::Pdf
     }
 
+    /// Other outputs' files that need to be rendered before this one,
+    /// ie. the `merge` files and the `page_start_after` file.
+    pub fn dependencies(&self) -> impl Iterator<Item = &PathBuf> {
+        self.merge.iter().chain(self.page_start_after.iter())
+    }
+
+    /// Returns the number of the first page of songs of this output.
+    ///
+    /// With `page_start_after`, this is the first page number of the preceding output
+    /// plus the number of all pages in its PDF file, which must have been rendered already.
+    /// If the file is missing (eg. when TeX is disabled), its pages are not counted.
+    pub fn page_start(&self, app: &App, outputs: &[Output]) -> Result<u32> {
+        let after = match (self.page_start, self.page_start_after.as_ref()) {
+            (Some(page_start), _) => return Ok(page_start),
+            (None, None) => return Ok(1),
+            (None, Some(after)) => after,
+        };
+
+        // NB. Unwrap is ok, the dependency is validated in render_order()
+        let prev = outputs.iter().find(|o| &o.file == after).unwrap();
+        let prev_start = prev.page_start(app, outputs)?;
+        if !prev.file.exists() {
+            app.warning(format!(
+                "Output {:?} continues page numbering after {:?}, but that file doesn't exist, its pages are not counted.",
+                self.output_filename(),
+                prev.output_filename(),
+            ));
+            return Ok(prev_start);
+        }
+
+        let pages = pdf_page_count(&prev.file)?;
+        Ok(prev_start + pages)
+    }
+
     pub fn dpi(&self) -> f32 {
         self.dpi
             .unwrap_or_else(|| self.format.unwrap().default_dpi())
     }
 
+    /// Returns the `[book]` section with overrides of this output applied.
+    ///
+    /// Additionally, for each array field, a `<field>_joined` string is added, see `join_list()`.
     pub fn override_book_section<'a>(&self, project_book: &'a Metadata) -> Cow<'a, Metadata> {
-        if self.book_overrides.is_empty() {
+        let mut meta = if self.book_overrides.is_empty() {
             Cow::Borrowed(project_book)
         } else {
             let mut meta = project_book.clone();
@@ -149,6 +465,224 @@ impl Output {
                     .map(|(k, v)| (k.clone(), v.clone())),
             );
             Cow::Owned(meta)
+        };
+
+        let joined: Vec<_> = meta
+            .iter()
+            .filter_map(|(key, value)| {
+                let key = format!("{}_joined", key);
+                let list = value.as_array()?;
+                (!meta.contains_key(key.as_str())).then(|| (key, join_list(&meta, list)))
+            })
+            .collect();
+        if !joined.is_empty() {
+            let meta = meta.to_mut();
+            for (key, value) in joined {
+                meta.insert(key.into(), value.into());
+            }
+        }
+
+        meta
+    }
+}
+
+/// Replaces `{placeholder}`s in an output `file` name.
+///
+/// A placeholder may be a string or number field of the `[book]` section,
+/// or one of the build-time values `date` (as `YYYY-MM-DD`) and `bard_version`.
+/// `{{` and `}}` stand for literal braces.
+fn interpolate_file(file: &Path, book: &Metadata) -> Result<PathBuf> {
+    let is_brace = |c| c == '{' || c == '}';
+    let template = match file.to_str() {
+        Some(template) if template.contains(is_brace) => template,
+        _ => return Ok(file.to_owned()),
+    };
+
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(pos) = rest.find(is_brace) {
+        res.push_str(&rest[..pos]);
+        let c = rest.as_bytes()[pos];
+        rest = &rest[pos + 1..];
+        if rest.as_bytes().first() == Some(&c) {
+            res.push(c as char);
+            rest = &rest[1..];
+            continue;
+        }
+        if c == b'}' {
+            bail!("Unmatched '}}' in output file name {:?}.", template);
+        }
+
+        let end = rest
+            .find('}')
+            .ok_or_else(|| anyhow!("Unclosed '{{' in output file name {:?}.", template))?;
+        let name = rest[..end].trim();
+        rest = &rest[end + 1..];
+
+        let value = match (book.get(name), name) {
+            (Some(Value::String(s)), _) => s.clone(),
+            (Some(Value::Integer(i)), _) => i.to_string(),
+            (Some(Value::Float(f)), _) => f.to_string(),
+            (Some(_), _) => bail!(
+                "Placeholder {{{}}} in output file name {:?} is not a string or a number in the [book] section.",
+                name,
+                template,
+            ),
+            (None, "date") => build_date(),
+            (None, "bard_version") => env!("CARGO_PKG_VERSION").to_owned(),
+            (None, _) => bail!(
+                "Unknown placeholder {{{}}} in output file name {:?}.\n\
+                Hint: Placeholders may refer to fields of the [book] section, 'date', or 'bard_version'.",
+                name,
+                template,
+            ),
+        };
+        // Values may not introduce path components
+        res.extend(value.chars().map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        }));
+    }
+    res.push_str(rest);
+
+    Ok(res.into())
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`.
+pub(crate) fn build_date() -> String {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Conversion of days since epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Joins `list` items into a string such as `"A, B and C"`.
+///
+/// The separators may be configured in `meta` using the `list_separator`
+/// and `list_last_separator` fields, by default these are `", "` and `" and "`.
+fn join_list(meta: &Metadata, list: &[Value]) -> String {
+    let separator = |key: &str, default| meta.get(key).and_then(Value::as_str).unwrap_or(default);
+    let sep = separator("list_separator", ", ");
+    let last_sep = separator("list_last_separator", " and ");
+
+    let mut res = String::new();
+    for (i, item) in list.iter().enumerate() {
+        if i > 0 {
+            res.push_str(if i + 1 == list.len() { last_sep } else { sep });
+        }
+        match item {
+            Value::String(s) => res.push_str(s),
+            other => res.push_str(&other.to_string()),
+        }
+    }
+    res
+}
+
+/// Computes the order in which `outputs` are rendered, as indices into the slice.
+///
+/// Outputs listed in another output's `merge` or `page_start_after` field are rendered before it,
+/// otherwise the order in which outputs are defined is preserved.
+pub fn render_order(outputs: &[Output]) -> Result<Vec<usize>> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mark {
+        Unvisited,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        i: usize,
+        outputs: &[Output],
+        marks: &mut [Mark],
+        order: &mut Vec<usize>,
+    ) -> Result<()> {
+        match marks[i] {
+            Mark::Done => return Ok(()),
+            Mark::Visiting => bail!(
+                "Output {:?} is part of a cycle of 'merge' or 'page_start_after' dependencies.",
+                outputs[i].output_filename()
+            ),
+            Mark::Unvisited => {}
+        }
+
+        marks[i] = Mark::Visiting;
+        for file in outputs[i].dependencies() {
+            let relation = if outputs[i].merge.contains(file) {
+                "merges"
+            } else {
+                "continues page numbering after"
+            };
+            let dep = outputs
+                .iter()
+                .position(|o| &o.file == file)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Output {:?} {} {:?}, which is not an output of this project.",
+                        outputs[i].output_filename(),
+                        relation,
+                        file.file_name().unwrap_or(file.as_os_str()),
+                    )
+                })?;
+
+            if !matches!(outputs[dep].format(), Format::Pdf | Format::Merge) {
+                bail!(
+                    "Output {:?} {} {:?}, which is not a PDF output.",
+                    outputs[i].output_filename(),
+                    relation,
+                    outputs[dep].output_filename(),
+                );
+            }
+
+            visit(dep, outputs, marks, order)?;
+        }
+
+        marks[i] = Mark::Done;
+        order.push(i);
+        Ok(())
+    }
+
+    let mut marks = vec![Mark::Unvisited; outputs.len()];
+    let mut order = Vec::with_capacity(outputs.len());
+    for i in 0..outputs.len() {
+        visit(i, outputs, &mut marks, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+/// Splits the `order` returned by `render_order()` into groups of outputs that can be rendered in parallel,
+/// each output is in a later group than the outputs it depends on, see `MakeOpts::jobs`.
+pub fn render_waves(outputs: &[Output], order: &[usize]) -> Vec<Vec<usize>> {
+    let mut wave_of = vec![0; outputs.len()];
+    let mut waves: Vec<Vec<usize>> = vec![];
+    for &i in order {
+        // NB. Dependencies were validated and come before their dependents in `order`
+        let wave = outputs[i]
+            .dependencies()
+            .filter_map(|file| outputs.iter().position(|o| &o.file == file))
+            .map(|dep| wave_of[dep] + 1)
+            .max()
+            .unwrap_or(0);
+        wave_of[i] = wave;
+        if waves.len() <= wave {
+            waves.resize_with(wave + 1, Vec::new);
         }
+        waves[wave].push(i);
     }
+    waves
 }
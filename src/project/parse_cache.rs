@@ -0,0 +1,132 @@
+//! Persistent cache of parsed song files, see `ParseCache`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::book::Song;
+use crate::parser::ParserConfig;
+use crate::prelude::*;
+use crate::util::write_atomic;
+
+/// Songs parsed from input files, stored in the `.bard-cache` directory in the output directory,
+/// so that unchanged files aren't parsed again on rebuilds, eg. in `bard watch`.
+///
+/// Entries are keyed by the file's path, mtime, and content hash. The cache is invalidated
+/// when the `ParserConfig` or the bard version changes. Files which produced diagnostics
+/// aren't cached, so that those are reported on every build. Entries of files which are
+/// no longer inputs of the project are removed, see `prune()`.
+///
+/// The cache is best-effort: a missing or unreadable entry is a miss
+/// and errors writing an entry are ignored.
+#[derive(Debug)]
+pub struct ParseCache {
+    dir: PathBuf,
+    config: u64,
+}
+
+/// Identifies the content of an input file, see `ParseCache::key()`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct Key {
+    mtime: SystemTime,
+    hash: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    path: PathBuf,
+    key: Key,
+    config: u64,
+    songs: Vec<Song>,
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl ParseCache {
+    /// Name of the cache directory in the output directory.
+    pub const DIR: &'static str = ".bard-cache";
+
+    pub fn new(dir_output: &Path, config: &ParserConfig) -> Self {
+        Self {
+            dir: dir_output.join(Self::DIR),
+            config: hash((config, env!("CARGO_PKG_VERSION"))),
+        }
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", hash(path)))
+    }
+
+    /// Key of the file at `path` with the `source` that is parsed, as read from the file,
+    /// so that the entry is stored for exactly the content it was parsed from.
+    pub fn key(&self, path: &Path, source: &str) -> Option<Key> {
+        let mtime = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+        Some(Key {
+            mtime,
+            hash: hash(source),
+        })
+    }
+
+    /// Returns the songs parsed from `path`, if it hasn't changed since they were cached.
+    pub fn get(&self, path: &Path, key: &Key) -> Option<Vec<Song>> {
+        let entry = fs::read(self.entry_path(path)).ok()?;
+        let entry: Entry = serde_json::from_slice(&entry).ok()?;
+        let hit = entry.path == path && entry.key == *key && entry.config == self.config;
+        hit.then_some(entry.songs)
+    }
+
+    /// Removes entries of files other than `inputs`, eg. song files which were removed or renamed.
+    pub fn prune(&self, inputs: &[&Path]) {
+        let keep: HashSet<PathBuf> = inputs.iter().map(|path| self.entry_path(path)).collect();
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if Self::is_entry(&path) && !keep.contains(&path) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+
+    /// Whether `path` is named like an entry, see `entry_path()`.
+    fn is_entry(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        match name.strip_suffix(".json") {
+            Some(stem) => stem.len() == 16 && stem.chars().all(|c| c.is_ascii_hexdigit()),
+            None => false,
+        }
+    }
+
+    /// Stores the `songs` parsed from `path`.
+    pub fn put(&self, path: &Path, key: Key, songs: &[Song]) {
+        let entry = Entry {
+            path: path.to_owned(),
+            key,
+            config: self.config,
+            songs: songs.to_vec(),
+        };
+
+        let _ = fs::create_dir_all(&self.dir)
+            .map_err(Error::from)
+            .and_then(|_| {
+                write_atomic(&self.entry_path(path), |file| {
+                    serde_json::to_writer(&mut *file, &entry)?;
+                    file.flush()?;
+                    Ok(())
+                })
+            });
+    }
+}
@@ -0,0 +1,250 @@
+//! Dependencies of a project, ie. files which trigger a rebuild in `bard watch`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::iter;
+
+use super::{parse_song_file, Output, Project, Settings, SongFile, SongsGlobs};
+use crate::book::Song;
+use crate::parser::{Diagnostic, ParserConfig};
+use crate::prelude::*;
+use crate::util::write_atomic;
+
+/// What changed in a `WatchSet` after `WatchSet::update()`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WatchChange {
+    /// None of the changed files is a dependency of the project.
+    None,
+    /// Some dependencies changed, but the set of dependencies is the same.
+    Contents,
+    /// The set of dependencies changed, eg. a song file was added or an image reference removed.
+    Paths,
+}
+
+/// Files a project depends on: `bard.toml`, song files, templates, and images.
+///
+/// Unlike `Project`, this only parses the song files to find referenced images,
+/// the book isn't postprocessed and images aren't read. After files change,
+/// `update()` only re-reads what is needed, eg. a changed song file
+/// or the songs directory listing when files are added or removed.
+#[derive(Debug)]
+pub struct WatchSet {
+    project_file: PathBuf,
+    dir_songs: PathBuf,
+    dir_output: PathBuf,
+    songs: SongsGlobs,
+    max_song_files: usize,
+    parser_config: ParserConfig,
+
+    templates: Vec<PathBuf>,
+    inputs: Vec<PathBuf>,
+    /// Images referenced by each of the `inputs`.
+    images: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl WatchSet {
+    /// Name of the file in the output directory recording the dependencies of the last build.
+    pub const RECORD: &'static str = ".bard-dependencies.json";
+
+    /// Discovers dependencies of the project in `cwd` or its parent directories.
+    pub fn new<P: AsRef<Path>>(cwd: P) -> Result<Self> {
+        let cwd = cwd.as_ref();
+        let (project_file, project_dir) = Project::find_in_parents(cwd).ok_or_else(|| {
+            anyhow!(
+                "Could not find bard.toml file in current or parent directories\nCurrent directory: {:?}",
+                cwd,
+            )
+        })?;
+
+        let settings = Settings::from_file(&project_file, &project_dir)?;
+        let mut this = Self::from_settings(project_file, &settings);
+        this.inputs = this.resolve_input_paths()?;
+        this.images = this
+            .inputs
+            .iter()
+            .map(|input| Ok((input.clone(), this.scan_images(input)?)))
+            .collect::<Result<_>>()?;
+        Ok(this)
+    }
+
+    /// A set without any inputs, these are added by `Project` as it loads songs, see `add_input()`.
+    pub(super) fn from_settings(project_file: PathBuf, settings: &Settings) -> Self {
+        Self {
+            project_file,
+            dir_songs: settings.dir_songs.clone(),
+            dir_output: settings.dir_output.clone(),
+            songs: settings.songs.clone(),
+            max_song_files: settings.max_song_files,
+            parser_config: settings.parser_config(),
+            templates: settings
+                .output
+                .iter()
+                .filter_map(Output::template_path)
+                .map(Path::to_owned)
+                .collect(),
+            inputs: vec![],
+            images: BTreeMap::new(),
+        }
+    }
+
+    pub(super) fn resolve_inputs(&self) -> Result<Vec<SongFile>> {
+        self.songs.resolve(&self.dir_songs, self.max_song_files)
+    }
+
+    fn resolve_input_paths(&self) -> Result<Vec<PathBuf>> {
+        let inputs = self.resolve_inputs()?;
+        Ok(inputs.into_iter().map(|input| input.path).collect())
+    }
+
+    pub(super) fn add_input(&mut self, input: &Path, songs: &[Song]) {
+        self.inputs.push(input.to_owned());
+        self.images
+            .insert(input.to_owned(), self.image_paths(songs));
+    }
+
+    /// Paths of images referenced in `songs`, sorted and deduplicated.
+    fn image_paths(&self, songs: &[Song]) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = songs
+            .iter()
+            .flat_map(Song::iter_images)
+            .map(|image| Path::new(&*image.path))
+            // Invalid paths are reported when the project is loaded
+            .filter(|path| path.is_relative())
+            .map(|path| self.dir_output.join(path))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Parses `input` to find referenced images, parser diagnostics are ignored.
+    fn scan_images(&self, input: &Path) -> Result<Vec<PathBuf>> {
+        let songs = parse_song_file(
+            input,
+            self.project_dir(),
+            self.parser_config.clone(),
+            |_: Diagnostic| {},
+        )?;
+        Ok(self.image_paths(&songs))
+    }
+
+    fn project_dir(&self) -> &Path {
+        // NB. Unwrap is fine here, the project file is always in the project directory
+        self.project_file.parent().unwrap()
+    }
+
+    pub fn project_file(&self) -> &Path {
+        &self.project_file
+    }
+
+    pub fn inputs(&self) -> &[PathBuf] {
+        &self.inputs
+    }
+
+    /// All the files the project depends on.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        let templates = self.templates.iter().map(PathBuf::as_ref);
+        let inputs = self.inputs.iter().map(PathBuf::as_ref);
+        let images = self.images.values().flatten().map(PathBuf::as_ref);
+
+        iter::once(self.project_file.as_path())
+            .chain(inputs)
+            .chain(templates)
+            .chain(images)
+    }
+
+    /// Directories in which added or removed files may change the set of dependencies,
+    /// ie. the songs directory when songs are specified using globs.
+    pub fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.songs
+            .has_globs()
+            .then_some(self.dir_songs.as_path())
+            .into_iter()
+    }
+
+    fn record_path(&self) -> PathBuf {
+        self.dir_output.join(Self::RECORD)
+    }
+
+    /// Records the current set of dependencies as that of a successful build, see `changed_since_build()`.
+    ///
+    /// Errors are ignored, without a record the next `changed_since_build()` is `true`.
+    pub fn record_build(&self) {
+        let paths: BTreeSet<&Path> = self.paths().collect();
+        let _ = fs::remove_file(self.record_path());
+        let _ = write_atomic(&self.record_path(), |file| {
+            serde_json::to_writer(&mut *file, &paths)?;
+            file.flush()?;
+            Ok(())
+        });
+    }
+
+    /// Returns `true` if the set of dependencies differs from the one recorded by `record_build()`,
+    /// eg. a song file matching a `songs` glob was added or removed, or if no build was recorded.
+    ///
+    /// This catches changes which file mtimes don't reflect, such as a song copied in with its original mtime.
+    pub fn changed_since_build(&self) -> bool {
+        let recorded: Option<BTreeSet<PathBuf>> = fs::read(self.record_path())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok());
+        let current: BTreeSet<PathBuf> = self.paths().map(Path::to_owned).collect();
+        recorded.as_ref() != Some(&current)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths().any(|p| p == path)
+    }
+
+    /// Updates the set after the files at `changed` were modified, added, or removed.
+    ///
+    /// `bard.toml` is reloaded if it changed, the songs directory is re-read
+    /// if a file was added to or removed from it, and changed song files are parsed for images.
+    pub fn update(&mut self, changed: &[PathBuf]) -> Result<WatchChange> {
+        let old_paths: BTreeSet<PathBuf> = self.paths().map(Path::to_owned).collect();
+
+        if changed.contains(&self.project_file) {
+            let settings = Settings::from_file(&self.project_file, self.project_dir())?;
+            *self = Self::from_settings(self.project_file.clone(), &settings);
+            self.update_inputs()?;
+        } else if changed
+            .iter()
+            .any(|path| path.starts_with(&self.dir_songs) && !self.images.contains_key(path))
+            || self.inputs.iter().any(|input| !input.exists())
+        {
+            self.update_inputs()?;
+        }
+
+        for path in changed.iter() {
+            if self.images.contains_key(path) {
+                let images = self.scan_images(path)?;
+                self.images.insert(path.clone(), images);
+            }
+        }
+
+        let new_paths: BTreeSet<PathBuf> = self.paths().map(Path::to_owned).collect();
+        let change = if old_paths != new_paths {
+            WatchChange::Paths
+        } else if changed.iter().any(|path| new_paths.contains(path)) {
+            WatchChange::Contents
+        } else {
+            WatchChange::None
+        };
+        Ok(change)
+    }
+
+    /// Re-resolves song files, new ones are parsed for images.
+    fn update_inputs(&mut self) -> Result<()> {
+        self.inputs = self.resolve_input_paths()?;
+        let mut images = BTreeMap::new();
+        for input in self.inputs.iter() {
+            let input_images = match self.images.remove(input) {
+                Some(input_images) => input_images,
+                None => self.scan_images(input)?,
+            };
+            images.insert(input.clone(), input_images);
+        }
+        self.images = images;
+        Ok(())
+    }
+}
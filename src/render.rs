@@ -1,28 +1,38 @@
 use std::borrow::Cow;
+use std::fs;
 
 use semver::Version;
 use serde::Serialize;
 
-use crate::app::App;
-use crate::book::{Song, SongRef};
+use crate::app::{App, SongRange};
+use crate::book::{self, ChordEntry, Credit, GlossEntry, Song, SongRef};
 use crate::music::Notation;
 use crate::prelude::*;
-use crate::project::{Format, Metadata, Output, Project};
+use crate::project::{AlternateChords, Format, Metadata, Output, Project, Split};
 use crate::util::ImgCache;
 use crate::{ProgramMeta, PROGRAM_META};
 
 #[macro_use]
 pub mod template;
+pub mod chordpro;
+pub mod epub;
 pub mod hovorka;
 pub mod html;
 pub mod json;
+pub mod licenses;
+pub mod merge;
 pub mod pdf;
+pub mod tex_log;
 pub mod tex_tools;
 pub mod xml;
 
+pub use self::chordpro::RChordPro;
+pub use self::epub::RPub;
 pub use self::hovorka::RHovorka;
 pub use self::html::RHtml;
 pub use self::json::RJson;
+pub use self::licenses::RLicenses;
+pub use self::merge::RMerge;
 pub use self::pdf::RPdf;
 use self::template::DefaultTemaplate;
 pub use self::xml::RXml;
@@ -31,31 +41,286 @@ pub static DEFAULT_TEMPLATES: &[&DefaultTemaplate] = &[
     &pdf::DEFAULT_TEMPLATE,
     &html::DEFAULT_TEMPLATE,
     &hovorka::DEFAULT_TEMPLATE,
+    &epub::DEFAULT_TEMPLATE,
 ];
 
 #[derive(Serialize, Debug)]
 pub struct RenderContext<'a> {
     book: Cow<'a, Metadata>,
-    songs: &'a [Song],
-    songs_sorted: &'a [SongRef],
+    songs: Cow<'a, [Song]>,
+    songs_sorted: Cow<'a, [SongRef]>,
+    /// Number of songs at the start of `songs` loaded from pinned files, see `Book::pinned`.
+    pinned: usize,
+    /// Glossary entries of all songs sorted by term, see `Book::glossary`.
+    glossary: &'a [GlossEntry],
+    /// Attribution of songs for a credits page, see `book::credits()`.
+    credits: Box<[Credit]>,
+    /// Chords used in the songs if `Output::chord_index` is set, see `book::chord_index()`.
+    chords: Box<[ChordEntry]>,
     notation: Notation,
     output: &'a Output,
     program: &'static ProgramMeta,
+    /// Set when only a subset of songs is rendered, see `MakeOpts::preview`.
+    partial: bool,
+    /// Number of the first page of songs, see `Output::page_start()`.
+    page_start: u32,
+    /// Variables defined on the command line, see `MakeOpts::define`.
+    vars: Metadata,
+    /// Set for outputs split into a file per song, see `Output::split`.
+    split: Option<SplitPage>,
+}
+
+/// Paths between the files of a split output, see `Split::PerSong`.
+#[derive(Serialize, Clone, Debug)]
+pub struct SplitPage {
+    /// Directory of the song files relative to the index file.
+    dir: String,
+    /// The index file relative to song files.
+    index: String,
+    /// Prefix of paths relative to the output directory, `"../"` in song files.
+    root: String,
+    /// Extension of the song files.
+    ext: String,
+    /// Set in the files of individual songs, unset in the index file.
+    song: bool,
 }
 
 impl<'a> RenderContext<'a> {
-    fn new(project: &'a Project, output: &'a Output) -> Self {
-        RenderContext {
+    fn new(
+        app: &App,
+        project: &'a Project,
+        output: &'a Output,
+        preview: Option<SongRange>,
+    ) -> Result<Self> {
+        let (mut songs, mut songs_sorted, pinned) = match preview {
+            Some(range) => {
+                let (songs, songs_sorted) = preview_songs(project, range)?;
+                let pinned = project
+                    .pinned()
+                    .saturating_sub(range.first - 1)
+                    .min(songs.len());
+                (Cow::Owned(songs), Cow::Owned(songs_sorted), pinned)
+            }
+            None => (
+                Cow::Borrowed(project.songs()),
+                Cow::Borrowed(project.songs_sorted()),
+                project.pinned(),
+            ),
+        };
+        let notation = output.notation.unwrap_or(project.settings.notation);
+        // Relative notations don't change with transposition and capo, so they're converted first
+        if output.notation.is_some() {
+            songs = Cow::Owned(
+                songs
+                    .iter()
+                    .map(|song| {
+                        song.with_notation(notation)
+                            .unwrap_or_else(|| {
+                                app.warning(format!(
+                                    "Song {:?} has no {{key: ...}} annotation, its chords are left as written in output {:?}\nHint: Chords are converted between the {} and {} notations relative to the key of the song, add an annotation such as {{key: G}} below its title.",
+                                    song.title, output.file, song.notation, notation
+                                ));
+                                song.clone()
+                            })
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(by) = output.transpose {
+            songs = Cow::Owned(songs.iter().map(|song| song.transposed(by)).collect());
+        }
+        if let Some(to_key) = output.to_key.as_deref() {
+            songs = Cow::Owned(
+                songs
+                    .iter()
+                    .map(|song| {
+                        song.transposed_to(to_key).unwrap_or_else(|| {
+                            app.warning(format!(
+                                "Song {:?} has no {{key: ...}} annotation, it is not transposed to {} in output {:?}\nHint: Add an annotation such as {{key: G}} below its title.",
+                                song.title, to_key, output.file
+                            ));
+                            song.clone()
+                        })
+                    })
+                    .collect(),
+            );
+        }
+        if output.capo.is_some() || songs.iter().any(|song| song.capo.is_some()) {
+            songs = Cow::Owned(
+                songs
+                    .iter()
+                    .map(|song| song.with_capo(output.capo, output.capo_chords))
+                    .collect(),
+            );
+        }
+        if output.alternate_chords != AlternateChords::Primary {
+            songs = Cow::Owned(
+                songs
+                    .iter()
+                    .map(|song| song.with_alternate_chords(output.alternate_chords))
+                    .collect(),
+            );
+        }
+        let unicode_accidentals = output
+            .unicode_accidentals
+            .unwrap_or(project.settings.unicode_accidentals);
+        if unicode_accidentals && !output.ascii_only {
+            songs = Cow::Owned(songs.iter().map(Song::with_unicode_accidentals).collect());
+        }
+        if output.chords_only {
+            songs = Cow::Owned(songs.iter().map(Song::chords_only).collect());
+        }
+        if output.ascii_only {
+            songs = Cow::Owned(songs.iter().map(Song::ascii_only).collect());
+            songs_sorted = Cow::Owned(songs_sorted.iter().map(SongRef::ascii_only).collect());
+        }
+        if output.qr_codes {
+            let dir_output = project.settings.dir_output();
+            songs = Cow::Owned(
+                songs
+                    .iter()
+                    .map(|song| song.with_qr(dir_output))
+                    .collect::<Result<_>>()?,
+            );
+        }
+
+        let credits = book::credits(&songs).into();
+        let chords = if output.chord_index {
+            book::chord_index(&songs).into()
+        } else {
+            Box::default()
+        };
+
+        Ok(RenderContext {
             book: output.override_book_section(project.book_section()),
-            songs: project.songs(),
-            songs_sorted: project.songs_sorted(),
-            notation: project.settings.notation,
+            songs,
+            songs_sorted,
+            pinned,
+            glossary: project.glossary(),
+            credits,
+            chords,
+            notation,
             output,
             program: &PROGRAM_META,
+            partial: preview.is_some(),
+            page_start: output.page_start(app, &project.settings.output)?,
+            vars: app
+                .vars()
+                .iter()
+                .map(|var| (var.key.as_str().into(), var.value.as_str().into()))
+                .collect(),
+            split: (output.split != Split::None).then(|| SplitPage::new(output, false)),
+        })
+    }
+
+    /// Adapts the AST for templates written for an older AST `version`
+    /// which don't know newer elements that replace existing ones.
+    fn downgrade(&mut self, version: &Version) {
+        // Templates older than 1.19 get chord lines as individual chords, see `Verse::group_chord_lines()`
+        if *version < Version::new(1, 19, 0) {
+            self.songs = Cow::Owned(
+                self.songs
+                    .iter()
+                    .map(Song::without_chord_sequences)
+                    .collect(),
+            );
+        }
+        // Templates older than 1.28 get LilyPond snippets as preformatted text
+        if *version < Version::new(1, 28, 0) {
+            self.songs = Cow::Owned(self.songs.iter().map(Song::without_lilypond).collect());
+        }
+    }
+
+    /// Context of the file of the song at `idx` in a split output, only containing that song.
+    fn song_page(&self, project: &'a Project, idx: usize) -> Self {
+        Self {
+            split: Some(SplitPage::new(self.output, true)),
+            ..self.single_song(idx, &project.songs()[idx].glossary)
+        }
+    }
+
+    /// Context only containing the song at `idx` with its `glossary`.
+    fn single_song(&self, idx: usize, glossary: &'a [GlossEntry]) -> Self {
+        let song = self.songs[idx].clone();
+        let songs = vec![song];
+        let songs_sorted = vec![SongRef {
+            title: songs[0].title.clone(),
+            idx: 0,
+        }];
+        let chords = if self.output.chord_index {
+            book::chord_index(&songs).into()
+        } else {
+            Box::default()
+        };
+
+        RenderContext {
+            book: self.book.clone(),
+            credits: book::credits(&songs).into(),
+            chords,
+            songs: Cow::Owned(songs),
+            songs_sorted: Cow::Owned(songs_sorted),
+            pinned: 0,
+            glossary,
+            notation: self.notation,
+            output: self.output,
+            program: self.program,
+            partial: false,
+            page_start: self.page_start,
+            vars: self.vars.clone(),
+            split: self.split.clone(),
+        }
+    }
+}
+
+impl SplitPage {
+    fn new(output: &Output, song: bool) -> Self {
+        let name = |path: &Path| {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        };
+        Self {
+            dir: name(&output.split_dir()),
+            index: format!("../{}", name(&output.file)),
+            root: if song { "../" } else { "" }.into(),
+            ext: output
+                .file
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+            song,
         }
     }
 }
 
+/// Returns the songs in `range` and the sorted references to them.
+fn preview_songs(project: &Project, range: SongRange) -> Result<(Vec<Song>, Vec<SongRef>)> {
+    let songs = project.songs();
+    if range.first > songs.len() {
+        bail!(
+            "Preview range {} is out of bounds, the project only has {} songs.",
+            range,
+            songs.len()
+        );
+    }
+
+    let start = range.first - 1;
+    let end = range.last.min(songs.len());
+    let songs_sorted = project
+        .songs_sorted()
+        .iter()
+        .filter(|song_ref| (start..end).contains(&song_ref.idx))
+        .map(|song_ref| SongRef {
+            title: song_ref.title.clone(),
+            idx: song_ref.idx - start,
+        })
+        .collect();
+
+    Ok((songs[start..end].to_vec(), songs_sorted))
+}
+
 trait Render {
     /// Render the output file based on `project` and `output`.
     fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()>;
@@ -78,8 +343,12 @@ impl<'a> Renderer<'a> {
             Format::Pdf => Box::new(RPdf::new(project, output, img_cache)?),
             Format::Html => Box::new(RHtml::new(project, output, img_cache)?),
             Format::Hovorka => Box::new(RHovorka::new(project, output, img_cache)?),
+            Format::Epub => Box::new(RPub::new(project, output, img_cache)?),
             Format::Json => Box::new(RJson::new()),
             Format::Xml => Box::new(RXml::new()),
+            Format::Merge => Box::new(RMerge::new(output)),
+            Format::Licenses => Box::new(RLicenses::new()),
+            Format::ChordPro => Box::new(RChordPro::new()),
         };
 
         Ok(Self {
@@ -94,7 +363,48 @@ impl<'a> Renderer<'a> {
     }
 
     pub fn render(&self, app: &App) -> Result<()> {
-        let context = RenderContext::new(self.project, self.output);
+        let preview = app.preview().filter(|_| self.output.is_pdf());
+        let mut context = RenderContext::new(app, self.project, self.output, preview)?;
+        if let Some(version) = self.version() {
+            context.downgrade(&version);
+        }
+        if self.output.qr_codes {
+            for song in context.songs.iter() {
+                app.check_interrupted()?;
+                song.write_qr()?;
+            }
+        }
+        match preview {
+            Some(range) => {
+                let file = self.output.file.with_extension("preview.pdf");
+                app.indent(format!(
+                    "Preview of songs {} in {:?}",
+                    range,
+                    file.file_name().unwrap()
+                ));
+                self.render.render(app, &file, context)
+            }
+            None if self.output.split != Split::None => self.render_split(app, context),
+            None => self.render.render(app, &self.output.file, context),
+        }
+    }
+
+    /// Renders each song into a file named by its slug in `Output::split_dir()`
+    /// and then the index into the output file.
+    fn render_split(&self, app: &App, context: RenderContext) -> Result<()> {
+        let dir = self.output.split_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create output directory {:?}", dir))?;
+
+        let ext = self.output.file.extension().unwrap_or_default();
+        for (idx, song) in context.songs.iter().enumerate() {
+            app.check_interrupted()?;
+            let mut file = dir.join(&*song.slug);
+            file.set_extension(ext);
+            self.render
+                .render(app, &file, context.song_page(self.project, idx))?;
+        }
+
         self.render.render(app, &self.output.file, context)
     }
 }
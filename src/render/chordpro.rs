@@ -0,0 +1,183 @@
+//! ChordPro renderer.
+//!
+//! Writes the songs as ChordPro text for other songbook tools, see `parser::chordpro` for the import.
+//! Verses and choruses are written as `{start_of_verse}` and `{start_of_chorus}` blocks,
+//! chords inline as `[C]` in the output's notation. Songs are separated by `{new_song}`.
+
+use std::fmt::Write as _;
+use std::io::Write;
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::{Block, Inline, Song, Verse, VerseLabel};
+use crate::prelude::*;
+use crate::util::write_atomic;
+
+#[derive(Debug, Default)]
+pub struct RChordPro;
+
+impl RChordPro {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+struct Writer<'a> {
+    text: String,
+    chorus_label: &'a str,
+}
+
+impl<'a> Writer<'a> {
+    fn directive(&mut self, name: &str, value: &str) {
+        writeln!(self.text, "{{{}: {}}}", name, value.replace(['{', '}'], "")).unwrap();
+    }
+
+    fn song(&mut self, song: &Song) {
+        self.directive("title", &song.title);
+        for subtitle in song.subtitles.iter() {
+            self.directive("subtitle", subtitle);
+        }
+        let attribution = &song.attribution;
+        for author in attribution.authors.iter() {
+            self.directive("artist", author);
+        }
+        for composer in attribution.composers.iter() {
+            self.directive("composer", composer);
+        }
+        for copyright in attribution.copyright.iter() {
+            self.directive("copyright", copyright);
+        }
+        if let Some(key) = song.key.as_ref() {
+            self.directive("key", key);
+        }
+        if let Some(capo) = song.capo {
+            self.directive("capo", &capo.to_string());
+        }
+
+        for block in song.blocks.iter() {
+            self.text.push('\n');
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        match block {
+            Block::Verse(verse) => self.verse(verse),
+            Block::BulletList(list) => {
+                for item in list.items.iter() {
+                    writeln!(self.text, "- {}", item).unwrap();
+                }
+            }
+            Block::HorizontalLine => {}
+            Block::Pre { text } => {
+                self.text.push_str("{start_of_tab}\n");
+                self.text.push_str(text.trim_end());
+                self.text.push_str("\n{end_of_tab}\n");
+            }
+            Block::HtmlBlock(inlines) => {
+                self.inlines(&inlines.inlines);
+                self.text.push('\n');
+            }
+            Block::Lilypond(lilypond) => {
+                self.text.push_str("{start_of_ly}\n");
+                self.text.push_str(lilypond.source.trim_end());
+                self.text.push_str("\n{end_of_ly}\n");
+            }
+        }
+    }
+
+    fn verse(&mut self, verse: &Verse) {
+        let (start, end) = match &verse.label {
+            VerseLabel::Chorus(..) | VerseLabel::NamedChorus { .. } => {
+                ("start_of_chorus", "end_of_chorus")
+            }
+            VerseLabel::None {} => ("", ""),
+            _ => ("start_of_verse", "end_of_verse"),
+        };
+        match &verse.label {
+            VerseLabel::Custom(label) | VerseLabel::NamedChorus { name: label, .. } => {
+                self.directive(start, label)
+            }
+            _ if !start.is_empty() => writeln!(self.text, "{{{}}}", start).unwrap(),
+            _ => {}
+        }
+
+        for (i, paragraph) in verse.paragraphs.iter().enumerate() {
+            if i > 0 {
+                self.text.push('\n');
+            }
+            self.inlines(paragraph);
+            self.text.push('\n');
+        }
+
+        if !end.is_empty() {
+            writeln!(self.text, "{{{}}}", end).unwrap();
+        }
+        if let Some(repeat) = verse.repeat {
+            self.directive("comment", &format!("x{}", repeat));
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            match inline {
+                Inline::Text { text } => self.text.push_str(text),
+                Inline::Chord(chord) => {
+                    write!(self.text, "[{}]", chord.chord).unwrap();
+                    self.inlines(&chord.inlines);
+                }
+                Inline::ChordSequence(seq) => {
+                    let chords: Vec<_> = seq
+                        .chords
+                        .iter()
+                        .map(|chord| format!("[{}]", chord.chord))
+                        .collect();
+                    self.text.push_str(&chords.join(" "));
+                }
+                Inline::Break => self.text.push('\n'),
+                Inline::Emph(inlines) | Inline::Strong(inlines) => self.inlines(&inlines.inlines),
+                Inline::Link(link) => self.text.push_str(&link.text),
+                Inline::ChorusRef(chorus_ref) => {
+                    self.text.push_str(&chorus_ref.prefix_space);
+                    if let Some(name) = chorus_ref.name.as_ref() {
+                        self.text.push_str(name);
+                    } else {
+                        self.text.push_str(self.chorus_label);
+                        if let Some(num) = chorus_ref.num {
+                            write!(self.text, "{}", num).unwrap();
+                        }
+                    }
+                }
+                Inline::Nav { marker } => self.text.push_str(marker.ascii_text()),
+                Inline::Image(..) | Inline::HtmlTag(..) | Inline::Transpose(..) => {}
+            }
+        }
+    }
+}
+
+impl Render for RChordPro {
+    fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let chorus_label = context
+            .book
+            .get("chorus_label")
+            .and_then(|label| label.as_str())
+            .unwrap_or("");
+        let mut writer = Writer {
+            text: String::new(),
+            chorus_label,
+        };
+
+        for (i, song) in context.songs.iter().enumerate() {
+            if i > 0 {
+                writer.text.push_str("\n{new_song}\n");
+            }
+            writer.song(song);
+        }
+
+        write_atomic(output, |file| {
+            file.write_all(writer.text.as_bytes())?;
+            Ok(())
+        })
+        .with_context(|| format!("Error writing output file: {:?}", output))
+    }
+}
@@ -0,0 +1,439 @@
+//! EPUB renderer.
+//!
+//! The title page and each song are rendered with the `epub.hbs` template into XHTML pages,
+//! which are packaged together with the `content.opf` package document, the `nav.xhtml` and `toc.ncx`
+//! tables of contents, and the files referenced by the pages, such as fonts and images, into a zip file.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::Component;
+
+use once_cell::sync::Lazy;
+use quick_xml::escape::escape;
+use regex::Regex;
+use semver::Version;
+
+use super::template::{DpiHelper, HbRender};
+use super::{Render, RenderContext, SplitPage};
+use crate::app::App;
+use crate::prelude::*;
+use crate::project::{build_date, Output, Project};
+use crate::util::archive::ZipWriter;
+use crate::util::{write_atomic, ImgCache};
+
+default_template!(DEFAULT_TEMPLATE, "epub.hbs");
+
+/// Local files referenced by the pages.
+static PAGE_REF: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<(?:link|img)\s[^>]*?(?:href|src)="([^"]+)""#).unwrap());
+/// Files referenced by stylesheets, such as fonts.
+static CSS_URL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"url\(\s*['"]?([^'")]+?)['"]?\s*\)"#).unwrap());
+
+const TITLE_PAGE: &str = "title.xhtml";
+
+pub struct RPub {
+    hb: HbRender,
+    /// Referenced files are resolved relative to the output directory, like with HTML outputs.
+    out_dir: PathBuf,
+}
+
+impl RPub {
+    pub fn new(project: &Project, output: &Output, img_cache: &ImgCache) -> Result<Self> {
+        let mut hb = HbRender::new(project, output, &DEFAULT_TEMPLATE, img_cache)?;
+        hb.hb
+            .register_helper("scale", DpiHelper::new(output, "scale"));
+
+        Ok(Self {
+            hb,
+            out_dir: project.settings.dir_output().to_owned(),
+        })
+    }
+
+    fn page_split(song: bool) -> SplitPage {
+        SplitPage {
+            dir: String::new(),
+            index: TITLE_PAGE.into(),
+            root: String::new(),
+            ext: "xhtml".into(),
+            song,
+        }
+    }
+}
+
+/// A file in the EPUB package.
+struct Item {
+    id: String,
+    href: String,
+    content: Vec<u8>,
+}
+
+impl Item {
+    fn media_type(&self) -> &'static str {
+        let ext = self.href.rsplit_once('.').map_or("", |(_, ext)| ext);
+        match ext.to_ascii_lowercase().as_str() {
+            "xhtml" => "application/xhtml+xml",
+            "ncx" => "application/x-dtbncx+xml",
+            "css" => "text/css",
+            "ttf" => "font/ttf",
+            "otf" => "font/otf",
+            "woff" => "font/woff",
+            "woff2" => "font/woff2",
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+/// Collects the pages and the files they reference.
+struct Package<'a> {
+    app: &'a App,
+    out_dir: &'a Path,
+    items: Vec<Item>,
+    resources: BTreeSet<String>,
+}
+
+impl<'a> Package<'a> {
+    fn page(&mut self, id: String, href: String, content: String) -> Result<()> {
+        for reference in PAGE_REF.captures_iter(&content) {
+            self.resource(&reference[1])?;
+        }
+        self.items.push(Item {
+            id,
+            href,
+            content: content.into_bytes(),
+        });
+        Ok(())
+    }
+
+    /// Adds a file referenced as `href`, relative to the output directory, unless already added.
+    fn resource(&mut self, href: &str) -> Result<()> {
+        let href = href.split('#').next().unwrap_or_default();
+        let is_local = !(href.is_empty() || href.contains(':') || href.ends_with(".xhtml"));
+        if !is_local || self.resources.contains(href) {
+            return Ok(());
+        }
+
+        let normal = Path::new(href)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        let path = self.out_dir.join(href);
+        if !normal || !path.is_file() {
+            self.app.warning(format!(
+                "File {:?} referenced in the EPUB output not found in the output directory, it is left out.",
+                href
+            ));
+            return Ok(());
+        }
+
+        let content = fs::read(&path).with_context(|| format!("Could not read file {:?}", path))?;
+        self.resources.insert(href.to_owned());
+        if href.to_ascii_lowercase().ends_with(".css") {
+            let css = String::from_utf8_lossy(&content);
+            let dir = href.rsplit_once('/').map_or("", |(dir, _)| dir);
+            for url in CSS_URL.captures_iter(&css) {
+                let url = &url[1];
+                if dir.is_empty() || url.contains(':') {
+                    self.resource(url)?;
+                } else {
+                    self.resource(&format!("{}/{}", dir, url))?;
+                }
+            }
+        }
+
+        self.items.push(Item {
+            id: format!("res-{}", self.resources.len()),
+            href: href.to_owned(),
+            content,
+        });
+        Ok(())
+    }
+}
+
+/// A table of contents entry, songs are grouped under sections, if any.
+struct TocEntry {
+    title: String,
+    href: Option<String>,
+    children: Vec<TocEntry>,
+}
+
+fn toc(context: &RenderContext, hrefs: &[String]) -> Vec<TocEntry> {
+    let entry = |title: &str, idx: usize| TocEntry {
+        title: title.to_owned(),
+        href: Some(hrefs[idx].clone()),
+        children: vec![],
+    };
+
+    if context.output.toc_sort {
+        return context
+            .songs_sorted
+            .iter()
+            .map(|song_ref| entry(&song_ref.title, song_ref.idx))
+            .collect();
+    }
+
+    let mut toc: Vec<TocEntry> = vec![];
+    let mut in_section = false;
+    for (idx, song) in context.songs.iter().enumerate() {
+        if let Some(section) = song.section.as_ref() {
+            toc.push(TocEntry {
+                title: section.to_string(),
+                href: None,
+                children: vec![],
+            });
+            in_section = true;
+        }
+        if !song.index {
+            continue;
+        }
+        match toc.last_mut() {
+            Some(section) if in_section => section.children.push(entry(&song.title, idx)),
+            _ => toc.push(entry(&song.title, idx)),
+        }
+    }
+    // Sections with no songs in the ToC
+    toc.retain(|entry| entry.href.is_some() || !entry.children.is_empty());
+    toc
+}
+
+fn nav_xhtml(title: &str, toc: &[TocEntry]) -> String {
+    fn list(res: &mut String, entries: &[TocEntry], indent: usize) {
+        let pad = " ".repeat(indent);
+        res.push_str(&format!("{}<ol>\n", pad));
+        for entry in entries {
+            let title = escape(&entry.title);
+            match entry.href.as_ref() {
+                Some(href) => res.push_str(&format!(
+                    "{}  <li><a href=\"{}\">{}</a>",
+                    pad,
+                    escape(href),
+                    title
+                )),
+                None => res.push_str(&format!("{}  <li><span>{}</span>", pad, title)),
+            }
+            if !entry.children.is_empty() {
+                res.push('\n');
+                list(res, &entry.children, indent + 4);
+                res.push_str(&pad);
+                res.push_str("  ");
+            }
+            res.push_str("</li>\n");
+        }
+        res.push_str(&format!("{}</ol>\n", pad));
+    }
+
+    let mut res = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <meta charset="UTF-8"/>
+  <title>{}</title>
+</head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <h2>Contents</h2>
+"#,
+        escape(title)
+    );
+    list(&mut res, toc, 4);
+    res.push_str("  </nav>\n</body>\n</html>\n");
+    res
+}
+
+fn toc_ncx(uid: &str, title: &str, toc: &[TocEntry]) -> String {
+    let mut points = String::new();
+    let songs = toc.iter().flat_map(|entry| {
+        entry
+            .href
+            .as_ref()
+            .map(|_| entry)
+            .into_iter()
+            .chain(&entry.children)
+    });
+    for (i, song) in songs.enumerate() {
+        points.push_str(&format!(
+            r#"    <navPoint id="nav-{0}" playOrder="{0}">
+      <navLabel><text>{1}</text></navLabel>
+      <content src="{2}"/>
+    </navPoint>
+"#,
+            i + 1,
+            escape(&song.title),
+            escape(song.href.as_deref().unwrap_or_default())
+        ));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <head>
+    <meta name="dtb:uid" content="{}"/>
+  </head>
+  <docTitle><text>{}</text></docTitle>
+  <navMap>
+{}  </navMap>
+</ncx>
+"#,
+        escape(uid),
+        escape(title),
+        points
+    )
+}
+
+/// Metadata of the `content.opf` package document, escaped.
+struct OpfMetadata {
+    uid: String,
+    title: String,
+    language: String,
+}
+
+impl OpfMetadata {
+    fn new(uid: &str, context: &RenderContext) -> Self {
+        let book = |key: &str| {
+            context
+                .book
+                .get(key)
+                .and_then(|value| value.as_str())
+                .map(|value| escape(value).into_owned())
+        };
+        Self {
+            uid: escape(uid).into_owned(),
+            title: book("title").unwrap_or_default(),
+            language: book("locale").unwrap_or_else(|| "en".into()),
+        }
+    }
+
+    fn content_opf(&self, items: &[Item], spine: &[String]) -> String {
+        let mut manifest = String::new();
+        for item in items {
+            let properties = if item.id == "nav" {
+                r#" properties="nav""#
+            } else {
+                ""
+            };
+            manifest.push_str(&format!(
+                "    <item id=\"{}\" href=\"{}\" media-type=\"{}\"{}/>\n",
+                item.id,
+                escape(&item.href),
+                item.media_type(),
+                properties
+            ));
+        }
+        let spine: String = spine
+            .iter()
+            .map(|id| format!("    <itemref idref=\"{}\"/>\n", id))
+            .collect();
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">{}</dc:identifier>
+    <dc:title>{}</dc:title>
+    <dc:language>{}</dc:language>
+    <meta property="dcterms:modified">{}T00:00:00Z</meta>
+  </metadata>
+  <manifest>
+{}  </manifest>
+  <spine toc="ncx">
+{}  </spine>
+</package>
+"#,
+            self.uid,
+            self.title,
+            self.language,
+            build_date(),
+            manifest,
+            spine
+        )
+    }
+}
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+impl Render for RPub {
+    fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let title = context
+            .book
+            .get("title")
+            .and_then(|title| title.as_str())
+            .unwrap_or_default()
+            .to_owned();
+        let uid = format!("urn:bard:{}", crate::book::slug(&title));
+        let hrefs: Vec<_> = context
+            .songs
+            .iter()
+            .map(|song| format!("{}.xhtml", song.slug))
+            .collect();
+        let toc = toc(&context, &hrefs);
+        let mut spine = vec!["title".to_string(), "nav".to_string()];
+
+        let mut package = Package {
+            app,
+            out_dir: &self.out_dir,
+            items: vec![],
+            resources: BTreeSet::new(),
+        };
+        for (idx, href) in hrefs.iter().enumerate() {
+            app.check_interrupted()?;
+            let page = RenderContext {
+                split: Some(Self::page_split(true)),
+                ..context.single_song(idx, &[])
+            };
+            let id = format!("song-{}", idx);
+            package.page(id.clone(), href.clone(), self.hb.render_str(page)?)?;
+            spine.push(id);
+        }
+        let opf_metadata = OpfMetadata::new(&uid, &context);
+        let title_page = RenderContext {
+            split: Some(Self::page_split(false)),
+            ..context
+        };
+        package.page(
+            "title".into(),
+            TITLE_PAGE.into(),
+            self.hb.render_str(title_page)?,
+        )?;
+
+        let mut items = package.items;
+        items.push(Item {
+            id: "nav".into(),
+            href: "nav.xhtml".into(),
+            content: nav_xhtml(&title, &toc).into_bytes(),
+        });
+        items.push(Item {
+            id: "ncx".into(),
+            href: "toc.ncx".into(),
+            content: toc_ncx(&uid, &title, &toc).into_bytes(),
+        });
+        let opf = opf_metadata.content_opf(&items, &spine);
+
+        let mut zip = ZipWriter::new();
+        // The mimetype has to come first and uncompressed
+        zip.add("mimetype", b"application/epub+zip", false)?;
+        zip.add("META-INF/container.xml", CONTAINER_XML.as_bytes(), true)?;
+        zip.add("content.opf", opf.as_bytes(), true)?;
+        for item in items.iter() {
+            zip.add(&item.href, &item.content, true)?;
+        }
+
+        let zip = zip.finish();
+        write_atomic(output, |file| Ok(file.write_all(&zip)?))
+            .with_context(|| format!("Error writing output file: {:?}", output))
+    }
+
+    fn version(&self) -> Option<Version> {
+        self.hb.version()
+    }
+}
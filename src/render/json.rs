@@ -1,8 +1,7 @@
-use std::fs::File;
-
 use super::{Render, RenderContext};
 use crate::app::App;
 use crate::prelude::*;
+use crate::util::write_atomic;
 
 #[derive(Debug, Default)]
 pub struct RJson;
@@ -15,9 +14,9 @@ impl RJson {
 
 impl Render for RJson {
     fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
-        File::create(output)
-            .map_err(Error::from)
-            .and_then(|mut f| serde_json::to_writer_pretty(&mut f, &context).map_err(Error::from))
-            .with_context(|| format!("Error writing output file: {:?}", output))
+        write_atomic(output, |file| {
+            Ok(serde_json::to_writer_pretty(file, &context)?)
+        })
+        .with_context(|| format!("Error writing output file: {:?}", output))
     }
 }
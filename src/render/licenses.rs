@@ -0,0 +1,134 @@
+//! License report renderer.
+//!
+//! Lists songs with their copyright, CCLI number, and license for usage reporting,
+//! eg. to CCLI. The report is written as CSV if the output file has the `.csv` extension,
+//! otherwise as a plain text table.
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::iter;
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::Song;
+use crate::prelude::*;
+use crate::util::write_atomic;
+
+const HEADER: [&str; 6] = ["No.", "Title", "Copyright", "CCLI", "License", "Status"];
+const MISSING_CCLI: &str = "MISSING CCLI";
+
+#[derive(Debug, Default)]
+pub struct RLicenses;
+
+impl RLicenses {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Report columns of a song, see `HEADER`.
+fn row(number: usize, song: &Song) -> [String; 6] {
+    let attribution = &song.attribution;
+    let copyright = attribution
+        .copyright
+        .iter()
+        .map(|line| &**line)
+        .collect::<Vec<_>>()
+        .join("; ");
+    let status = if attribution.ccli.is_some() {
+        "OK"
+    } else {
+        MISSING_CCLI
+    };
+
+    [
+        number.to_string(),
+        song.title.to_string(),
+        copyright,
+        attribution.ccli.as_deref().unwrap_or("").to_string(),
+        attribution.license.as_deref().unwrap_or("").to_string(),
+        status.to_string(),
+    ]
+}
+
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+fn write_csv(file: &mut impl Write, rows: &[[String; 6]]) -> Result<()> {
+    let header = HEADER.map(String::from);
+    for row in iter::once(&header).chain(rows) {
+        let line: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+        writeln!(file, "{}", line.join(","))?;
+    }
+    Ok(())
+}
+
+fn write_table(file: &mut impl Write, rows: &[[String; 6]]) -> Result<()> {
+    let header = HEADER.map(String::from);
+    let mut widths = [0; 6];
+    for row in iter::once(&header).chain(rows) {
+        for (width, field) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let write_row = |file: &mut dyn Write, row: &[String; 6]| -> Result<()> {
+        let fields: Vec<_> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("{:<width$}", field, width = width))
+            .collect();
+        writeln!(file, "{}", fields.join(" | ").trim_end())?;
+        Ok(())
+    };
+
+    write_row(file, &header)?;
+    let rule: Vec<_> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    writeln!(file, "{}", rule.join("-+-"))?;
+    for row in rows {
+        write_row(file, row)?;
+    }
+    Ok(())
+}
+
+impl Render for RLicenses {
+    fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        // Songs left out of the index, such as instructions, are not reported
+        let rows: Vec<_> = context
+            .songs
+            .iter()
+            .enumerate()
+            .filter(|(_, song)| song.index)
+            .map(|(idx, song)| row(idx + 1, song))
+            .collect();
+
+        let csv = output
+            .extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("csv"));
+        write_atomic(output, |file| {
+            if csv {
+                write_csv(file, &rows)
+            } else {
+                write_table(file, &rows)
+            }
+        })
+        .with_context(|| format!("Error writing output file: {:?}", output))?;
+
+        let missing = rows.iter().filter(|row| row[5] == MISSING_CCLI).count();
+        if missing > 0 {
+            app.warning(format!(
+                "{} of {} songs have no CCLI number, they are flagged in {:?}.",
+                missing,
+                rows.len(),
+                output.file_name().unwrap_or_default(),
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! PDF merge renderer, concatenates PDFs of other outputs into one file.
+
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::prelude::*;
+use crate::project::Output;
+use crate::util::write_atomic;
+
+/// Page attributes which may be inherited from the page tree.
+/// These are copied onto pages as the source page trees are discarded.
+const INHERITABLE_ATTRS: &[&[u8]] = &[b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+#[derive(Debug)]
+pub struct RMerge {
+    inputs: Vec<PathBuf>,
+}
+
+impl RMerge {
+    pub fn new(output: &Output) -> Self {
+        Self {
+            inputs: output.merge.clone(),
+        }
+    }
+}
+
+impl Render for RMerge {
+    fn render(&self, app: &App, output: &Path, _context: RenderContext) -> Result<()> {
+        if !app.post_process() {
+            // The PDFs to merge are not generated without post-processing.
+            return Ok(());
+        }
+
+        merge_pdfs(&self.inputs, output)
+    }
+}
+
+fn inherited_attr(doc: &Document, page: &Dictionary, attr: &[u8]) -> Option<Object> {
+    let mut node = page;
+    loop {
+        let parent = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+        if let Ok(value) = node.get(attr) {
+            return Some(value.clone());
+        }
+    }
+}
+
+/// Returns the number of pages of the PDF file at `path`.
+pub fn pdf_page_count(path: &Path) -> Result<u32> {
+    let doc =
+        Document::load(path).with_context(|| format!("Could not read PDF file {:?}", path))?;
+    Ok(doc.get_pages().len() as u32)
+}
+
+/// Concatenate pages of the `inputs` PDF files into a new PDF file at `output`.
+///
+/// Document-level structures such as outlines and named destinations are not carried over.
+pub fn merge_pdfs(inputs: &[impl AsRef<Path>], output: &Path) -> Result<()> {
+    let mut merged = Document::with_version("1.5");
+    let mut pages: Vec<(ObjectId, Dictionary)> = vec![];
+
+    for input in inputs.iter().map(AsRef::as_ref) {
+        let mut doc = Document::load(input)
+            .with_context(|| format!("Could not read PDF file {:?}", input))?;
+        doc.renumber_objects_with(merged.max_id + 1);
+        merged.max_id = doc.max_id;
+
+        for page_id in doc.page_iter() {
+            let mut page = doc
+                .get_dictionary(page_id)
+                .with_context(|| format!("Invalid page object in PDF file {:?}", input))?
+                .clone();
+            for &attr in INHERITABLE_ATTRS {
+                if !page.has(attr) {
+                    if let Some(value) = inherited_attr(&doc, &page, attr) {
+                        page.set(attr, value);
+                    }
+                }
+            }
+            pages.push((page_id, page));
+        }
+
+        for (id, object) in doc.objects.into_iter() {
+            match object.type_name().unwrap_or("") {
+                "Catalog" | "Pages" | "Page" | "Outlines" | "Outline" => {}
+                _ => {
+                    merged.objects.insert(id, object);
+                }
+            }
+        }
+    }
+
+    let pages_id = merged.new_object_id();
+    let kids: Vec<Object> = pages.iter().map(|(id, _)| (*id).into()).collect();
+    let count = kids.len() as u32;
+    for (id, mut page) in pages.into_iter() {
+        page.set("Parent", pages_id);
+        merged.objects.insert(id, page.into());
+    }
+    merged.objects.insert(
+        pages_id,
+        dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }
+        .into(),
+    );
+
+    let catalog_id = merged.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    merged.trailer.set("Root", catalog_id);
+
+    write_atomic(output, |file| Ok(merged.save_to(file)?))
+        .with_context(|| format!("Error writing output file: {:?}", output))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use lopdf::content::{Content, Operation};
+    use lopdf::Stream;
+
+    use super::*;
+
+    fn make_pdf(path: &Path, num_pages: u32) {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..num_pages)
+            .map(|i| {
+                let content = Content {
+                    operations: vec![Operation::new(
+                        "Tj",
+                        vec![Object::string_literal(format!("{}", i))],
+                    )],
+                };
+                let content_id =
+                    doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => num_pages,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }
+            .into(),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.save(path).unwrap();
+    }
+
+    #[test]
+    fn merge_pdfs_concatenates_pages() {
+        let dir = env::temp_dir().join(format!("bard-merge-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, merged) = (dir.join("a.pdf"), dir.join("b.pdf"), dir.join("merged.pdf"));
+        make_pdf(&a, 2);
+        make_pdf(&b, 3);
+
+        merge_pdfs(&[&a, &b], &merged).unwrap();
+        assert_eq!(pdf_page_count(&a).unwrap(), 2);
+        assert_eq!(pdf_page_count(&merged).unwrap(), 5);
+
+        let doc = Document::load(&merged).unwrap();
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 5);
+
+        // Page order is preserved and the inherited MediaBox is carried over
+        let texts: Vec<_> = pages
+            .values()
+            .map(|&id| {
+                let page = doc.get_dictionary(id).unwrap();
+                assert!(page.has(b"MediaBox"));
+                let content = Content::decode(&doc.get_page_content(id).unwrap()).unwrap();
+                content.operations[0].operands[0].as_str().unwrap().to_vec()
+            })
+            .collect();
+        assert_eq!(texts, [b"0", b"1", b"0", b"1", b"2"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
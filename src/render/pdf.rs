@@ -1,4 +1,9 @@
+use std::io::Write as _;
+use std::time::Duration;
+
 use handlebars::handlebars_helper;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use semver::Version;
 
 use super::template::{DpiHelper, HbRender};
@@ -6,9 +11,10 @@ use super::tex_tools::TexTools;
 use super::{Render, RenderContext};
 use crate::app::App;
 use crate::prelude::*;
-use crate::project::{Output, Project};
+use crate::project::{book_collator, Output, Project, TexRuns};
 use crate::render::tex_tools::TexRenderJob;
-use crate::util::ImgCache;
+use crate::util::collation::Collator;
+use crate::util::{write_atomic, ImgCache};
 
 default_template!(DEFAULT_TEMPLATE, "pdf.hbs");
 
@@ -41,10 +47,117 @@ handlebars_helper!(hb_pre: |input: str| {
     latex_escape(input, true)
 });
 
+static TEX_ENV: Lazy<Regex> = Lazy::new(|| Regex::new(r"\\(begin|end)\{([^}]*)\}").unwrap());
+
+/// Environments whose content is left as is by `normalize_tex()`.
+const TEX_VERBATIM_ENVS: &[&str] = &["verbatim", "verbatim*", "Verbatim", "lstlisting", "comment"];
+
+/// Returns the part of a TeX source line before a comment, if any.
+fn tex_strip_comment(line: &str) -> &str {
+    let mut escape = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '%' if !escape => return &line[..i],
+            '\\' => escape = !escape,
+            _ => escape = false,
+        }
+    }
+    line
+}
+
+/// Cosmetic normalization of generated TeX source, used with `Output::tex_normalize`.
+///
+/// Trailing whitespace is removed, runs of blank lines are collapsed into one,
+/// and lines are indented by two spaces per level of environment nesting.
+/// This doesn't change the meaning of the source, since TeX skips spaces
+/// at the beginning and end of lines and several blank lines are the same as one.
+/// Verbatim environments are left as they are.
+fn normalize_tex(source: &str) -> String {
+    let is_space = |c| c == ' ' || c == '\t';
+
+    let mut res = String::with_capacity(source.len());
+    let mut depth = 0usize;
+    let mut verbatim: Option<String> = None;
+    let mut blank = true;
+    for line in source.lines() {
+        if let Some(env) = verbatim.as_deref() {
+            res.push_str(line);
+            res.push('\n');
+            if line.contains(&format!("\\end{{{}}}", env)) {
+                verbatim = None;
+                depth = depth.saturating_sub(1);
+            }
+            continue;
+        }
+
+        let line = line.trim_matches(is_space);
+        if line.is_empty() {
+            if !blank {
+                res.push('\n');
+                blank = true;
+            }
+            continue;
+        }
+        blank = false;
+
+        // Environments ended at the start of the line are already dedented
+        let code = tex_strip_comment(line);
+        let mut leading_ends = 0;
+        let mut rest = code;
+        while let Some(tail) = rest.strip_prefix("\\end{") {
+            leading_ends += 1;
+            rest = tail.split_once('}').map_or("", |(_, tail)| tail);
+            rest = rest.trim_start_matches(is_space);
+        }
+
+        for _ in 0..depth.saturating_sub(leading_ends) {
+            res.push_str("  ");
+        }
+        res.push_str(line);
+        res.push('\n');
+
+        let mut pos = 0;
+        while let Some(caps) = TEX_ENV.captures(tex_strip_comment(&line[pos..])) {
+            pos += caps.get(0).unwrap().end();
+            if &caps[1] == "end" {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+
+            depth += 1;
+            if TEX_VERBATIM_ENVS.contains(&&caps[2]) {
+                // Skip verbatim content, which may continue on the following lines
+                let end = format!("\\end{{{}}}", &caps[2]);
+                match line[pos..].find(&end) {
+                    Some(end_pos) => {
+                        depth -= 1;
+                        pos += end_pos + end.len();
+                    }
+                    None => {
+                        verbatim = Some(caps[2].to_string());
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    if blank && res.ends_with("\n\n") {
+        res.pop();
+    }
+    res
+}
+
 pub struct RPdf {
     hb: HbRender,
     toc_sort_key: Option<String>,
-    tex_runs: u32,
+    collator: Collator,
+    tex_runs: TexRuns,
+    tex_normalize: bool,
+    synctex: bool,
+    tex_timeout: Option<Duration>,
+    prepend_pdf: Vec<PathBuf>,
+    append_pdf: Vec<PathBuf>,
 }
 
 impl RPdf {
@@ -59,30 +172,67 @@ impl RPdf {
 
         Ok(Self {
             hb,
+            // The key is ignored unless sorting is enabled, see `TexRenderJob::toc_sort_key`
             toc_sort_key: output.toc_sort.then(|| output.toc_sort_key.clone()),
+            collator: book_collator(&output.override_book_section(project.book_section())),
             tex_runs: output.tex_runs,
+            tex_normalize: output.tex_normalize,
+            synctex: output.synctex,
+            tex_timeout: project.settings.tex_timeout(),
+            prepend_pdf: output.prepend_pdf.clone(),
+            append_pdf: output.append_pdf.clone(),
         })
     }
 }
 
 impl Render for RPdf {
     fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        // Without ToC, there's no need to rerun TeX
+        let runs = if context.partial {
+            TexRuns::Fixed(1)
+        } else {
+            self.tex_runs
+        };
+
         // Render TeX first
         let tex_file = output.with_extension("tex");
-        self.hb.render(&tex_file, context)?;
-        if self.tex_runs == 0 || !app.post_process() {
+        if self.tex_normalize {
+            let tex = normalize_tex(&self.hb.render_str(context)?);
+            write_atomic(&tex_file, |file| Ok(file.write_all(tex.as_bytes())?))
+                .with_context(|| format!("Error writing output file: {:?}", tex_file))?;
+        } else {
+            self.hb.render(&tex_file, context)?;
+        }
+        if self.tex_runs.max() == 0 || !app.post_process() {
             // TODO: test this
             return Ok(());
         }
 
+        let missing = self
+            .prepend_pdf
+            .iter()
+            .chain(self.append_pdf.iter())
+            .find(|pdf| !pdf.is_file());
+        if let Some(missing) = missing {
+            bail!(
+                "PDF file {:?} to be included in output {:?} doesn't exist.",
+                missing,
+                output.file_name().unwrap_or_default()
+            );
+        }
+
         // Run TeX
         let job = TexRenderJob::new(
             tex_file,
             output,
             app.keep_interm(),
             self.toc_sort_key.as_deref(),
-            self.tex_runs - 1,
-        )?;
+            self.collator,
+            runs,
+        )?
+        .with_included_pdfs(&self.prepend_pdf, &self.append_pdf)
+        .with_timeout(self.tex_timeout)
+        .with_synctex(self.synctex);
         TexTools::get().render_pdf(app, job)
     }
 
@@ -90,3 +240,47 @@ impl Render for RPdf {
         self.hb.version()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_tex_whitespace() {
+        let source = concat!(
+            "\n",
+            "\\begin{document}  \n",
+            "\\begin{center}\n",
+            "    Hello % \\begin{comment}\n",
+            "\n",
+            "\n",
+            "\\end{center}\n",
+            "\\begin{verbatim}\n",
+            "    % pre  \n",
+            "\n",
+            "\n",
+            "\\end{verbatim}\n",
+            "\t\\begin{verbatim}100%\\end{verbatim}\n",
+            "\n",
+            "\\end{document}\n",
+            "\n",
+        );
+        let expected = concat!(
+            "\\begin{document}\n",
+            "  \\begin{center}\n",
+            "    Hello % \\begin{comment}\n",
+            "\n",
+            "  \\end{center}\n",
+            "  \\begin{verbatim}\n",
+            "    % pre  \n",
+            "\n",
+            "\n",
+            "\\end{verbatim}\n",
+            "  \\begin{verbatim}100%\\end{verbatim}\n",
+            "\n",
+            "\\end{document}\n",
+        );
+        assert_eq!(normalize_tex(source), expected);
+        assert_eq!(normalize_tex(expected), expected);
+    }
+}
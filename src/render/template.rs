@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::fs;
-use std::io;
+use std::io::{self, Write as _};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
@@ -16,7 +16,7 @@ use super::RenderContext;
 use crate::prelude::*;
 use crate::project::Format;
 use crate::project::{Output, Project};
-use crate::util::ImgCache;
+use crate::util::{write_atomic, ImgCache};
 
 type RegexCache = HashMap<String, Result<Regex, ReError>>;
 
@@ -229,7 +229,9 @@ impl HelperDef for DpiHelper {
             })?;
 
         let res = match self.format {
-            Format::Html => JsonValue::from((self.dpi as f64 * value).round() as u32),
+            Format::Html | Format::Epub => {
+                JsonValue::from((self.dpi as f64 * value).round() as u32)
+            }
             _ => JsonValue::from((value / self.dpi as f64) * Self::INCH_MM),
         };
 
@@ -449,14 +451,18 @@ impl HbRender {
     }
 
     pub(crate) fn render(&self, output: &Path, context: RenderContext) -> Result<()> {
-        let rendered = self.hb.render(&self.tpl_name, &context)?;
+        let rendered = self.render_str(context)?;
 
-        fs::write(output, rendered.as_bytes())
+        write_atomic(output, |file| Ok(file.write_all(rendered.as_bytes())?))
             .with_context(|| format!("Error writing output file: {:?}", output))?;
 
         Ok(())
     }
 
+    pub(crate) fn render_str(&self, context: RenderContext) -> Result<String> {
+        Ok(self.hb.render(&self.tpl_name, &context)?)
+    }
+
     pub(crate) fn version(&self) -> Option<Version> {
         Some(
             self.version
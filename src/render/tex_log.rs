@@ -0,0 +1,197 @@
+//! Errors in the output of TeX programs, see `TexLogError::parse()`.
+//!
+//! When TeX fails with normal verbosity, these are reported instead of the whole output,
+//! which is mostly package loading noise.
+
+use std::fmt;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Located error, `<file>.tex:<line>: <message>`, as printed by Tectonic (prefixed by `error: `)
+/// and by TeX Live with `-file-line-error`.
+static FILE_LINE_ERROR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:error: )?[^:]*\.tex:(\d+): (.+)$").unwrap());
+/// Line marker printed by TeX Live after an error, followed by the source up to the error.
+static LINE_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^l\.(\d+)(?: (.*))?$").unwrap());
+/// Song titles in the default PDF template, see `TexLogError::resolve()`.
+static SONG_TITLE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*\\songtitle(?:unlisted|key)?\{\s*(.*?)\s*\}").unwrap());
+
+/// Maximum number of errors listed by `TexLogErrors`.
+const MAX_LISTED: usize = 3;
+
+/// An error found in the output of XeLaTeX or Tectonic.
+#[derive(PartialEq, Eq, Debug)]
+pub struct TexLogError {
+    pub message: String,
+    /// Line in the `.tex` file, if reported.
+    pub line: Option<u32>,
+    /// The TeX source where the error occured, see `resolve()`.
+    pub source: Option<String>,
+    /// Title of the song the error is in, see `resolve()`.
+    pub song: Option<String>,
+}
+
+impl TexLogError {
+    fn new(message: &str, line: Option<u32>) -> Self {
+        Self {
+            message: message.trim().to_string(),
+            line,
+            source: None,
+            song: None,
+        }
+    }
+
+    /// Scans the output of a TeX program for errors.
+    ///
+    /// TeX Live reports errors as `! <message>`, followed by the line marker `l.<line> <source>`
+    /// a few lines later. Tectonic reports them as `error: <file>.tex:<line>: <message>`.
+    pub fn parse<'a>(output: impl Iterator<Item = &'a [u8]>) -> Vec<Self> {
+        let mut errors: Vec<Self> = vec![];
+        for line in output {
+            let line = String::from_utf8_lossy(line);
+            let line = line.trim_end();
+
+            if let Some(message) = line.strip_prefix("! ") {
+                errors.push(Self::new(message, None));
+            } else if let Some(caps) = FILE_LINE_ERROR.captures(line) {
+                errors.push(Self::new(&caps[2], caps[1].parse().ok()));
+            } else if let Some(caps) = LINE_MARKER.captures(line) {
+                let num = caps[1].parse().ok();
+                if let Some(error) = errors.last_mut() {
+                    if error.source.is_none() && error.line.map_or(true, |line| Some(line) == num) {
+                        error.line = num;
+                        error.source = caps.get(2).map(|src| src.as_str().trim().to_string());
+                    }
+                }
+            } else if let Some(message) = line.strip_prefix("error: ") {
+                // Tectonic errors without a location, except for its note that it stopped
+                if !message.starts_with("halted on") {
+                    errors.push(Self::new(message, None));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Fills in `source` and `song` from the `tex` source the error's line refers to.
+    ///
+    /// The song is found by the last song title before the line,
+    /// so this only works with templates based on the default one.
+    pub fn resolve(&mut self, tex: &str) {
+        let line = match self.line {
+            Some(line) if line > 0 => line as usize,
+            _ => return,
+        };
+
+        let lines: Vec<_> = tex.lines().take(line).collect();
+        if lines.len() < line {
+            return;
+        }
+
+        self.song = lines
+            .iter()
+            .rev()
+            .find_map(|line| SONG_TITLE.captures(line))
+            .map(|caps| caps[1].to_string());
+        if self.source.is_none() {
+            self.source = Some(lines[line - 1].trim().to_string());
+        }
+    }
+}
+
+impl fmt::Display for TexLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TeX error: {}", self.message)?;
+        if let Some(line) = self.line {
+            write!(f, "\nAt line {}", line)?;
+            if let Some(song) = self.song.as_ref() {
+                write!(f, ", in song '{}'", song)?;
+            }
+            if let Some(source) = self.source.as_ref().filter(|src| !src.is_empty()) {
+                write!(f, ": {}", source)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors reported by a failed TeX run, the first few of them are listed.
+#[derive(Debug)]
+pub struct TexLogErrors(pub Vec<TexLogError>);
+
+impl fmt::Display for TexLogErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().take(MAX_LISTED).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        if self.0.len() > MAX_LISTED {
+            write!(f, "\n... and {} more errors", self.0.len() - MAX_LISTED)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(output: &str) -> Vec<TexLogError> {
+        TexLogError::parse(output.lines().map(str::as_bytes))
+    }
+
+    #[test]
+    fn tex_log_texlive() {
+        let errors = parse(
+            "(./songbook.tex\n\
+             ! Undefined control sequence.\n\
+             l.42 Oh the \\foo\n\
+             \x20                 summer time\n\
+             ! LaTeX Error: File `bar.sty' not found.\n\
+             \n\
+             l.7 \\usepackage\n\
+             Output written on songbook.pdf",
+        );
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "Undefined control sequence.");
+        assert_eq!(errors[0].line, Some(42));
+        assert_eq!(errors[0].source.as_deref(), Some("Oh the \\foo"));
+        assert_eq!(errors[1].message, "LaTeX Error: File `bar.sty' not found.");
+        assert_eq!(errors[1].line, Some(7));
+    }
+
+    #[test]
+    fn tex_log_tectonic() {
+        let mut errors = parse(
+            "note: Running TeX ...\n\
+             warning: songbook.tex:3: Overfull \\hbox\n\
+             error: /tmp/out/songbook.tex:5: Undefined control sequence\n\
+             error: halted on potentially-recoverable error as specified",
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Undefined control sequence");
+        assert_eq!(errors[0].line, Some(5));
+
+        let tex = "\\begin{document}\n\
+                   \\songtitle{ Danny Boy }\n\
+                   Oh Danny boy\n\
+                   \\songtitleunlisted{ Wild Mountain Thyme }\n\
+                   Oh the \\foo summer time\n";
+        errors[0].resolve(tex);
+        assert_eq!(errors[0].song.as_deref(), Some("Wild Mountain Thyme"));
+        assert_eq!(
+            errors[0].source.as_deref(),
+            Some("Oh the \\foo summer time")
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "TeX error: Undefined control sequence\n\
+             At line 5, in song 'Wild Mountain Thyme': Oh the \\foo summer time"
+        );
+    }
+}
@@ -1,28 +1,51 @@
 use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error as StdError;
 use std::ffi::{OsStr, OsString};
-use std::io::{BufRead, Write};
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
 use std::ops::Deref;
 use std::process::{Command, Stdio};
 use std::str::FromStr;
-use std::time::Duration;
-use std::{env, fmt, fs, io, thread};
+use std::time::{Duration, Instant};
+use std::{env, fmt, fs, io, iter, mem, thread};
 
 use parking_lot::{const_mutex, Mutex, MutexGuard};
-use serde::de::Error as _;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString, EnumVariantNames, VariantNames as _};
 
-use crate::app::{keeplevel, verbosity, App, InterruptFlag};
+use super::merge::merge_pdfs;
+use super::tex_log::{TexLogError, TexLogErrors};
+use crate::app::{keeplevel, verbosity, App, DeadlineError, InterruptFlag};
 use crate::prelude::*;
-use crate::util::{ExitStatusExt, ProcessLines, StrExt, TempPath};
+use crate::project::TexRuns;
+use crate::util::collation::Collator;
+use crate::util::{move_file, ErrorExt as _, ExitStatusExt, ProcessLines, StrExt, TempPath};
 use crate::util_cmd;
 
 static TEX_TOOLS: Mutex<Option<TexTools>> = const_mutex(None);
 
+/// Default time limit for a TeX program to report its version, see `probe_timeout()`.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Extension of the compressed SyncTeX files written by TeX with `-synctex=1`.
+const SYNCTEX_EXT: &str = ".synctex.gz";
+
+/// A tiny document rendered by `TexTools::self_test()`.
+/// It uses `fontspec` like the default template so that font setup issues show up.
+static SELF_TEST_DOC: &str = r"\documentclass{article}
+\usepackage{fontspec}
+\begin{document}
+Hello from bard!
+\end{document}
+";
+
 #[derive(EnumString, EnumVariantNames, Display, Clone, Copy, PartialEq, Eq, Debug)]
 #[strum(ascii_case_insensitive, serialize_all = "kebab-case")]
 pub enum TexDistro {
     Xelatex,
+    Lualatex,
     Tectonic,
     TectonicEmbedded,
     None,
@@ -32,6 +55,7 @@ impl TexDistro {
     fn default_program(&self, app: &App) -> Option<OsString> {
         match self {
             Self::Xelatex => Some("xelatex".to_string().into()),
+            Self::Lualatex => Some("lualatex".to_string().into()),
             Self::Tectonic => Some("tectonic".to_string().into()),
             Self::TectonicEmbedded => Some(app.bard_exe().to_owned().into()),
             _ => None,
@@ -47,9 +71,16 @@ impl TexDistro {
 pub struct TexConfig {
     distro: TexDistro,
     program: Option<OsString>,
+    /// Extra arguments passed to the program before the TeX file, set in the table form of the `tex` setting.
+    args: Vec<String>,
 }
 
 impl TexConfig {
+    /// Returns `true` with `tex = "none"`, in which case PDF outputs are only rendered as TeX files.
+    pub fn is_none(&self) -> bool {
+        self.distro.is_none()
+    }
+
     fn try_from_env() -> Result<Option<Self>> {
         env::var_os("BARD_TEX")
             .map(|var| Self::try_from(var.as_ref()))
@@ -60,6 +91,7 @@ impl TexConfig {
         Self {
             distro,
             program: None,
+            args: vec![],
         }
     }
 
@@ -67,6 +99,7 @@ impl TexConfig {
         Self {
             distro: TexDistro::TectonicEmbedded,
             program: TexDistro::TectonicEmbedded.default_program(app),
+            args: vec![],
         }
     }
 
@@ -80,20 +113,27 @@ impl TexConfig {
         }
 
         let interrupt = app.interrupt_flag();
+        let timeout = probe_timeout()?;
         let version = match self.distro {
-            TexDistro::Xelatex => {
-                test_program(interrupt, self.program.as_ref().unwrap(), "-version")?
-            }
-            TexDistro::Tectonic => {
-                test_program(interrupt, self.program.as_ref().unwrap(), "--version")?
-            }
+            TexDistro::Xelatex | TexDistro::Lualatex => test_program(
+                interrupt,
+                self.program.as_ref().unwrap(),
+                "-version",
+                timeout,
+            )?,
+            TexDistro::Tectonic => test_program(
+                interrupt,
+                self.program.as_ref().unwrap(),
+                "--version",
+                timeout,
+            )?,
             #[cfg(not(feature = "tectonic"))]
             TexDistro::TectonicEmbedded => {
                 bail!("This bard binary was not built with embedded Tectonic.")
             }
             #[cfg(feature = "tectonic")]
             TexDistro::TectonicEmbedded => {
-                *self = Self::with_embedded_tectonic(app);
+                self.program = TexDistro::TectonicEmbedded.default_program(app);
                 "Tectonic (embedded)".to_string()
             }
             _ => unreachable!(),
@@ -105,8 +145,13 @@ impl TexConfig {
 
     fn render_args(&self, job: &TexRenderJob) -> Vec<OsString> {
         let mut args = match self.distro {
-            TexDistro::Xelatex => vec![
+            TexDistro::Xelatex | TexDistro::Lualatex => vec![
                 "-interaction=nonstopmode".to_os_string(),
+                {
+                    let mut jobname = "-jobname=".to_os_string();
+                    jobname.push(&job.jobname);
+                    jobname
+                },
                 "-output-directory".to_os_string(),
                 job.tmp_dir.to_os_string(),
             ],
@@ -135,15 +180,24 @@ impl TexConfig {
             TexDistro::None => unreachable!(),
         };
 
+        if job.synctex {
+            args.push(match self.distro {
+                TexDistro::Xelatex | TexDistro::Lualatex => "-synctex=1".to_os_string(),
+                _ => "--synctex".to_os_string(),
+            });
+        }
+
+        // Extra arguments go before the separator so that they aren't taken for the input file
+        args.extend(self.args.iter().map(OsString::from));
         args.extend(["--".to_os_string(), job.tex_file.to_os_string()]);
         args
     }
 
     /// Returns what should be the stderr status prefix when logging lines in scrolled mode,
     /// see `App::subprocess_output()`.
-    fn program_status(&self) -> Cow<str> {
+    fn program_status(&self) -> Cow<'_, str> {
         match self.distro {
-            TexDistro::Xelatex | TexDistro::Tectonic => {
+            TexDistro::Xelatex | TexDistro::Lualatex | TexDistro::Tectonic => {
                 self.program.as_ref().unwrap().to_string_lossy()
             }
             TexDistro::TectonicEmbedded => "tectonic".into(),
@@ -171,7 +225,11 @@ impl<'a> TryFrom<&'a OsStr> for TexConfig {
             )
         })?;
 
-        Ok(Self { distro, program })
+        Ok(Self {
+            distro,
+            program,
+            args: vec![],
+        })
     }
 }
 #[cfg(windows)]
@@ -196,7 +254,11 @@ impl<'a> TryFrom<&'a OsStr> for TexConfig {
             )
         })?;
 
-        Ok(Self { distro, program })
+        Ok(Self {
+            distro,
+            program,
+            args: vec![],
+        })
     }
 }
 
@@ -209,15 +271,46 @@ impl FromStr for TexConfig {
     }
 }
 
+/// Table form of the `tex` setting, eg. `tex = { distro = "xelatex", args = ["-shell-escape"] }`.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TexConfigTable {
+    /// Same as the string form, ie. `distro_type` or `distro_type:path`.
+    distro: String,
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+struct TexConfigVisitor;
+
+impl<'de> Visitor<'de> for TexConfigVisitor {
+    type Value = TexConfig;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a TeX distribution such as \"xelatex\", or a table with distro and args"
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, input: &str) -> Result<TexConfig, E> {
+        OsStr::new(input).try_into().map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<TexConfig, A::Error> {
+        let table = TexConfigTable::deserialize(MapAccessDeserializer::new(map))?;
+        let mut config: TexConfig = self.visit_str(&table.distro)?;
+        config.args = table.args;
+        Ok(config)
+    }
+}
+
 impl<'de> Deserialize<'de> for TexConfig {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let input: Cow<'de, str> = Deserialize::deserialize(deserializer)?;
-        OsStr::new(input.as_ref())
-            .try_into()
-            .map_err(D::Error::custom)
+        deserializer.deserialize_any(TexConfigVisitor)
     }
 }
 
@@ -238,16 +331,93 @@ impl Serialize for TexConfig {
     where
         S: serde::Serializer,
     {
-        let s = self.to_string();
-        s.serialize(serializer)
+        if self.args.is_empty() {
+            self.to_string().serialize(serializer)
+        } else {
+            TexConfigTable {
+                distro: self.to_string(),
+                args: self.args.clone(),
+            }
+            .serialize(serializer)
+        }
     }
 }
 
-/// Run a command and get first line from stdout, if any
+/// Time limit for probing TeX programs, may be set in seconds with the `BARD_TEX_PROBE_TIMEOUT` env var.
+fn probe_timeout() -> Result<Duration> {
+    let var = match env::var_os("BARD_TEX_PROBE_TIMEOUT") {
+        Some(var) => var,
+        None => return Ok(PROBE_TIMEOUT),
+    };
+
+    var.to_str()
+        .and_then(|var| var.trim().parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid value of the BARD_TEX_PROBE_TIMEOUT environment variable: {:?}, expected a number of seconds.",
+                var
+            )
+        })
+}
+
+/// A program probed by `test_program()` didn't finish in time.
+#[derive(Debug)]
+pub struct ProbeTimeoutError {
+    program: OsString,
+    timeout: Duration,
+}
+
+impl fmt::Display for ProbeTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Program {:?} did not respond within {:.1}s and was stopped.",
+            self.program,
+            self.timeout.as_secs_f64()
+        )?;
+        write!(
+            f,
+            "Hint: If TeX is slow to start on this system, set a longer time limit in seconds with the BARD_TEX_PROBE_TIMEOUT environment variable, eg. BARD_TEX_PROBE_TIMEOUT=30"
+        )
+    }
+}
+
+impl StdError for ProbeTimeoutError {}
+
+/// A TeX program run by `run_program()` didn't finish within the `tex_timeout` setting.
+#[derive(Debug)]
+pub struct TexTimeoutError {
+    program: OsString,
+    timeout: Duration,
+}
+
+impl fmt::Display for TexTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Program {:?} did not finish within {}s and was stopped.",
+            self.program,
+            self.timeout.as_secs()
+        )?;
+        write!(
+            f,
+            "Hint: TeX may be stuck, eg. waiting for a missing file or in an infinite loop in the template. If the book just takes long to typeset, raise the limit with the tex_timeout setting in bard.toml, eg. tex_timeout = 900"
+        )
+    }
+}
+
+impl StdError for TexTimeoutError {}
+
+/// Run a command and get first line from stdout, if any.
+///
+/// The program is killed if it doesn't finish within `timeout`, in which case `ProbeTimeoutError` is returned.
 fn test_program(
     interrupt: InterruptFlag,
     program: impl AsRef<OsStr>,
     arg1: &str,
+    timeout: Duration,
 ) -> Result<String> {
     let program = program.as_ref();
     let mut child = Command::new(program)
@@ -255,10 +425,12 @@ fn test_program(
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::null())
-        .spawn()?;
+        .spawn()
+        .with_context(|| format!("Could not run program {:?}", program))?;
 
     // Crude way to wait for the subprocess with a timeout.
-    for _ in 0..30 {
+    let start = Instant::now();
+    loop {
         interrupt.check_interrupted()?;
 
         if let Some(status) = child.try_wait()? {
@@ -266,9 +438,18 @@ fn test_program(
             break;
         }
 
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(ProbeTimeoutError {
+                program: program.to_owned(),
+                timeout,
+            }
+            .into());
+        }
+
         thread::sleep(Duration::from_millis(50));
     }
-    let _ = child.kill();
 
     let stdout = child.stdout.take().map(io::BufReader::new).unwrap();
     let first_line = stdout
@@ -281,20 +462,27 @@ fn test_program(
     Ok(first_line)
 }
 
+/// Runs a TeX `program`. If it fails with normal verbosity, errors found in its output
+/// are reported, located in the `tex_file` if given, see `TexLogError`.
+/// When there are none, the whole output is printed instead.
+///
+/// The program is killed if it doesn't finish within `timeout`, in which case `TexTimeoutError` is returned.
 fn run_program(
     app: &App,
     program: impl AsRef<OsStr>,
     args: &[impl AsRef<OsStr>],
     cwd: &Path,
     status: &str,
+    tex_file: Option<&Path>,
+    timeout: Option<Duration>,
 ) -> Result<()> {
     let program = program.as_ref();
     if app.verbosity() >= verbosity::VERBOSE {
         app.status_bare("Command", program.to_string_lossy());
         for arg in args.iter() {
-            eprint!(" {}", arg.as_ref().to_string_lossy());
+            app.eprint(format_args!(" {}", arg.as_ref().to_string_lossy()));
         }
-        eprintln!();
+        app.eprint(format_args!("\n"));
     }
 
     let mut child = Command::new(program)
@@ -309,36 +497,97 @@ fn run_program(
     let mut ps_lines =
         ProcessLines::new(child.stdout.take().unwrap(), child.stderr.take().unwrap());
 
-    app.subprocess_output(&mut ps_lines, program, status)?;
-
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
     let status = app
-        .child_wait(&mut child)
-        .with_context(|| format!("Error running program {:?}", program))?;
-
-    if !status.success() && app.verbosity() == verbosity::NORMAL {
-        app.status_bare("Command", program.to_string_lossy());
-        for arg in args.iter() {
-            eprint!(" {}", arg.as_ref().to_string_lossy());
+        .subprocess_output(&mut ps_lines, program, status, deadline)
+        .and_then(|_| {
+            app.child_wait(&mut child, deadline)
+                .with_context(|| format!("Error running program {:?}", program))
+        });
+
+    let status = match status {
+        Ok(status) => status,
+        Err(err) => {
+            // Typically we get here when interrupted or out of time. Make sure the program
+            // doesn't linger and show how far it got before unwinding.
+            let _ = child.kill();
+            let _ = child.wait();
+            if app.verbosity() == verbosity::NORMAL {
+                print_collected_output(app, program, args, &ps_lines);
+            }
+            return match (err.ultimate_source(), timeout) {
+                (Some(source), Some(timeout)) if source.is::<DeadlineError>() => {
+                    Err(TexTimeoutError {
+                        program: program.to_owned(),
+                        timeout,
+                    }
+                    .into())
+                }
+                _ => Err(err),
+            };
         }
-        eprintln!();
+    };
 
-        let stderr = io::stderr();
-        let mut stderr = stderr.lock();
-        for line in ps_lines.collected_lines() {
-            let _ = stderr.write_all(line);
+    if !status.success() && app.verbosity() == verbosity::NORMAL {
+        let mut errors = TexLogError::parse(ps_lines.collected_lines());
+        if errors.is_empty() {
+            print_collected_output(app, program, args, &ps_lines);
+        } else {
+            if let Some(tex) = tex_file.and_then(|path| fs::read_to_string(path).ok()) {
+                errors.iter_mut().for_each(|error| error.resolve(&tex));
+            }
+            return status.into_result().context(TexLogErrors(errors));
         }
     }
 
     status.into_result()
 }
 
+/// Print the command line and the output collected from the program so far.
+///
+/// Used with normal verbosity, where the output is otherwise only shown as a scrolled line.
+fn print_collected_output(
+    app: &App,
+    program: &OsStr,
+    args: &[impl AsRef<OsStr>],
+    ps_lines: &ProcessLines,
+) {
+    app.status_bare("Command", program.to_string_lossy());
+    for arg in args.iter() {
+        app.eprint(format_args!(" {}", arg.as_ref().to_string_lossy()));
+    }
+    app.eprint(format_args!("\n"));
+
+    for line in ps_lines.collected_lines() {
+        app.write_stderr(line);
+    }
+}
+
 #[derive(Debug)]
 pub struct TexRenderJob<'a> {
     pub tex_file: TempPath,
+    /// Stem of intermediate files in `tmp_dir`, derived from the output PDF file name.
+    ///
+    /// Tectonic doesn't support setting the jobname and names the files
+    /// after the `.tex` file instead, which is why it's named after the output too.
+    jobname: OsString,
     tmp_dir: TempPath,
     pdf_file: &'a Path,
+    /// Regex extracting the key to sort `.toc` lines by between TeX runs, see `sort_toc()`.
+    /// With `None`, the `.toc` file is not modified, but TeX still reruns `reruns` times.
     toc_sort_key: Option<&'a str>,
+    collator: Collator,
+    /// Maximum number of reruns after the first run.
     reruns: u32,
+    /// Stop rerunning once the `.toc` and `.aux` files don't change, see `TexRuns::Auto`.
+    until_stable: bool,
+    /// Time limit of each TeX run, see `Settings::tex_timeout`.
+    timeout: Option<Duration>,
+    /// Have TeX write SyncTeX data, which is moved next to the PDF, see `move_pdf()`.
+    synctex: bool,
+    /// External PDF files merged before and after the TeX output, see `Output::prepend_pdf`.
+    prepend_pdf: &'a [PathBuf],
+    append_pdf: &'a [PathBuf],
 }
 
 impl<'a> TexRenderJob<'a> {
@@ -347,16 +596,46 @@ impl<'a> TexRenderJob<'a> {
         pdf_path: &'a Path,
         keep: u8,
         toc_sort_key: Option<&'a str>,
-        reruns: u32,
+        collator: Collator,
+        runs: TexRuns,
     ) -> Result<Self> {
+        let jobname = pdf_path
+            .file_stem()
+            .ok_or_else(|| anyhow!("Invalid output file name: {:?}", pdf_path))?
+            .to_owned();
+        debug_assert_eq!(tex_file.file_stem(), Some(jobname.as_os_str()));
+
         Ok(Self {
             tex_file: TempPath::new_file(tex_file, keep < keeplevel::TEX_ONLY),
+            jobname,
             tmp_dir: TempPath::make_temp_dir(pdf_path, keep < keeplevel::ALL)?,
             pdf_file: pdf_path,
             toc_sort_key,
-            reruns,
+            collator,
+            reruns: runs.max().saturating_sub(1),
+            until_stable: runs == TexRuns::Auto,
+            timeout: None,
+            synctex: false,
+            prepend_pdf: &[],
+            append_pdf: &[],
         })
     }
+
+    pub fn with_included_pdfs(mut self, prepend: &'a [PathBuf], append: &'a [PathBuf]) -> Self {
+        self.prepend_pdf = prepend;
+        self.append_pdf = append;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_synctex(mut self, synctex: bool) -> Self {
+        self.synctex = synctex;
+        self
+    }
 }
 
 impl<'a> TexRenderJob<'a> {
@@ -370,31 +649,132 @@ impl<'a> TexRenderJob<'a> {
             None => return Ok(()),
         };
 
-        let tex_stem = self.tex_file.file_stem().unwrap();
-        let toc = self.tmp_dir.join_stem(tex_stem, ".toc");
+        let toc = self.tmp_dir.join_stem(&self.jobname, ".toc");
 
         if toc.exists() {
-            util_cmd::sort_lines(key, &toc)
+            util_cmd::sort_lines(key, &toc, &self.collator)
                 .with_context(|| format!("Could not sort TOC file {:?}", toc))?;
         }
 
         Ok(())
     }
 
+    /// Hash of the `.toc` and `.aux` files, which TeX reads on the next run.
+    /// Once it doesn't change between runs, page numbers and references are settled.
+    fn rerun_state(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for ext in [".toc", ".aux"] {
+            fs::read(self.tmp_dir.join_stem(&self.jobname, ext))
+                .ok()
+                .hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Moves the PDF from `tmp_dir` to the output file. With `synctex`, the SyncTeX file
+    /// is moved along, so that it's not removed with `tmp_dir` and editors find it next to the PDF.
     fn move_pdf(&self) -> Result<()> {
-        let tex_stem = self.tex_file.file_stem().unwrap();
-        let out_pdf = self.tmp_dir.join_stem(tex_stem, ".pdf");
-        fs::rename(out_pdf, self.pdf_file)
-            .with_context(|| format!("Could not move to output file {:?}", self.pdf_file))
+        let out_pdf = self.tmp_dir.join_stem(&self.jobname, ".pdf");
+        move_file(&out_pdf, self.pdf_file)
+            .with_context(|| format!("Could not move to output file {:?}", self.pdf_file))?;
+
+        if self.synctex {
+            let synctex = self.tmp_dir.join_stem(&self.jobname, SYNCTEX_EXT);
+            let dest = self.synctex_file();
+            move_file(&synctex, &dest)
+                .with_context(|| format!("Could not move SyncTeX file to {:?}", dest))?;
+        }
+
+        Ok(())
+    }
+
+    /// Where the SyncTeX file is placed, named after the PDF as editors expect.
+    fn synctex_file(&self) -> PathBuf {
+        self.cwd().join_stem(&self.jobname, SYNCTEX_EXT)
+    }
+
+    fn include_pdfs(&self) -> Result<()> {
+        if self.prepend_pdf.is_empty() && self.append_pdf.is_empty() {
+            return Ok(());
+        }
+
+        let inputs: Vec<&Path> = self
+            .prepend_pdf
+            .iter()
+            .map(PathBuf::as_path)
+            .chain(iter::once(self.pdf_file))
+            .chain(self.append_pdf.iter().map(PathBuf::as_path))
+            .collect();
+        merge_pdfs(&inputs, self.pdf_file)
+            .with_context(|| format!("Could not include PDF files in {:?}", self.pdf_file))
+    }
+}
+
+/// Returned by `TexTools::initialize()` when no TeX distribution was found by automatic lookup.
+#[derive(Clone, Copy, Debug)]
+pub struct TexNotFoundError;
+
+impl fmt::Display for TexNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "No TeX distribution found.")?;
+        writeln!(
+            f,
+            "Looked for the 'xelatex' and 'tectonic' programs in PATH, but neither could be run."
+        )?;
+        writeln!(f, "To fix this, either:")?;
+        writeln!(
+            f,
+            "- install XeLaTeX or Tectonic, see https://bard.md/book/install.html,"
+        )?;
+        writeln!(
+            f,
+            "- set the BARD_TEX environment variable, eg. BARD_TEX=xelatex:/path/to/xelatex,"
+        )?;
+        writeln!(
+            f,
+            "- or set the tex field in bard.toml, eg. tex = \"tectonic\"."
+        )?;
+        write!(
+            f,
+            "To skip PDF rendering, use tex = \"none\". See https://bard.md/book/tex.html for details."
+        )
+    }
+}
+
+impl StdError for TexNotFoundError {}
+
+/// Where the TeX configuration in use comes from, see `TexTools::initialize()`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TexSource {
+    /// Disabled by the command being run, see `App::tex_disabled()`.
+    Disabled,
+    Env,
+    Settings,
+    Auto,
+}
+
+impl fmt::Display for TexSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Disabled => "the command being run",
+            Self::Env => "the BARD_TEX environment variable",
+            Self::Settings => "the bard.toml project file",
+            Self::Auto => "automatic detection",
+        })
     }
 }
 
 pub struct TexTools {
     config: TexConfig,
+    source: TexSource,
 }
 
 impl TexTools {
     pub fn initialize(app: &App, from_settings: Option<&TexConfig>) -> Result<()> {
+        if app.tex_disabled() {
+            return Self::set(TexConfig::with_distro(TexDistro::None), TexSource::Disabled);
+        }
+
         app.status("Locating", "TeX tools...");
 
         // 1. Priority: BARD_TEX env var
@@ -402,7 +782,7 @@ impl TexTools {
             config.probe(app).with_context(|| {
                 format!(
                     "Error using TeX distribution '{}' configured from the BARD_TEX environment variable.", config)})?;
-            return Self::set(config);
+            return Self::set(config, TexSource::Env);
         }
 
         // 2. Config from bard.toml
@@ -413,25 +793,32 @@ impl TexTools {
                     config
                 )
             })?;
-            return Self::set(config);
+            return Self::set(config, TexSource::Settings);
         }
 
         // 3. No explicit config
         if cfg!(feature = "tectonic") {
             // We have embedded tectonic...
             let config = TexConfig::with_embedded_tectonic(app);
-            return Self::set(config);
+            return Self::set(config, TexSource::Auto);
         } else {
             // try to probe automatically...
             for kind in [TexDistro::Xelatex, TexDistro::Tectonic] {
                 let mut config = TexConfig::with_distro(kind);
-                if config.probe(app).is_ok() {
-                    return Self::set(config);
+                match config.probe(app) {
+                    Ok(()) => return Self::set(config, TexSource::Auto),
+                    // The program exists, but is too slow, this is not the same as not found
+                    Err(err) if err.is::<ProbeTimeoutError>() => {
+                        return Err(
+                            err.context(format!("Error using TeX distribution '{}'.", config))
+                        )
+                    }
+                    Err(_) => {}
                 }
             }
         }
 
-        bail!("No TeX distribution found. Please install a TeX distribution. For more information see https://bard.md/book/install.html.");
+        Err(TexNotFoundError.into())
     }
 
     pub fn get() -> impl Deref<Target = Self> {
@@ -448,12 +835,118 @@ impl TexTools {
         Guard(TEX_TOOLS.lock())
     }
 
-    fn set(config: TexConfig) -> Result<()> {
-        let this = Self { config };
+    fn set(config: TexConfig, source: TexSource) -> Result<()> {
+        let this = Self { config, source };
         *TEX_TOOLS.lock() = Some(this);
         Ok(())
     }
 
+    pub fn config(&self) -> &TexConfig {
+        &self.config
+    }
+
+    pub fn source(&self) -> TexSource {
+        self.source
+    }
+
+    /// Describes the configuration in use: the distribution, where it was configured,
+    /// the program, and the command line it's run with for a PDF file named `songbook.pdf`.
+    ///
+    /// Nothing is run, the paths of intermediate files are only examples.
+    pub fn describe(&self) -> Result<String> {
+        let mut res = format!(
+            "Distribution: {}\nConfigured from: {}\n",
+            self.config.distro, self.source
+        );
+        let program = match self.config.program.as_ref() {
+            Some(program) => program,
+            None => {
+                res.push_str("TeX is disabled, PDF outputs are only rendered as TeX files.\n");
+                return Ok(res);
+            }
+        };
+
+        let work_dir = TempPath::make_temp_dir(env::temp_dir().join("bard-tex-command"), true)?;
+        let pdf_file = work_dir.join("songbook.pdf");
+        let job = TexRenderJob::new(
+            work_dir.join("songbook.tex"),
+            &pdf_file,
+            keeplevel::NONE,
+            None,
+            Collator::default(),
+            TexRuns::Fixed(1),
+        )?;
+        let args = self.config.render_args(&job);
+        let command: Vec<_> = iter::once(program)
+            .chain(args.iter())
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                if arg.contains(char::is_whitespace) {
+                    format!("{:?}", arg)
+                } else {
+                    arg.into_owned()
+                }
+            })
+            .collect();
+
+        res.push_str(&format!(
+            "Program: {}\nCommand: {}\nWorking directory: the directory of the output file\n",
+            program.to_string_lossy(),
+            command.join(" ")
+        ));
+        Ok(res)
+    }
+
+    /// Render a tiny built-in document to check that the TeX distribution works.
+    ///
+    /// Returns the time it took, or `None` if TeX is disabled, ie. `tex = "none"`.
+    pub fn self_test(&self, app: &App) -> Result<Option<Duration>> {
+        if self.config.distro.is_none() {
+            return Ok(None);
+        }
+
+        let keep = app.keep_interm();
+        let work_dir = TempPath::make_temp_dir(
+            env::temp_dir().join("bard-self-test"),
+            keep < keeplevel::ALL,
+        )?;
+        let tex_file = work_dir.join("self-test.tex");
+        fs::write(&tex_file, SELF_TEST_DOC)
+            .with_context(|| format!("Could not write file {:?}", tex_file))?;
+        let pdf_file = work_dir.join("self-test.pdf");
+
+        let start = Instant::now();
+        let job = TexRenderJob::new(
+            tex_file,
+            &pdf_file,
+            keep,
+            None,
+            Collator::default(),
+            TexRuns::Fixed(1),
+        )?;
+        self.render_pdf(app, job)?;
+        Ok(Some(start.elapsed()))
+    }
+
+    /// Hints about common causes of `self_test()` failure with the configured distribution.
+    pub fn self_test_hints(&self) -> &'static [&'static str] {
+        match self.config.distro {
+            TexDistro::Xelatex => &[
+                "If fontspec or a font is reported missing, install the XeTeX packages and the Latin Modern fonts,",
+                "eg. texlive-xetex and fonts-lmodern on Debian/Ubuntu.",
+            ],
+            TexDistro::Lualatex => &[
+                "If fontspec or a font is reported missing, install the LuaTeX packages and the Latin Modern fonts,",
+                "eg. texlive-luatex and fonts-lmodern on Debian/Ubuntu.",
+            ],
+            TexDistro::Tectonic | TexDistro::TectonicEmbedded => &[
+                "Tectonic downloads its bundle of TeX packages and fonts on first use.",
+                "If the download failed, check that network access is not blocked by a firewall or proxy.",
+            ],
+            TexDistro::None => &[],
+        }
+    }
+
     pub fn render_pdf(&self, app: &App, mut job: TexRenderJob) -> Result<()> {
         if self.config.distro.is_none() {
             // TODO: test this:
@@ -467,13 +960,59 @@ impl TexTools {
         let program = self.config.program.as_ref().unwrap();
         let status = self.config.program_status();
 
-        run_program(app, program, &args, job.cwd(), &status)?;
+        let tex_file = Some(&*job.tex_file);
+        let mut state = job.rerun_state();
+        run_program(
+            app,
+            program,
+            &args,
+            job.cwd(),
+            &status,
+            tex_file,
+            job.timeout,
+        )?;
         for _ in 0..job.reruns {
+            // A no-op without a sort key, the reruns are still needed for the ToC and references
             job.sort_toc()?;
-            run_program(app, program, &args, job.cwd(), &status)?;
+            if job.until_stable {
+                let prev = mem::replace(&mut state, job.rerun_state());
+                if state == prev {
+                    break;
+                }
+            }
+            run_program(
+                app,
+                program,
+                &args,
+                job.cwd(),
+                &status,
+                tex_file,
+                job.timeout,
+            )?;
         }
 
         job.move_pdf()?;
+        job.include_pdfs()?;
+
+        if job.synctex {
+            // SyncTeX refers to lines of the TeX file, which is only kept with bard make -k
+            let synctex = job.synctex_file();
+            if app.keep_interm() >= keeplevel::TEX_ONLY {
+                app.status(
+                    "SyncTeX",
+                    format!("{:?} refers to {:?}", synctex, &*job.tex_file),
+                );
+            } else {
+                app.status(
+                    "SyncTeX",
+                    format!(
+                        "{:?} refers to the TeX file, which is removed, keep it with bard make -k",
+                        synctex
+                    ),
+                );
+            }
+        }
+
         Ok(())
     }
 }
@@ -488,6 +1027,10 @@ mod tests {
         assert_eq!(config.distro, TexDistro::Xelatex);
         assert_eq!(config.program, None);
 
+        let config: TexConfig = ("lualatex").parse().unwrap();
+        assert_eq!(config.distro, TexDistro::Lualatex);
+        assert_eq!(config.program, None);
+
         let config: TexConfig = ("tectonic").parse().unwrap();
         assert_eq!(config.distro, TexDistro::Tectonic);
         assert_eq!(config.program, None);
@@ -496,6 +1039,10 @@ mod tests {
         assert_eq!(config.distro, TexDistro::Xelatex);
         assert_eq!(config.program, Some("foo:bar".to_string().into()));
 
+        let config: TexConfig = ("LuaLaTeX:foo:bar").parse().unwrap();
+        assert_eq!(config.distro, TexDistro::Lualatex);
+        assert_eq!(config.program, Some("foo:bar".to_string().into()));
+
         let config: TexConfig = ("tectonic:foo:bar").parse().unwrap();
         assert_eq!(config.distro, TexDistro::Tectonic);
         assert_eq!(config.program, Some("foo:bar".to_string().into()));
@@ -507,17 +1054,124 @@ mod tests {
         TexConfig::from_str("xxx").unwrap_err();
     }
 
+    #[test]
+    fn tex_config_args() {
+        #[derive(Deserialize, Debug)]
+        struct Settings {
+            tex: TexConfig,
+        }
+
+        let settings: Settings = toml::from_str(
+            r#"tex = { distro = "xelatex:foo", args = ["-shell-escape", "-output-driver=xdvipdfmx -z0"] }"#,
+        )
+        .unwrap();
+        let config = settings.tex;
+        assert_eq!(config.distro, TexDistro::Xelatex);
+        assert_eq!(config.program, Some("foo".to_string().into()));
+        assert_eq!(
+            toml::Value::try_from(&config).unwrap(),
+            toml::Value::try_from(TexConfigTable {
+                distro: "xelatex:foo".into(),
+                args: config.args.clone(),
+            })
+            .unwrap()
+        );
+
+        let work_dir =
+            TempPath::make_temp_dir(env::temp_dir().join("bard-tex-args"), true).unwrap();
+        let pdf_file = work_dir.join("songbook.pdf");
+        let job = TexRenderJob::new(
+            work_dir.join("songbook.tex"),
+            &pdf_file,
+            keeplevel::NONE,
+            None,
+            Collator::default(),
+            TexRuns::Fixed(1),
+        )
+        .unwrap();
+        let args = config.render_args(&job);
+        let args: Vec<_> = args.iter().map(|arg| arg.to_str().unwrap()).collect();
+        assert_eq!(
+            args[args.len() - 4..args.len() - 1],
+            ["-shell-escape", "-output-driver=xdvipdfmx -z0", "--"]
+        );
+
+        toml::from_str::<Settings>(r#"tex = { distro = "xelatex", arg = ["-shell-escape"] }"#)
+            .unwrap_err();
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_test_program() {
         use std::sync::atomic::AtomicBool;
+        use std::time::Instant;
 
         static INTERRUPT: AtomicBool = AtomicBool::new(false);
         let interrupt = InterruptFlag(&INTERRUPT);
 
-        assert_eq!(test_program(interrupt, "echo", "hello").unwrap(), "hello");
-        test_program(interrupt, "xxx-surely-this-doesnt-exist", "").unwrap_err();
-        test_program(interrupt, "false", "").unwrap_err();
-        test_program(interrupt, "sleep", "9800").unwrap_err();
+        let timeout = PROBE_TIMEOUT;
+        assert_eq!(
+            test_program(interrupt, "echo", "hello", timeout).unwrap(),
+            "hello"
+        );
+        let err = test_program(interrupt, "xxx-surely-this-doesnt-exist", "", timeout).unwrap_err();
+        assert!(!err.is::<ProbeTimeoutError>());
+        test_program(interrupt, "false", "", timeout).unwrap_err();
+
+        let start = Instant::now();
+        let timeout = Duration::from_millis(200);
+        let err = test_program(interrupt, "sleep", "9800", timeout).unwrap_err();
+        assert!(err.is::<ProbeTimeoutError>());
+        assert!(start.elapsed() < PROBE_TIMEOUT);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_program_interrupted() {
+        use std::sync::atomic::AtomicBool;
+        use std::time::Instant;
+
+        use crate::app::InterruptError;
+
+        static INTERRUPT: AtomicBool = AtomicBool::new(true);
+        let app = App::with_test_mode(true, "bard".into(), InterruptFlag(&INTERRUPT));
+
+        let start = Instant::now();
+        let err = run_program(
+            &app,
+            "sleep",
+            &["9800"],
+            &env::temp_dir(),
+            "sleep",
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.ultimate_source().unwrap().is::<InterruptError>());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_program_timeout() {
+        use std::sync::atomic::AtomicBool;
+
+        static INTERRUPT: AtomicBool = AtomicBool::new(false);
+        let app = App::with_test_mode(true, "bard".into(), InterruptFlag(&INTERRUPT));
+
+        let start = Instant::now();
+        let timeout = Some(Duration::from_millis(200));
+        let err = run_program(
+            &app,
+            "sleep",
+            &["9800"],
+            &env::temp_dir(),
+            "sleep",
+            None,
+            timeout,
+        )
+        .unwrap_err();
+        assert!(err.is::<TexTimeoutError>());
+        assert!(start.elapsed() < Duration::from_secs(10));
     }
 }
@@ -1,17 +1,24 @@
 //! XML Renderer.
 
-use std::fs::File;
 use std::io;
 use std::io::Write;
 
 use super::Render;
 use super::RenderContext;
+use super::SplitPage;
 use crate::app::App;
+use crate::music::Notation;
 use crate::prelude::*;
 use crate::ProgramMeta;
 
+use crate::project::AlternateChords;
+use crate::project::CapoChords;
 use crate::project::Format;
 use crate::project::Output;
+use crate::project::PageNumbering;
+use crate::project::Split;
+use crate::project::TexRuns;
+use crate::util::write_atomic;
 use crate::util::xml_support::*;
 use crate::xml_write;
 
@@ -40,21 +47,100 @@ impl XmlWrite for Format {
     }
 }
 
+impl XmlWrite for AlternateChords {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
+impl XmlWrite for CapoChords {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
+impl XmlWrite for Notation {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
+impl XmlWrite for Split {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
+impl XmlWrite for TexRuns {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
+impl XmlWrite for PageNumbering {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(self)
+    }
+}
+
 xml_write!(struct Output {
     file,
     template,
     format,
     toc_sort,
     toc_sort_key,
+    toc_keys,
     sans_font,
     font_size,
     dpi,
     tex_runs,
     script,
+    chords_only,
+    ascii_only,
+    transpose,
+    to_key,
+    capo,
+    capo_chords,
+    notation,
+    unicode_accidentals,
+    alternate_chords,
+    split,
+    qr_codes,
+    chord_index,
+    tex_normalize,
+    synctex,
+    front_matter_numbering,
+    page_start,
+    page_start_after,
+    merge,
+    prepend_pdf,
+    append_pdf,
     book_overrides,
 } -> |w| {
     let _ = file;
     let _ = template;
+    let _ = page_start_after;
+    let _ = merge;
+    let _ = prepend_pdf;
+    let _ = append_pdf;
     let _ = book_overrides;
     w.tag("output")
         .content()?
@@ -63,26 +149,64 @@ xml_write!(struct Output {
         .field(font_size)?
         .field(toc_sort)?
         .field(toc_sort_key)?
+        .field(toc_keys)?
         .field_opt(dpi)?
         .field(tex_runs)?
         .field_opt(script)?
+        .field(chords_only)?
+        .field(ascii_only)?
+        .field_opt(transpose)?
+        .field_opt(to_key)?
+        .field_opt(capo)?
+        .field(capo_chords)?
+        .field_opt(notation)?
+        .field_opt(unicode_accidentals)?
+        .field(alternate_chords)?
+        .field(split)?
+        .field(qr_codes)?
+        .field(chord_index)?
+        .field(tex_normalize)?
+        .field(synctex)?
+        .field(front_matter_numbering)?
+        .field_opt(page_start)?
 });
 
 xml_write!(struct RenderContext<'a> {
     book,
     songs,
     songs_sorted,
+    pinned,
+    glossary,
+    credits,
+    chords,
     notation,
     output,
     program,
+    partial,
+    page_start,
+    vars,
+    split,
 } -> |w| {
     w.tag("songbook")
         .attr(notation)
+        .attr(partial)
+        .attr(pinned)
+        .attr(page_start)
         .content()?
         .comment("The [book] section in bard.toml")?
         .field(book)?
         .comment("References to <song> elements in alphabetically-sorted order")?
         .value_wrap("songs-sorted", songs_sorted)?
+        .comment("Glossary entries of all songs sorted by term")?
+        .value_wrap("glossary", glossary)?
+        .comment("Attribution of songs which have any")?
+        .value_wrap("credits", credits)?
+        .comment("Chords used in the book if chord_index is set in the output")?
+        .value_wrap("chords", chords)?
+        .comment("Variables defined on the command line")?
+        .field(vars)?
+        .comment("Paths between files of an output split into a file per song")?
+        .value_opt(split)?
         .comment("Fields in the [[output]] section in bard.toml")?
         .value_wrap("output", output)?
         .comment("Software metadata")?
@@ -91,6 +215,21 @@ xml_write!(struct RenderContext<'a> {
         .field(songs)?
 });
 
+xml_write!(struct SplitPage {
+    dir,
+    index,
+    root,
+    ext,
+    song,
+} -> |w| {
+    w.tag("split")
+        .attr(dir)
+        .attr(index)
+        .attr(root)
+        .attr(ext)
+        .attr(song)
+});
+
 #[derive(Debug, Default)]
 pub struct RXml;
 
@@ -102,16 +241,14 @@ impl RXml {
 
 impl Render for RXml {
     fn render(&self, _app: &App, output: &Path, context: RenderContext) -> anyhow::Result<()> {
-        File::create(output)
-            .map_err(Error::from)
-            .and_then(|f| {
-                let mut writer = Writer::new_with_indent(f, b' ', 2);
-                context.write(&mut writer)?;
-
-                let mut f = writer.into_inner();
-                f.write_all(b"\n")?;
-                Ok(())
-            })
-            .with_context(|| format!("Error writing output file: {:?}", output))
+        write_atomic(output, |file| {
+            let mut writer = Writer::new_with_indent(file, b' ', 2);
+            context.write(&mut writer)?;
+
+            let file = writer.into_inner();
+            file.write_all(b"\n")?;
+            Ok(())
+        })
+        .with_context(|| format!("Error writing output file: {:?}", output))
     }
 }
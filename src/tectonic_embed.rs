@@ -34,6 +34,9 @@ pub struct Tectonic {
     /// Output directory path
     #[arg(short)]
     out_dir: Option<PathBuf>,
+    /// Write SyncTeX data
+    #[arg(long)]
+    synctex: bool,
 
     /// Input TeX file
     input: PathBuf,
@@ -81,6 +84,7 @@ impl Tectonic {
             .keep_intermediates(self.keep)
             .keep_logs(self.keep)
             .reruns(self.reruns as _)
+            .synctex(self.synctex)
             .print_stdout(app.verbosity() >= 2)
             .output_format(driver::OutputFormat::Pdf);
 
@@ -10,11 +10,17 @@ use parking_lot::RwLock;
 
 use crate::prelude::*;
 
+pub mod archive;
+pub mod collation;
+pub mod lilypond;
+mod lock;
 mod path;
 mod process;
+pub mod qr;
 pub mod xml_support;
 
-pub use path::{PathBufExt, PathExt, TempPath};
+pub use lock::FileLock;
+pub use path::{copy_dir, move_file, write_atomic, PathBufExt, PathExt, TempPath};
 pub use process::{ExitStatusExt, ProcessLines};
 
 #[cfg(unix)]
@@ -44,7 +50,6 @@ where
 pub type BStr = Box<str>;
 
 /// `str` utils
-
 pub trait StrExt {
     fn to_os_string(&self) -> OsString;
     fn clone_bstr(&self) -> Box<str>;
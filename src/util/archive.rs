@@ -0,0 +1,390 @@
+//! Extraction of project archives, see `bard make --archive`, and writing of zip files, see `ZipWriter`.
+//!
+//! Supported are zip files with stored or deflated entries, and tar files, optionally gzipped.
+//! The format is detected from the file content. Only regular files and directories are extracted,
+//! other entries such as symlinks are skipped.
+//!
+//! Archives are processed in memory, so both the archive and its extracted content are limited
+//! to `MAX_SIZE`, which also stops archives that decompress to a huge size, aka zip bombs.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Component;
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+use crate::prelude::*;
+
+const ZIP_LOCAL_HEADER: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP_END_OF_CENTRAL_DIR: u32 = 0x06054b50;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const TAR_BLOCK: usize = 512;
+/// Limit of the size of an archive and of the total size of its extracted content.
+const MAX_SIZE: usize = 1 << 30;
+
+fn too_large_error() -> Error {
+    anyhow!(
+        "The archive is too large, its content exceeds the limit of {} MiB.\nHint: Project archives should only contain the project's files, such as bard.toml, songs, templates, and images.",
+        MAX_SIZE >> 20
+    )
+}
+
+/// Returns the directory of the project extracted in `dir`, which is either `dir` itself,
+/// or a single top-level directory, as is common in tarballs.
+pub fn project_root(dir: &Path) -> Result<PathBuf> {
+    if dir.join("bard.toml").is_file() {
+        return Ok(dir.to_owned());
+    }
+
+    let entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    match &entries[..] {
+        [entry] if entry.path().join("bard.toml").is_file() => Ok(entry.path()),
+        _ => bail!("The archive doesn't contain a bard.toml file\nHint: The bard.toml file has to be at the root of the archive or in its only top-level directory."),
+    }
+}
+
+/// Extracts the archive at `path` into the `dest` directory, which has to exist.
+pub fn extract(path: &Path, dest: &Path) -> Result<()> {
+    let data = fs::File::open(path)
+        .and_then(|file| {
+            let mut data = vec![];
+            file.take(MAX_SIZE as u64 + 1).read_to_end(&mut data)?;
+            Ok(data)
+        })
+        .with_context(|| format!("Could not read archive {:?}", path))?;
+    let res = if data.len() > MAX_SIZE {
+        Err(too_large_error())
+    } else if data.len() >= 4 && read_u32(&data, 0) == ZIP_LOCAL_HEADER {
+        extract_zip(&data, dest)
+    } else if data.len() >= 4 && read_u32(&data, 0) == ZIP_END_OF_CENTRAL_DIR {
+        // An empty zip file
+        Ok(())
+    } else if data.starts_with(&GZIP_MAGIC) {
+        let mut tar = vec![];
+        GzDecoder::new(&data[..])
+            .take(MAX_SIZE as u64 + 1)
+            .read_to_end(&mut tar)
+            .context("Could not decompress gzip data")
+            .and_then(|_| match tar.len() {
+                len if len > MAX_SIZE => Err(too_large_error()),
+                _ => extract_tar(&tar, dest),
+            })
+    } else if is_tar(&data) {
+        extract_tar(&data, dest)
+    } else {
+        bail!(
+            "Unsupported archive format of {:?}\nHint: Supported archives are zip files and tar files, optionally gzipped.",
+            path
+        );
+    };
+
+    res.with_context(|| format!("Could not extract archive {:?}", path))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// Checks that `data` has at least `len` bytes from `pos`.
+fn check_len(data: &[u8], pos: usize, len: usize) -> Result<()> {
+    if pos.checked_add(len).map_or(true, |end| end > data.len()) {
+        bail!("Unexpected end of archive data");
+    }
+    Ok(())
+}
+
+/// Resolves an archive entry `name` inside `dest`, rejecting absolute paths and paths outside of `dest`.
+fn entry_path(dest: &Path, name: &str) -> Result<PathBuf> {
+    let mut path = dest.to_owned();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => bail!("Invalid path in archive: {:?}", name),
+        }
+    }
+    Ok(path)
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create directory {:?}", parent))?;
+    }
+    fs::write(path, content).with_context(|| format!("Could not write file {:?}", path))
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    fs::create_dir_all(path).with_context(|| format!("Could not create directory {:?}", path))
+}
+
+fn extract_zip(data: &[u8], dest: &Path) -> Result<()> {
+    // The end of central directory record is at the end of the file, followed by a comment of up to 64 KiB
+    let min_pos = data.len().saturating_sub(22 + u16::MAX as usize);
+    let eocd = (min_pos..=data.len().saturating_sub(22))
+        .rev()
+        .find(|&pos| read_u32(data, pos) == ZIP_END_OF_CENTRAL_DIR)
+        .ok_or_else(|| anyhow!("Zip end of central directory not found"))?;
+
+    let num_entries = read_u16(data, eocd + 10) as usize;
+    let mut pos = read_u32(data, eocd + 16) as usize;
+    if num_entries == 0xffff || pos == 0xffffffff {
+        bail!("Zip64 archives are not supported");
+    }
+
+    // Sizes in the headers are checked before decompressing, the content is checked against them afterwards
+    let mut total_size: usize = 0;
+    for _ in 0..num_entries {
+        check_len(data, pos, 46)?;
+        if read_u32(data, pos) != ZIP_CENTRAL_HEADER {
+            bail!("Invalid zip central directory entry");
+        }
+
+        let flags = read_u16(data, pos + 8);
+        let method = read_u16(data, pos + 10);
+        let crc = read_u32(data, pos + 16);
+        let compressed_size = read_u32(data, pos + 20) as usize;
+        let size = read_u32(data, pos + 24) as usize;
+        let name_len = read_u16(data, pos + 28) as usize;
+        let extra_len = read_u16(data, pos + 30) as usize;
+        let comment_len = read_u16(data, pos + 32) as usize;
+        let external_attrs = read_u32(data, pos + 38);
+        let local_pos = read_u32(data, pos + 42) as usize;
+        check_len(data, pos + 46, name_len)?;
+        let name = String::from_utf8_lossy(&data[pos + 46..pos + 46 + name_len]).into_owned();
+        pos += 46 + name_len + extra_len + comment_len;
+
+        if flags & 1 != 0 {
+            bail!("Encrypted zip entries are not supported: {:?}", name);
+        }
+
+        let path = entry_path(dest, &name)?;
+        if name.ends_with('/') {
+            create_dir(&path)?;
+            continue;
+        }
+        // Symlinks created on unix systems
+        const S_IFMT: u32 = 0o170000;
+        const S_IFLNK: u32 = 0o120000;
+        if (external_attrs >> 16) & S_IFMT == S_IFLNK {
+            continue;
+        }
+
+        check_len(data, local_pos, 30)?;
+        if read_u32(data, local_pos) != ZIP_LOCAL_HEADER {
+            bail!("Invalid zip local header of {:?}", name);
+        }
+        let data_pos = local_pos
+            + 30
+            + read_u16(data, local_pos + 26) as usize
+            + read_u16(data, local_pos + 28) as usize;
+        check_len(data, data_pos, compressed_size)?;
+        let compressed = &data[data_pos..data_pos + compressed_size];
+        total_size = total_size.saturating_add(size);
+        if total_size > MAX_SIZE {
+            return Err(too_large_error());
+        }
+
+        let content = match method {
+            0 => compressed.to_vec(),
+            8 => {
+                let mut content = Vec::with_capacity(size);
+                // One more byte than the declared size is enough to tell that it's wrong
+                DeflateDecoder::new(compressed)
+                    .take(size as u64 + 1)
+                    .read_to_end(&mut content)
+                    .with_context(|| format!("Could not decompress {:?}", name))?;
+                content
+            }
+            _ => bail!(
+                "Unsupported zip compression method {} of {:?}, only stored and deflated entries are supported",
+                method,
+                name
+            ),
+        };
+
+        let mut actual_crc = Crc::new();
+        actual_crc.update(&content);
+        if content.len() != size || actual_crc.sum() != crc {
+            bail!("Corrupted zip entry {:?}", name);
+        }
+
+        write_file(&path, &content)?;
+    }
+
+    Ok(())
+}
+
+fn is_tar(data: &[u8]) -> bool {
+    data.len() >= TAR_BLOCK && &data[257..262] == b"ustar"
+}
+
+/// Parses a NUL or space terminated octal number of a tar header.
+fn tar_octal(field: &[u8]) -> Result<usize> {
+    let digits = field
+        .iter()
+        .skip_while(|&&b| b == b' ')
+        .take_while(|&&b| b != 0 && b != b' ');
+    let mut value: usize = 0;
+    for &digit in digits {
+        if !(b'0'..=b'7').contains(&digit) {
+            bail!("Invalid number in tar header");
+        }
+        value = value
+            .checked_mul(8)
+            .and_then(|value| value.checked_add((digit - b'0') as usize))
+            .ok_or_else(|| anyhow!("Invalid number in tar header"))?;
+    }
+    Ok(value)
+}
+
+fn tar_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Finds the `path` record of pax extended header data.
+fn pax_path(data: &[u8]) -> Option<String> {
+    let data = String::from_utf8_lossy(data);
+    data.lines()
+        .filter_map(|line| line.split_once(' ')?.1.split_once('='))
+        .find(|(key, _)| *key == "path")
+        .map(|(_, value)| value.to_string())
+}
+
+fn extract_tar(data: &[u8], dest: &Path) -> Result<()> {
+    let mut pos = 0;
+    // Name of the next entry given by a GNU long name or a pax header
+    let mut long_name: Option<String> = None;
+
+    while pos + TAR_BLOCK <= data.len() {
+        let header = &data[pos..pos + TAR_BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let size = tar_octal(&header[124..136])?;
+        let typeflag = header[156];
+        let content_pos = pos + TAR_BLOCK;
+        check_len(data, content_pos, size)?;
+        let content = &data[content_pos..content_pos + size];
+        pos = content_pos + (size + TAR_BLOCK - 1) / TAR_BLOCK * TAR_BLOCK;
+
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => {
+                let name = tar_str(&header[0..100]);
+                let prefix = tar_str(&header[345..500]);
+                if is_tar(header) && !prefix.is_empty() {
+                    format!("{}/{}", prefix, name)
+                } else {
+                    name
+                }
+            }
+        };
+
+        match typeflag {
+            b'L' => long_name = Some(tar_str(content)),
+            b'x' => long_name = pax_path(content),
+            b'0' | 0 | b'7' => write_file(&entry_path(dest, &name)?, content)?,
+            b'5' => create_dir(&entry_path(dest, &name)?)?,
+            _ => {} // Links, devices, global pax headers, etc. are skipped
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a zip file in memory, entries are added in order, see `RPub`.
+#[derive(Default, Debug)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    central: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    /// MS-DOS date of entries, 1980-01-01, the earliest one.
+    const DOS_DATE: u16 = (1 << 5) | 1;
+    /// Flag of entry names encoded in UTF-8.
+    const FLAG_UTF8: u16 = 1 << 11;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file entry, `deflate` compresses the `content`, otherwise it's stored as is.
+    pub fn add(&mut self, name: &str, content: &[u8], deflate: bool) -> Result<()> {
+        let mut crc = Crc::new();
+        crc.update(content);
+        let compressed;
+        let (method, data): (u16, &[u8]) = if deflate {
+            let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+            encoder.write_all(content)?;
+            compressed = encoder.finish()?;
+            (8, &compressed)
+        } else {
+            (0, content)
+        };
+        if self.entries == u16::MAX || self.data.len() + data.len() > u32::MAX as usize {
+            bail!("Zip file too large: {:?}", name);
+        }
+
+        let mut common = vec![];
+        common.extend_from_slice(&20u16.to_le_bytes()); // Version needed
+        common.extend_from_slice(&Self::FLAG_UTF8.to_le_bytes());
+        common.extend_from_slice(&method.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // Time
+        common.extend_from_slice(&Self::DOS_DATE.to_le_bytes());
+        common.extend_from_slice(&crc.sum().to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // Extra length
+
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(&ZIP_LOCAL_HEADER.to_le_bytes());
+        self.data.extend_from_slice(&common);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(data);
+
+        self.central
+            .extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+        self.central.extend_from_slice(&20u16.to_le_bytes()); // Version made by
+        self.central.extend_from_slice(&common);
+        self.central.extend_from_slice(&[0; 6]); // Comment length, disk, internal attributes
+        self.central.extend_from_slice(&0u32.to_le_bytes()); // External attributes
+        self.central.extend_from_slice(&offset.to_le_bytes());
+        self.central.extend_from_slice(name.as_bytes());
+        self.entries += 1;
+
+        Ok(())
+    }
+
+    /// Returns the content of the zip file.
+    pub fn finish(self) -> Vec<u8> {
+        let Self {
+            mut data,
+            central,
+            entries,
+        } = self;
+
+        let central_offset = data.len() as u32;
+        data.extend_from_slice(&central);
+        data.extend_from_slice(&ZIP_END_OF_CENTRAL_DIR.to_le_bytes());
+        data.extend_from_slice(&[0; 4]); // Disk numbers
+        data.extend_from_slice(&entries.to_le_bytes());
+        data.extend_from_slice(&entries.to_le_bytes());
+        data.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        data.extend_from_slice(&central_offset.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes()); // Comment length
+        data
+    }
+}
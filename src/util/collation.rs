@@ -0,0 +1,217 @@
+//! Locale-aware string collation, used for sorting song titles.
+
+use std::cmp::Ordering;
+
+use deunicode::deunicode_char;
+use lexical_sort::lexical_cmp;
+
+/// Letters or digraphs sorted as separate letters in a language, see `TAILORINGS`.
+///
+/// Each entry is the (lowercase) sequence, the letter after which it sorts, and its rank,
+/// entries sorting after the same letter are ordered by rank and equal rank means equal letters.
+type Tailoring = &'static [(&'static str, char, u32)];
+
+/// Languages whose alphabetical order differs from plain transliteration to latin letters,
+/// based on the Unicode CLDR collation rules.
+static TAILORINGS: &[(&str, Tailoring)] = &[
+    (
+        "cs",
+        &[
+            ("č", 'c', 1),
+            ("ch", 'h', 1),
+            ("ř", 'r', 1),
+            ("š", 's', 1),
+            ("ž", 'z', 1),
+        ],
+    ),
+    (
+        "sk",
+        &[
+            ("ä", 'a', 1),
+            ("č", 'c', 1),
+            ("ch", 'h', 1),
+            ("ô", 'o', 1),
+            ("ř", 'r', 1),
+            ("š", 's', 1),
+            ("ž", 'z', 1),
+        ],
+    ),
+    (
+        "pl",
+        &[
+            ("ą", 'a', 1),
+            ("ć", 'c', 1),
+            ("ę", 'e', 1),
+            ("ł", 'l', 1),
+            ("ń", 'n', 1),
+            ("ó", 'o', 1),
+            ("ś", 's', 1),
+            ("ź", 'z', 1),
+            ("ż", 'z', 2),
+        ],
+    ),
+    ("es", &[("ñ", 'n', 1)]),
+    (
+        "sv",
+        &[
+            ("å", 'z', 1),
+            ("ä", 'z', 2),
+            ("æ", 'z', 2),
+            ("ö", 'z', 3),
+            ("ø", 'z', 3),
+        ],
+    ),
+    (
+        "fi",
+        &[
+            ("å", 'z', 1),
+            ("ä", 'z', 2),
+            ("æ", 'z', 2),
+            ("ö", 'z', 3),
+            ("ø", 'z', 3),
+        ],
+    ),
+    ("da", NORWEGIAN),
+    ("nb", NORWEGIAN),
+    ("nn", NORWEGIAN),
+    ("no", NORWEGIAN),
+    (
+        "tr",
+        &[
+            ("ç", 'c', 1),
+            ("ğ", 'g', 1),
+            ("ö", 'o', 1),
+            ("ş", 's', 1),
+            ("ü", 'u', 1),
+        ],
+    ),
+];
+
+/// Danish and Norwegian share the same rules.
+const NORWEGIAN: Tailoring = &[
+    ("æ", 'z', 1),
+    ("ä", 'z', 1),
+    ("ø", 'z', 2),
+    ("ö", 'z', 2),
+    ("å", 'z', 3),
+    ("aa", 'z', 3),
+];
+
+/// A collation element, numbers sort before letters and are compared by value.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Element<'a> {
+    /// Digits without leading zeros, compared by length first.
+    Number(usize, &'a str),
+    Letter(u32),
+}
+
+/// Compares strings according to the rules of a language.
+///
+/// Without a locale or for languages without special rules, this is the same
+/// as the lexical order used elsewhere in bard, see `lexical_cmp()`: Letters with diacritics sort
+/// together with base latin letters, case is ignored, and numbers are compared by value.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Collator {
+    tailoring: Tailoring,
+}
+
+impl Collator {
+    /// Collator for `locale` such as `"cs"` or `"sv-SE"`, only the language part is used.
+    pub fn new(locale: Option<&str>) -> Self {
+        let lang = locale
+            .and_then(|locale| locale.split(['-', '_']).next())
+            .map(str::to_lowercase);
+        let tailoring = TAILORINGS
+            .iter()
+            .find(|(l, _)| Some(*l) == lang.as_deref())
+            .map_or(&[][..], |(_, tailoring)| *tailoring);
+
+        Self { tailoring }
+    }
+
+    pub fn compare(&self, lhs: &str, rhs: &str) -> Ordering {
+        if self.tailoring.is_empty() {
+            return lexical_cmp(lhs, rhs);
+        }
+
+        let (lhs_lower, rhs_lower) = (lhs.to_lowercase(), rhs.to_lowercase());
+        self.key(&lhs_lower)
+            .cmp(&self.key(&rhs_lower))
+            .then_with(|| lexical_cmp(lhs, rhs))
+    }
+
+    /// Collation key of a lowercase string.
+    fn key<'a>(&self, mut s: &'a str) -> Vec<Element<'a>> {
+        let letter = |c: char, rank: u32| Element::Letter(c as u32 * 8 + rank);
+
+        let mut key = Vec::with_capacity(s.len());
+        while let Some(c) = s.chars().next() {
+            if let Some((seq, after, rank)) = self
+                .tailoring
+                .iter()
+                .filter(|(seq, _, _)| s.starts_with(seq))
+                .max_by_key(|(seq, _, _)| seq.len())
+            {
+                key.push(letter(*after, *rank));
+                s = &s[seq.len()..];
+            } else if c.is_ascii_digit() {
+                let len = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+                let digits = s[..len].trim_start_matches('0');
+                key.push(Element::Number(digits.len(), digits));
+                s = &s[len..];
+            } else {
+                let latin = deunicode_char(c).unwrap_or("");
+                key.extend(
+                    latin
+                        .chars()
+                        .filter(char::is_ascii_alphanumeric)
+                        .map(|c| letter(c.to_ascii_lowercase(), 0)),
+                );
+                s = &s[c.len_utf8()..];
+            }
+        }
+
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(locale: Option<&str>, words: &[&'static str]) -> Vec<&'static str> {
+        let collator = Collator::new(locale);
+        let mut words = words.to_vec();
+        words.sort_by(|lhs, rhs| collator.compare(lhs, rhs));
+        words
+    }
+
+    #[test]
+    fn collation() {
+        let words = ["Zorro", "Ángel", "angel", "Hora", "Chata", "Cesta", "Čaj"];
+        assert_eq!(
+            sorted(None, &words),
+            ["angel", "Ángel", "Čaj", "Cesta", "Chata", "Hora", "Zorro"]
+        );
+        assert_eq!(
+            sorted(Some("cs-CZ"), &words),
+            ["angel", "Ángel", "Cesta", "Čaj", "Hora", "Chata", "Zorro"]
+        );
+
+        let words = ["Öl", "Zebra", "Åsa", "Äpple", "Ost"];
+        assert_eq!(
+            sorted(Some("en"), &words),
+            ["Äpple", "Åsa", "Öl", "Ost", "Zebra"]
+        );
+        assert_eq!(
+            sorted(Some("sv"), &words),
+            ["Ost", "Zebra", "Åsa", "Äpple", "Öl"]
+        );
+
+        let words = ["Song 10", "Song 9", "Straße", "Strasse 2"];
+        assert_eq!(
+            sorted(Some("pl"), &words),
+            ["Song 9", "Song 10", "Straße", "Strasse 2"]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Rendering of LilyPond snippets to images, see `Book::render_lilypond()`.
+//!
+//! Snippets are rendered by the `lilypond` program, or the one set by the `BARD_LILYPOND`
+//! env var, into PNG files named by a hash of the snippet, so that unchanged snippets
+//! are not rendered again on rebuilds.
+
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::error::Error as StdError;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::app::{App, DeadlineError};
+use crate::prelude::*;
+use crate::util::{move_file, TempPath};
+
+/// Name of the directory in the output directory where the images are placed.
+pub const DIR: &str = "lilypond";
+/// Resolution of the images, matches the default `dpi` of PDF outputs.
+const RESOLUTION: u32 = 144;
+
+/// Document the snippet is placed in, without page decorations and indentation.
+const PREAMBLE: &str = r#"\header { tagline = ##f }
+\paper { indent = 0\mm }
+"#;
+
+/// Returned by `render_png()` when the LilyPond program could not be run.
+#[derive(Debug)]
+pub struct LilypondNotFoundError {
+    program: OsString,
+}
+
+impl fmt::Display for LilypondNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "LilyPond program {:?} could not be run, LilyPond snippets are left out.\nHint: Install LilyPond, see https://lilypond.org, or set the BARD_LILYPOND environment variable to the path of the lilypond program.",
+            self.program
+        )
+    }
+}
+
+impl StdError for LilypondNotFoundError {}
+
+fn program() -> OsString {
+    env::var_os("BARD_LILYPOND").unwrap_or_else(|| "lilypond".into())
+}
+
+/// Renders the LilyPond `source` to a PNG file in the `DIR` directory of `output_dir`,
+/// returns its path relative to `output_dir`. If the file exists already, it's reused.
+///
+/// LilyPond is stopped if it doesn't finish within `timeout`, the same limit as TeX runs.
+pub fn render_png(
+    app: &App,
+    source: &str,
+    output_dir: &Path,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    let mut hasher = DefaultHasher::new();
+    (source, env!("CARGO_PKG_VERSION")).hash(&mut hasher);
+    let name = format!("{:016x}", hasher.finish());
+
+    let path = format!("{}/{}.png", DIR, name);
+    let dir = output_dir.join(DIR);
+    let png = output_dir.join(&path);
+    if png.exists() {
+        return Ok(path);
+    }
+
+    fs::create_dir_all(&dir).with_context(|| format!("Could not create directory {:?}", dir))?;
+    let ly = TempPath::new_file(dir.join(format!("{}.ly", name)), true);
+    fs::write(&ly, format!("{}{}", PREAMBLE, source))
+        .with_context(|| format!("Could not write file {:?}", &*ly))?;
+    // LilyPond's messages go to a file rather than a pipe, which could fill up while waiting for it
+    let log = TempPath::new_file(dir.join(format!("{}.log", name)), true);
+    let log_file =
+        File::create(&log).with_context(|| format!("Could not create file {:?}", &*log))?;
+
+    let program = program();
+    let child = Command::new(&program)
+        .current_dir(&dir)
+        .arg("--png")
+        .arg("-dcrop")
+        .arg(format!("-dresolution={}", RESOLUTION))
+        .arg("-o")
+        .arg(&name)
+        .arg(&*ly)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log_file)
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(LilypondNotFoundError { program }.into())
+        }
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Could not run LilyPond program {:?}", program))
+        }
+    };
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = match app.child_wait(&mut child, deadline) {
+        Ok(status) => status,
+        Err(err) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return match timeout {
+                Some(timeout) if err.is::<DeadlineError>() => bail!(
+                    "LilyPond did not finish within {}s and was stopped.\nHint: If the snippet just takes long to render, raise the limit with the tex_timeout setting in bard.toml, eg. tex_timeout = 900",
+                    timeout.as_secs()
+                ),
+                _ => Err(err),
+            };
+        }
+    };
+    if !status.success() {
+        bail!(
+            "LilyPond failed with {}:\n{}",
+            status,
+            fs::read_to_string(&log).unwrap_or_default().trim_end()
+        );
+    }
+
+    // The cropped image is only written by LilyPond versions supporting -dcrop
+    let cropped = dir.join(format!("{}.cropped.png", name));
+    if cropped.exists() {
+        move_file(&cropped, &png)?;
+    }
+    if !png.exists() {
+        bail!("LilyPond did not produce the image file {:?}", png);
+    }
+
+    Ok(path)
+}
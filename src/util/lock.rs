@@ -0,0 +1,64 @@
+//! Advisory file locks, used to prevent concurrent builds of a project.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+
+use crate::prelude::*;
+
+/// An exclusive advisory lock on a file, held until the value is dropped.
+///
+/// The lock is tied to the open file, so the OS releases it when the process exits,
+/// including on crashes. The lock file itself is left in place.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Locks the file at `path`, creating it if it doesn't exist.
+    /// Returns `None` if the lock is held by someone else.
+    pub fn try_lock(path: &Path) -> io::Result<Option<Self>> {
+        Ok(try_lock_file(path)?.map(|file| Self { _file: file }))
+    }
+}
+
+#[cfg(unix)]
+fn try_lock_file(path: &Path) -> io::Result<Option<File>> {
+    use std::os::unix::io::AsRawFd;
+
+    use nix::errno::Errno;
+    use nix::fcntl::{flock, FlockArg};
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+        Ok(()) => Ok(Some(file)),
+        Err(Errno::EWOULDBLOCK) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(windows)]
+fn try_lock_file(path: &Path) -> io::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+
+    // Opening the file with no sharing allowed fails while anyone else has it open.
+    let res = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .share_mode(0)
+        .open(path);
+    match res {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
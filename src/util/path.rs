@@ -1,5 +1,8 @@
 use std::ffi::{OsStr, OsString};
-use std::{fs, io, iter, ops};
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Duration;
+use std::{fs, io, iter, ops, thread};
 
 use crate::prelude::*;
 
@@ -49,6 +52,93 @@ impl PathBufExt for PathBuf {
     }
 }
 
+/// Copies the content of the `src` directory into `dest` recursively, overwriting existing files.
+pub fn copy_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+// Atomic file writing
+
+/// Write a file atomically, ie. `path` either keeps its previous content or gets all of the new content,
+/// even if writing fails or bard is killed.
+///
+/// The content is written by `write` to a temporary file in the same directory, which then replaces `path`.
+pub fn write_atomic<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let rand: String = iter::repeat_with(fastrand::alphanumeric)
+        .take(TempPath::RAND_CHARS as usize)
+        .collect();
+    let tmp = TempPath::new_file(dir.join(format!(".{}.{}.tmp", name, rand)), true);
+
+    let mut file = File::create(&tmp).map(BufWriter::new)?;
+    write(&mut file)?;
+    file.into_inner()
+        .map_err(io::IntoInnerError::into_error)?
+        .sync_all()?;
+    replace_file(&tmp, path)
+}
+
+/// Rename `from` over `to`, replacing it if it exists.
+///
+/// On Windows this fails while `to` is open in another program, such as a PDF viewer.
+/// This is often only momentary (eg. the viewer reloading the file), so the rename is retried a few times.
+fn replace_file(from: &Path, to: &Path) -> Result<()> {
+    const RETRIES: u32 = if cfg!(windows) { 10 } else { 0 };
+    const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+    let mut retries = RETRIES;
+    loop {
+        match fs::rename(from, to) {
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied && retries > 0 => {
+                retries -= 1;
+                thread::sleep(RETRY_DELAY);
+            }
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied && cfg!(windows) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "Could not replace file {:?}, is it open in another program?",
+                        to
+                    )
+                })
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Move a file like `fs::rename()`, but also across filesystems, in which case the file
+/// is copied using `write_atomic()` and then removed.
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        // The target may be locked on Windows, retry and report that
+        Err(err) if err.kind() == io::ErrorKind::PermissionDenied => return replace_file(from, to),
+        Err(_) => {}
+    }
+
+    write_atomic(to, |out| {
+        io::copy(&mut File::open(from)?, out)?;
+        Ok(())
+    })?;
+    fs::remove_file(from)?;
+    Ok(())
+}
+
 // TempPath
 
 #[derive(Clone, Copy, Debug)]
@@ -109,6 +199,27 @@ impl TempPath {
         );
     }
 
+    /// Returns `true` if `path` is a directory named like those made by `make_temp_dir()` with `prefix`.
+    pub fn is_temp_dir(path: &Path, prefix: &Path) -> bool {
+        if path.parent() != prefix.parent() || !path.is_dir() {
+            return false;
+        }
+
+        let (name, prefix) = match (
+            path.file_name().and_then(OsStr::to_str),
+            prefix.file_name().and_then(OsStr::to_str),
+        ) {
+            (Some(name), Some(prefix)) => (name, prefix),
+            _ => return false,
+        };
+        name.strip_prefix(prefix)
+            .and_then(|suffix| suffix.strip_prefix('.'))
+            .map_or(false, |rand| {
+                rand.len() == Self::RAND_CHARS as usize
+                    && rand.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+    }
+
     fn create_dir(path: impl AsRef<OsStr>) -> Result<bool> {
         let path = Path::new(path.as_ref());
         match fs::create_dir(path) {
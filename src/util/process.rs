@@ -1,6 +1,7 @@
 use std::{
     io, mem,
     process::{ChildStderr, ChildStdout, ExitStatus},
+    time::Instant,
 };
 
 #[cfg(unix)]
@@ -58,8 +59,13 @@ impl ProcessLines {
         }
     }
 
-    pub fn read_line(&mut self, interrupt: InterruptFlag) -> Result<Option<Vec<u8>>> {
-        let res = self.inner.read_line(interrupt);
+    /// Reads the next line from either pipe, fails with `DeadlineError` once the `deadline` passes.
+    pub fn read_line(
+        &mut self,
+        interrupt: InterruptFlag,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<u8>>> {
+        let res = self.inner.read_line(interrupt, deadline);
         if let Ok(Some(line)) = res.as_ref() {
             self.lines.push(line.clone());
         }
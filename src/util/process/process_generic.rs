@@ -4,8 +4,9 @@ use std::io;
 use std::process::{ChildStderr, ChildStdout};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-use crate::app::InterruptFlag;
+use crate::app::{DeadlineError, InterruptFlag};
 use crate::prelude::*;
 
 use super::BinaryLines;
@@ -39,7 +40,23 @@ impl ProcessLines {
         Self { rx }
     }
 
-    pub fn read_line(&mut self, interrupt: InterruptFlag) -> Result<Option<Vec<u8>>> {
-        Ok(interrupt.channel_recv(&self.rx)?.transpose()?)
+    pub fn read_line(
+        &mut self,
+        interrupt: InterruptFlag,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<u8>>> {
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => return Ok(interrupt.channel_recv(&self.rx)?.transpose()?),
+        };
+
+        let left = deadline.saturating_duration_since(Instant::now());
+        match interrupt.channel_recv_timeout(&self.rx, left)? {
+            Some(line) => Ok(Some(line?)),
+            None => {
+                DeadlineError::check(Some(deadline))?;
+                Ok(None)
+            }
+        }
     }
 }
@@ -3,11 +3,12 @@
 use std::io;
 use std::os::unix::io::{AsRawFd, RawFd};
 use std::process::{ChildStderr, ChildStdout};
+use std::time::Instant;
 
 use nix::errno::Errno;
 use nix::poll::{self, PollFd, PollFlags};
 
-use crate::app::InterruptFlag;
+use crate::app::{DeadlineError, InterruptFlag};
 use crate::prelude::*;
 
 use super::BinaryLines;
@@ -45,7 +46,11 @@ impl ProcessLines {
         }
     }
 
-    pub fn read_line(&mut self, interrupt: InterruptFlag) -> Result<Option<Vec<u8>>> {
+    pub fn read_line(
+        &mut self,
+        interrupt: InterruptFlag,
+        deadline: Option<Instant>,
+    ) -> Result<Option<Vec<u8>>> {
         loop {
             if self.stdout.eof() && self.stderr.eof() {
                 return Ok(None);
@@ -57,7 +62,8 @@ impl ProcessLines {
             let mut fds = [p_stdout, p_stderr];
 
             while !poll(&mut fds)? {
-                interrupt.check_interrupted()?
+                interrupt.check_interrupted()?;
+                DeadlineError::check(deadline)?;
             }
 
             let [p_stdout, p_stderr] = fds;
@@ -0,0 +1,816 @@
+//! A minimal QR code encoder, used to render song links as images, see `Output::qr_codes`.
+//!
+//! Only the byte mode and the medium error correction level are supported,
+//! with versions 1 to 10, ie. up to 213 bytes of data, which is plenty for URLs.
+//! The implementation follows ISO/IEC 18004.
+
+use std::io::{self, Write};
+
+use image::{GrayImage, ImageOutputFormat, Luma};
+
+use crate::prelude::*;
+use crate::util::write_atomic;
+
+const MAX_VERSION: usize = 10;
+/// Error correction codewords per block at the medium level, indexed by version.
+const ECC_PER_BLOCK: [usize; MAX_VERSION + 1] = [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+/// Number of error correction blocks at the medium level, indexed by version.
+const NUM_BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+/// Format bits of the medium error correction level.
+const ECC_FORMAT_BITS: u32 = 0;
+
+/// Size of a module in pixels when written as an image.
+const MODULE_PX: u32 = 4;
+/// Width of the quiet zone around the code in modules.
+const QUIET_ZONE: u32 = 4;
+
+#[derive(Clone, Debug)]
+pub struct QrCode {
+    version: usize,
+    size: usize,
+    /// Modules in row-major order, `true` is dark.
+    modules: Vec<bool>,
+    /// Marks modules of function patterns, which are not masked.
+    function: Vec<bool>,
+    mask: u8,
+}
+
+impl QrCode {
+    pub fn encode(data: &[u8]) -> Result<Self> {
+        let version = (1..=MAX_VERSION)
+            .find(|&ver| data_bits(ver, data.len()) <= data_codewords(ver) * 8)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Data too long for a QR code: {} bytes, at most {} bytes are supported.",
+                    data.len(),
+                    max_data_len()
+                )
+            })?;
+
+        let codewords = add_ecc_and_interleave(version, &data_codewords_of(version, data));
+
+        let size = version * 4 + 17;
+        let mut qr = Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+            mask: 0,
+        };
+        qr.draw_function_patterns();
+        qr.draw_codewords(&codewords);
+
+        // Pick the mask with the lowest penalty score
+        let mut best = (i32::MAX, 0);
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            qr.apply_mask(mask); // Undoes the mask, it's a XOR
+        }
+        qr.mask = best.1;
+        qr.apply_mask(qr.mask);
+        qr.draw_format_bits(qr.mask);
+
+        Ok(qr)
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    /// Width and height in modules.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Whether the module at column `x` and row `y` is dark.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    /// Size of the image written by `write_png()` in pixels, the image is square.
+    pub fn image_size(&self) -> u32 {
+        (self.size as u32 + 2 * QUIET_ZONE) * MODULE_PX
+    }
+
+    /// The code as a PNG image with a quiet zone, see `image_size()`.
+    pub fn png(&self) -> Result<Vec<u8>> {
+        let px = self.image_size();
+        let img = GrayImage::from_fn(px, px, |x, y| {
+            let x = (x / MODULE_PX).checked_sub(QUIET_ZONE);
+            let y = (y / MODULE_PX).checked_sub(QUIET_ZONE);
+            let dark = match (x, y) {
+                (Some(x), Some(y)) if (x as usize) < self.size && (y as usize) < self.size => {
+                    self.get(x as usize, y as usize)
+                }
+                _ => false,
+            };
+            Luma([if dark { 0 } else { 255 }])
+        });
+
+        let mut buffer = io::Cursor::new(vec![]);
+        img.write_to(&mut buffer, ImageOutputFormat::Png)?;
+        Ok(buffer.into_inner())
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        write_atomic(path, |f| {
+            f.write_all(&self.png()?)?;
+            Ok(())
+        })
+        .with_context(|| format!("Could not write QR code image {:?}", path))
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size as i32 - 4, 3);
+        self.draw_finder(3, size as i32 - 4);
+
+        let align = alignment_positions(self.version);
+        let last = align.len().saturating_sub(1);
+        for (i, &x) in align.iter().enumerate() {
+            for (j, &y) in align.iter().enumerate() {
+                // Alignment patterns don't overlap the finders
+                let at_finder = i == 0 && (j == 0 || j == last) || i == last && j == 0;
+                if !at_finder {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Reserve the format bits area, the bits are drawn once the mask is known
+        self.draw_format_bits(0);
+        self.draw_version_bits();
+    }
+
+    /// Draws a finder pattern centered at `x`, `y` with its separator.
+    fn draw_finder(&mut self, x: i32, y: i32) {
+        for dy in -4..=4 {
+            for dx in -4..=4 {
+                let (xx, yy) = (x + dx, y + dy);
+                if (0..self.size as i32).contains(&xx) && (0..self.size as i32).contains(&yy) {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(xx as usize, yy as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2i32..=2 {
+            for dx in -2i32..=2 {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as i32 + dx) as usize, (y as i32 + dy) as usize, dark);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        let bits = format_bits(mask);
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // Around the top left finder
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // Along the other two finders
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // The dark module
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version_bits(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+
+        let bits = version_bits(self.version);
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    /// Places the `codewords` in the zig-zag pattern of two-module wide columns
+    /// going from the bottom right corner, skipping function patterns.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let num_bits = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size as i32 - 1;
+        while right >= 1 {
+            // Skip the vertical timing pattern
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = right as usize - j;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    if !self.function[y * size + x] && i < num_bits {
+                        self.modules[y * size + x] = (codewords[i / 8] >> (7 - i % 8)) & 1 != 0;
+                        i += 1;
+                    }
+                    // Remainder bits are left light
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    7 => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                    _ => unreachable!("Invalid QR mask: {}", mask),
+                };
+                let idx = y * self.size + x;
+                if invert && !self.function[idx] {
+                    self.modules[idx] = !self.modules[idx];
+                }
+            }
+        }
+    }
+
+    /// Penalty score of the current modules as per the mask evaluation rules of the standard.
+    fn penalty(&self) -> i32 {
+        let size = self.size;
+        let mut penalty = 0;
+
+        let rows = (0..size).map(|y| (0..size).map(|x| self.get(x, y)).collect::<Vec<_>>());
+        let cols = (0..size).map(|x| (0..size).map(|y| self.get(x, y)).collect::<Vec<_>>());
+        for line in rows.chain(cols) {
+            // Runs of five or more modules of the same color
+            let mut run = 1;
+            for i in 1..=size {
+                if i < size && line[i] == line[i - 1] {
+                    run += 1;
+                } else {
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+            }
+
+            // Patterns looking like a finder, 1:1:3:1:1 with four light modules on either side
+            const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+            let light = |i: i32| i < 0 || i >= size as i32 || !line[i as usize];
+            for start in 0..=size.saturating_sub(7) {
+                if line[start..start + 7] != FINDER {
+                    continue;
+                }
+                let start = start as i32;
+                if (1..=4).all(|d| light(start - d)) || (1..=4).all(|d| light(start + 6 + d)) {
+                    penalty += 40;
+                }
+            }
+        }
+
+        // Blocks of 2x2 modules of the same color
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.get(x, y);
+                if dark == self.get(x + 1, y)
+                    && dark == self.get(x, y + 1)
+                    && dark == self.get(x + 1, y + 1)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+
+        // Balance of dark and light modules, 10 points for each 5 % away from 50 %
+        let total = (size * size) as i32;
+        let dark = self.modules.iter().filter(|&&dark| dark).count() as i32;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        penalty + 10 * k
+    }
+}
+
+/// Maximum number of data bytes that can be encoded.
+pub fn max_data_len() -> usize {
+    (data_codewords(MAX_VERSION) * 8 - 4 - char_count_bits(MAX_VERSION)) / 8
+}
+
+fn char_count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Number of bits of the mode indicator, character count, and data.
+fn data_bits(version: usize, len: usize) -> usize {
+    4 + char_count_bits(version) + len * 8
+}
+
+/// Number of modules available for data and error correction codewords.
+fn raw_data_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let num_align = version / 7 + 2;
+        result -= (25 * num_align - 10) * num_align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_data_modules(version) / 8 - ECC_PER_BLOCK[version] * NUM_BLOCKS[version]
+}
+
+/// Centers of alignment patterns, both as rows and columns.
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return vec![];
+    }
+
+    let num_align = version / 7 + 2;
+    let size = version * 4 + 17;
+    let step = (version * 8 + num_align * 3 + 5) / (num_align * 4 - 4) * 2;
+    let mut result: Vec<_> = (0..num_align - 1).map(|i| size - 7 - i * step).collect();
+    result.push(6);
+    result.reverse();
+    result
+}
+
+/// Format information of the medium error correction level and `mask`, with its BCH code.
+fn format_bits(mask: u8) -> u32 {
+    let data = ECC_FORMAT_BITS << 3 | mask as u32;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// Version information with its BCH code, used from version 7 onwards.
+fn version_bits(version: usize) -> u32 {
+    let data = version as u32;
+    let mut rem = data;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1f25);
+    }
+    data << 12 | rem
+}
+
+/// Encodes `data` in byte mode with a terminator and padding to fill the version's data capacity.
+fn data_codewords_of(version: usize, data: &[u8]) -> Vec<u8> {
+    let capacity = data_codewords(version) * 8;
+    let mut bits = BitBuffer::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, char_count_bits(version));
+    for &byte in data {
+        bits.push(byte as u32, 8);
+    }
+    bits.push(0, (capacity - bits.len).min(4));
+    bits.push(0, (8 - bits.len % 8) % 8);
+
+    let mut codewords = bits.bytes;
+    for pad in [0xec, 0x11].iter().cycle() {
+        if codewords.len() >= capacity / 8 {
+            break;
+        }
+        codewords.push(*pad);
+    }
+    codewords
+}
+
+/// Splits `data` into blocks, appends their error correction codewords, and interleaves them.
+fn add_ecc_and_interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let num_blocks = NUM_BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw_codewords = raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let data_len = short_block_len - ecc_len + usize::from(i >= num_short_blocks);
+        let mut block = data[k..k + data_len].to_vec();
+        k += data_len;
+        let ecc = rs_remainder(&block, &divisor);
+        if i < num_short_blocks {
+            // Placeholder so that all blocks have the same length, skipped below
+            block.push(0);
+        }
+        block.extend(ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..=short_block_len {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_len - ecc_len || j >= num_short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// Multiplication in GF(2^8) modulo the QR code polynomial `x^8 + x^4 + x^3 + x^2 + 1`.
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= ((y as u32 >> i) & 1) * x as u32;
+    }
+    z as u8
+}
+
+/// Reed-Solomon generator polynomial of the given `degree`, without the leading term.
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+/// Error correction codewords of `data`.
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitBuffer {
+    /// Appends the `count` low bits of `value`, most significant first.
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len % 8 == 0 {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.len % 8);
+            self.len += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qr_capacity() {
+        let expected = [0, 16, 28, 44, 64, 86, 108, 124, 154, 182, 216];
+        for (version, &codewords) in expected.iter().enumerate().skip(1) {
+            assert_eq!(data_codewords(version), codewords);
+        }
+        assert_eq!(max_data_len(), 213);
+        assert_eq!(QrCode::encode(&[b'a'; 14]).unwrap().version(), 1);
+        assert_eq!(QrCode::encode(&[b'a'; 15]).unwrap().version(), 2);
+        assert_eq!(QrCode::encode(&[b'a'; 213]).unwrap().version(), 10);
+        QrCode::encode(&[b'a'; 214]).unwrap_err();
+    }
+
+    #[test]
+    fn qr_bch_codes() {
+        // Values from the tables in the standard
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(7), 0b100101010100000);
+        assert_eq!(version_bits(7), 0x07c94);
+        assert_eq!(version_bits(10), 0x0a4d3);
+    }
+
+    #[test]
+    fn qr_reed_solomon() {
+        // The HELLO WORLD example in version 1-M
+        let data = [
+            0x20, 0x5b, 0x0b, 0x78, 0xd1, 0x72, 0xdc, 0x4d, 0x43, 0x40, 0xec, 0x11, 0xec, 0x11,
+            0xec, 0x11,
+        ];
+        let ecc = rs_remainder(&data, &rs_divisor(10));
+        assert_eq!(ecc, [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]);
+    }
+
+    #[test]
+    fn qr_structure() {
+        let qr = QrCode::encode(b"https://example.com/recordings/wild-mountain-thyme.mp3").unwrap();
+        assert_eq!(qr.version(), 4);
+        assert_eq!(qr.size(), 33);
+
+        // Finder patterns
+        let size = qr.size();
+        for &(x0, y0) in &[(0, 0), (size - 7, 0), (0, size - 7)] {
+            for i in 0..7 {
+                assert!(qr.get(x0 + i, y0));
+                assert!(qr.get(x0 + i, y0 + 6));
+                assert!(qr.get(x0, y0 + i));
+                assert!(qr.get(x0 + 6, y0 + i));
+            }
+            assert!(!qr.get(x0 + 1, y0 + 1));
+            assert!(qr.get(x0 + 3, y0 + 3));
+        }
+
+        // Timing patterns
+        for i in 8..size - 8 {
+            assert_eq!(qr.get(i, 6), i % 2 == 0);
+            assert_eq!(qr.get(6, i), i % 2 == 0);
+        }
+
+        // Both copies of the format bits decode to the chosen mask
+        let bits = format_bits(qr.mask());
+        for i in 0..8 {
+            assert_eq!(qr.get(size - 1 - i, 8), (bits >> i) & 1 != 0);
+        }
+        for i in 8..15 {
+            assert_eq!(qr.get(8, size - 15 + i), (bits >> i) & 1 != 0);
+        }
+    }
+
+    /// A decoder of undamaged, upright codes at the medium level, written from the tables of the standard
+    /// rather than the encoder's computations, so that `qr_decode()` can check the encoder against it.
+    mod decoder {
+        use super::*;
+
+        /// Format bits of the medium level with masks 0 to 7.
+        const FORMAT_M: [u32; 8] = [
+            0x5412, 0x5125, 0x5e7c, 0x5b4b, 0x45f9, 0x40ce, 0x4f97, 0x4aa0,
+        ];
+        /// Alignment pattern centers, indexed by version.
+        const ALIGNMENT: [&[usize]; 11] = [
+            &[],
+            &[],
+            &[6, 18],
+            &[6, 22],
+            &[6, 26],
+            &[6, 30],
+            &[6, 34],
+            &[6, 22, 38],
+            &[6, 24, 42],
+            &[6, 26, 46],
+            &[6, 28, 50],
+        ];
+        /// Error correction codewords per block and data codewords of each block at the medium level,
+        /// indexed by version.
+        const BLOCKS: [(usize, &[usize]); 11] = [
+            (0, &[]),
+            (10, &[16]),
+            (16, &[28]),
+            (26, &[44]),
+            (18, &[32, 32]),
+            (24, &[43, 43]),
+            (16, &[27, 27, 27, 27]),
+            (18, &[31, 31, 31, 31]),
+            (22, &[38, 38, 39, 39]),
+            (22, &[36, 36, 36, 37, 37]),
+            (26, &[43, 43, 43, 43, 44]),
+        ];
+
+        struct Grid {
+            size: usize,
+            dark: Vec<bool>,
+        }
+
+        impl Grid {
+            /// Samples the modules of a PNG image, locating the code by its top left finder pattern.
+            fn from_png(png: &[u8]) -> Grid {
+                let img = image::load_from_memory(png).unwrap().to_luma8();
+                let dark = |x: u32, y: u32| img.get_pixel(x, y).0[0] < 128;
+                let (w, h) = img.dimensions();
+                let dark_px: Vec<_> = (0..h)
+                    .flat_map(|y| (0..w).map(move |x| (x, y)))
+                    .filter(|&(x, y)| dark(x, y))
+                    .collect();
+                let (left, top) = dark_px[0];
+                let right = dark_px.iter().map(|&(x, _)| x).max().unwrap();
+
+                let finder_px = (left..w).take_while(|&x| dark(x, top)).count() as u32;
+                assert_eq!(finder_px % 7, 0);
+                let module = finder_px / 7;
+                let size = ((right - left + 1) / module) as usize;
+
+                let dark = (0..size * size)
+                    .map(|i| {
+                        let (x, y) = ((i % size) as u32, (i / size) as u32);
+                        dark(
+                            left + x * module + module / 2,
+                            top + y * module + module / 2,
+                        )
+                    })
+                    .collect();
+                Grid { size, dark }
+            }
+
+            fn get(&self, row: usize, col: usize) -> bool {
+                self.dark[row * self.size + col]
+            }
+
+            fn is_function(&self, version: usize, row: usize, col: usize) -> bool {
+                let size = self.size;
+                let near = |a: usize, b: usize| (a as i32 - b as i32).abs() <= 2;
+                let align = ALIGNMENT[version];
+                let finder =
+                    (row < 9 && (col < 9 || col >= size - 8)) || (row >= size - 8 && col < 9);
+                let alignment = align.iter().any(|&ar| {
+                    align.iter().any(|&ac| {
+                        let at_finder =
+                            (ar == 6 && (ac == 6 || ac == size - 7)) || (ar == size - 7 && ac == 6);
+                        !at_finder && near(row, ar) && near(col, ac)
+                    })
+                });
+                let version_info = version >= 7
+                    && ((row < 6 && (size - 11..size - 8).contains(&col))
+                        || (col < 6 && (size - 11..size - 8).contains(&row)));
+                finder || alignment || version_info || row == 6 || col == 6
+            }
+        }
+
+        fn bits_value(bits: impl Iterator<Item = bool>) -> u32 {
+            bits.fold(0, |acc, bit| acc << 1 | bit as u32)
+        }
+
+        pub fn decode(png: &[u8]) -> (usize, Vec<u8>) {
+            let grid = Grid::from_png(png);
+            let size = grid.size;
+            assert_eq!(size % 4, 1);
+            let version = (size - 17) / 4;
+
+            // Format bits from the most significant one, in both copies
+            let format_1 = bits_value(
+                (0..6)
+                    .map(|c| grid.get(8, c))
+                    .chain([grid.get(8, 7), grid.get(8, 8), grid.get(7, 8)])
+                    .chain((0..6).rev().map(|r| grid.get(r, 8))),
+            );
+            let format_2 = bits_value(
+                (0..7)
+                    .map(|i| grid.get(size - 1 - i, 8))
+                    .chain((0..8).map(|i| grid.get(8, size - 8 + i))),
+            );
+            assert_eq!(format_1, format_2);
+            let mask = FORMAT_M.iter().position(|&f| f == format_1).unwrap();
+
+            // Data modules in the zig-zag order, unmasked
+            let mut bits = vec![];
+            let mut col = size - 1;
+            let mut upward = true;
+            loop {
+                for i in 0..size {
+                    let row = if upward { size - 1 - i } else { i };
+                    for c in [col, col - 1] {
+                        if grid.is_function(version, row, c) {
+                            continue;
+                        }
+                        let (i, j) = (row, c);
+                        let invert = match mask {
+                            0 => (i + j) % 2 == 0,
+                            1 => i % 2 == 0,
+                            2 => j % 3 == 0,
+                            3 => (i + j) % 3 == 0,
+                            4 => (i / 2 + j / 3) % 2 == 0,
+                            5 => (i * j) % 2 + (i * j) % 3 == 0,
+                            6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+                            _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+                        };
+                        bits.push(grid.get(row, c) != invert);
+                    }
+                }
+                upward = !upward;
+                if col < 3 {
+                    break;
+                }
+                col -= 2;
+                if col == 6 {
+                    col = 5;
+                }
+            }
+            let codewords: Vec<u8> = bits
+                .chunks_exact(8)
+                .map(|byte| bits_value(byte.iter().copied()) as u8)
+                .collect();
+
+            // De-interleave the blocks and check their error correction codewords
+            let (ecc_len, data_lens) = BLOCKS[version];
+            let mut blocks: Vec<Vec<u8>> = vec![vec![]; data_lens.len()];
+            let mut codewords = codewords.into_iter();
+            for i in 0..*data_lens.iter().max().unwrap() {
+                for (block, _) in blocks.iter_mut().zip(data_lens).filter(|(_, &len)| i < len) {
+                    block.push(codewords.next().unwrap());
+                }
+            }
+            for _ in 0..ecc_len {
+                for block in blocks.iter_mut() {
+                    block.push(codewords.next().unwrap());
+                }
+            }
+            let mut root = 1;
+            for _ in 0..ecc_len {
+                for block in blocks.iter() {
+                    let syndrome = block.iter().fold(0, |acc, &c| gf_mul(acc, root) ^ c);
+                    assert_eq!(syndrome, 0);
+                }
+                root = gf_mul(root, 0x02);
+            }
+
+            // Byte mode segment
+            let data: Vec<bool> = blocks
+                .iter()
+                .zip(data_lens)
+                .flat_map(|(block, &len)| block[..len].to_vec())
+                .flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1 != 0))
+                .collect();
+            assert_eq!(bits_value(data[..4].iter().copied()), 0b0100);
+            let count_bits = if version < 10 { 8 } else { 16 };
+            let len = bits_value(data[4..4 + count_bits].iter().copied()) as usize;
+            let bytes = data[4 + count_bits..]
+                .chunks_exact(8)
+                .take(len)
+                .map(|byte| bits_value(byte.iter().copied()) as u8)
+                .collect();
+            (version, bytes)
+        }
+    }
+
+    #[test]
+    fn qr_decode() {
+        let long_link = "https://example.com/recordings/".to_string() + &"a".repeat(182);
+        let cases = [
+            ("https://a.io/", 1),
+            ("https://example.com/recordings/wild-mountain-thyme.mp3", 4),
+            (&long_link[..110], 7),
+            (&long_link[..150], 8),
+            (&long_link[..160], 9),
+            (&long_link[..], 10),
+        ];
+        for (link, version) in cases {
+            let qr = QrCode::encode(link.as_bytes()).unwrap();
+            let (decoded_version, decoded) = decoder::decode(&qr.png().unwrap());
+            assert_eq!(decoded_version, version);
+            assert_eq!(String::from_utf8(decoded).unwrap(), link);
+        }
+    }
+}
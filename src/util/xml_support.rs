@@ -24,7 +24,7 @@ pub trait XmlWrite {
         W: io::Write;
 }
 
-impl<'a, T> XmlWrite for &'a T
+impl<T> XmlWrite for &T
 where
     T: XmlWrite + ?Sized,
 {
@@ -50,16 +50,13 @@ where
 
 impl<'a, T> XmlWrite for Cow<'a, T>
 where
-    T: XmlWrite + Clone + ?Sized,
+    T: XmlWrite + ToOwned + ?Sized,
 {
     fn write<W>(&self, writer: &mut Writer<W>) -> XmlResult<()>
     where
         W: io::Write,
     {
-        match self {
-            Cow::Borrowed(b) => b.write(writer),
-            Cow::Owned(o) => o.write(writer),
-        }
+        T::write(self, writer)
     }
 }
 
@@ -285,6 +282,17 @@ where
         Ok(self)
     }
 
+    pub fn value_opt<T>(self, field: Field<&Option<T>>) -> XmlResult<Self>
+    where
+        T: XmlWrite,
+    {
+        if let Some(field) = field.transpose() {
+            self.value(field)
+        } else {
+            Ok(self)
+        }
+    }
+
     pub fn value_wrap(self, tag_name: &str, value: impl XmlWrite) -> XmlResult<Self> {
         self.writer
             .tag(tag_name)
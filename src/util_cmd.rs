@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::env;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::str::FromStr;
@@ -5,8 +7,10 @@ use std::str::FromStr;
 use regex::Regex;
 
 use crate::app::App;
+use crate::book::{self, Song};
 use crate::prelude::*;
-use crate::util::sort_lexical_by;
+use crate::project::Project;
+use crate::util::collation::Collator;
 
 #[derive(clap::Parser)]
 pub enum UtilCmd {
@@ -16,6 +20,18 @@ pub enum UtilCmd {
         regex: String,
         /// The file whose lines to sort, in-place
         file: String,
+        /// Sort according to the rules of a language, eg. "cs" or "sv-SE"
+        #[arg(long)]
+        locale: Option<String>,
+    },
+    /// Prints the chords used in the songs of the project, one per line
+    ExportChords {
+        /// Sort by the number of songs using each chord, most used first
+        #[arg(long)]
+        by_frequency: bool,
+        /// Write the list to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 }
 
@@ -24,12 +40,34 @@ impl UtilCmd {
         use UtilCmd::*;
 
         match self {
-            SortLines { regex, file } => {
-                if sort_lines(&regex, file)? == 0 {
+            SortLines {
+                regex,
+                file,
+                locale,
+            } => {
+                let collator = Collator::new(locale.as_deref());
+                if sort_lines(&regex, file, &collator)? == 0 {
                     app.warning("sort-lines: No lines matched the regex.");
                 }
                 Ok(())
             }
+            ExportChords {
+                by_frequency,
+                output,
+            } => {
+                let cwd = env::current_dir().context("Could not read current directory")?;
+                let project = Project::new(app, cwd)?;
+                let chords = export_chords(project.songs(), by_frequency);
+                match output {
+                    Some(path) => write_chords(&chords, &path),
+                    None => {
+                        for (chord, _) in &chords {
+                            println!("{}", chord);
+                        }
+                        Ok(())
+                    }
+                }
+            }
         }
     }
 }
@@ -60,7 +98,7 @@ fn line_read(mut lines: Vec<Line>, line: io::Result<String>, regex: &Regex) -> R
     Ok(lines)
 }
 
-pub fn sort_lines(regex: &str, path: impl Into<PathBuf>) -> Result<usize> {
+pub fn sort_lines(regex: &str, path: impl Into<PathBuf>, collator: &Collator) -> Result<usize> {
     let regex = Regex::from_str(regex).with_context(|| format!("Invalid regex: '{}'", regex))?;
 
     let path = path.into();
@@ -76,7 +114,9 @@ pub fn sort_lines(regex: &str, path: impl Into<PathBuf>) -> Result<usize> {
         .as_mut_slice()
         .split_mut(|line| line.key.is_none())
         .map(|slice| {
-            sort_lexical_by(slice, |line| line.key.as_ref().unwrap());
+            slice.sort_by(|lhs, rhs| {
+                collator.compare(lhs.key.as_ref().unwrap(), rhs.key.as_ref().unwrap())
+            });
             slice.len()
         })
         .sum();
@@ -92,3 +132,36 @@ pub fn sort_lines(regex: &str, path: impl Into<PathBuf>) -> Result<usize> {
 
     Ok(count)
 }
+
+/// Unique chords used in `songs` in the order of `book::chord_index()`, with the number of songs using each.
+/// With `by_frequency`, the most used chords come first.
+pub fn export_chords(songs: &[Song], by_frequency: bool) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, chord) in songs.iter().flat_map(Song::chords) {
+        *counts.entry(chord).or_default() += 1;
+    }
+
+    let mut chords: Vec<_> = book::chord_index(songs)
+        .into_iter()
+        .map(|entry| {
+            let count = counts.get(&*entry.chord).copied().unwrap_or(0);
+            (entry.chord.to_string(), count)
+        })
+        .collect();
+    if by_frequency {
+        // Stable, so chords used equally often stay in the chord index order
+        chords.sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+    }
+    chords
+}
+
+fn write_chords(chords: &[(String, usize)], path: &Path) -> Result<()> {
+    let write_err = || format!("Could not write file {:?}", path);
+    let mut file = File::create(path)
+        .map(BufWriter::new)
+        .with_context(write_err)?;
+    for (chord, _) in chords {
+        writeln!(&mut file, "{}", chord).with_context(write_err)?;
+    }
+    file.flush().with_context(write_err)
+}
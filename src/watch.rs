@@ -1,20 +1,26 @@
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver};
 use std::sync::{Arc, Barrier};
-use std::thread;
 use std::time::Duration;
 
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 
 use crate::app::{InterruptError, InterruptFlag};
 use crate::prelude::*;
-use crate::project::Project;
+use crate::project::{WatchChange, WatchSet};
 
 type NotifyResult = notify::Result<notify::Event>;
 
+/// Default of `bard watch --debounce`, in milliseconds.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
 pub struct Watch {
     watcher: RecommendedWatcher,
     evt_rx: Receiver<NotifyResult>,
+    /// Paths currently registered with the `watcher`.
+    watched: Vec<PathBuf>,
+    /// Events arriving within this time of each other are coalesced into one change.
+    debounce: Duration,
     test_barrier: Option<Arc<Barrier>>,
 }
 
@@ -39,10 +45,17 @@ impl Watch {
         Ok(Watch {
             watcher,
             evt_rx,
+            watched: vec![],
+            debounce: Duration::from_millis(DEFAULT_DEBOUNCE_MS),
             test_barrier: None,
         })
     }
 
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
     /// Create with the test sync flag on, for testing.
     pub fn with_test_sync() -> Result<(Self, WatchControl)> {
         let mut this = Self::new()?;
@@ -56,50 +69,76 @@ impl Watch {
         Ok((this, control))
     }
 
+    /// Waits until some of the files in `watch_set` change and returns their paths,
+    /// or `None` if interrupted.
+    ///
+    /// `watch_set` is updated with the changes, changes of files which are not dependencies
+    /// of the project, such as editor backup files in the songs directory, are ignored.
     pub fn watch(
         &mut self,
-        project: &Project,
+        watch_set: &mut WatchSet,
         interrupt: InterruptFlag,
     ) -> Result<Option<Vec<PathBuf>>> {
-        self.watch_files(project)?;
+        self.watch_files(watch_set)?;
 
         // Synchronize with test code, if any
         self.test_barrier.as_deref().map(Barrier::wait);
 
-        let paths = match interrupt.channel_recv(&self.evt_rx) {
-            Ok(Some(res)) => res.context("Error watching files")?.paths,
-            Ok(None) => bail!("Internal error: Channel receive failed"),
-            Err(InterruptError) => return Ok(None),
-        };
-
-        // Delaying mechanism - don't return back until we've
-        // seen no event for a timeout's duration.
-        loop {
-            thread::sleep(Duration::from_millis(250));
+        let paths = loop {
+            let mut paths = match interrupt.channel_recv(&self.evt_rx) {
+                Ok(Some(res)) => res.context("Error watching files")?.paths,
+                Ok(None) => bail!("Internal error: Channel receive failed"),
+                Err(InterruptError) => {
+                    self.unwatch_files();
+                    return Ok(None);
+                }
+            };
+
+            // Debouncing - don't return back until we've
+            // seen no event for the debounce duration.
+            loop {
+                match interrupt.channel_recv_timeout(&self.evt_rx, self.debounce) {
+                    Ok(Some(res)) => paths.extend(res.into_iter().flat_map(|evt| evt.paths)),
+                    Ok(None) => break,
+                    Err(InterruptError) => {
+                        self.unwatch_files();
+                        return Ok(None);
+                    }
+                }
+            }
 
-            if self.evt_rx.try_recv().is_ok() {
-                // Drain all immediately available evts
-                while self.evt_rx.try_recv().is_ok() {}
-            } else {
-                break;
+            paths.sort();
+            paths.dedup();
+            // If the set can't be updated, eg. due to a syntax error in bard.toml,
+            // let the rebuild report the error.
+            match watch_set.update(&paths) {
+                Ok(WatchChange::None) => {}
+                _ => break paths,
             }
-        }
+        };
 
-        self.unwatch_files(project);
+        self.unwatch_files();
         Ok(Some(paths))
     }
 
-    fn watch_files(&mut self, project: &Project) -> Result<()> {
-        project.watch_paths().try_for_each(|path| {
+    fn watch_files(&mut self, watch_set: &WatchSet) -> Result<()> {
+        let files = watch_set
+            .paths()
+            .map(|path| (path, RecursiveMode::NonRecursive));
+        let dirs = watch_set.dirs().map(|dir| (dir, RecursiveMode::Recursive));
+        for (path, mode) in files.chain(dirs) {
             self.watcher
-                .watch(path, RecursiveMode::NonRecursive)
-                .context("Error watching files")
-        })
+                .watch(path, mode)
+                .context("Error watching files")?;
+            self.watched.push(path.to_owned());
+        }
+
+        Ok(())
     }
 
-    fn unwatch_files(&mut self, project: &Project) {
-        for path in project.watch_paths() {
-            let _ = self.watcher.unwatch(path);
+    fn unwatch_files(&mut self) {
+        for path in self.watched.drain(..) {
+            let _ = self.watcher.unwatch(&path);
         }
     }
 }
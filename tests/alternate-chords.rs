@@ -0,0 +1,38 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn alternate_chords() {
+    let build = TestProject::new("alternate-chords")
+        .output("primary.json")
+        .output_toml(toml! {
+            file = "alternate.json"
+            alternate_chords = "alternate"
+        })
+        .output_toml(toml! {
+            file = "both.json"
+            alternate_chords = "both"
+        })
+        .song(
+            "song.md",
+            r#"# Song
+
+1. `[Cmaj7|C]`Hello `G`world
+"#,
+        )
+        .build()
+        .unwrap();
+
+    let primary = build.read_output("primary.json");
+    assert!(primary.contains(r#""chord": "Cmaj7""#));
+    assert!(primary.contains(r#""alternate": {"#));
+
+    let alternate = build.read_output("alternate.json");
+    assert!(alternate.contains(r#""chord": "C""#));
+    assert!(!alternate.contains("Cmaj7"));
+    assert!(alternate.contains(r#""alternate_chords": "alternate""#));
+
+    let both = build.read_output("both.json");
+    assert!(both.contains(r#""chord": "Cmaj7 (C)""#));
+    assert!(both.contains(r#""chord": "G""#));
+}
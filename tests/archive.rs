@@ -0,0 +1,123 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bard::util::archive::ZipWriter;
+
+mod util_ng;
+pub use util_ng::*;
+
+type Files = Vec<(String, Vec<u8>)>;
+
+/// Directory with the archived project and tar archives of it made with the `tar` tool,
+/// `project.tar.gz` has the project in a top-level directory, `evil.tar` has an entry at `../evil.md`.
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/test-projects/archive")
+}
+
+/// Reads `paths` of the archived project as archive entries.
+fn project_files(paths: &[&str]) -> Files {
+    let dir = fixtures().join("project");
+    paths
+        .iter()
+        .map(|path| (path.to_string(), fs::read(dir.join(path)).unwrap()))
+        .collect()
+}
+
+fn zip(files: &Files, deflate: bool) -> Vec<u8> {
+    let mut zip = ZipWriter::new();
+    for (name, content) in files {
+        zip.add(name, content, deflate).unwrap();
+    }
+    zip.finish()
+}
+
+/// A project used for its `App` and as a directory for the archives and their outputs.
+fn scratch_project(name: &str) -> TestBuild {
+    TestProject::new(name)
+        .output("songbook.json")
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn archive_make() {
+    let build = scratch_project("archive-make");
+    let dir = build.dir_output().join("archives");
+    fs::create_dir_all(&dir).unwrap();
+
+    let files = project_files(&["bard.toml", "songs/songs.md"]);
+    let archives = [
+        (
+            "project.tar",
+            fs::read(fixtures().join("project.tar")).unwrap(),
+        ),
+        (
+            "project.tar.gz",
+            fs::read(fixtures().join("project.tar.gz")).unwrap(),
+        ),
+        ("project.zip", zip(&files, false)),
+        ("project-deflated.zip", zip(&files, true)),
+    ];
+    for (name, content) in archives.iter() {
+        let archive = dir.join(name);
+        fs::write(&archive, content).unwrap();
+
+        let output_dir = dir.join(format!("{}-output", name));
+        bard::bard_make_archive_at(build.app(), &archive, &output_dir).unwrap();
+        let json = fs::read_to_string(output_dir.join("songbook.json")).unwrap();
+        assert!(json.contains("Wild Mountain Thyme"), "{}", name);
+        assert!(output_dir.join("songbook.html").exists(), "{}", name);
+        assert!(output_dir.join("split").is_dir(), "{}", name);
+
+        // Only outputs are copied, not bard's own files in the output directory
+        let mut names: Vec<_> = fs::read_dir(&output_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(
+            names,
+            ["songbook.html", "songbook.json", "split", "split.html"],
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn archive_invalid() {
+    let build = scratch_project("archive-invalid");
+    let dir = build.dir_output();
+    let make = |name: &str, content: Vec<u8>| {
+        let archive = dir.join(name);
+        fs::write(&archive, content).unwrap();
+        let err = bard::bard_make_archive_at(build.app(), &archive, &dir.join("out")).unwrap_err();
+        format!("{:?}", err)
+    };
+
+    let err = make("evil.tar", fs::read(fixtures().join("evil.tar")).unwrap());
+    assert!(err.contains("Invalid path in archive: \"../evil.md\""));
+    assert!(!dir.join("evil.md").exists());
+
+    let song = project_files(&["songs/songs.md"]);
+    let err = make("no-toml.zip", zip(&song, true));
+    assert!(err.contains("The archive doesn't contain a bard.toml file"));
+
+    let err = make("not-an-archive.txt", song[0].1.clone());
+    assert!(err.contains("Unsupported archive format"));
+
+    // Zip entries claiming a huge size aren't decompressed, nor is more than the size they claim
+    let files = project_files(&["bard.toml", "songs/songs.md"]);
+    let with_size = |size: u32| {
+        let mut zip = zip(&files, true);
+        let eocd = zip.len() - 22;
+        let central = u32::from_le_bytes(zip[eocd + 16..eocd + 20].try_into().unwrap()) as usize;
+        zip[central + 24..central + 28].copy_from_slice(&size.to_le_bytes());
+        zip
+    };
+    let err = make("bomb.zip", with_size(u32::MAX));
+    assert!(err.contains("The archive is too large"), "{}", err);
+    let err = make("wrong-size.zip", with_size(1));
+    assert!(err.contains("Corrupted zip entry \"bard.toml\""), "{}", err);
+}
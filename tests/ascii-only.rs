@@ -0,0 +1,48 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, ascii_only: bool) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "song.md",
+            indoc! {r#"
+                # "Quoted" Song
+
+                1. |: `Bb`It's a `F#m7`song -- really... :|
+                {segno} {D.S. al Coda}
+            "#},
+        )
+        .output_toml(toml! {
+            file = "songbook.json"
+            ascii_only = ascii_only
+        })
+        .settings(|toml| toml.set("unicode_accidentals", true))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn ascii_only() {
+    let build = build_with("ascii-only", true);
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""title": "\"Quoted\" Song""#));
+    assert!(json.contains(r#""chord": "Bb""#));
+    assert!(json.contains(r#""chord": "F#m7""#));
+    assert!(json.contains("It's a "));
+    assert!(json.contains("song -- really..."));
+    assert!(json.contains(r#""text": "|:""#));
+    assert!(json.contains(r#""text": "Segno""#));
+    assert!(json.contains(r#""text": "D.S. al Coda""#));
+    assert!(!json.contains("i-nav"));
+    assert!(!json.contains(['♭', '♯', '’', '“', '–', '…']));
+    assert!(json.contains(r#""ascii_only": true"#));
+}
+
+#[test]
+fn ascii_only_off() {
+    let build = build_with("ascii-only-off", false);
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""chord": "B♭""#));
+    assert!(json.contains("It’s a "));
+    assert!(json.contains("i-nav"));
+}
@@ -50,3 +50,24 @@ fn ast_version_check_example_project() {
     let build = Builder::build_with_name(ROOT / "example", "ast-version-check-example").unwrap();
     assert_project_versions(&build.project);
 }
+
+#[test]
+fn ast_deprecations() {
+    use bard::render::DEFAULT_TEMPLATES;
+
+    for template in DEFAULT_TEMPLATES {
+        assert_eq!(
+            book::version::deprecated_in(template.content).count(),
+            0,
+            "{}",
+            template.filename
+        );
+    }
+
+    let source =
+        r#"{{#*inline "i-image"}}<img src="{{ path }}" width="{{ img_w path }}">{{/inline}}"#;
+    let deprecated: Vec<_> = book::version::deprecated_in(source)
+        .map(|dep| dep.replacement)
+        .collect();
+    assert_eq!(deprecated, ["width"]);
+}
@@ -0,0 +1,66 @@
+use std::fs;
+use std::io::Write;
+
+use bard::prelude::*;
+use bard::util::{move_file, write_atomic};
+
+mod util;
+pub use util::*;
+
+#[test]
+fn failed_render_keeps_output() {
+    let app = Builder::app(false);
+    let project_dir = init_project(&app, "failed-render-keeps-output").unwrap();
+    let settings_path = project_dir.join("bard.toml");
+    let settings = fs::read_to_string(&settings_path)
+        .unwrap()
+        .replace("[[output]]\nfile = \"songbook.pdf\"\n\n", "");
+    fs::write(&settings_path, &settings).unwrap();
+
+    let project = bard::bard_make_at(&app, &project_dir).unwrap();
+    let output_dir = project.settings.dir_output().to_owned();
+    let html_path = output_dir.join("songbook.html");
+    let html = fs::read_to_string(&html_path).unwrap();
+
+    // A broken template fails the build, the previous output has to be left intact
+    let template_path = project_dir.join("templates").join("broken.hbs");
+    fs::create_dir_all(template_path.parent().unwrap()).unwrap();
+    fs::write(&template_path, "{{#each songs}}").unwrap();
+    let settings = settings.replace(
+        "file = \"songbook.html\"",
+        "file = \"songbook.html\"\ntemplate = \"broken.hbs\"",
+    );
+    fs::write(&settings_path, settings).unwrap();
+    bard::bard_make_at(&app, &project_dir).unwrap_err();
+
+    assert_eq!(fs::read_to_string(&html_path).unwrap(), html);
+    let tmp_files = fs::read_dir(&output_dir)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().file_ends_with(".tmp"))
+        .count();
+    assert_eq!(tmp_files, 0);
+}
+
+#[test]
+fn write_atomic_file() {
+    let dir = work_dir("write-atomic", true).unwrap();
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("output.txt");
+
+    write_atomic(&path, |file| Ok(file.write_all(b"first")?)).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+    // A failed write leaves the previous content in place and no temporary files
+    write_atomic(&path, |file| {
+        file.write_all(b"partial")?;
+        bail!("Interrupted");
+    })
+    .unwrap_err();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    let moved = dir.join("moved.txt");
+    move_file(&path, &moved).unwrap();
+    assert!(!path.exists());
+    assert_eq!(fs::read_to_string(&moved).unwrap(), "first");
+}
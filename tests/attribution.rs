@@ -0,0 +1,72 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, missing_copyright: Option<&'static str>) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "songs.md",
+            indoc! {"
+                # Song 1
+
+                {author: Alice} {author: Bob}
+                {composer: Carol}
+                {copyright: © 2020 Example Music}
+
+                1. Lyrics
+
+                # Song 2
+
+                1. Lyrics
+
+                # Instructions
+
+                !index=false
+                Text
+            "},
+        )
+        .output("songbook.html")
+        .output("songbook.pdf")
+        .settings(move |toml| {
+            toml.set("tex", "none");
+            if let Some(missing_copyright) = missing_copyright {
+                toml.set("missing_copyright", missing_copyright);
+            }
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn attribution() {
+    let build = build_with("attribution", None);
+    let project = build.unwrap();
+    let attribution = &project.songs()[0].attribution;
+    assert_eq!(attribution.authors, ["Alice".into(), "Bob".into()]);
+    assert_eq!(attribution.composers, ["Carol".into()]);
+    assert!(attribution.arrangers.is_empty());
+    assert_eq!(attribution.copyright, ["© 2020 Example Music".into()]);
+    assert!(project.songs()[1].attribution.is_empty());
+
+    let html = build.read_output("html");
+    assert!(html.contains(r#"<p class="attribution">Words: Alice, Bob</p>"#));
+    assert!(html.contains(r##"<h4><a href="#song-0">Song 1</a></h4>"##));
+    assert!(!html.contains(r##"<a href="#song-1">Song 2</a></h4>"##));
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(tex.contains(r"\attribution{Music: Carol}"));
+    assert!(tex.contains(r"\attribution{© 2020 Example Music}"));
+}
+
+#[test]
+fn attribution_missing_copyright() {
+    let build = build_with("attribution-missing-copyright-warn", Some("warn"));
+    build.unwrap();
+
+    // Songs left out of the index, such as instructions, are not checked
+    let build = build_with("attribution-missing-copyright-error", Some("error"));
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("'Song 2'"));
+    assert!(!err.contains("'Instructions'"));
+}
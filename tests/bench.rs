@@ -0,0 +1,45 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn bench_phases() {
+    let build = TestProject::new("bench-phases")
+        .postprocess(false)
+        .output("songbook.html")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    let app = build.app().clone().with_timings().with_tex_disabled();
+    let report = bard::bard_bench_at(&app, &build.unwrap().project_dir, 3).unwrap();
+    assert_eq!(report.outputs, 2);
+    for phase in [
+        "load",
+        "parse",
+        "postprocess",
+        "templates",
+        "render",
+        "total",
+    ] {
+        let stats = report.phase(phase).unwrap();
+        assert_eq!(stats.samples.len(), 3);
+        assert!(stats.min() <= stats.mean() && stats.mean() <= stats.max());
+    }
+
+    let report = report.to_string();
+    assert!(report.starts_with("3 runs"));
+    assert!(report.contains("stddev"));
+}
+
+#[test]
+fn bench_requires_no_postprocess() {
+    let build = TestProject::new("bench-requires-no-postprocess")
+        .postprocess(true)
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let app = build.app().clone().with_timings().with_tex_disabled();
+    let err = bard::bard_bench_at(&app, &build.unwrap().project_dir, 1).unwrap_err();
+    assert!(err.to_string().contains("without post-processing"));
+}
@@ -0,0 +1,29 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn book_list_joined() {
+    let build = TestProject::new("book-list-joined")
+        .output("songbook.json")
+        .output_toml(toml! {
+            file = "songbook2.json"
+            book = { list_last_separator = ", and " }
+        })
+        .settings(|toml| {
+            let book = toml.get_mut("book").unwrap().as_table_mut().unwrap();
+            book.set(
+                "authors",
+                toml::Value::Array(vec!["A".into(), "B".into(), "C".into()]),
+            );
+            book.set("tags", toml::Value::Array(vec!["folk".into()]));
+        })
+        .build()
+        .unwrap();
+
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""authors_joined": "A, B and C""#));
+    assert!(json.contains(r#""tags_joined": "folk""#));
+
+    let json = build.read_output("songbook2.json");
+    assert!(json.contains(r#""authors_joined": "A, B, and C""#));
+}
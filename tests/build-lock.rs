@@ -0,0 +1,25 @@
+use bard::project::Project;
+use bard::util::FileLock;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn build_lock() {
+    let build = TestProject::new("build-lock")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project_dir = &build.unwrap().project_dir;
+
+    // The lock is released after a build
+    let lock_path = project_dir.join(Project::LOCK_FILE);
+    let lock = FileLock::try_lock(&lock_path).unwrap().unwrap();
+
+    // Another build can't run while the lock is held
+    let err = bard::bard_make_at(build.app(), project_dir).unwrap_err();
+    assert!(format!("{:?}", err).contains("Another bard build is in progress"));
+
+    drop(lock);
+    bard::bard_make_at(build.app(), project_dir).unwrap();
+}
@@ -0,0 +1,88 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONGS: &str = r#"# Wild Mountain Thyme
+{capo: 2}
+
+1. Oh the `D`summer time is `G/B`coming
+
+# Scarborough Fair
+
+1. Are you `Am`going to Scarborough `G`Fair
+
+# Danny Boy
+{capo: 0}
+
+1. Oh `C`Danny boy
+"#;
+
+#[test]
+fn capo() {
+    let build = TestProject::new("capo")
+        .song("songs.md", SONGS)
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "songbook-capo.xml"
+            capo = 5
+        })
+        .output_toml(toml! {
+            file = "songbook.txt"
+            format = "hovorka"
+        })
+        .settings(|toml| {
+            toml.get_mut("book")
+                .unwrap()
+                .as_table_mut()
+                .unwrap()
+                .insert("capo_label".into(), "Kapodastr".into());
+        })
+        .build()
+        .unwrap();
+
+    // Chords are transposed down by the capo position
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(r#"<p class="capo">Kapodastr 2</p>"#));
+    assert_eq!(html.matches(r#"<p class="capo">"#).count(), 1);
+    assert!(html.contains(">C<") && html.contains(">F/A<"));
+    assert!(!html.contains(">D<"));
+    assert!(html.contains(">Am<"));
+
+    let hovorka = build.read_output("songbook.txt");
+    assert!(hovorka.contains("<![CDATA[\nKapodastr 2\n\n"));
+
+    // The song's own capo takes precedence over the output's one, capo 0 means no capo
+    let xml = build.read_output("songbook-capo.xml");
+    assert!(xml.contains(r#"capo="2""#));
+    assert!(xml.contains(r#"capo="5""#));
+    assert!(xml.contains(r#"chord="Em""#) && xml.contains(r#"chord="D""#));
+    assert!(xml.contains(r#"chord="C""#));
+    assert_eq!(xml.matches("capo=").count(), 2);
+    assert!(xml.contains("<capo>5</capo>"));
+}
+
+#[test]
+fn capo_chords() {
+    let build = TestProject::new("capo-chords")
+        .song("songs.md", SONGS)
+        .output_toml(toml! {
+            file = "sounding.xml"
+            capo_chords = "sounding"
+        })
+        .output_toml(toml! {
+            file = "both.xml"
+            capo_chords = "both"
+        })
+        .build()
+        .unwrap();
+
+    // The capo is shown, but the chords are left as written
+    let xml = build.read_output("sounding.xml");
+    assert!(xml.contains(r#"capo="2""#));
+    assert!(xml.contains(r#"chord="D""#) && xml.contains(r#"chord="G/B""#));
+
+    let xml = build.read_output("both.xml");
+    assert!(xml.contains(r#"chord="D (C)""#) && xml.contains(r#"chord="G/B (F/A)""#));
+    // Songs without a capo are not affected
+    assert!(xml.contains(r#"chord="Am""#));
+    assert!(xml.contains("<capo_chords>both</capo_chords>"));
+}
@@ -0,0 +1,54 @@
+use std::fs;
+
+use bard::parser::DiagKind;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn check() {
+    let build = TestProject::new("check")
+        .song("danny.md", "# Danny Boy\n\n1. Oh Danny boy\n")
+        .song(
+            "thyme.md",
+            "# Wild Mountain Thyme\n\n1. Oh the summer time\n",
+        )
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let app = build.app();
+    let dir = &project.project_dir;
+
+    bard::bard_check_at(app, dir, true).unwrap();
+
+    // Warnings only fail with deny_warnings
+    let danny = build.dir_songs().join("danny.md");
+    let thyme = build.dir_songs().join("thyme.md");
+    fs::write(&danny, "# Danny Boy\n{author:}\n\n1. Oh Danny boy\n").unwrap();
+    bard::bard_check_at(app, dir, false).unwrap();
+    let err = bard::bard_check_at(app, dir, true).unwrap_err();
+    assert!(format!("{:#}", err).contains("1 warning(s)"));
+    assert!(app
+        .parser_diags()
+        .lock()
+        .iter()
+        .any(|diag| matches!(diag.kind, DiagKind::AttributionAnnotation { .. })));
+
+    // Errors in all files are reported
+    app.parser_diags().lock().clear();
+    fs::write(&danny, "# Danny Boy\n\n1. Bad \u{7} char\n").unwrap();
+    fs::write(
+        &thyme,
+        "# Wild Mountain Thyme\n\n!+2\n\n1. `X`Oh the summer time\n",
+    )
+    .unwrap();
+    bard::bard_check_at(app, dir, false).unwrap_err();
+    let diags = app.parser_diags().lock();
+    assert!(diags
+        .iter()
+        .any(|diag| matches!(diag.kind, DiagKind::ControlChar { .. })));
+    assert!(diags
+        .iter()
+        .any(|diag| matches!(diag.kind, DiagKind::Transposition { .. })));
+}
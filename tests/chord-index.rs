@@ -0,0 +1,55 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn chord_index() {
+    let build = TestProject::new("chord-index")
+        .song(
+            "1.md",
+            indoc! {"
+                # Song 1
+
+                1. `Am7`Lyrics `G/B`lyrics `C`lyrics *`D`emph*
+                2. `C`Lyrics `Am7 N.C.`lyrics
+            "},
+        )
+        .song(
+            "2.md",
+            indoc! {"
+                # Song 2
+
+                1. `C#m`Lyrics `Bb`lyrics `A`lyrics
+            "},
+        )
+        .output_toml(toml! {
+            file = "songbook.html"
+            chord_index = true
+        })
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            chord_index = true
+        })
+        .output("songbook.json")
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let html = build.read_output("html");
+    let index = html.split(r#"<ul class="chord-index">"#).nth(1).unwrap();
+    let index = index.split("</ul>").next().unwrap();
+    let chords: Vec<_> = index
+        .split("<li>")
+        .skip(1)
+        .map(|li| li.split("</li>").next().unwrap())
+        .collect();
+    assert_eq!(chords, ["C", "C#m", "D", "G/B", "A", "Am7", "Bb"]);
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(tex.contains("\\mbox{\\textbf{\\sffamily\\color{red}G/B}}\\quad"));
+
+    // The index is only provided for outputs which enable it
+    let json = build.read_output("json");
+    assert!(json.contains(r#""chords": []"#));
+}
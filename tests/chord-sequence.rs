@@ -0,0 +1,45 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = r#"# Wild Mountain Thyme
+
+1. `Am` `C` `G`
+Oh the `G`summer time is coming
+"#;
+
+const TEMPLATE_1_2: &str = r#"{{~ version_check "1.2.0" ~}}
+{{#*inline "i-text"}}{{ text }}{{/inline}}
+{{#*inline "i-break"}}|{{/inline}}
+{{#*inline "i-chord"}}[{{ chord }}]{{#each inlines}}{{> (lookup this "type") }}{{/each}}{{/inline}}
+{{#*inline "b-verse"}}{{#each paragraphs}}{{#each this}}{{> (lookup this "type") }}{{/each}}{{/each}}{{/inline}}
+{{#each songs}}{{#each blocks}}{{> (lookup this "type") }}{{/each}}{{/each}}
+"#;
+
+#[test]
+fn chord_sequence() {
+    let build = TestProject::new("chord-sequence")
+        .song("song.md", SONG)
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "songbook.txt"
+            format = "hovorka"
+        })
+        .output("songbook.xml")
+        .output("old-template.html")
+        .template("old-template.html", "old-template.hbs", TEMPLATE_1_2)
+        .build()
+        .unwrap();
+
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(r#"<span class="chord-sequence">"#));
+
+    let hovorka = build.read_output("songbook.txt");
+    assert!(hovorka.contains("[Am] [C] [G]"));
+
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains("<chord-sequence>"));
+
+    // Templates older than AST version 1.19 get the chords separately
+    let html = build.read_output("old-template.html");
+    assert!(html.contains("[Am] [C] [G]|Oh the [G]summer time is coming"));
+}
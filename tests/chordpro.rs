@@ -0,0 +1,141 @@
+use bard::book::Block;
+use bard::parser::DiagKind;
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = r#"{title: Wild Mountain Thyme}
+{subtitle: Irish & Scottish traditional}
+{artist: Francis McPeake}
+{key: G}
+# A comment line
+
+Oh the [G]summer time is [G/B]coming
+And the [C]trees are sweetly [G]blooming
+
+{start_of_chorus}
+And we'll [C]all go to[G]gether
+
+To pull [C]wild mountain [Em]thyme
+{end_of_chorus}
+
+{c: Repeat the chorus}
+{textsize: 12}
+"#;
+
+#[test]
+fn chordpro() {
+    let build = TestProject::new("chordpro")
+        .song("thyme.cho", SONG)
+        .song("danny.md", "# Danny Boy\n\n1. Oh `C`Danny boy\n")
+        .settings(|toml| {
+            toml.set("songs", vec!["*.cho", "*.md"]);
+        })
+        .output("songbook.xml")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let titles: Vec<_> = project.songs().iter().map(|song| &*song.title).collect();
+    assert_eq!(titles, ["Wild Mountain Thyme", "Danny Boy"]);
+    let song = &project.songs()[0];
+    assert_eq!(&*song.subtitles[0], "Irish & Scottish traditional");
+    assert_eq!(song.key.as_deref(), Some("G"));
+    assert_eq!(&*song.attribution.authors[0], "Francis McPeake");
+
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains(r#"chord="G/B""#) && xml.contains(r#"chord="Em""#));
+    assert!(xml.contains("<chorus"));
+    assert!(xml.contains("Repeat the chorus"));
+    // The paragraphs of the chorus stay in one chorus
+    assert_eq!(xml.matches("<chorus").count(), 1);
+
+    // Unknown directives are reported with their line in the ChordPro file
+    build.assert_parser_diag(DiagKind::ChordProDirective {
+        directive: "{textsize: 12}".into(),
+    });
+    let diags = build.app().parser_diags().lock();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0].line, 17);
+}
+
+const SONGS_MD: &str = r#"# Wild Mountain Thyme
+## Irish & Scottish traditional
+{author: Francis McPeake}
+{key: G}
+
+1. Oh the `G`summer time is `G/B`coming
+And the `C`trees are sweetly `G`blooming
+
+> And we'll `C`all go to`G`gether
+
+To pull `C`wild mountain `Em`thyme {x2}
+
+2. `Am` `C` `G`
+I will `D7`build my love a bower
+
+# Danny Boy
+
+### Intro
+`C`Oh Danny boy
+"#;
+
+#[test]
+fn chordpro_export() {
+    let build = TestProject::new("chordpro-export")
+        .song("songs.md", SONGS_MD)
+        .output("songbook.cho")
+        .output_toml(toml! {
+            file = "nashville.cho"
+            notation = "nashville"
+        })
+        .build()
+        .unwrap();
+
+    let cho = build.read_output("songbook.cho");
+    assert!(
+        cho.starts_with("{title: Wild Mountain Thyme}\n{subtitle: Irish & Scottish traditional}\n")
+    );
+    assert!(cho.contains("{artist: Francis McPeake}\n{key: G}\n"));
+    assert!(cho.contains(
+        "{start_of_verse}\nOh the [G]summer time is [G/B]coming\nAnd the [C]trees are sweetly [G]blooming\n{end_of_verse}\n"
+    ));
+    assert!(cho.contains("{start_of_chorus}\nAnd we’ll [C]all go to[G]gether\n\nTo pull [C]wild mountain [Em]thyme\n{end_of_chorus}\n{comment: x2}\n"));
+    assert!(cho.contains("[Am] [C] [G]\n"));
+    assert!(cho.contains("{new_song}\n{title: Danny Boy}\n"));
+    assert!(cho.contains("{start_of_verse: Intro}\n"));
+
+    // Chords are written in the output's notation
+    let cho = build.read_output("nashville.cho");
+    assert!(cho.contains("Oh the [1]summer time is [1/3]coming"));
+
+    // Chord positions are preserved when the output is loaded back
+    let songs_cho = build.read_output("songbook.cho");
+    let reparsed = TestProject::new("chordpro-export-reparse")
+        .song("songs.cho", songs_cho)
+        .settings(|toml| {
+            toml.set("songs", "*.cho");
+        })
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    // Custom verse labels are not kept by ChordPro, so only the lyrics and repeats are compared
+    let paragraphs = |project: &Project| -> Vec<String> {
+        project
+            .songs()
+            .iter()
+            .flat_map(|song| song.blocks.iter())
+            .filter_map(|block| match block {
+                Block::Verse(verse) if verse.label.is_some() => {
+                    Some(serde_json::to_string(&(&verse.paragraphs, verse.repeat)).unwrap())
+                }
+                _ => None,
+            })
+            .collect()
+    };
+    let original = paragraphs(build.unwrap());
+    assert_eq!(original.len(), 4);
+    assert_eq!(original, paragraphs(reparsed.unwrap()));
+}
@@ -0,0 +1,28 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn chords_only() {
+    let build = TestProject::new("chords-only")
+        .output_toml(toml! {
+            file = "songbook.json"
+            chords_only = true
+        })
+        .song(
+            "song.md",
+            r#"# Song
+
+1. `C`Hello `G`world
+No chords here
+`Am`Last line
+"#,
+        )
+        .build()
+        .unwrap();
+
+    let json = build.read_output("songbook.json");
+    assert!(!json.contains("Hello"));
+    assert!(!json.contains("No chords here"));
+    assert!(json.contains(r#""chord": "Am""#));
+    assert!(json.contains(r#""chords_only": true"#));
+}
@@ -0,0 +1,101 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn clean() {
+    let build = TestProject::new("clean")
+        .output("songbook.html")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let app = build.app();
+    let dir = &project.project_dir;
+    let dir_output = build.dir_output();
+
+    // TeX build directories and images generated by bard are removed, other files are kept
+    let tmp_dir = dir_output.join("songbook.html.a1B2c3");
+    let other = dir_output.join("songbook.html.old");
+    fs::create_dir(&tmp_dir).unwrap();
+    fs::create_dir(&other).unwrap();
+    for dir in ["qr", "lilypond"] {
+        fs::create_dir(dir_output.join(dir)).unwrap();
+        fs::write(dir_output.join(dir).join("image.png"), "").unwrap();
+    }
+
+    let listed = bard::bard_clean_at(app, dir, true).unwrap();
+    let mut names: Vec<_> = listed
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        [
+            ".bard-cache",
+            ".bard-dependencies.json",
+            "lilypond",
+            "qr",
+            "songbook.html",
+            "songbook.html.a1B2c3",
+            "songbook.json"
+        ]
+    );
+    assert!(listed.iter().all(|path| path.exists()));
+
+    let removed = bard::bard_clean_at(app, dir, false).unwrap();
+    assert_eq!(removed, listed);
+    assert!(removed.iter().all(|path| !path.exists()));
+    assert!(other.exists());
+    assert!(build.dir_songs().join("yippie.md").exists());
+
+    let removed = bard::bard_clean_at(app, dir, false).unwrap();
+    assert!(removed.is_empty());
+}
+
+#[test]
+fn clean_outside_project() {
+    let build = TestProject::new("clean-outside-project")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let dir = &project.project_dir;
+
+    let bard_toml = dir.join("bard.toml");
+    let settings = fs::read_to_string(&bard_toml).unwrap();
+    fs::write(&bard_toml, format!("dir_output = \"..\"\n{}", settings)).unwrap();
+
+    let err = bard::bard_clean_at(build.app(), dir, true).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("is not inside the project directory"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn clean_output_file_outside() {
+    let build = TestProject::new("clean-output-file-outside")
+        .output("songbook.json")
+        .output_toml(toml! {
+            file = "../outside.json"
+        })
+        .output_toml(toml! {
+            file = "split.html"
+            split = "per-song"
+        })
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let dir = &project.project_dir;
+    let outside = dir.join("outside.json");
+    assert!(outside.exists());
+
+    let removed = bard::bard_clean_at(build.app(), dir, false).unwrap();
+    assert_eq!(removed.len(), 5);
+    assert!(!build.dir_output().join("split").exists());
+    assert!(outside.exists());
+}
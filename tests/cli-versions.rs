@@ -6,7 +6,7 @@ use semver::Version;
 pub use util::*;
 
 fn get_version(args: &[&str]) -> String {
-    Command::new(&bard_exe())
+    Command::new(bard_exe())
         .args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::inherit())
@@ -43,4 +43,7 @@ fn project_default_templates_save() {
 
     let hovorka = fs::read_to_string(templates.join("hovorka.hbs")).unwrap();
     assert_eq!(hovorka, render::hovorka::DEFAULT_TEMPLATE.content);
+
+    let epub = fs::read_to_string(templates.join("epub.hbs")).unwrap();
+    assert_eq!(epub, render::epub::DEFAULT_TEMPLATE.content);
 }
@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+
+use bard::app::{App, DiagnosticsFormat};
+use bard::parser::{DiagKind, Diagnostic};
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn diag_sink_custom() {
+    let build = TestProject::new("diag-sink-custom")
+        .song(
+            "song.md",
+            indoc! {"
+                # Song
+
+                {author: }
+
+                1. Lyrics
+            "},
+        )
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project_dir = build.unwrap().project_dir.clone();
+    let app = build.app();
+    app.parser_diags().lock().clear();
+
+    let diags = RefCell::new(vec![]);
+    let sink = |diag: Diagnostic| diags.borrow_mut().push(diag);
+    let project = Project::with_diag_sink(app, &project_dir, &sink).unwrap();
+
+    let diags = diags.into_inner();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(
+        diags[0].kind,
+        DiagKind::AttributionAnnotation {
+            annotation: "{author: }".into()
+        }
+    );
+    assert_eq!(diags[0].line, 3);
+
+    // Diagnostics go to the custom sink only
+    assert!(app.parser_diags().lock().is_empty());
+    assert_eq!(project.songs().len(), 1);
+}
+
+#[test]
+fn diag_json() {
+    let build = TestProject::new("diag-json")
+        .song(
+            "song.md",
+            indoc! {"
+                # Song
+
+                {author: }
+
+                1. Lyrics
+            "},
+        )
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project_dir = build.unwrap().project_dir.clone();
+    let app = build
+        .app()
+        .clone()
+        .with_diagnostics_format(DiagnosticsFormat::Json);
+    app.parser_diags().lock().clear();
+
+    Project::with_diag_sink(&app, &project_dir, &app).unwrap();
+    let diags = app.parser_diags().lock();
+    assert_eq!(diags.len(), 1);
+
+    let json = diags[0].to_json();
+    assert!(json["path"].as_str().unwrap().ends_with("song.md"));
+    assert_eq!(json["line"], 3);
+    assert!(json["column"].is_null());
+    assert_eq!(json["severity"], "warning");
+    assert_eq!(json["code"], diags[0].code());
+    assert!(json["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Empty attribution annotation"));
+}
+
+#[test]
+fn diag_json_error() {
+    let build = TestProject::new("diag-json-error")
+        .output("songbook.html")
+        .template("songbook.html", "broken.hbs", "{{#if book.title}}\n")
+        .build()
+        .unwrap();
+
+    let json = App::error_to_json(build.unwrap_err());
+    assert!(json["path"].is_null());
+    assert_eq!(json["severity"], "error");
+    assert!(json["code"].is_null());
+    assert_eq!(json["message"], format!("{}", build.unwrap_err()));
+    let causes = json["causes"].as_array().unwrap();
+    assert!(causes
+        .iter()
+        .any(|cause| cause.as_str().unwrap().contains("broken.hbs")));
+}
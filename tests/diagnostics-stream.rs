@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn diagnostics_stream_stdout() {
+    TestProject::new("diagnostics-stream")
+        .song(
+            "song.md",
+            indoc! {"
+                # Song
+
+                {author: }
+
+                1. Lyrics
+            "},
+        )
+        .output("songbook.html")
+        .template("songbook.html", "broken.hbs", "{{#if book.title}}\n")
+        .build()
+        .unwrap();
+    let project_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("test-projects")
+        .join("diagnostics-stream");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bard"))
+        .args([
+            "make",
+            "--diagnostics-format",
+            "json",
+            "--diagnostics-stream",
+            "stdout",
+        ])
+        .current_dir(&project_dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    // Only JSON messages are printed to stdout, status stays on stderr
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let messages: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(messages.len(), 2, "{}", stdout);
+
+    let diag = &messages[0];
+    assert!(diag["path"].as_str().unwrap().ends_with("song.md"));
+    assert_eq!(diag["line"], 3);
+    assert_eq!(diag["severity"], "warning");
+    assert_eq!(diag["code"], "B0007");
+
+    // The error the build failed with comes last
+    let error = &messages[1];
+    assert_eq!(error["severity"], "error");
+    assert_eq!(error["message"], "Could not make project");
+    assert!(error["causes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|cause| cause.as_str().unwrap().contains("broken.hbs")));
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Loading"), "{}", stderr);
+    assert!(!stderr.contains("B0007"), "{}", stderr);
+}
@@ -0,0 +1,19 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn doctor_tex_command() {
+    let tex_mock_exe = env!("CARGO_BIN_EXE_tex-mock");
+    let tex = format!("xelatex:{}", tex_mock_exe);
+    let build = TestProject::new("doctor-tex-command")
+        .output("songbook.html")
+        .settings(move |toml| toml.set("tex", tex.as_str()))
+        .build()
+        .unwrap();
+
+    let desc = bard::bard_tex_command_at(build.app(), &build.unwrap().project_dir).unwrap();
+    assert!(desc.contains("Distribution: xelatex"));
+    assert!(desc.contains("Configured from: the bard.toml project file"));
+    assert!(desc.contains(&format!("Program: {}", tex_mock_exe)));
+    assert!(desc.contains("-jobname=songbook"));
+}
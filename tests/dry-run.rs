@@ -0,0 +1,28 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn dry_run() {
+    let build = TestProject::new("dry-run")
+        .output("songbook.html")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    for file in ["songbook.html", "songbook.json"] {
+        fs::remove_file(build.dir_output().join(file)).unwrap();
+    }
+    let app = build.app().clone().with_dry_run();
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+    for file in ["songbook.html", "songbook.json"] {
+        assert!(!build.dir_output().join(file).exists(), "{}", file);
+    }
+
+    // Parser errors are still reported
+    let song = project.input_paths()[0].clone();
+    fs::write(&song, "# Song\n\n1. Bad \u{7} char\n").unwrap();
+    bard::bard_make_at(&app, &project.project_dir).unwrap_err();
+}
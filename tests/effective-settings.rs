@@ -0,0 +1,41 @@
+use std::fs;
+
+use bard::project::Settings;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn effective_settings_round_trip() {
+    let build = TestProject::new("effective-settings")
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "songbook.json"
+            book = { title = "Other title" }
+        })
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let effective = project.settings.to_effective_toml().unwrap();
+    let toml: toml::Table = toml::from_str(&effective).unwrap();
+
+    assert_eq!(toml["version"].as_integer(), Some(Settings::version() as _));
+    assert_eq!(toml["dir_songs"].as_str(), Some("songs"));
+    assert_eq!(toml["smart_punctuation"].as_bool(), Some(true));
+    let output = &toml["output"][0];
+    assert_eq!(output["file"].as_str(), Some("songbook.html"));
+    assert_eq!(output["format"].as_str(), Some("html"));
+    assert_eq!(output["font_size"].as_integer(), Some(12));
+    assert_eq!(output["dpi"].as_float(), Some(1.0));
+    assert_eq!(
+        toml["output"][1]["book"]["title"].as_str(),
+        Some("Other title")
+    );
+
+    // The effective settings should load back the same
+    let project_file = project.project_dir.join("bard-effective.toml");
+    fs::write(&project_file, &effective).unwrap();
+    let settings = Settings::from_file(&project_file, &project.project_dir).unwrap();
+    assert_eq!(settings.to_effective_toml().unwrap(), effective);
+}
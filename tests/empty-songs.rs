@@ -0,0 +1,53 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, policy: Option<&'static str>) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "songs.md",
+            indoc! {"
+                # Song 1
+
+                1. Lyrics
+
+                # Empty
+
+                # Song 2
+
+                1. Lyrics
+            "},
+        )
+        .output("songbook.json")
+        .settings(move |toml| {
+            if let Some(policy) = policy {
+                toml.set("empty_songs", policy);
+            }
+        })
+        .build()
+        .unwrap()
+}
+
+fn titles(build: &TestBuild) -> Vec<String> {
+    build
+        .unwrap()
+        .songs()
+        .iter()
+        .map(|song| song.title.to_string())
+        .collect()
+}
+
+#[test]
+fn empty_songs_policy() {
+    let build = build_with("empty-songs-default", None);
+    assert_eq!(titles(&build), ["Song 1", "Song 2"]);
+
+    let build = build_with("empty-songs-skip", Some("skip"));
+    assert_eq!(titles(&build), ["Song 1", "Song 2"]);
+
+    let build = build_with("empty-songs-keep", Some("keep"));
+    assert_eq!(titles(&build), ["Song 1", "Empty", "Song 2"]);
+
+    let build = build_with("empty-songs-error", Some("error"));
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("'Empty'"));
+}
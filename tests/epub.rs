@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::PathBuf;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SMOL_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAQAAAAEAAQMAAABmvDolAAAAA1BMVEW10NBjBBbqAAAAH0lEQVRoge3BAQ0AAADCoPdPbQ43oAAAAAAAAAAAvg0hAAABmmDh1QAAAABJRU5ErkJggg==";
+
+const SONGS: &str = indoc! {r#"
+# Wild Mountain Thyme
+
+1. Oh the `G`summer time is `C`coming {x2}
+![smol](smol.png "center")
+
+> And we'll `C`all go to`G`gether
+
+# Danny Boy
+
+1. `C`Oh Danny boy & the pipes
+"#};
+
+fn assert_xml(name: &str, content: &str) {
+    let mut reader = Reader::from_str(content);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => panic!("{} is not well-formed: {}\n{}", name, err, content),
+        }
+    }
+}
+
+fn build(name: &str, toc_sort: bool) -> (TestBuild, PathBuf) {
+    let build = TestProject::new(name)
+        .song("songs.md", SONGS)
+        .binary_asset("smol.png", SMOL_PNG)
+        .output_toml(toml! {
+            file = "songbook.epub"
+            toc_sort = toc_sort
+        })
+        .build()
+        .unwrap();
+
+    let epub = build.output_path(".epub").unwrap();
+    let dir = build.dir_output().join("extracted");
+    fs::create_dir_all(&dir).unwrap();
+    bard::util::archive::extract(&epub, &dir).unwrap();
+    (build, dir)
+}
+
+#[test]
+fn epub() {
+    let (build, dir) = build("epub", false);
+
+    // The mimetype has to be the first entry, stored
+    let epub = fs::read(build.output_path(".epub").unwrap()).unwrap();
+    assert_eq!(&epub[8..10], &[0, 0]);
+    assert_eq!(&epub[30..58], b"mimetypeapplication/epub+zip");
+
+    let read = |path: &str| {
+        let content = fs::read_to_string(dir.join(path)).unwrap();
+        if path.ends_with(".xhtml") || path.ends_with(".opf") || path.ends_with(".ncx") {
+            assert_xml(path, &content);
+        }
+        content
+    };
+    assert!(read("META-INF/container.xml").contains(r#"full-path="content.opf""#));
+
+    let opf = read("content.opf");
+    assert!(opf.contains("<dc:title>My Songbook</dc:title>"));
+    assert!(opf.contains(r#"href="nav.xhtml" media-type="application/xhtml+xml" properties="nav""#));
+    assert!(opf.contains(r#"href="fonts/BardSerif-Regular.ttf" media-type="font/ttf""#));
+    assert!(opf.contains(r#"href="smol.png" media-type="image/png""#));
+    let spine = ["title", "nav", "song-0", "song-1"].map(|id| format!(r#"idref="{}""#, id));
+    let positions: Vec<_> = spine.iter().map(|id| opf.find(id).unwrap()).collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    assert!(dir.join("fonts/fonts.css").is_file());
+    assert!(dir.join("fonts/BardSans-Bold.ttf").is_file());
+    assert_eq!(
+        fs::read(dir.join("smol.png")).unwrap(),
+        &*SMOL_PNG.decode_base64()
+    );
+
+    let song = read("wild-mountain-thyme.xhtml");
+    assert!(song.contains("<h2>Wild Mountain Thyme</h2>"));
+    assert!(song.contains(r#"<img class="center" src="smol.png""#));
+    assert!(!song.contains("Danny Boy"));
+    assert!(read("danny-boy.xhtml").contains("Oh Danny boy &amp; the pipes"));
+    assert!(read("title.xhtml").contains("<h1>My Songbook</h1>"));
+
+    let nav = read("nav.xhtml");
+    let wild = nav.find(r#"<a href="wild-mountain-thyme.xhtml">"#).unwrap();
+    let danny = nav.find(r#"<a href="danny-boy.xhtml">"#).unwrap();
+    assert!(wild < danny);
+    let ncx = read("toc.ncx");
+    assert!(ncx.contains(r#"<navPoint id="nav-2" playOrder="2">"#));
+}
+
+#[test]
+fn epub_toc_sort() {
+    let (_build, dir) = build("epub-toc-sort", true);
+
+    let nav = fs::read_to_string(dir.join("nav.xhtml")).unwrap();
+    let wild = nav.find("Wild Mountain Thyme").unwrap();
+    let danny = nav.find("Danny Boy").unwrap();
+    assert!(danny < wild);
+
+    let ncx = fs::read_to_string(dir.join("toc.ncx")).unwrap();
+    assert!(ncx.find("Danny Boy").unwrap() < ncx.find("Wild Mountain Thyme").unwrap());
+}
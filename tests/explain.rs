@@ -0,0 +1,62 @@
+mod util;
+use std::process::{Command, Stdio};
+
+use bard::parser::explain::{explain, EXPLANATIONS};
+use bard::parser::DiagKind;
+pub use util::*;
+
+#[test]
+fn explain_codes() {
+    let kinds = [
+        DiagKind::ControlChar { char: 0 },
+        DiagKind::Transposition { chord: "X".into() },
+        DiagKind::HtmlReservedTag { tag: "tex".into() },
+        DiagKind::HtmlIgnoredText { text: "".into() },
+        DiagKind::NavMarker { marker: "".into() },
+        DiagKind::GlossAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::AttributionAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::EmptyVerse,
+        DiagKind::LinkAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::RepeatAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::CapoAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::KeyAnnotation {
+            annotation: "".into(),
+        },
+        DiagKind::ChordProDirective {
+            directive: "".into(),
+        },
+        DiagKind::ChorusAnnotation {
+            annotation: "".into(),
+        },
+    ];
+    for kind in kinds.iter() {
+        assert_eq!(explain(kind.code()).unwrap().code, kind.code());
+    }
+    assert_eq!(EXPLANATIONS.len(), kinds.len());
+
+    assert_eq!(explain("b0002").unwrap().code, "B0002");
+    assert_eq!(explain("0002").unwrap().code, "B0002");
+    assert!(explain("B9999").is_err());
+}
+
+#[test]
+fn explain_cli() {
+    let output = Command::new(bard_exe())
+        .args(["explain", "B0004"])
+        .stdout(Stdio::piped())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("B0004\n\nText in an HTML block was ignored."));
+}
@@ -0,0 +1,56 @@
+use bard::util_cmd;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn export_chords() {
+    let build = TestProject::new("export-chords")
+        .song(
+            "1.md",
+            indoc! {"
+                # Song 1
+
+                1. `Am7`Lyrics `G/B`lyrics `C`lyrics
+                2. `C`Lyrics `G/B`lyrics
+            "},
+        )
+        .song(
+            "2.md",
+            indoc! {"
+                # Song 2
+
+                1. `C`Lyrics `Bb`lyrics `G/B`lyrics
+            "},
+        )
+        .song(
+            "3.md",
+            indoc! {"
+                # Song 3
+
+                1. `G/B`Lyrics `Bb`lyrics
+            "},
+        )
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let songs = build.unwrap().songs();
+
+    let chords = util_cmd::export_chords(songs, false);
+    assert_eq!(
+        chords,
+        [
+            ("C".to_string(), 2),
+            ("G/B".to_string(), 3),
+            ("Am7".to_string(), 1),
+            ("Bb".to_string(), 2),
+        ]
+    );
+
+    // Chords are counted once per song, ties keep the order of the chord index
+    let chords: Vec<_> = util_cmd::export_chords(songs, true)
+        .into_iter()
+        .map(|(chord, _)| chord)
+        .collect();
+    assert_eq!(chords, ["G/B", "C", "Bb", "Am7"]);
+}
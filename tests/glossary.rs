@@ -0,0 +1,40 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn glossary() {
+    let build = TestProject::new("glossary")
+        .song(
+            "1.md",
+            indoc! {"
+                # Song 1
+
+                1. Where art {gloss: art = are} thou {gloss: thou = you}
+            "},
+        )
+        .song(
+            "2.md",
+            indoc! {"
+                # Song 2
+
+                1. {gloss: Thee = you} I love thee {gloss: thou = you}
+                {gloss: art = is}
+            "},
+        )
+        .output("songbook.json")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let glossary: Vec<_> = project
+        .glossary()
+        .iter()
+        .map(|entry| (&*entry.term, &*entry.definition))
+        .collect();
+    // Duplicates are merged, the first of conflicting definitions is used
+    assert_eq!(glossary, [("art", "are"), ("Thee", "you"), ("thou", "you")]);
+
+    let html = build.read_output("html");
+    assert!(html.contains("<dt>Thee</dt><dd>you</dd>"));
+}
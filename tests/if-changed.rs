@@ -0,0 +1,59 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_if_changed() {
+    let build = TestProject::new("if-changed")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let dir = &project.project_dir;
+    assert!(!bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+
+    // Modify a song, the project should be rebuilt
+    thread::sleep(Duration::from_millis(50));
+    let song = project.input_paths()[0].clone();
+    let content = fs::read_to_string(&song).unwrap();
+    fs::write(&song, content + "\nMore lyrics.\n").unwrap();
+    assert!(bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+    assert!(!bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+
+    // Missing output should be rebuilt as well
+    fs::remove_file(build.output_path("songbook.html").unwrap()).unwrap();
+    assert!(bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+}
+
+#[test]
+fn project_if_changed_glob() {
+    let build = TestProject::new("if-changed-glob")
+        .settings(|toml| toml.set("songs", "*.md"))
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let dir = &project.project_dir;
+    assert!(!bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+
+    // Add a song matching the glob, then make the output newer than it,
+    // as if the song was copied in with its original mtime
+    let song = build.dir_songs().join("new-song.md");
+    fs::write(&song, "# New Song\n\n1. `C`Lyrics.\n").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    let output = build.output_path("songbook.html").unwrap();
+    fs::write(&output, fs::read(&output).unwrap()).unwrap();
+    assert!(bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+    assert!(build.read_output("songbook.html").contains("New Song"));
+    assert!(!bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+
+    // Removing a song is a change as well
+    fs::remove_file(&song).unwrap();
+    assert!(bard::bard_make_if_changed_at(build.app(), dir).unwrap());
+    assert!(!build.read_output("songbook.html").contains("New Song"));
+}
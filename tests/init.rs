@@ -1,7 +1,9 @@
 mod util;
 use std::fs;
+use std::process::Command;
 
-use bard::default_project::DEFAULT_PROJECT;
+use bard::default_project::{ProjectTemplate, DEFAULT_PROJECT};
+use bard::project::Settings;
 pub use util::*;
 
 #[test]
@@ -49,3 +51,103 @@ fn init_doesnt_overwrite_2() {
     assert!(out_dir.exists());
     assert!(!project_file.exists());
 }
+
+#[test]
+fn init_from_template_dir() {
+    let template_dir = work_dir("init-template-src", true).unwrap();
+    let app = Builder::app(false);
+    bard::bard_init_at(&app, &template_dir).unwrap();
+    let custom_song = "# Custom\n\n1. Custom lyrics\n";
+    fs::write(template_dir.join("songs").join("yippie.md"), custom_song).unwrap();
+
+    let work_dir = work_dir("init-template", true).unwrap();
+    let template = ProjectTemplate::from_arg(template_dir.to_str().unwrap()).unwrap();
+    bard::bard_init_template_at(&app, &work_dir, &template).unwrap();
+
+    let song = fs::read_to_string(work_dir.join("songs").join("yippie.md")).unwrap();
+    assert_eq!(song, custom_song);
+    assert!(work_dir.join("bard.toml").exists());
+    assert!(work_dir
+        .join("output")
+        .join("fonts")
+        .join("fonts.tex")
+        .exists());
+
+    ProjectTemplate::from_arg("default").unwrap();
+    ProjectTemplate::from_arg("nonexistent-template").unwrap_err();
+}
+
+#[test]
+fn init_from_template_git() {
+    let repo_dir = work_dir("init-template-git-src", true).unwrap();
+    let app = Builder::app(false);
+    bard::bard_init_at(&app, &repo_dir).unwrap();
+    let custom_song = "# Custom\n\n1. Custom lyrics\n";
+    fs::write(repo_dir.join("songs").join("yippie.md"), custom_song).unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=bard", "-c", "user.email=bard@example.com"])
+            .args(args)
+            .current_dir(&repo_dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "--quiet"]);
+    git(&["add", "."]);
+    git(&["commit", "--quiet", "-m", "Template"]);
+
+    let url = format!("file://{}", repo_dir.display());
+    let template = ProjectTemplate::from_arg(&url).unwrap();
+    let work_dir = work_dir("init-template-git", true).unwrap();
+    bard::bard_init_template_at(&app, &work_dir, &template).unwrap();
+
+    let song = fs::read_to_string(work_dir.join("songs").join("yippie.md")).unwrap();
+    assert_eq!(song, custom_song);
+    assert!(work_dir.join("bard.toml").exists());
+    assert!(!work_dir.join(".git").exists());
+
+    let missing = format!("file://{}", repo_dir.join("nonexistent.git").display());
+    ProjectTemplate::from_arg(&missing).unwrap_err();
+}
+
+#[test]
+fn init_stamps_version() {
+    let template_dir = work_dir("init-version-src", true).unwrap();
+    fs::create_dir_all(&template_dir).unwrap();
+    fs::write(template_dir.join("bard.toml"), "songs = \"*.md\"\n").unwrap();
+    let template = ProjectTemplate::from_arg(template_dir.to_str().unwrap()).unwrap();
+
+    let new_dir = work_dir("init-version", true).unwrap();
+    let app = Builder::app(false);
+    let version = Settings::version();
+    bard::bard_init_template_at(&app, &new_dir, &template).unwrap();
+
+    let project_file = fs::read_to_string(new_dir.join("bard.toml")).unwrap();
+    assert!(project_file.starts_with(&format!("version = {}\n", version)));
+}
+
+#[test]
+fn init_stamps_version_book_field() {
+    let template_dir = work_dir("init-version-book-src", true).unwrap();
+    fs::create_dir_all(&template_dir).unwrap();
+    let app = Builder::app(false);
+
+    for (i, top_level) in ["", "version = 1\n"].iter().enumerate() {
+        fs::write(
+            template_dir.join("bard.toml"),
+            format!(
+                "{}songs = \"*.md\"\n\n[[output]]\nfile = \"songbook.html\"\n\n[book]\ntitle = \"Songs\"\nversion = \"2nd edition\"\n",
+                top_level
+            ),
+        )
+        .unwrap();
+        let template = ProjectTemplate::from_arg(template_dir.to_str().unwrap()).unwrap();
+
+        let new_dir = work_dir(&format!("init-version-book-{}", i), true).unwrap();
+        bard::bard_init_template_at(&app, &new_dir, &template).unwrap();
+
+        let settings = Settings::from_file(&new_dir.join("bard.toml"), &new_dir).unwrap();
+        assert_eq!(settings.book["version"].as_str(), Some("2nd edition"));
+    }
+}
@@ -0,0 +1,38 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, unplayable_chords: Option<&'static str>) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "songs.md",
+            indoc! {"
+                # Song 1
+
+                1. `Am`Yippie yea `C/G`oh! `Dsus`Yay!
+
+                # Song 2
+
+                1. `C7#9`Yippie `Fmaj13, G`yea!
+            "},
+        )
+        .output("songbook.html")
+        .settings(move |toml| {
+            toml.set("instrument", "ukulele");
+            if let Some(unplayable_chords) = unplayable_chords {
+                toml.set("unplayable_chords", unplayable_chords);
+            }
+        })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn instrument_unplayable_chords() {
+    let build = build_with("instrument-unplayable-chords-warn", None);
+    build.unwrap();
+
+    let build = build_with("instrument-unplayable-chords-error", Some("error"));
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("'Song 2' (C7#9, Fmaj13)"));
+    assert!(!err.contains("'Song 1'"));
+}
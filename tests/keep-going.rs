@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+mod util_ng;
+pub use util_ng::*;
+
+/// The broken output and the merge output depending on it come first in the render order.
+fn project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .output("broken.pdf")
+        .template("broken.pdf", "broken.hbs", "{{#if book.title}}\n")
+        .output_toml(toml! {
+            file = "volume.pdf"
+            merge = ["broken.pdf"]
+        })
+        .output("songbook.json")
+        .settings(|toml| toml.set("tex", "none"))
+}
+
+#[test]
+fn keep_going_off() {
+    let build = project("keep-going-off").build().unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("broken.hbs"), "{}", err);
+    assert!(!err.contains("could not be rendered"), "{}", err);
+
+    // The outputs after the failed one are not rendered
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("test-projects")
+        .join("keep-going-off");
+    assert!(!dir.join("output").join("songbook.json").exists());
+
+    // The summary is printed after the error, which is counted
+    let output = Command::new(env!("CARGO_BIN_EXE_bard"))
+        .arg("make")
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let error_at = stderr.find("bard error").unwrap();
+    let summary_at = stderr.find("with 0 warnings, 1 error.").unwrap();
+    assert!(error_at < summary_at, "{}", stderr);
+}
+
+#[test]
+fn keep_going() {
+    let build = project("keep-going").build().unwrap();
+    build.unwrap_err();
+
+    let app = build.app().clone().with_keep_going();
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("test-projects")
+        .join("keep-going");
+    let err = bard::bard_make_at(&app, &dir).unwrap_err();
+    let err = format!("{:?}", err);
+    assert!(
+        err.contains(r#"2 outputs could not be rendered: "broken.pdf", "volume.pdf""#),
+        "{}",
+        err
+    );
+
+    // The output not depending on the broken one was rendered
+    assert!(dir.join("output").join("songbook.json").exists());
+
+    // Errors of the failed outputs are counted
+    assert_eq!(app.diag_counts(), (0, 2));
+}
@@ -0,0 +1,67 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, keep_together: bool) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "songs.md",
+            indoc! {"
+                # Song 1
+
+                !keep_together=true
+
+                1. Lyrics
+
+                # Song 2
+
+                1. Lyrics
+
+                # Song 3
+
+                !keep_together=false
+
+                1. Lyrics
+            "},
+        )
+        .output("songbook.html")
+        .output("songbook.pdf")
+        .settings(move |toml| {
+            toml.set("tex", "none");
+            toml.set("keep_together", keep_together);
+        })
+        .build()
+        .unwrap()
+}
+
+fn keep_together(build: &TestBuild) -> Vec<bool> {
+    build
+        .unwrap()
+        .songs()
+        .iter()
+        .map(|song| song.keep_together)
+        .collect()
+}
+
+#[test]
+fn keep_together_song_setting() {
+    let build = build_with("keep-together-song-setting", false);
+    assert_eq!(keep_together(&build), [true, false, false]);
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert_eq!(tex.matches(r"\begin{keeptogether}").count(), 1);
+    assert_eq!(tex.matches(r"\end{keeptogether}").count(), 1);
+
+    let html = build.read_output(".html");
+    assert_eq!(html.matches(r#"class="song pad keep-together""#).count(), 1);
+}
+
+#[test]
+fn keep_together_default() {
+    let build = build_with("keep-together-default", true);
+    assert_eq!(keep_together(&build), [true, true, false]);
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert_eq!(tex.matches(r"\begin{keeptogether}").count(), 2);
+}
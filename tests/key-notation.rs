@@ -0,0 +1,74 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONGS: &str = r#"# Wild Mountain Thyme
+{key: G}
+
+1. Oh the `G`summer time is `G/B`coming \
+And the `Em`trees are sweetly `D7`blooming
+
+# Scarborough Fair
+
+1. Are you `Am`going to Scarborough `G`Fair
+"#;
+
+#[test]
+fn key_notation() {
+    let build = TestProject::new("key-notation")
+        .song("songs.md", SONGS)
+        .output_toml(toml! {
+            file = "nashville.xml"
+            notation = "nashville"
+            capo = 2
+        })
+        .output_toml(toml! {
+            file = "roman.xml"
+            notation = "roman"
+        })
+        .output_toml(toml! {
+            file = "german.xml"
+            notation = "german"
+        })
+        .build()
+        .unwrap();
+
+    // Numbers are relative to the key regardless of the capo
+    let xml = build.read_output("nashville.xml");
+    assert!(xml.contains(r#"key="G""#));
+    assert!(xml.contains(r#"notation="nashville""#));
+    assert!(xml.contains(r#"chord="1""#) && xml.contains(r#"chord="1/3""#));
+    assert!(xml.contains(r#"chord="6m""#) && xml.contains(r#"chord="5(7)""#));
+    // Songs without a key are left in the project's notation, so the capo applies to them
+    assert!(xml.contains(r#"chord="Gm""#) && xml.contains(r#"chord="F""#));
+    assert!(xml.contains("<notation>nashville</notation>"));
+
+    let xml = build.read_output("roman.xml");
+    assert!(xml.contains(r#"chord="I/III""#) && xml.contains(r#"chord="V7""#));
+
+    // Converting between absolute notations doesn't need a key
+    let xml = build.read_output("german.xml");
+    assert!(xml.contains(r#"chord="Am""#));
+}
+
+#[test]
+fn key_notation_relative_source() {
+    let build = TestProject::new("key-notation-relative-source")
+        .song(
+            "song.md",
+            "# Danny Boy\n{key: Bb}\n\n1. Oh `1`Danny boy, the `4/6`pipes, the pipes are `5(7)`calling\n",
+        )
+        .settings(|toml| {
+            toml.insert("notation".into(), "nashville".into());
+        })
+        .output_toml(toml! {
+            file = "songbook.xml"
+            notation = "english"
+        })
+        .build()
+        .unwrap();
+
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains(r#"key="Bb""#));
+    assert!(xml.contains(r#"chord="Bb""#) && xml.contains(r#"chord="Eb/G""#));
+    assert!(xml.contains(r#"chord="F7""#));
+}
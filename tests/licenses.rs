@@ -0,0 +1,71 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[rustfmt::skip]
+fn prepare_project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .song("songs.md", indoc! {"
+        # Amazing Grace
+
+        {copyright: Public Domain} {license: Public Domain} {ccli: 22025}
+
+        1. Lyrics
+
+        # Song, with a comma
+
+        {copyright: © 2020 Example Music} {copyright: Admin. by \"Someone\"}
+        {ccli: 7654321} {license: CCLI}
+
+        1. Lyrics
+
+        # Unlicensed
+
+        1. Lyrics
+
+        # Instructions
+
+        !index=false
+        Text
+        "},
+        )
+        .settings(|toml| toml.set("smart_punctuation", false))
+}
+
+#[test]
+fn licenses_csv() {
+    let build = prepare_project("licenses-csv")
+        .output("licenses.csv")
+        .build()
+        .unwrap();
+
+    let csv = build.read_output(".csv");
+    assert_eq!(
+        csv,
+        indoc! {r#"
+        No.,Title,Copyright,CCLI,License,Status
+        1,Amazing Grace,Public Domain,22025,Public Domain,OK
+        2,"Song, with a comma","© 2020 Example Music; Admin. by ""Someone""",7654321,CCLI,OK
+        3,Unlicensed,,,,MISSING CCLI
+        "#}
+    );
+}
+
+#[test]
+fn licenses_table() {
+    let build = prepare_project("licenses-table")
+        .output_toml(toml! {
+            file = "licenses.txt"
+            format = "licenses"
+        })
+        .build()
+        .unwrap();
+
+    let table = build.read_output(".txt");
+    let lines: Vec<_> = table.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[0].starts_with("No. | Title "));
+    assert!(lines[1].starts_with("----+-"));
+    assert!(lines[4].starts_with("3   | Unlicensed "));
+    assert!(lines[4].ends_with("| MISSING CCLI"));
+    assert!(!table.contains("Instructions"));
+}
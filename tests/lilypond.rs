@@ -0,0 +1,93 @@
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = indoc! {r#"
+# Danny Boy
+
+1. Oh Danny boy, the pipes, the pipes are calling
+
+```lilypond
+\relative c' { c4 d e f }
+```
+"#};
+
+#[test]
+fn lilypond() {
+    // The env var is set for the whole test binary, so all the cases are in one test
+    env::set_var("BARD_LILYPOND", "bard-lilypond-not-found");
+    let build = TestProject::new("lilypond-not-found")
+        .song("song.md", SONG)
+        .output("songbook.xml")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    // Without LilyPond, the snippet is left out
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains(r"<source>\relative c&apos; { c4 d e f }"));
+    assert!(!xml.contains("<image"));
+    let html = build.read_output("songbook.html");
+    assert!(!html.contains("<img"));
+
+    env::set_var("BARD_LILYPOND", env!("CARGO_BIN_EXE_lilypond-mock"));
+    let build = TestProject::new("lilypond")
+        .song("song.md", SONG)
+        .output("songbook.xml")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let xml = build.read_output("songbook.xml");
+    let path = xml
+        .split(r#"path=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap()
+        .to_string();
+    assert!(path.starts_with("lilypond/") && path.ends_with(".png"));
+    assert!(build.dir_output().join(&path).exists());
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(&format!(r#"<img class="center" src="{}""#, path)));
+
+    // Rendered snippets are reused
+    env::set_var("BARD_LILYPOND", "bard-lilypond-not-found");
+    let project = build.unwrap();
+    bard::bard_make_at(build.app(), &project.project_dir).unwrap();
+    assert!(build.read_output("songbook.xml").contains(&path));
+
+    // Snippets are only rendered when making the outputs, not by check or dry runs
+    env::set_var("BARD_LILYPOND", env!("CARGO_BIN_EXE_lilypond-mock"));
+    let lilypond_dir = build.dir_output().join("lilypond");
+    fs::remove_dir_all(&lilypond_dir).unwrap();
+    bard::bard_check_at(build.app(), &project.project_dir, false).unwrap();
+    let dry_run = build.app().clone().with_dry_run();
+    bard::bard_make_at(&dry_run, &project.project_dir).unwrap();
+    assert!(!lilypond_dir.exists());
+
+    // LilyPond errors fail the build
+    env::set_var("BARD_LILYPOND", env!("CARGO_BIN_EXE_lilypond-mock"));
+    let build = TestProject::new("lilypond-error")
+        .song("song.md", SONG.replace("c4 d e f", "error"))
+        .output("songbook.xml")
+        .build()
+        .unwrap();
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains(r#"LilyPond snippet in song "Danny Boy""#));
+    assert!(err.contains("syntax error"));
+
+    // LilyPond is stopped after the TeX time limit
+    let start = Instant::now();
+    let build = TestProject::new("lilypond-timeout")
+        .song("song.md", SONG.replace("c4 d e f", "sleep"))
+        .output("songbook.xml")
+        .settings(|toml| toml.set("tex_timeout", 1))
+        .build()
+        .unwrap();
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("LilyPond did not finish within 1s"), "{}", err);
+    assert!(start.elapsed() < Duration::from_secs(30));
+}
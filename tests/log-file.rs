@@ -0,0 +1,31 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn log_file() {
+    let build = TestProject::new("log-file")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let log = project.project_dir.join("bard.log");
+
+    let app = build.app().clone().with_log_file(&log, false).unwrap();
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+    let content = fs::read_to_string(&log).unwrap();
+    assert_eq!(content.matches("Rendering songbook.html").count(), 1);
+    assert!(!content.contains('\u{1b}'));
+
+    // The file is truncated for each run, unless appending
+    let app = build.app().clone().with_log_file(&log, false).unwrap();
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+    let content = fs::read_to_string(&log).unwrap();
+    assert_eq!(content.matches("Rendering songbook.html").count(), 1);
+
+    let app = build.app().clone().with_log_file(&log, true).unwrap();
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+    let content = fs::read_to_string(&log).unwrap();
+    assert_eq!(content.matches("Rendering songbook.html").count(), 2);
+}
@@ -0,0 +1,180 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_merge_order() {
+    let build = TestProject::new("merge-order")
+        .output_toml(toml! {
+            file = "volume.pdf"
+            merge = ["part-1.pdf", "part-2.pdf"]
+        })
+        .output("part-1.pdf")
+        .output("part-2.pdf")
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let order: Vec<_> = build
+        .unwrap()
+        .settings
+        .outputs_render_order()
+        .map(|o| o.output_filename().to_string())
+        .collect();
+    assert_eq!(order, ["part-1.pdf", "part-2.pdf", "volume.pdf"]);
+}
+
+#[test]
+fn project_merge_cycle() {
+    let build = TestProject::new("merge-cycle")
+        .output_toml(toml! {
+            file = "volume-1.pdf"
+            merge = ["volume-2.pdf"]
+        })
+        .output_toml(toml! {
+            file = "volume-2.pdf"
+            merge = ["volume-1.pdf"]
+        })
+        .build()
+        .unwrap();
+
+    let cause = format!("{}", build.unwrap_err().root_cause());
+    cause.find("cycle").unwrap();
+}
+
+#[test]
+fn project_merge_not_pdf() {
+    let build = TestProject::new("merge-not-pdf")
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "volume.pdf"
+            merge = ["songbook.html"]
+        })
+        .build()
+        .unwrap();
+
+    let cause = format!("{}", build.unwrap_err().root_cause());
+    cause.find("not a PDF output").unwrap();
+}
+
+#[test]
+fn project_page_start_after() {
+    let build = TestProject::new("page-start-after")
+        .output_toml(toml! {
+            file = "part-2.pdf"
+            page_start_after = "part-1.pdf"
+        })
+        .output_toml(toml! {
+            file = "part-1.pdf"
+            page_start = 5
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let order: Vec<_> = build
+        .unwrap()
+        .settings
+        .outputs_render_order()
+        .map(|o| o.output_filename().to_string())
+        .collect();
+    assert_eq!(order, ["part-1.pdf", "part-2.pdf"]);
+
+    // With TeX disabled, there's no PDF to count the pages of
+    let tex = build.read_output("part-2.tex");
+    assert!(tex.contains("\\setcounter{page}{5}"));
+}
+
+#[test]
+fn include_pdf_not_pdf() {
+    let build = TestProject::new("include-pdf-not-pdf")
+        .output_toml(toml! {
+            file = "songbook.html"
+            prepend_pdf = ["cover.pdf"]
+        })
+        .build()
+        .unwrap();
+
+    let cause = format!("{}", build.unwrap_err().root_cause());
+    cause
+        .find("Only PDF outputs can include PDF files")
+        .unwrap();
+}
+
+#[test]
+fn include_pdf_missing() {
+    let build = TestProject::new("include-pdf-missing")
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            prepend_pdf = ["cover.pdf"]
+            append_pdf = ["back.pdf"]
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .postprocess(true)
+        .build()
+        .unwrap();
+
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("cover.pdf"), "{}", err);
+    assert!(err.contains("doesn't exist"), "{}", err);
+}
+
+#[test]
+fn project_render_parallel() {
+    let build = TestProject::new("render-parallel")
+        .output_toml(toml! {
+            file = "volume.pdf"
+            merge = ["part-1.pdf", "part-2.pdf"]
+        })
+        .output("part-1.pdf")
+        .output("part-2.pdf")
+        .output("songbook.html")
+        .output("songbook.json")
+        .output("songbook.epub")
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    let waves: Vec<Vec<_>> = project
+        .settings
+        .outputs_render_waves()
+        .iter()
+        .map(|wave| {
+            wave.iter()
+                .map(|o| o.output_filename().to_string())
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        waves,
+        [
+            vec![
+                "part-1.pdf",
+                "part-2.pdf",
+                "songbook.html",
+                "songbook.json",
+                "songbook.epub"
+            ],
+            vec!["volume.pdf"],
+        ]
+    );
+
+    for file in [
+        "songbook.html",
+        "songbook.json",
+        "songbook.epub",
+        "part-1.tex",
+    ] {
+        std::fs::remove_file(build.dir_output().join(file)).unwrap();
+    }
+    let app = build.app().clone().with_jobs(4);
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+    for file in [
+        "songbook.html",
+        "songbook.json",
+        "songbook.epub",
+        "part-1.tex",
+    ] {
+        assert!(build.dir_output().join(file).exists(), "{}", file);
+    }
+}
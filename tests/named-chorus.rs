@@ -0,0 +1,39 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn named_chorus() {
+    let build = TestProject::new("named-chorus")
+        .song(
+            "1.md",
+            indoc! {"
+                # Song
+
+                > Chorus one. {chorus: Refrain 1}
+
+                >> Chorus two.
+
+                1. Lyrics !>
+                2. Lyrics !>>
+            "},
+        )
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "songbook.tex"
+            format = "pdf"
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let html = build.read_output("html");
+    assert!(html.contains(r#"<span class="label">Refrain 1</span>"#));
+    assert!(html.contains(r#"<span class="label">Ch2.</span>"#));
+    assert!(html.contains("<em> Refrain 1</em>"));
+    assert!(html.contains("<em> Ch2.</em>"));
+
+    let tex = build.read_output("tex");
+    assert!(tex.contains(r"\Verse{Refrain 1}"));
+    assert!(tex.contains(r"\emph{Refrain 1}"));
+    assert!(tex.contains(r"\emph{Ch2.}"));
+}
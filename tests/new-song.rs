@@ -0,0 +1,73 @@
+use std::fs;
+
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn new_song() {
+    let build = TestProject::new("new-song")
+        .song("danny-boy.md", "# Danny Boy\n\n1. Oh Danny boy\n")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let app = build.app();
+    let dir = &build.unwrap().project_dir;
+
+    // The file is named by the slug of the title
+    let path = bard::bard_new_song_at(app, dir, "Žluťoučký kůň", false, false).unwrap();
+    assert_eq!(path, build.dir_songs().join("zlutoucky-kun.md"));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# Žluťoučký kůň\n\n1. \n"
+    );
+
+    // The songs field lists files, so it isn't part of the book unless added
+    let project = Project::new(app, dir).unwrap();
+    assert_eq!(project.songs().len(), 1);
+
+    // Existing files are not overwritten without force
+    fs::write(&path, "# Changed\n").unwrap();
+    bard::bard_new_song_at(app, dir, "Žluťoučký kůň", false, true).unwrap_err();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "# Changed\n");
+
+    // The new song is empty until it's filled in
+    bard::bard_new_song_at(app, dir, "Žluťoučký kůň", true, true).unwrap();
+    fs::write(&path, "# Žluťoučký kůň\n\n1. Úpěl ďábelské ódy\n").unwrap();
+    let project = Project::new(app, dir).unwrap();
+    assert_eq!(project.songs().len(), 2);
+    assert_eq!(&*project.songs()[1].title, "Žluťoučký kůň");
+
+    // Adding the file again doesn't duplicate it in bard.toml
+    bard::bard_new_song_at(app, dir, "Žluťoučký kůň", true, true).unwrap();
+    let bard_toml = fs::read_to_string(dir.join("bard.toml")).unwrap();
+    assert_eq!(bard_toml.matches("zlutoucky-kun.md").count(), 1);
+}
+
+#[test]
+fn new_song_glob() {
+    let build = TestProject::new("new-song-glob")
+        .settings(|toml| {
+            toml.insert("songs".into(), "*.md".into());
+        })
+        .build()
+        .unwrap();
+    let app = build.app();
+    let dir = &build.unwrap().project_dir;
+    let bard_toml = fs::read_to_string(dir.join("bard.toml")).unwrap();
+
+    // Matched by the default *.md glob, so bard.toml isn't changed
+    bard::bard_new_song_at(app, dir, "Wild Mountain Thyme", false, true).unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join("bard.toml")).unwrap(),
+        bard_toml
+    );
+    let project = Project::new(app, dir).unwrap();
+    assert!(project
+        .input_paths()
+        .iter()
+        .any(|path| path.ends_with("wild-mountain-thyme.md")));
+
+    bard::bard_new_song_at(app, dir, "  ", false, false).unwrap_err();
+}
@@ -0,0 +1,52 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn output_file_template() {
+    let build = TestProject::new("output-file-template")
+        .output_toml(toml! {
+            file = "{title}-{bard_version}.json"
+        })
+        .output_toml(toml! {
+            file = "{title}-{{{date}}}.html"
+            book = { title = "A/B" }
+        })
+        .build()
+        .unwrap();
+
+    let outputs: Vec<_> = build
+        .unwrap()
+        .settings
+        .output
+        .iter()
+        .map(|output| {
+            output
+                .file
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned()
+        })
+        .collect();
+
+    let title = "My Songbook";
+    let version = env!("CARGO_PKG_VERSION");
+    assert_eq!(outputs[0], format!("{title}-{version}.json"));
+    assert!(build.output_path(&outputs[0]).unwrap().exists());
+
+    let html = &outputs[1];
+    assert!(html.starts_with("A-B-{20"));
+    assert!(html.ends_with("}.html"));
+    assert_eq!(html.len(), "A-B-{YYYY-MM-DD}.html".len());
+}
+
+#[test]
+fn output_file_template_unknown() {
+    let build = TestProject::new("output-file-template-unknown")
+        .output("songbook-{edition}.html")
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("Unknown placeholder {edition}"));
+}
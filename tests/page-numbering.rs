@@ -0,0 +1,78 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn build_with(name: &str, numbering: Option<&'static str>) -> TestBuild {
+    TestProject::new(name)
+        .song(
+            "songs.md",
+            indoc! {"
+                # Song 1
+
+                1. Lyrics
+
+                # Song 2
+
+                1. Lyrics
+            "},
+        )
+        .output_toml(if let Some(numbering) = numbering {
+            toml! {
+                file = "songbook.pdf"
+                front_matter_numbering = numbering
+                page_start = 5
+            }
+        } else {
+            toml! {
+                file = "songbook.pdf"
+            }
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap()
+}
+
+fn read_tex(build: &TestBuild) -> String {
+    fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap()
+}
+
+#[test]
+fn page_numbering_default() {
+    let build = build_with("page-numbering-default", None);
+    let tex = read_tex(&build);
+    assert!(!tex.contains(r"\pagenumbering"));
+    assert!(tex.contains(r"\pagestyle{empty}"));
+    assert!(tex.contains(r"\setcounter{page}{1}"));
+
+    // Labels for cross-references to song pages
+    assert!(tex.contains(r"\label{song-0}"));
+    assert!(tex.contains(r"\label{song-1}"));
+}
+
+#[test]
+fn page_numbering_roman() {
+    let build = build_with("page-numbering-roman", Some("roman"));
+    let tex = read_tex(&build);
+    let roman = tex.find(r"\pagenumbering{roman}").unwrap();
+    let toc = tex.find(r"\tableofcontents*").unwrap();
+    let arabic = tex.find(r"\pagenumbering{arabic}").unwrap();
+    let start = tex.find(r"\setcounter{page}{5}").unwrap();
+    assert!(roman < toc);
+    assert!(toc < arabic);
+    assert!(arabic < start);
+    assert!(tex.contains(r"\aliaspagestyle{chapter}{songs}"));
+}
+
+#[test]
+fn page_numbering_invalid() {
+    let build = TestProject::new("page-numbering-invalid")
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            front_matter_numbering = "greek"
+        })
+        .build()
+        .unwrap();
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("greek"));
+}
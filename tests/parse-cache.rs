@@ -0,0 +1,105 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use bard::project::{ParseCache, Project};
+
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = indoc! {r#"
+# Wild Mountain Thyme
+## Scottish folk
+
+{key: G}
+
+1. Oh the `G`summer time is `[C|Cmaj7]`coming {x2}
+And the *trees* are **sweetly** `Am`bloomin' !+2
+`G` `D` `C`
+
+> And we'll all go together |:
+
+1. I will build my love a tower >
+"#};
+
+const SONG_DIAG: &str = indoc! {r#"
+# Danny Boy
+
+{author:}
+
+1. `C`Oh Danny boy
+"#};
+
+fn songs_json(project: &Project) -> serde_json::Value {
+    serde_json::to_value(project.songs()).unwrap()
+}
+
+#[test]
+fn parse_cache() {
+    let build = TestProject::new("parse-cache")
+        .song("thyme.md", SONG)
+        .song("danny.md", SONG_DIAG)
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let app = build.app();
+    let dir = &project.project_dir;
+
+    // The song with a diagnostic isn't cached
+    let cache_dir = build.dir_output().join(ParseCache::DIR);
+    let entries: Vec<_> = fs::read_dir(&cache_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(entries.len(), 1);
+    let entry = &entries[0];
+
+    // Songs loaded from the cache are the same as parsed
+    let cached = Project::new(app, dir).unwrap();
+    assert_eq!(songs_json(&cached), songs_json(project));
+
+    // The cached songs are used as long as the file doesn't change
+    let content = fs::read_to_string(entry).unwrap();
+    fs::write(
+        entry,
+        content.replace("Wild Mountain Thyme", "Cached Thyme"),
+    )
+    .unwrap();
+    let cached = Project::new(app, dir).unwrap();
+    assert_eq!(&*cached.songs()[0].title, "Cached Thyme");
+
+    // The cache is invalidated by a change of the parser configuration
+    let bard_toml = dir.join("bard.toml");
+    let settings = fs::read_to_string(&bard_toml).unwrap();
+    fs::write(
+        &bard_toml,
+        format!("smart_punctuation = false\n{}", settings),
+    )
+    .unwrap();
+    let reparsed = Project::new(app, dir).unwrap();
+    assert_eq!(&*reparsed.songs()[0].title, "Wild Mountain Thyme");
+    fs::write(&bard_toml, &settings).unwrap();
+
+    // ... and by a change of the file
+    fs::write(
+        entry,
+        content.replace("Wild Mountain Thyme", "Cached Thyme"),
+    )
+    .unwrap();
+    thread::sleep(Duration::from_millis(50));
+    let song = dir.join("songs").join("thyme.md");
+    fs::write(&song, SONG.replace("Wild", "Mild")).unwrap();
+    let reparsed = Project::new(app, dir).unwrap();
+    assert_eq!(&*reparsed.songs()[0].title, "Mild Mountain Thyme");
+    assert!(entry.exists());
+
+    // Entries of files which are no longer inputs are removed, other files are kept
+    let other = cache_dir.join("notes.txt");
+    fs::write(&other, "").unwrap();
+    fs::write(&bard_toml, settings.replace("    \"thyme.md\",\n", "")).unwrap();
+    let project = Project::new(app, dir).unwrap();
+    assert_eq!(project.songs().len(), 1);
+    assert!(!entry.exists());
+    assert!(other.exists());
+}
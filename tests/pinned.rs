@@ -0,0 +1,116 @@
+use std::fs;
+
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[rustfmt::skip]
+fn prepare_project(name: &str, songs: &'static str, pinned: &'static [&'static str]) -> TestProject {
+    TestProject::new(name)
+        .song("c.md", indoc! {"
+        # Song C
+
+        Foo bar.
+        "},
+        )
+        .song("b.md", indoc! {"
+        # Song B1
+
+        Foo bar.
+
+        # Song B2
+
+        Foo bar.
+        "},
+        )
+        .song("a.md", indoc! {"
+        # Song A
+
+        Foo bar.
+        "},
+        )
+        .song("anthem.md", indoc! {"
+        # Zulu Anthem
+
+        Foo bar.
+        "},
+        )
+        .output("songbook.pdf")
+        .settings(move |toml| {
+            toml.set("tex", "none");
+            toml.set("songs", songs);
+            toml.set("pinned", pinned.to_vec());
+        })
+}
+
+fn titles(project: &Project) -> Vec<&str> {
+    project.songs().iter().map(|song| &*song.title).collect()
+}
+
+fn titles_sorted(project: &Project) -> Vec<&str> {
+    project
+        .songs_sorted()
+        .iter()
+        .map(|song_ref| &*song_ref.title)
+        .collect()
+}
+
+#[test]
+fn pinned_first() {
+    let build = prepare_project("pinned-first", "*.md", &["anthem.md", "b.md"])
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    assert_eq!(
+        titles(project),
+        ["Zulu Anthem", "Song B1", "Song B2", "Song A", "Song C"]
+    );
+    assert_eq!(project.pinned(), 3);
+    assert_eq!(
+        titles_sorted(project),
+        ["Zulu Anthem", "Song B1", "Song B2", "Song A", "Song C"]
+    );
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert_eq!(tex.matches(r"\addtocontents{toc}{\relax}").count(), 3);
+}
+
+#[test]
+fn pinned_none() {
+    let build = prepare_project("pinned-none", "*.md", &[]).build().unwrap();
+    let project = build.unwrap();
+
+    assert_eq!(
+        titles(project),
+        ["Song A", "Zulu Anthem", "Song B1", "Song B2", "Song C"]
+    );
+    assert_eq!(project.pinned(), 0);
+    assert_eq!(
+        titles_sorted(project),
+        ["Song A", "Song B1", "Song B2", "Song C", "Zulu Anthem"]
+    );
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(!tex.contains(r"\addtocontents{toc}{\relax}"));
+}
+
+#[test]
+fn pinned_not_matched() {
+    let build = prepare_project("pinned-not-matched", "a.md", &["anthem.md"])
+        .build()
+        .unwrap();
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("anthem.md"));
+    assert!(err.contains("not matched"));
+}
+
+#[test]
+fn pinned_duplicate() {
+    let build = prepare_project("pinned-duplicate", "*.md", &["a.md", "a.md"])
+        .build()
+        .unwrap();
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("more than once"));
+}
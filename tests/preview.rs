@@ -0,0 +1,48 @@
+use std::fs;
+
+use bard::app::{App, InterruptFlag};
+use std::sync::atomic::AtomicBool;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn preview_song_range() {
+    let song = |name: &str| {
+        formatdoc! {"
+            # {0}
+
+            1. Lyrics {0}
+        ", name}
+    };
+
+    let build = TestProject::new("preview-song-range")
+        .song("a.md", song("Song-A"))
+        .song("b.md", song("Song-B"))
+        .song("c.md", song("Song-C"))
+        .output("songbook.pdf")
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let bard_exe = env!("CARGO_BIN_EXE_bard").into();
+    static INTERRUPT: AtomicBool = AtomicBool::new(false);
+    let app = App::with_test_mode(true, bard_exe, InterruptFlag(&INTERRUPT))
+        .with_preview("2-3".parse().unwrap());
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.preview.tex")).unwrap();
+    assert!(!tex.contains("Song-A"));
+    assert!(tex.contains("Song-B"));
+    assert!(tex.contains("Song-C"));
+    assert!(!tex.contains("tableofcontents"));
+
+    let full = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(full.contains("Song-A"));
+    assert!(full.contains("tableofcontents"));
+
+    let app = App::with_test_mode(true, "bard".into(), InterruptFlag(&INTERRUPT))
+        .with_preview("4-5".parse().unwrap());
+    bard::bard_make_at(&app, &project.project_dir).unwrap_err();
+}
@@ -0,0 +1,33 @@
+use std::fs;
+
+mod util;
+pub use util::*;
+
+#[test]
+fn project_file_encoding() {
+    let app = Builder::app(false);
+    let project_dir = init_project(&app, "project-file-encoding").unwrap();
+    let settings_path = project_dir.join("bard.toml");
+    let settings = fs::read_to_string(&settings_path).unwrap();
+
+    // UTF-8 BOM is skipped
+    fs::write(&settings_path, format!("\u{feff}{}", settings)).unwrap();
+    bard::bard_make_at(&app, &project_dir).unwrap();
+
+    let utf16: Vec<u8> = "\u{feff}version = 2"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    fs::write(&settings_path, &utf16).unwrap();
+    let err = bard::bard_make_at(&app, &project_dir).unwrap_err();
+    assert!(format!("{:#}", err).contains("encoded as UTF-16"));
+
+    // Also without BOM
+    fs::write(&settings_path, &utf16[2..]).unwrap();
+    let err = bard::bard_make_at(&app, &project_dir).unwrap_err();
+    assert!(format!("{:#}", err).contains("encoded as UTF-16"));
+
+    fs::write(&settings_path, b"version = 2\n# caf\xe9\n").unwrap();
+    let err = bard::bard_make_at(&app, &project_dir).unwrap_err();
+    assert!(format!("{:#}", err).contains("not valid UTF-8 text (invalid data at byte 17)"));
+}
@@ -0,0 +1,41 @@
+use std::fs;
+
+mod util;
+pub use util::*;
+
+#[test]
+fn project_file_hints() {
+    let app = Builder::app(false);
+    let project_dir = init_project(&app, "project-file-hints").unwrap();
+    let settings_path = project_dir.join("bard.toml");
+    let header = "version = 2\nsongs = \"*.md\"\n";
+
+    let hint = |body: &str| {
+        fs::write(&settings_path, format!("{}{}", header, body)).unwrap();
+        let err = format!("{:#}", bard::bard_make_at(&app, &project_dir).unwrap_err());
+        err.split("Hint: ").nth(1).map(str::to_owned)
+    };
+
+    let single_output = hint("[output]\nfile = \"songbook.pdf\"\n").unwrap();
+    assert!(single_output.starts_with("Did you mean `[[output]]`?"));
+    let duplicate_output =
+        hint("[output]\nfile = \"a.pdf\"\n[output]\nfile = \"b.pdf\"\n").unwrap();
+    assert!(duplicate_output.starts_with("Did you mean `[[output]]`?"));
+    let double_book = hint("[[book]]\ntitle = \"Songs\"\n").unwrap();
+    assert!(double_book.starts_with("Did you mean `[book]`?"));
+
+    let trailing_comma = hint("output = [\n  { file = \"songbook.pdf\", },\n]\n").unwrap();
+    assert!(trailing_comma.contains("trailing comma"));
+    let unquoted = hint("[[output]]\nfile = songbook.pdf\n").unwrap();
+    assert!(unquoted.contains("have to be quoted"));
+    let colon = hint("[[output]]\nfile: \"songbook.pdf\"\n").unwrap();
+    assert!(colon.contains("assigned with `=`"));
+    let backslash = hint("[[output]]\nfile = \"C:\\Users\\songbook.pdf\"\n").unwrap();
+    assert!(backslash.contains("single quotes"));
+
+    // Errors without a known cause have no hint
+    assert_eq!(
+        hint("[[output]]\nfile = \"songbook.pdf\"\ntex_runs = \"two\"\n"),
+        None
+    );
+}
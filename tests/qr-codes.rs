@@ -0,0 +1,72 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONGS: &str = r#"# Wild Mountain Thyme
+{link: https://example.com/recordings/wild-mountain-thyme.mp3}
+
+1. Oh the summer time is coming
+
+# Scarborough Fair
+
+1. Are you going to Scarborough Fair
+"#;
+
+#[test]
+fn qr_codes() {
+    let build = TestProject::new("qr-codes")
+        .song("songs.md", SONGS)
+        .output_toml(toml! {
+            file = "songbook.html"
+            qr_codes = true
+        })
+        .output_toml(toml! {
+            file = "songbook.json"
+            qr_codes = true
+        })
+        .output_toml(toml! {
+            file = "no-qr.html"
+        })
+        .build()
+        .unwrap();
+
+    let qr = build.dir_output().join("qr/wild-mountain-thyme.png");
+    let (w, h) = image::image_dimensions(&qr).unwrap();
+    assert_eq!((w, h), (164, 164));
+    assert!(!build.dir_output().join("qr/scarborough-fair.png").exists());
+
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(
+        r#"<a class="qr" href="https://example.com/recordings/wild-mountain-thyme.mp3">"#
+    ));
+    assert!(html.contains(r#"src="qr/wild-mountain-thyme.png""#));
+    assert_eq!(html.matches(r#"class="qr""#).count(), 2);
+
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""link": "https://example.com/recordings/wild-mountain-thyme.mp3""#));
+    assert!(json.contains(r#""path": "qr/wild-mountain-thyme.png""#));
+
+    // Without qr_codes, the link is rendered as text
+    let html = build.read_output("no-qr.html");
+    assert!(!html.contains("qr/wild-mountain-thyme.png"));
+    assert!(html.contains(r#"<a href="https://example.com/recordings/wild-mountain-thyme.mp3">"#));
+}
+
+#[test]
+fn qr_codes_link_too_long() {
+    let song = format!(
+        "# Song\n{{link: https://example.com/{}}}\n\n1. Lyrics\n",
+        "a".repeat(300)
+    );
+    let build = TestProject::new("qr-codes-link-too-long")
+        .song("song.md", &song)
+        .output_toml(toml! {
+            file = "songbook.html"
+            qr_codes = true
+        })
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("Could not create QR code of the link of song \"Song\""));
+    assert!(err.contains("Data too long for a QR code"));
+}
@@ -17,7 +17,9 @@ fn prepare_project(name: &str, postprocess: bool) -> TestProject {
             OUTPUT = \"${OUTPUT}\"
             PROJECT_DIR = \"${PROJECT_DIR}\"
             OUTPUT_DIR = \"${OUTPUT_DIR}\"
+            SONG_COUNT = \"${SONG_COUNT}\"
             " > "${OUTPUT_STEM}.toml"
+            printf '%s\n' "${INPUT_FILES}" > "${OUTPUT_STEM}.inputs"
 
             "#},
             indoc! {r#"
@@ -34,7 +36,9 @@ fn prepare_project(name: &str, postprocess: bool) -> TestProject {
             echo OUTPUT = "%OUTPUT%"
             echo PROJECT_DIR = "%PROJECT_DIR%"
             echo OUTPUT_DIR = "%OUTPUT_DIR%"
+            echo SONG_COUNT = "%SONG_COUNT%"
             ) > "%OUTPUT_STEM%.toml"
+            echo %INPUT_FILES%> "%OUTPUT_STEM%.inputs"
 
             "#},
         )
@@ -57,6 +61,14 @@ fn project_script() {
         build.unwrap().project_dir.to_str().unwrap()
     );
     assert_eq!(out["OUTPUT_DIR"], build.dir_output().to_str().unwrap());
+    assert_eq!(out["SONG_COUNT"], "1");
+
+    let inputs = build.read_output("songbook.inputs");
+    let inputs: Vec<_> = inputs.lines().collect();
+    assert_eq!(
+        inputs,
+        [build.dir_songs().join("yippie.md").to_str().unwrap()]
+    );
 }
 
 #[test]
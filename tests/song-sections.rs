@@ -0,0 +1,120 @@
+use std::fs;
+
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[rustfmt::skip]
+fn prepare_project(name: &str, songs: toml::Value, pinned: &'static [&'static str]) -> TestProject {
+    TestProject::new(name)
+        .song("ballad-b.md", indoc! {"
+        # Song B1
+
+        Foo bar.
+
+        # Song B2
+
+        Foo bar.
+        "},
+        )
+        .song("ballad-a.md", indoc! {"
+        # Song A
+
+        Foo bar.
+        "},
+        )
+        .song("reel-a-empty.md", "# Empty\n")
+        .song("reel-c.md", indoc! {"
+        # Song C
+
+        Foo bar.
+        "},
+        )
+        .song("anthem.md", indoc! {"
+        # Zulu Anthem
+
+        Foo bar.
+        "},
+        )
+        .output("songbook.pdf")
+        .output("songbook.html")
+        .settings(move |toml| {
+            toml.set("tex", "none");
+            toml.set("empty_songs", "skip");
+            toml.insert("songs".into(), songs);
+            toml.set("pinned", pinned.to_vec());
+        })
+}
+
+fn sections(project: &Project) -> Vec<(&str, Option<&str>)> {
+    project
+        .songs()
+        .iter()
+        .map(|song| (&*song.title, song.section.as_deref()))
+        .collect()
+}
+
+#[test]
+fn song_sections() {
+    let songs = toml! {
+        songs = [
+            { section = "Ballads", globs = ["ballad-*.md"] },
+            { section = "Reels", globs = ["reel-*.md", "anthem.md"] },
+        ]
+    };
+    let build = prepare_project("song-sections", songs["songs"].clone(), &[])
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    // Globs are sorted within each section, the empty song's section break moves to the next song
+    assert_eq!(
+        sections(project),
+        [
+            ("Song A", Some("Ballads")),
+            ("Song B1", None),
+            ("Song B2", None),
+            ("Song C", Some("Reels")),
+            ("Zulu Anthem", None),
+        ]
+    );
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(tex.contains(r"\booksection{Ballads}"));
+    assert!(tex.contains(r"\booksection{Reels}"));
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(r#"<h3 class="section">Ballads</h3>"#));
+    assert!(html.contains(r#"<h1 class="section pad">Reels</h1>"#));
+}
+
+#[test]
+fn song_sections_pinned() {
+    let songs = toml! {
+        songs = [
+            { section = "Ballads", globs = ["ballad-*.md", "anthem.md"] },
+            { section = "Reels", globs = ["reel-c.md"] },
+        ]
+    };
+    let build = prepare_project(
+        "song-sections-pinned",
+        songs["songs"].clone(),
+        &["anthem.md"],
+    )
+    .build()
+    .unwrap();
+    let project = build.unwrap();
+
+    // Pinned songs come before all sections
+    assert_eq!(
+        sections(project),
+        [
+            ("Zulu Anthem", None),
+            ("Song A", Some("Ballads")),
+            ("Song B1", None),
+            ("Song B2", None),
+            ("Song C", Some("Reels")),
+        ]
+    );
+    assert_eq!(project.pinned(), 1);
+}
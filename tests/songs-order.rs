@@ -0,0 +1,52 @@
+use bard::book::SortKey;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn songs_ordered_by() {
+    let song = |title: &str, subtitle: &str| {
+        formatdoc! {"
+            # {}
+            ## {}
+
+            1. Lyrics
+        ", title, subtitle}
+    };
+
+    let build = TestProject::new("songs-ordered-by")
+        .song("1.md", song("Čtvrtá", "Author B"))
+        .song("2.md", song("beta", "Author A"))
+        .song("3.md", song("Alpha", "Author B"))
+        .song("4.md", song("Delta", "Author A"))
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let titles = |key| -> Vec<_> {
+        project
+            .songs_ordered_by(key)
+            .into_iter()
+            .map(|song_ref| song_ref.title.to_string())
+            .collect()
+    };
+
+    assert_eq!(
+        titles(SortKey::Source),
+        ["Čtvrtá", "beta", "Alpha", "Delta"]
+    );
+    assert_eq!(titles(SortKey::Title), ["Alpha", "beta", "Čtvrtá", "Delta"]);
+    assert_eq!(
+        titles(SortKey::Subtitle),
+        ["beta", "Delta", "Alpha", "Čtvrtá"]
+    );
+
+    let sorted: Vec<_> = project.songs_sorted().iter().map(|s| s.idx).collect();
+    let by_title: Vec<_> = project
+        .songs_ordered_by(SortKey::Title)
+        .iter()
+        .map(|s| s.idx)
+        .collect();
+    assert_eq!(sorted, by_title);
+}
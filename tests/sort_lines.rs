@@ -1,5 +1,6 @@
 use std::fs;
 
+use bard::util::collation::Collator;
 use bard::util_cmd;
 
 mod util;
@@ -38,7 +39,7 @@ xxx
 
     fs::write(&file, content_to_sort.as_bytes()).unwrap();
 
-    let count = util_cmd::sort_lines(r#"baz=(.+)$"#, &file).unwrap();
+    let count = util_cmd::sort_lines(r#"baz=(.+)$"#, &file, &Collator::default()).unwrap();
     let sorted_content = fs::read_to_string(&file).unwrap();
 
     assert_eq!(sorted_content, expected);
@@ -51,7 +52,7 @@ fn sort_lines_no_capture_group() {
     let content_to_sort = "foo bar baz=b\n";
 
     fs::write(&file, content_to_sort.as_bytes()).unwrap();
-    util_cmd::sort_lines(r#"baz=.+$"#, file).unwrap_err();
+    util_cmd::sort_lines(r#"baz=.+$"#, file, &Collator::default()).unwrap_err();
 }
 
 #[test]
@@ -63,6 +64,6 @@ zzz
 "#;
 
     fs::write(&file, content_to_sort.as_bytes()).unwrap();
-    let count = util_cmd::sort_lines(r#"baz=(.+)$"#, file).unwrap();
+    let count = util_cmd::sort_lines(r#"baz=(.+)$"#, file, &Collator::default()).unwrap();
     assert_eq!(count, 0);
 }
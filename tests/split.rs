@@ -0,0 +1,101 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = r#"# Wild Mountain Thyme
+
+1. Oh the summer time is coming
+"#;
+
+const SONG_2: &str = r#"# Śpiewnik
+
+1. Lyrics
+"#;
+
+#[test]
+fn split_per_song() {
+    let build = TestProject::new("split-per-song")
+        .song("a.md", SONG)
+        .song("b.md", SONG)
+        .song("c.md", SONG_2)
+        .output_toml(toml! {
+            file = "songbook.html"
+            split = "per-song"
+            toc_sort = true
+        })
+        .output_toml(toml! {
+            file = "songbook.json"
+            split = "per-song"
+        })
+        .output_toml(toml! {
+            file = "songbook.xml"
+            split = "per-song"
+        })
+        .build()
+        .unwrap();
+
+    let index = build.read_output("songbook.html");
+    assert!(index.contains(r#"href="songbook/wild-mountain-thyme.html""#));
+    assert!(index.contains(r#"href="songbook/wild-mountain-thyme-2.html""#));
+    assert!(index.contains(r#"href="songbook/spiewnik.html""#));
+    assert!(!index.contains("Oh the summer time"));
+
+    let dir = build.dir_output().join("songbook");
+    let song = fs::read_to_string(dir.join("wild-mountain-thyme-2.html")).unwrap();
+    assert!(song.contains("Oh the summer time"));
+    assert!(song.contains(r#"href="../songbook.html""#));
+    assert!(song.contains(r#"href="../fonts/fonts.css""#));
+    let song = fs::read_to_string(dir.join("spiewnik.html")).unwrap();
+    assert!(song.contains("Śpiewnik"));
+    assert!(!song.contains("Oh the summer time"));
+
+    let json = fs::read_to_string(dir.join("spiewnik.json")).unwrap();
+    assert!(json.contains(r#""slug": "spiewnik""#));
+    assert!(!json.contains("wild-mountain-thyme"));
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""slug": "wild-mountain-thyme-2""#));
+
+    let xml = fs::read_to_string(dir.join("wild-mountain-thyme.xml")).unwrap();
+    assert!(xml.contains(
+        r#"<split dir="songbook" ext="xml" index="../songbook.xml" root="../" song="true"/>"#
+    ));
+}
+
+#[test]
+fn split_pdf() {
+    let build = TestProject::new("split-pdf")
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            split = "per-song"
+        })
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("Only HTML, JSON, and XML outputs can be split"));
+}
+
+#[test]
+fn split_none_links() {
+    let build = TestProject::new("split-none-links")
+        .song("a.md", SONG)
+        .song("b.md", SONG_2)
+        .output_toml(toml! {
+            file = "songbook.html"
+            toc_sort = true
+        })
+        .output_toml(toml! {
+            file = "songbook2.html"
+        })
+        .build()
+        .unwrap();
+
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(r##"<a href="#song-1">Śpiewnik</a>"##));
+    assert!(html.contains(r##"<a href="#song-0">Wild Mountain Thyme</a>"##));
+    assert!(html.contains(r#"href="fonts/fonts.css""#));
+    let html = build.read_output("songbook2.html");
+    assert!(html.contains(r##"<a href="#song-1">Śpiewnik</a>"##));
+    assert!(!build.dir_output().join("songbook").exists());
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::sync::atomic::AtomicBool;
+
+use bard::app::{App, InterruptFlag, TemplateVar};
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn template_vars() {
+    let build = TestProject::new("template-vars")
+        .song("song.md", "# Song\n\n1. Lyrics\n")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let bard_exe = env!("CARGO_BIN_EXE_bard").into();
+    static INTERRUPT: AtomicBool = AtomicBool::new(false);
+    let app = App::with_test_mode(true, bard_exe, InterruptFlag(&INTERRUPT))
+        .with_var("edition", "pocket")
+        .with_var("year", "2024");
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+
+    let json = fs::read_to_string(build.dir_output().join("songbook.json")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        json["vars"],
+        serde_json::json!({ "edition": "pocket", "year": "2024" })
+    );
+}
+
+#[test]
+fn template_var_parse() {
+    let var: TemplateVar = "edition=pocket=small".parse().unwrap();
+    assert_eq!(var.key, "edition");
+    assert_eq!(var.value, "pocket=small");
+    let var: TemplateVar = "empty=".parse().unwrap();
+    assert_eq!(var.value, "");
+
+    "edition".parse::<TemplateVar>().unwrap_err();
+    "=pocket".parse::<TemplateVar>().unwrap_err();
+}
@@ -0,0 +1,30 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn tex_normalize() {
+    let build = TestProject::new("tex-normalize")
+        .song(
+            "song.md",
+            indoc! {"
+                # Song
+
+                1. `G`Lyrics
+                lyrics
+            "},
+        )
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            tex_normalize = true
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let tex = fs::read_to_string(build.dir_output().join("songbook.tex")).unwrap();
+    assert!(tex.contains("\n  \\begin{titlingpage*}\n    \\begin{vplace}"));
+    assert!(!tex.contains("\n\n\n"));
+    assert!(tex.lines().all(|line| !line.ends_with([' ', '\t'])));
+}
@@ -4,6 +4,8 @@
 //! This may or may not be a manifestation of <https://github.com/rust-lang/rust/issues/37519>.
 #![cfg(not(windows))]
 
+use std::fs;
+
 mod util;
 pub use util::*;
 
@@ -60,6 +62,20 @@ fn tex_tools_env_full_path() {
     assert_first_line_contains(builder.out_dir().join("songbook.pdf"), tex_mock_exe);
 }
 
+#[test]
+fn tex_tools_jobname() {
+    let tex_mock_exe = ExeBuilder::tex_mock_exe();
+    let builder = ExeBuilder::init("tex-tools-jobname")
+        .unwrap()
+        .with_env("BARD_TEX", format!("xelatex:{}", tex_mock_exe.display()))
+        .run(&["make", "-kv"])
+        .unwrap();
+
+    // Intermediate files are named after the output file
+    let pdf = fs::read_to_string(builder.out_dir().join("songbook.pdf")).unwrap();
+    assert!(pdf.lines().any(|arg| arg == "-jobname=songbook"));
+}
+
 #[test]
 fn tex_tools_none() {
     let builder = ExeBuilder::init("tex-tools-none")
@@ -117,3 +133,94 @@ fn tex_tools_tectonic_embedded_explicit() {
     let pdf = builder.out_dir().join("songbook.pdf");
     assert_file_is_pdf(&pdf);
 }
+
+#[test]
+fn tex_tools_doctor() {
+    let tex_mock_exe = ExeBuilder::tex_mock_exe();
+    ExeBuilder::init("tex-tools-doctor")
+        .unwrap()
+        .with_env("BARD_TEX", format!("xelatex:{}", tex_mock_exe.display()))
+        .run(&["doctor"])
+        .unwrap();
+}
+
+#[test]
+fn tex_tools_doctor_none() {
+    ExeBuilder::init("tex-tools-doctor-none")
+        .unwrap()
+        .with_env("BARD_TEX", "none")
+        .run(&["doctor"])
+        .unwrap();
+}
+
+fn tex_tools_pdf(name: &str, output_settings: &str) -> String {
+    let tex_mock_exe = ExeBuilder::tex_mock_exe();
+    let builder = ExeBuilder::init(name).unwrap();
+    let bard_toml = builder.work_dir.join("bard.toml");
+    let settings = fs::read_to_string(&bard_toml).unwrap().replace(
+        "file = \"songbook.pdf\"",
+        &format!("file = \"songbook.pdf\"\n{}", output_settings),
+    );
+    fs::write(&bard_toml, settings).unwrap();
+
+    let builder = builder
+        .with_env("BARD_TEX", format!("xelatex:{}", tex_mock_exe.display()))
+        .run(&["make"])
+        .unwrap();
+
+    fs::read_to_string(builder.out_dir().join("songbook.pdf")).unwrap()
+}
+
+fn tex_tools_toc(name: &str, output_settings: &str) -> Vec<String> {
+    // The mock TeX puts .toc entries read from the previous run in the PDF
+    tex_tools_pdf(name, output_settings)
+        .lines()
+        .filter_map(|line| line.strip_prefix(r"\contentsline {section}{\numberline "))
+        .map(|line| line[3..].split('}').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn tex_tools_toc_sort() {
+    let toc = tex_tools_toc("tex-tools-toc-sort", "toc_sort = true\ntex_runs = 3");
+    assert_eq!(toc, ["Song A", "Song B", "Song Rerun"]);
+}
+
+#[test]
+fn tex_tools_toc_no_sort() {
+    // TeX still reruns, but the .toc is left in the order TeX wrote it
+    let toc = tex_tools_toc("tex-tools-toc-no-sort", "toc_sort = false\ntex_runs = 3");
+    assert_eq!(toc, ["Song B", "Song A", "Song Rerun"]);
+
+    let toc = tex_tools_toc("tex-tools-toc-no-sort-1", "toc_sort = false\ntex_runs = 1");
+    assert!(toc.is_empty());
+}
+
+#[test]
+fn tex_tools_runs_auto() {
+    // The mock's .toc doesn't change after the third run
+    let pdf = tex_tools_pdf(
+        "tex-tools-runs-auto",
+        "toc_sort = true\ntex_runs = \"auto\"",
+    );
+    assert!(pdf.contains("runs: 3\n"));
+    assert!(pdf.contains("Song Rerun"));
+
+    let pdf = tex_tools_pdf("tex-tools-runs-2", "tex_runs = 2");
+    assert!(pdf.contains("runs: 2\n"));
+}
+
+#[test]
+fn tex_tools_synctex() {
+    let pdf = tex_tools_pdf("tex-tools-synctex", "synctex = true");
+    assert!(pdf.lines().any(|arg| arg == "-synctex=1"));
+    let out_dir = work_dir("tex-tools-synctex", false).unwrap().join("output");
+    assert!(out_dir.join("songbook.synctex.gz").exists());
+
+    let pdf = tex_tools_pdf("tex-tools-no-synctex", "");
+    assert!(!pdf.lines().any(|arg| arg == "-synctex=1"));
+    let out_dir = work_dir("tex-tools-no-synctex", false)
+        .unwrap()
+        .join("output");
+    assert!(!out_dir.join("songbook.synctex.gz").exists());
+}
@@ -0,0 +1,54 @@
+use bard::parser::DiagKind;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn toc_keys() {
+    let build = TestProject::new("toc-keys")
+        .song(
+            "1.md",
+            indoc! {"
+                # Song 1
+
+                {key: F#m}
+
+                1. `F#m`Lyrics
+            "},
+        )
+        .song(
+            "2.md",
+            indoc! {"
+                # Song 2
+
+                {key: G7}
+
+                1. `C`Lyrics
+            "},
+        )
+        .output_toml(toml! {
+            file = "songbook.html"
+            toc_keys = true
+        })
+        .output_toml(toml! {
+            file = "songbook.tex"
+            format = "pdf"
+            toc_keys = true
+        })
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    // Invalid keys are reported and left out
+    build.assert_parser_diag(DiagKind::KeyAnnotation {
+        annotation: "{key: G7}".into(),
+    });
+
+    let html = build.read_output("html");
+    assert!(html.contains(r#"Song 1</a> <span class="toc-key">F#m</span></li>"#));
+    assert!(html.contains("Song 2</a></li>"));
+
+    let tex = build.read_output("tex");
+    assert!(tex.contains(r"\songtitlekey{Song 1}{F\#m}"));
+    assert!(tex.contains(r"\songtitle{Song 2}"));
+}
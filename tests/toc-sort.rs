@@ -98,3 +98,28 @@ fn project_toc_sort_pdf() {
     assert!(pos2 < pos3);
     assert!(pos3 < pos4);
 }
+
+#[test]
+fn project_toc_sort_locale() {
+    let song = |title: &str| formatdoc!("# {}\n\nFoo bar.\n", title);
+    let build = TestProject::new("toc-sort-locale")
+        .song("1.md", song("Chata"))
+        .song("2.md", song("Hora"))
+        .song("3.md", song("Čaj"))
+        .song("4.md", song("Cesta"))
+        .output("songbook.json")
+        .settings(|toml| {
+            let book = toml.get_mut("book").unwrap().as_table_mut().unwrap();
+            book.insert("locale".into(), "cs".into());
+        })
+        .build()
+        .unwrap();
+
+    let titles: Vec<_> = build
+        .unwrap()
+        .songs_sorted()
+        .iter()
+        .map(|song_ref| song_ref.title.to_string())
+        .collect();
+    assert_eq!(titles, ["Cesta", "Čaj", "Hora", "Chata"]);
+}
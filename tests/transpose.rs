@@ -0,0 +1,172 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = r#"# Wild Mountain Thyme
+
+1. `Cmaj7` `G/B`
+Oh the `C/G`summer time is `[Cmaj7|C]`coming
+And the `Bb`trees are `N.C.`sweetly bloomin'
+"#;
+
+#[test]
+fn transpose() {
+    let build = TestProject::new("transpose")
+        .song("song.md", SONG)
+        .output("songbook.html")
+        .output_toml(toml! {
+            file = "songbook-up.html"
+            transpose = 2
+        })
+        .output_toml(toml! {
+            file = "songbook-down.xml"
+            transpose = -3
+        })
+        .build()
+        .unwrap();
+
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(">Cmaj7<") && html.contains(">G/B<") && html.contains(">C/G<"));
+
+    let html = build.read_output("songbook-up.html");
+    // Bass notes of slash chords, chord sequences, and alternates are transposed as well
+    assert!(html.contains(">Dmaj7<"));
+    assert!(html.contains(">A/C#<"));
+    assert!(html.contains(">D/A<"));
+    assert!(html.contains(">C<"));
+    assert!(!html.contains(">Cmaj7<"));
+    // Chords which aren't recognized are left as they are
+    assert!(html.contains(">N.C.<"));
+
+    let xml = build.read_output("songbook-down.xml");
+    // Transposing down wraps around the octave
+    assert!(xml.contains(r#"chord="Amaj7""#));
+    assert!(xml.contains(r#"chord="E/Ab""#));
+    assert!(xml.contains(r#"chord="A/E""#));
+    assert!(xml.contains(r#"alternate="A""#));
+    assert!(xml.contains(r#"chord="G""#));
+    assert!(xml.contains("<transpose>-3</transpose>"));
+}
+
+#[test]
+fn transpose_unicode_accidentals() {
+    let build = TestProject::new("transpose-unicode-accidentals")
+        .song("song.md", SONG)
+        .output_toml(toml! {
+            file = "songbook.xml"
+            transpose = 1
+        })
+        .settings(|toml| toml.set("unicode_accidentals", true))
+        .build()
+        .unwrap();
+
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains(r#"chord="C♯maj7""#));
+    assert!(xml.contains(r#"chord="B""#));
+}
+
+#[test]
+fn transpose_german() {
+    let build = TestProject::new("transpose-german")
+        .song(
+            "song.md",
+            "# Song\n\n1. `C`Lyrics `H7`lyrics `B`lyrics `d`lyrics\n",
+        )
+        .output_toml(toml! {
+            file = "songbook-up.xml"
+            transpose = 1
+        })
+        .output_toml(toml! {
+            file = "songbook-down.xml"
+            transpose = -1
+        })
+        .settings(|toml| toml.set("notation", "german"))
+        .build()
+        .unwrap();
+
+    // Sharps are used when transposing up, flats when transposing down
+    let xml = build.read_output("songbook-up.xml");
+    let chords: Vec<_> = ["C#", "C7", "H", "d#"]
+        .iter()
+        .map(|chord| xml.contains(&format!(r#"chord="{}""#, chord)))
+        .collect();
+    assert_eq!(chords, [true; 4]);
+
+    let xml = build.read_output("songbook-down.xml");
+    let chords: Vec<_> = ["H", "B7", "A", "db"]
+        .iter()
+        .map(|chord| xml.contains(&format!(r#"chord="{}""#, chord)))
+        .collect();
+    assert_eq!(chords, [true; 4]);
+}
+
+#[test]
+fn transpose_to_key() {
+    let build = TestProject::new("transpose-to-key")
+        .song(
+            "major.md",
+            "# Major\n\n{key: C}\n\n1. `C`Lyrics `F`lyrics `G7`lyrics `Eb`lyrics\n",
+        )
+        .song(
+            "minor.md",
+            "# Minor\n\n{key: Em}\n\n1. `Em`Lyrics `C`lyrics `B7`lyrics\n",
+        )
+        .song("no-key.md", "# No Key\n\n1. `A`Lyrics\n")
+        .output_toml(toml! {
+            file = "songbook.json"
+            to_key = "F"
+        })
+        .output_toml(toml! {
+            file = "songbook.xml"
+            to_key = "F#"
+        })
+        .build()
+        .unwrap();
+
+    // Chords are spelled according to the new key, minor songs stay minor
+    let json = build.read_output("songbook.json");
+    for chord in ["F", "Bb", "C7", "Ab", "Fm", "Db", "C7"] {
+        assert!(
+            json.contains(&format!(r#""chord": "{}""#, chord)),
+            "{}",
+            chord
+        );
+    }
+    assert!(json.contains(r#""key": "F""#));
+    assert!(json.contains(r#""key": "Fm""#));
+    // Songs without a key are left as they are
+    assert!(json.contains(r#""chord": "A""#));
+
+    let xml = build.read_output("songbook.xml");
+    for chord in ["F#", "B", "C#7", "A", "F#m", "D", "C#7"] {
+        assert!(xml.contains(&format!(r#"chord="{}""#, chord)), "{}", chord);
+    }
+    assert!(xml.contains("<to_key>F#</to_key>"));
+}
+
+#[test]
+fn transpose_to_key_invalid() {
+    let build = TestProject::new("transpose-to-key-both")
+        .output_toml(toml! {
+            file = "songbook.json"
+            transpose = 2
+            to_key = "G"
+        })
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(
+        err.contains("sets both 'transpose' and 'to_key'"),
+        "{}",
+        err
+    );
+
+    let build = TestProject::new("transpose-to-key-invalid")
+        .output_toml(toml! {
+            file = "songbook.json"
+            to_key = "X"
+        })
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("which is not a key"), "{}", err);
+}
@@ -0,0 +1,44 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn unicode_accidentals() {
+    let build = TestProject::new("unicode-accidentals")
+        .settings(|toml| toml.set("unicode_accidentals", true))
+        .output("unicode.json")
+        .output_toml(toml! {
+            file = "transposed.json"
+            transpose = 2
+        })
+        .output_toml(toml! {
+            file = "ascii.xml"
+            unicode_accidentals = false
+        })
+        .song(
+            "song.md",
+            r#"# Song
+{key: Bb}
+
+1. `Bb`Hello `C#m7`world `F#`!
+"#,
+        )
+        .build()
+        .unwrap();
+
+    let unicode = build.read_output("unicode.json");
+    assert!(unicode.contains(r#""chord": "B♭""#));
+    assert!(unicode.contains(r#""chord": "C♯m7""#));
+    assert!(unicode.contains(r#""key": "B♭""#));
+
+    // Transposition works on the ASCII chords, the glyphs are applied afterwards
+    let transposed = build.read_output("transposed.json");
+    assert!(transposed.contains(r#""chord": "C""#));
+    assert!(transposed.contains(r#""chord": "D♯m7""#));
+    assert!(transposed.contains(r#""chord": "G♯""#));
+    assert!(transposed.contains(r#""key": "C""#));
+
+    let ascii = build.read_output("ascii.xml");
+    assert!(ascii.contains("<unicode_accidentals>false</unicode_accidentals>"));
+    assert!(ascii.contains("C#m7"));
+    assert!(!ascii.contains('♭'));
+}
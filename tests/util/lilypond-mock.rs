@@ -0,0 +1,31 @@
+//! Small binary that mocks the lilypond CLI, used in some integration tests.
+
+use std::env;
+use std::fs;
+
+/// Written as the rendered image of any snippet.
+static PNG: &[u8] = include_bytes!("../test-projects/all-features/output/box.png");
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    let name = {
+        let flag_pos = args
+            .iter()
+            .position(|arg| arg == "-o")
+            .expect("Need the output name argument");
+        &args[flag_pos + 1]
+    };
+
+    let ly = fs::read_to_string(args.last().unwrap()).unwrap();
+    if ly.contains("sleep") {
+        std::thread::sleep(std::time::Duration::from_secs(60));
+    }
+    if ly.contains("error") {
+        eprintln!("{}: error: syntax error", args.last().unwrap());
+        std::process::exit(1);
+    }
+
+    // Like LilyPond with -dcrop, the cropped image is written next to the full page
+    fs::write(format!("{}.png", name), PNG).unwrap();
+    fs::write(format!("{}.cropped.png", name), PNG).unwrap();
+}
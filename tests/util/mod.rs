@@ -150,9 +150,7 @@ pub struct Builder {
 
 impl Builder {
     pub fn app(post_process: bool) -> App {
-        let bard_exe = option_env!("CARGO_BIN_EXE_bard")
-            .expect("$CARGO_BIN_EXE_bard")
-            .into();
+        let bard_exe = env!("CARGO_BIN_EXE_bard").into();
         App::with_test_mode(post_process, bard_exe, InterruptFlag(&INTERRUPT))
     }
 
@@ -186,7 +184,7 @@ impl Builder {
     }
 
     pub fn init_and_build(name: &str) -> Result<Self> {
-        Self::init_modify_build(name, |settings| Ok(settings))
+        Self::init_modify_build(name, Ok)
     }
 
     pub fn init_modify_build(
@@ -324,25 +322,25 @@ pub fn pdf_to_text(pdf: &Path, pages: impl RangeBounds<usize>) -> Result<String>
     let mut cmd = Command::new("pdftotext");
     cmd.arg("-layout");
 
-    match pages.start_bound() {
+    let first = match pages.start_bound() {
         Bound::Included(&f) => Some(f),
         Bound::Excluded(&f) => Some(f + 1),
         Bound::Unbounded => None,
-    }
-    .map(|f| {
-        cmd.arg("-f".to_string());
+    };
+    if let Some(f) = first {
+        cmd.arg("-f");
         cmd.arg(format!("{}", f));
-    });
+    }
 
-    match pages.end_bound() {
+    let last = match pages.end_bound() {
         Bound::Included(&i) => Some(i),
         Bound::Excluded(&i) => Some(i - 1),
         Bound::Unbounded => None,
-    }
-    .map(|l| {
-        cmd.arg("-l".to_string());
+    };
+    if let Some(l) = last {
+        cmd.arg("-l");
         cmd.arg(format!("{}", l));
-    });
+    }
 
     cmd.arg(pdf);
     cmd.arg("-");
@@ -1,7 +1,7 @@
 //! Small binary that mocks xelatex and tectonic CLI, used in some integration tests.
 
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
 
@@ -25,13 +25,61 @@ fn main() {
         (&args[flag_pos + 1]).into()
     };
 
-    let mut tex: PathBuf = args.iter().last().unwrap().into();
-    tex.set_extension("pdf");
-    let pdf = tex.file_name().unwrap();
+    // Like TeX, the jobname is used as is and the extension is appended
+    let pdf = match args.iter().find_map(|arg| arg.strip_prefix("-jobname=")) {
+        Some(jobname) => format!("{}.pdf", jobname).into(),
+        None => {
+            let mut tex: PathBuf = args.iter().last().unwrap().into();
+            tex.set_extension("pdf");
+            tex.file_name().unwrap().to_owned()
+        }
+    };
+
+    // Like TeX, read the .toc file written by the previous run and write one for the next,
+    // the entries are written out of order so that sorting can be observed.
+    let toc = out_dir.join(&pdf).with_extension("toc");
+    let prev_toc = fs::read_to_string(&toc).ok();
+    if prev_toc.is_none() {
+        fs::write(&toc, TOC).unwrap();
+    }
+
+    // Runs are counted in a file next to the .toc
+    let runs_file = out_dir.join(&pdf).with_extension("runs");
+    let runs = fs::read_to_string(&runs_file)
+        .ok()
+        .and_then(|runs| runs.parse::<u32>().ok())
+        .unwrap_or(0)
+        + 1;
+    fs::write(&runs_file, runs.to_string()).unwrap();
+
+    // SyncTeX data is written next to the PDF when requested
+    if args
+        .iter()
+        .any(|arg| arg == "-synctex=1" || arg == "--synctex")
+    {
+        let synctex = out_dir.join(&pdf).with_extension("synctex.gz");
+        fs::write(synctex, "synctex").unwrap();
+    }
 
     let mut dest = File::create(out_dir.join(pdf)).unwrap();
     for arg in env::args() {
         dest.write_all(arg.as_bytes()).unwrap();
         dest.write_all(b"\n").unwrap();
     }
+    writeln!(dest, "runs: {}", runs).unwrap();
+
+    // The second run adds an entry, so the .toc settles after the third one
+    if let Some(prev_toc) = prev_toc {
+        dest.write_all(prev_toc.as_bytes()).unwrap();
+        if !prev_toc.contains(TOC_RUN) {
+            fs::write(&toc, prev_toc + TOC_RUN).unwrap();
+        }
+    }
 }
+
+static TOC: &str = r"\contentsline {section}{\numberline {2}Song B}{2}
+\contentsline {section}{\numberline {1}Song A}{1}
+";
+
+static TOC_RUN: &str = r"\contentsline {section}{\numberline {3}Song Rerun}{3}
+";
@@ -31,11 +31,13 @@ pub fn interrupt() {
     INTERRUPT.store(true, Ordering::Relaxed);
 }
 
+type ModifySettings = Box<dyn FnOnce(&mut toml::Table)>;
+
 pub struct TestProject {
     path: PathBuf,
     postprocess: bool,
     outputs: Vec<Toml>,
-    modify_settings: Option<Box<dyn FnOnce(&mut toml::Table)>>,
+    modify_settings: Option<ModifySettings>,
     songs: Vec<(PathBuf, String)>,
     templates: Vec<Template>,
     scripts: Vec<Script>,
@@ -160,9 +162,7 @@ impl TestProject {
             .with_context(|| format!("Couldn't create directory: {:?}", self.path))?;
 
         // Instantiate App
-        let bard_exe = option_env!("CARGO_BIN_EXE_bard")
-            .expect("$CARGO_BIN_EXE_bard")
-            .into();
+        let bard_exe = env!("CARGO_BIN_EXE_bard").into();
         let app = App::with_test_mode(self.postprocess, bard_exe, InterruptFlag(&INTERRUPT));
 
         // Init default project
@@ -222,8 +222,8 @@ impl TestProject {
             fs::create_dir_all(&out_dir)
                 .with_context(|| format!("Couldn't create output directory: {:?}", tpl_dir))?;
             for script in self.scripts.iter() {
-                let path_sh = out_dir.join(&format!("{}.sh", script.name));
-                let path_bat = out_dir.join(&format!("{}.bat", script.name));
+                let path_sh = out_dir.join(format!("{}.sh", script.name));
+                let path_bat = out_dir.join(format!("{}.bat", script.name));
                 fs::write(&path_sh, script.content_sh.as_bytes())
                     .and_then(|_| path_sh.chmod(0o755))
                     .with_context(|| format!("Couldn't write script file: {:?}", path_sh))?;
@@ -346,7 +346,7 @@ impl TestBuild {
             Bound::Excluded(&f) => Some(f + 1),
             Bound::Unbounded => None,
         } {
-            cmd.arg("-f".to_string()).arg(format!("{}", f));
+            cmd.arg("-f").arg(format!("{}", f));
         };
 
         if let Some(l) = match pages.end_bound() {
@@ -354,7 +354,7 @@ impl TestBuild {
             Bound::Excluded(&i) => Some(i - 1),
             Bound::Unbounded => None,
         } {
-            cmd.arg("-l".to_string()).arg(format!("{}", l));
+            cmd.arg("-l").arg(format!("{}", l));
         };
 
         let output = self.output_path(output_suffix)?;
@@ -0,0 +1,35 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_verify_fresh() {
+    let build = TestProject::new("verify-fresh")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    project.verify_fresh(build.app()).unwrap();
+
+    // Modify a song without rebuilding, the output is stale
+    thread::sleep(Duration::from_millis(50));
+    let song = project.input_paths()[0].clone();
+    let content = fs::read_to_string(&song).unwrap();
+    fs::write(&song, content + "\nMore lyrics.\n").unwrap();
+    let err = project.verify_fresh(build.app()).unwrap_err().to_string();
+    assert!(err.contains("songbook.html"));
+    assert!(err.contains(song.file_name().unwrap().to_str().unwrap()));
+
+    // Rebuilding makes the output fresh again
+    let app = build.app().clone().with_verify_fresh();
+    bard::bard_make_at(&app, &project.project_dir).unwrap();
+
+    // Missing outputs are reported
+    fs::remove_file(build.output_path("songbook.html").unwrap()).unwrap();
+    let err = project.verify_fresh(build.app()).unwrap_err().to_string();
+    assert!(err.contains("was not produced"));
+}
@@ -0,0 +1,45 @@
+mod util_ng;
+pub use util_ng::*;
+
+const SONG: &str = r#"# Wild Mountain Thyme
+
+1. Oh the summer time is coming
+
+> Will ye go, lassie, go? {x2}
+
+2. I will build my love a bower
+"#;
+
+#[test]
+fn verse_repeat() {
+    let build = TestProject::new("verse-repeat")
+        .song("song.md", SONG)
+        .output("songbook.html")
+        .output("songbook.pdf")
+        .output_toml(toml! {
+            file = "songbook.txt"
+            format = "hovorka"
+        })
+        .output("songbook.xml")
+        .output("songbook.json")
+        .settings(|toml| toml.set("tex", "none"))
+        .build()
+        .unwrap();
+
+    let html = build.read_output("songbook.html");
+    assert!(html.contains(r#"go?<span class="repeat">(&times;2)</span>"#));
+    assert_eq!(html.matches(r#"<span class="repeat">"#).count(), 1);
+
+    let tex = build.read_output("songbook.tex");
+    assert!(tex.contains(r"go?\verseRepeat{2}"));
+
+    let hovorka = build.read_output("songbook.txt");
+    assert!(hovorka.contains("go? (×2)"));
+
+    let xml = build.read_output("songbook.xml");
+    assert!(xml.contains(r#"label-type="chorus" repeat="2""#));
+    assert_eq!(xml.matches("repeat=").count(), 1);
+
+    let json = build.read_output("songbook.json");
+    assert!(json.contains(r#""repeat": 2"#));
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use bard::watch::Watch;
+
+mod util_ng;
+pub use util_ng::*;
+
+// NB. Not in watch.rs, the interrupt flag is shared by tests in one file.
+#[test]
+fn watch_debounce() {
+    let build = TestProject::new("watch-debounce")
+        .song("song-a.md", "# Song A\n\n1. `C`Lyrics.\n")
+        .song("song-b.md", "# Song B\n\n1. `C`Lyrics.\n")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let app = build.app().clone();
+    let project_dir = build.dir_output().to_owned();
+    let mut watch_set = bard::bard_make_at(&app, &project_dir)
+        .unwrap()
+        .into_watch_set();
+    let (watch, control) = Watch::with_test_sync().unwrap();
+    let mut watch = watch.with_debounce(Duration::from_millis(500));
+    let watch_thread = thread::spawn(move || {
+        watch
+            .watch(&mut watch_set, app.interrupt_flag())
+            .unwrap()
+            .unwrap()
+    });
+
+    // A burst of changes within the debounce window is reported at once
+    control.wait_watching();
+    fs::write(
+        build.dir_songs().join("song-a.md"),
+        "# Song A\n\n1. Changed.\n",
+    )
+    .unwrap();
+    thread::sleep(Duration::from_millis(100));
+    fs::write(
+        build.dir_songs().join("song-b.md"),
+        "# Song B\n\n1. Changed.\n",
+    )
+    .unwrap();
+
+    let paths = watch_thread.join().unwrap();
+    assert!(paths.iter().any(|path| path.ends_with("song-a.md")));
+    assert!(paths.iter().any(|path| path.ends_with("song-b.md")));
+}
@@ -0,0 +1,111 @@
+use std::{fs, slice};
+
+use bard::project::{WatchChange, WatchSet};
+
+mod util_ng;
+pub use util_ng::*;
+
+const SMOL_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAQAAAAEAAQMAAABmvDolAAAAA1BMVEW10NBjBBbqAAAAH0lEQVRoge3BAQ0AAADCoPdPbQ43oAAAAAAAAAAAvg0hAAABmmDh1QAAAABJRU5ErkJggg==";
+
+#[test]
+fn watch_set() {
+    let build = TestProject::new("watch-set")
+        .song(
+            "song.md",
+            indoc! {r#"
+            # Song
+
+            1. `C`Lyrics.
+            ![smol](smol.png "center")
+        "#},
+        )
+        .binary_asset("smol.png", SMOL_PNG)
+        .output("songbook.html")
+        .settings(|toml| toml.set("songs", "*.md"))
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    let dir_songs = build.dir_songs();
+    let song = dir_songs.join("song.md");
+    let img = build.dir_output().join("smol.png");
+
+    // Dependencies are the same as those found by loading the project
+    let mut watch_set = WatchSet::new(&project.project_dir).unwrap();
+    let mut paths: Vec<_> = watch_set.paths().collect();
+    let mut project_paths: Vec<_> = project.watch_paths().collect();
+    paths.sort();
+    project_paths.sort();
+    assert_eq!(paths, project_paths);
+    assert!(paths.contains(&img.as_path()));
+    assert_eq!(watch_set.dirs().collect::<Vec<_>>(), [dir_songs]);
+
+    // Files not matched by the songs pattern are ignored
+    let backup = dir_songs.join("song.md~");
+    fs::write(&backup, "").unwrap();
+    assert_eq!(watch_set.update(&[backup]).unwrap(), WatchChange::None);
+
+    assert_eq!(
+        watch_set.update(slice::from_ref(&song)).unwrap(),
+        WatchChange::Contents
+    );
+
+    // New songs are picked up by the glob
+    let song2 = dir_songs.join("song2.md");
+    fs::write(&song2, "# Song 2\n").unwrap();
+    assert_eq!(
+        watch_set.update(slice::from_ref(&song2)).unwrap(),
+        WatchChange::Paths
+    );
+    assert_eq!(watch_set.inputs(), [song.clone(), song2.clone()]);
+
+    // Image references are updated
+    fs::write(&song, "# Song\n\n1. Lyrics.\n").unwrap();
+    assert_eq!(
+        watch_set.update(slice::from_ref(&song)).unwrap(),
+        WatchChange::Paths
+    );
+    assert!(!watch_set.contains(&img));
+
+    fs::remove_file(&song2).unwrap();
+    assert_eq!(watch_set.update(&[song2]).unwrap(), WatchChange::Paths);
+    assert_eq!(watch_set.inputs(), [song]);
+}
+
+#[test]
+fn book_images_sorted() {
+    let build = TestProject::new("book-images-sorted")
+        .song(
+            "song.md",
+            indoc! {r#"
+            # Song 1
+
+            1. ![b](b.png)
+            ![a](a.png)
+
+            # Song 2
+
+            1. ![b again](b.png)
+        "#},
+        )
+        .binary_asset("a.png", SMOL_PNG)
+        .binary_asset("b.png", SMOL_PNG)
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    let images: Vec<_> = project
+        .book
+        .iter_images()
+        .map(|image| &*image.path)
+        .collect();
+    assert_eq!(images, ["a.png", "b.png"]);
+    assert_eq!(
+        project
+            .watch_paths()
+            .filter(|path| path.extension() == Some("png".as_ref()))
+            .count(),
+        2
+    );
+}
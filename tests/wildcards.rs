@@ -27,7 +27,7 @@ fn project_wildcards_n() {
     let proj = TestProject::new("wildcards-n");
     let build = ['a', 'b', 'c']
         .iter()
-        .flat_map(|c| (1..4).into_iter().map(move |i| (c, i)))
+        .flat_map(|c| (1..4).map(move |i| (c, i)))
         .fold(proj, |proj, (c, i)| {
             proj.song(
                 format!("{}-{}.md", c, i),
@@ -53,3 +53,95 @@ fn project_wildcards_n() {
         }
     }
 }
+
+#[test]
+fn project_wildcards_bardignore() {
+    let song = |name: &str| {
+        formatdoc! {"
+        # Song
+
+        1. {}
+    ", name}
+    };
+
+    let build = TestProject::new("wildcards-bardignore")
+        .song("a.md", song("lyrics-a"))
+        .song("draft-1.md", song("lyrics-draft-1"))
+        .song("draft-2.md", song("lyrics-draft-2"))
+        .song(
+            ".bardignore",
+            indoc! {"
+            # Not ready yet
+            draft-*.md
+            !draft-2.md
+        "},
+        )
+        .output("songbook.html")
+        .settings(|toml| {
+            toml.set("songs", "*.md");
+        })
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert!(html.contains("lyrics-a"));
+    assert!(!html.contains("lyrics-draft-1"));
+    assert!(html.contains("lyrics-draft-2"));
+}
+
+#[test]
+fn project_multi_song_file() {
+    let build = TestProject::new("multi-song-file")
+        .song(
+            "book.md",
+            indoc! {"
+            # Song 1
+
+            1. Lyrics 1
+
+            # Song 2
+            ## Subtitle
+
+            1. Lyrics 2
+
+            # Song 3
+
+            1. Lyrics 3
+        "},
+        )
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    let titles: Vec<_> = project.songs().iter().map(|song| &*song.title).collect();
+    assert_eq!(titles, ["Song 1", "Song 2", "Song 3"]);
+    assert_eq!(project.input_paths().len(), 1);
+    assert!(project.input_paths()[0].ends_with("book.md"));
+}
+
+#[test]
+fn project_max_song_files() {
+    let project = |name: &str, limit: i64| {
+        ["a", "b", "c"]
+            .iter()
+            .fold(TestProject::new(name), |proj, name| {
+                proj.song(format!("{}.md", name), format!("# Song {}\n", name))
+            })
+            .output("songbook.json")
+            .settings(move |toml| {
+                toml.set("songs", "*.md");
+                toml.set("max_song_files", limit);
+            })
+            .build()
+            .unwrap()
+    };
+
+    let build = project("max-song-files", 2);
+    let err = format!("{:#}", build.unwrap_err());
+    assert!(err.contains("matches 3 files, more than the limit of 2"));
+    assert!(err.contains("\"a.md\""));
+
+    project("max-song-files-3", 3).unwrap();
+    project("max-song-files-off", 0).unwrap();
+}